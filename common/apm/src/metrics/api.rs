@@ -17,18 +17,37 @@ make_auto_flush_static_metric! {
         eth_getTransactionCount,
         eth_getBlockTransactionCountByNumber,
         eth_getBalance,
+        eth_getProof,
         eth_call,
         eth_estimateGas,
         eth_chainId,
         net_version,
         eth_getCode,
+        eth_getStorageAt,
         eth_getTransactionReceipt,
         net_peerCount,
         net_listening,
         eth_gasPrice,
+        eth_maxPriorityFeePerGas,
         eth_syncing,
         eth_getLogs,
         get_block,
+        axon_getTransactionStatus,
+        axon_consensusStatus,
+        eth_sendTransaction,
+        personal_unlockAccount,
+        personal_lockAccount,
+        eth_signTypedData_v4,
+        debug_traceTransaction,
+        axon_oldestAvailableBlock,
+        axon_isBlockCanonical,
+        axon_verifyBlockProof,
+        debug_storageRangeAt,
+        axon_callWithLogs,
+        axon_simulateAccessSet,
+        axon_multicall,
+        axon_simulateBundle,
+        axon_sendRawTransactionSync,
     }
 
     pub label_enum Request_Result {
@@ -76,6 +76,16 @@ lazy_static! {
         register_int_gauge!("muta_proposal_order_tx_len", "The ordered transactions len").unwrap();
     pub static ref ENGINE_SYNC_TX_GAUGE: IntGauge =
         register_int_gauge!("muta_proposal_sync_tx_len", "The sync transactions len").unwrap();
+    pub static ref ENGINE_QC_ACKED_COUNTER: IntCounter = register_int_counter!(
+        "muta_consensus_qc_acked_total",
+        "The aggregated votes acknowledged by consensus peers"
+    )
+    .unwrap();
+    pub static ref ENGINE_QC_UNACKED_COUNTER: IntCounter = register_int_counter!(
+        "muta_consensus_qc_unacked_total",
+        "The aggregated votes sent to consensus peers but not acknowledged"
+    )
+    .unwrap();
     pub static ref ENGINE_SYNC_BLOCK_COUNTER: IntCounter = register_int_counter!(
         "muta_consensus_sync_block_total",
         "The counter for sync blocks from remote"
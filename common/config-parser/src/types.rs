@@ -5,23 +5,206 @@ use std::path::PathBuf;
 use serde::Deserialize;
 use tentacle_multiaddr::MultiAddr;
 
-use core_consensus::{DEFAULT_OVERLORD_GAP, DEFAULT_SYNC_TXS_CHUNK_SIZE};
-use core_mempool::{DEFAULT_BROADCAST_TXS_INTERVAL, DEFAULT_BROADCAST_TXS_SIZE};
-use protocol::types::{Hex, H160, H256};
+use core_consensus::{
+    DEFAULT_MAX_TIMESTAMP_DRIFT, DEFAULT_OVERLORD_GAP, DEFAULT_SYNC_TXS_CHUNK_SIZE,
+};
+use core_mempool::{
+    DEFAULT_BROADCAST_DEDUP_TTL, DEFAULT_BROADCAST_TXS_INTERVAL, DEFAULT_BROADCAST_TXS_SIZE,
+};
+use protocol::types::{Hex, H160, H256, U256};
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct ConfigApi {
-    pub http_listening_address: Option<SocketAddr>,
-    pub ws_listening_address:   Option<SocketAddr>,
+    pub http_listening_address:      Option<SocketAddr>,
+    pub ws_listening_address:        Option<SocketAddr>,
     #[serde(default)]
-    pub maxconn:                usize,
+    pub maxconn:                     usize,
     #[serde(default)]
-    pub max_payload_size:       usize,
-    pub enable_dump_profile:    Option<bool>,
+    pub max_payload_size:            usize,
+    pub enable_dump_profile:         Option<bool>,
     #[serde(default)]
-    pub client_version:         String,
+    pub client_version:              String,
     #[serde(default)]
-    pub life_time:              u32,
+    pub chain_id:                    Option<u64>,
+    #[serde(default)]
+    pub life_time:                   u32,
+    #[serde(default)]
+    pub call_cache_size:             usize,
+    #[serde(default = "default_call_cache_ttl")]
+    pub call_cache_ttl:              u64,
+    #[serde(default = "default_nonce_cache_size")]
+    pub nonce_cache_size:            usize,
+    #[serde(default)]
+    pub enable_consensus_rpc:        bool,
+    #[serde(default)]
+    pub enable_personal_rpc:         bool,
+    #[serde(default)]
+    pub keystore:                    Vec<ConfigKeystoreAccount>,
+    #[serde(default = "default_ws_subscription_buffer_size")]
+    pub ws_subscription_buffer_size: usize,
+    #[serde(default)]
+    pub ws_subscription_lag_policy:  SubscriptionLagPolicy,
+    #[serde(default)]
+    pub get_logs_confirmations:      u64,
+    #[serde(default)]
+    pub max_txs_per_sender_per_sec:  u64,
+    #[serde(default)]
+    pub ws_max_conn_per_ip:          usize,
+    #[serde(default = "default_ws_max_buffered_bytes")]
+    pub ws_max_buffered_bytes:       usize,
+    #[serde(default)]
+    pub get_logs_max_block_range:    u64,
+    #[serde(default = "default_filter_max_buffered_logs")]
+    pub filter_max_buffered_logs:    usize,
+    #[serde(default)]
+    pub default_block_tag:           DefaultBlockTag,
+    #[serde(default = "default_max_concurrent_traces")]
+    pub max_concurrent_traces:       usize,
+    #[serde(default = "default_storage_range_max_result")]
+    pub storage_range_max_result:    usize,
+    #[serde(default)]
+    pub total_difficulty:            TotalDifficultyMode,
+    #[serde(default = "default_priority_fee")]
+    pub default_priority_fee:        u64,
+    #[serde(default)]
+    pub request_log:                 RequestLogLevel,
+    #[serde(default = "default_max_call_return_size")]
+    pub max_call_return_size:        usize,
+    #[serde(default)]
+    pub fee_history_interpolation:   FeeHistoryInterpolation,
+    #[serde(default)]
+    pub mining_rpc_compat:           bool,
+    #[serde(default = "default_max_bundle_calls")]
+    pub max_bundle_calls:            usize,
+}
+
+fn default_ws_subscription_buffer_size() -> usize {
+    64
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionLagPolicy {
+    /// Terminate the subscription.
+    Drop,
+    /// Skip the items that do not fit into the buffer.
+    Skip,
+}
+
+impl Default for SubscriptionLagPolicy {
+    fn default() -> Self {
+        SubscriptionLagPolicy::Skip
+    }
+}
+
+/// How the `totalDifficulty` of the blocks returned by the API is filled.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TotalDifficultyMode {
+    /// Always `null`.
+    None,
+    /// The same value for every block.
+    Constant(U256),
+    /// The sum of the difficulties of the canonical chain up to the block.
+    Sum,
+}
+
+impl Default for TotalDifficultyMode {
+    fn default() -> Self {
+        TotalDifficultyMode::Sum
+    }
+}
+
+/// How `eth_feeHistory` picks the reward at a percentile of the gas used by
+/// the transactions of a block, sorted by tip.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeHistoryInterpolation {
+    /// The tip of the first transaction at which the cumulative gas used
+    /// reaches the percentile, as geth does.
+    NearestRank,
+    /// Each transaction sits at the middle of its share of the gas used and
+    /// the tip is interpolated linearly between the two around the
+    /// percentile.
+    Linear,
+}
+
+impl Default for FeeHistoryInterpolation {
+    fn default() -> Self {
+        FeeHistoryInterpolation::NearestRank
+    }
+}
+
+/// Which calls the API logs with their method, params, status and duration.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RequestLogLevel {
+    Off,
+    /// Only the failed calls.
+    Failed,
+    All,
+}
+
+impl Default for RequestLogLevel {
+    fn default() -> Self {
+        RequestLogLevel::Off
+    }
+}
+
+/// The block of the state methods called without a block parameter.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DefaultBlockTag {
+    Latest,
+    Pending,
+}
+
+impl Default for DefaultBlockTag {
+    fn default() -> Self {
+        DefaultBlockTag::Latest
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ConfigKeystoreAccount {
+    pub privkey:  Hex,
+    pub password: String,
+}
+
+fn default_ws_max_buffered_bytes() -> usize {
+    4 * 1024 * 1024
+}
+
+fn default_call_cache_ttl() -> u64 {
+    1000
+}
+
+fn default_nonce_cache_size() -> usize {
+    1024
+}
+
+fn default_filter_max_buffered_logs() -> usize {
+    10_000
+}
+
+fn default_max_concurrent_traces() -> usize {
+    4
+}
+
+fn default_storage_range_max_result() -> usize {
+    1024
+}
+
+fn default_max_call_return_size() -> usize {
+    1024 * 1024
+}
+
+fn default_max_bundle_calls() -> usize {
+    64
+}
+
+fn default_priority_fee() -> u64 {
+    1
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -66,12 +249,19 @@ fn default_sync_txs_chunk_size() -> usize {
     DEFAULT_SYNC_TXS_CHUNK_SIZE
 }
 
+fn default_max_timestamp_drift() -> u64 {
+    DEFAULT_MAX_TIMESTAMP_DRIFT
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct ConfigConsensus {
     #[serde(default = "default_overlord_gap")]
     pub overlord_gap:        usize,
     #[serde(default = "default_sync_txs_chunk_size")]
     pub sync_txs_chunk_size: usize,
+    /// How many seconds a proposal timestamp may be ahead of the local clock.
+    #[serde(default = "default_max_timestamp_drift")]
+    pub max_timestamp_drift: u64,
 }
 
 fn default_broadcast_txs_size() -> usize {
@@ -82,6 +272,10 @@ fn default_broadcast_txs_interval() -> u64 {
     DEFAULT_BROADCAST_TXS_INTERVAL
 }
 
+fn default_broadcast_dedup_ttl() -> u64 {
+    DEFAULT_BROADCAST_DEDUP_TTL
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct ConfigMempool {
     pub pool_size:   u64,
@@ -91,12 +285,39 @@ pub struct ConfigMempool {
     pub broadcast_txs_size:     usize,
     #[serde(default = "default_broadcast_txs_interval")]
     pub broadcast_txs_interval: u64,
+    /// How many milliseconds a broadcast transaction is not gossiped again.
+    #[serde(default = "default_broadcast_dedup_ttl")]
+    pub broadcast_dedup_ttl:    u64,
+    /// A transaction whose effective tip, its priority fee capped by its fee
+    /// cap minus the base fee, reaches this many wei is gossiped right away
+    /// instead of waiting for the next batch, never when it is 0.
+    #[serde(default)]
+    pub broadcast_priority_tip: u64,
+    /// Keep the pooled transactions on disk and replay them after a restart.
+    #[serde(default)]
+    pub persist_pool:           bool,
+}
+
+fn default_call_stack_limit() -> usize {
+    1024
 }
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct ConfigExecutor {
-    pub light:             bool,
-    pub triedb_cache_size: usize,
+    pub light:              bool,
+    pub triedb_cache_size:  usize,
+    /// The EVM stack and memory limits of `eth_call`, `eth_estimateGas` and
+    /// tracing. Blocks are always executed with the protocol limits.
+    #[serde(default = "default_call_stack_limit")]
+    pub call_stack_limit:   usize,
+    #[serde(default)]
+    pub call_memory_limit:  Option<usize>,
+    /// The block from which the receipts root is an Ethereum receipts trie.
+    /// It changes the block headers, so every node of a network must set the
+    /// same one. The older blocks, or all of them if unset, keep the merkle
+    /// root of the hashes of the return data.
+    #[serde(default)]
+    pub receipts_trie_fork: Option<u64>,
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -209,4 +430,10 @@ impl Config {
         path_state.push("consensus_wal");
         path_state
     }
+
+    pub fn data_path_for_pool_journal(&self) -> PathBuf {
+        let mut path_state = self.data_path.clone();
+        path_state.push("pool_journal");
+        path_state
+    }
 }
@@ -1,10 +1,14 @@
+use std::collections::BTreeMap;
+use std::future::Future;
 use std::sync::Arc;
 
+use core_consensus::METADATA_CONTROLER;
 use core_executor::{EVMExecutorAdapter, EvmExecutor};
 use protocol::traits::{APIAdapter, Context, Executor, ExecutorAdapter, MemPool, Network, Storage};
 use protocol::types::{
-    Account, Block, BlockNumber, Bytes, ExecutorContext, Hash, Header, Proposal, Receipt,
-    SignedTransaction, TxResp, H160, U256,
+    AccessList, AccessSet, Account, AccountProof, Block, BlockNumber, BundleCall, Bytes,
+    ExecutorContext, Hash, Header, Metadata, OverlayReplay, PrestateAccount, Proof, Proposal,
+    Receipt, SignedTransaction, StorageRange, TxPoolStatus, TxPosition, TxResp, H160, H256, U256,
 };
 use protocol::{async_trait, codec::ProtocolCodec, ProtocolResult};
 
@@ -12,10 +16,11 @@ use crate::APIError;
 
 #[derive(Clone)]
 pub struct DefaultAPIAdapter<M, S, DB, Net> {
-    mempool: Arc<M>,
-    storage: Arc<S>,
-    trie_db: Arc<DB>,
-    net:     Arc<Net>,
+    mempool:  Arc<M>,
+    storage:  Arc<S>,
+    trie_db:  Arc<DB>,
+    net:      Arc<Net>,
+    executor: EvmExecutor,
 }
 
 impl<M, S, DB, Net> DefaultAPIAdapter<M, S, DB, Net>
@@ -25,12 +30,20 @@ where
     DB: cita_trie::DB + 'static,
     Net: Network + 'static,
 {
-    pub fn new(mempool: Arc<M>, storage: Arc<S>, trie_db: Arc<DB>, net: Arc<Net>) -> Self {
+    /// The `executor` runs `evm_call`, `trace_prestate` and `storage_range`.
+    pub fn new(
+        mempool: Arc<M>,
+        storage: Arc<S>,
+        trie_db: Arc<DB>,
+        net: Arc<Net>,
+        executor: EvmExecutor,
+    ) -> Self {
         Self {
             mempool,
             storage,
             trie_db,
             net,
+            executor,
         }
     }
 
@@ -100,6 +113,36 @@ where
         }
     }
 
+    async fn get_block_proof(
+        &self,
+        ctx: Context,
+        number: BlockNumber,
+    ) -> ProtocolResult<Option<Proof>> {
+        if let Some(next) = self
+            .storage
+            .get_block_header(ctx.clone(), number + 1)
+            .await?
+        {
+            return Ok(Some(next.proof));
+        }
+
+        let latest = self.storage.get_latest_proof(ctx).await?;
+        Ok((latest.number == number).then(|| latest))
+    }
+
+    async fn get_metadata(
+        &self,
+        ctx: Context,
+        number: BlockNumber,
+    ) -> ProtocolResult<Option<Metadata>> {
+        // The controller only keeps the current and the previous versions, the
+        // older ones are read from the storage.
+        match METADATA_CONTROLER.load().get(number) {
+            Some(metadata) => Ok(Some(metadata)),
+            None => self.storage.get_metadata(ctx, number).await,
+        }
+    }
+
     async fn get_receipt_by_tx_hash(
         &self,
         ctx: Context,
@@ -127,6 +170,14 @@ where
         self.storage.get_transaction_by_hash(ctx, &tx_hash).await
     }
 
+    async fn get_transaction_position(
+        &self,
+        ctx: Context,
+        tx_hash: Hash,
+    ) -> ProtocolResult<Option<TxPosition>> {
+        self.storage.get_transaction_position(ctx, &tx_hash).await
+    }
+
     async fn get_transactions_by_hashes(
         &self,
         ctx: Context,
@@ -152,14 +203,36 @@ where
         Account::decode(bytes)
     }
 
+    async fn get_proof(
+        &self,
+        _ctx: Context,
+        address: H160,
+        storage_keys: Vec<H256>,
+        number: Option<BlockNumber>,
+    ) -> ProtocolResult<AccountProof> {
+        self.evm_backend(number)
+            .await?
+            .account_proof(address, &storage_keys)
+    }
+
     async fn evm_call(
         &self,
         _ctx: Context,
+        from: Option<H160>,
         address: H160,
+        value: U256,
         data: Vec<u8>,
+        access_list: AccessList,
         state_root: Hash,
         mock_header: Proposal,
     ) -> ProtocolResult<TxResp> {
+        // The gas limit of the mock header is the `gas` of the call, or the
+        // block gas limit if it is omitted.
+        let gas_limit = if mock_header.gas_limit > U256::from(u64::MAX) {
+            u64::MAX
+        } else {
+            mock_header.gas_limit.as_u64()
+        };
         let mut backend = EVMExecutorAdapter::from_root(
             state_root,
             Arc::clone(&self.trie_db),
@@ -167,7 +240,139 @@ where
             ExecutorContext::from(mock_header),
         )?;
 
-        Ok(EvmExecutor::default().call(&mut backend, address, data))
+        Ok(self.executor.call(
+            &mut backend,
+            from,
+            address,
+            value,
+            gas_limit,
+            data,
+            access_list,
+        ))
+    }
+
+    async fn simulate_access_set(
+        &self,
+        _ctx: Context,
+        from: Option<H160>,
+        address: H160,
+        value: U256,
+        data: Vec<u8>,
+        access_list: AccessList,
+        state_root: Hash,
+        mock_header: Proposal,
+    ) -> ProtocolResult<AccessSet> {
+        let gas_limit = if mock_header.gas_limit > U256::from(u64::MAX) {
+            u64::MAX
+        } else {
+            mock_header.gas_limit.as_u64()
+        };
+        let backend = EVMExecutorAdapter::from_root(
+            state_root,
+            Arc::clone(&self.trie_db),
+            Arc::clone(&self.storage),
+            ExecutorContext::from(mock_header),
+        )?;
+
+        Ok(self
+            .executor
+            .access_set(&backend, from, address, value, gas_limit, data, access_list))
+    }
+
+    async fn simulate_bundle(
+        &self,
+        _ctx: Context,
+        calls: Vec<BundleCall>,
+        state_root: Hash,
+        proposal: Proposal,
+    ) -> ProtocolResult<Vec<TxResp>> {
+        let backend = EVMExecutorAdapter::from_root(
+            state_root,
+            Arc::clone(&self.trie_db),
+            Arc::clone(&self.storage),
+            ExecutorContext::from(proposal),
+        )?;
+
+        Ok(self.executor.call_bundle(&backend, calls))
+    }
+
+    async fn trace_prestate(
+        &self,
+        _ctx: Context,
+        preceding: Vec<SignedTransaction>,
+        tx: SignedTransaction,
+        state_root: Hash,
+        proposal: Proposal,
+    ) -> ProtocolResult<BTreeMap<H160, PrestateAccount>> {
+        let backend = EVMExecutorAdapter::from_root(
+            state_root,
+            Arc::clone(&self.trie_db),
+            Arc::clone(&self.storage),
+            ExecutorContext::from(proposal),
+        )?;
+
+        Ok(self.executor.trace_prestate(&backend, preceding, tx))
+    }
+
+    async fn storage_range(
+        &self,
+        _ctx: Context,
+        preceding: Vec<SignedTransaction>,
+        address: H160,
+        start: H256,
+        max_result: usize,
+        state_root: Hash,
+        proposal: Proposal,
+    ) -> ProtocolResult<StorageRange> {
+        let backend = EVMExecutorAdapter::from_root(
+            state_root,
+            Arc::clone(&self.trie_db),
+            Arc::clone(&self.storage),
+            ExecutorContext::from(proposal),
+        )?;
+
+        Ok(self
+            .executor
+            .storage_range(&backend, preceding, address, start, max_result))
+    }
+
+    async fn replay_account(
+        &self,
+        _ctx: Context,
+        preceding: Vec<SignedTransaction>,
+        address: H160,
+        slots: Vec<H256>,
+        state_root: Hash,
+        proposal: Proposal,
+    ) -> ProtocolResult<(Bytes, Vec<H256>)> {
+        let backend = EVMExecutorAdapter::from_root(
+            state_root,
+            Arc::clone(&self.trie_db),
+            Arc::clone(&self.storage),
+            ExecutorContext::from(proposal),
+        )?;
+
+        let (code, storage) = self
+            .executor
+            .replay_account(&backend, preceding, address, &slots);
+        Ok((Bytes::from(code), storage))
+    }
+
+    async fn replay_overlay(
+        &self,
+        _ctx: Context,
+        txs: Vec<SignedTransaction>,
+        state_root: Hash,
+        proposal: Proposal,
+    ) -> ProtocolResult<OverlayReplay> {
+        let backend = EVMExecutorAdapter::from_root(
+            state_root,
+            Arc::clone(&self.trie_db),
+            Arc::clone(&self.storage),
+            ExecutorContext::from(proposal),
+        )?;
+
+        Ok(self.executor.replay_overlay(&backend, txs))
     }
 
     async fn get_code_by_hash(&self, ctx: Context, hash: &Hash) -> ProtocolResult<Option<Bytes>> {
@@ -181,4 +386,64 @@ where
     async fn get_number_by_hash(&self, ctx: Context, hash: Hash) -> ProtocolResult<Option<u64>> {
         self.storage.get_number_by_hash(ctx, &hash).await
     }
+
+    async fn get_oldest_available_block(&self, ctx: Context) -> ProtocolResult<BlockNumber> {
+        let latest = self
+            .storage
+            .get_latest_block_header(ctx.clone())
+            .await?
+            .number;
+        lowest_stored_block(latest, |number| {
+            let ctx = ctx.clone();
+            async move { Ok(self.storage.get_block_header(ctx, number).await?.is_some()) }
+        })
+        .await
+    }
+
+    async fn get_pool_tx_status(
+        &self,
+        ctx: Context,
+        tx_hash: Hash,
+    ) -> ProtocolResult<Option<TxPoolStatus>> {
+        Ok(self.mempool.get_tx_status(ctx, &tx_hash))
+    }
+
+    async fn get_pending_txs(&self, ctx: Context) -> ProtocolResult<Vec<SignedTransaction>> {
+        Ok(self.mempool.get_pending_txs(ctx))
+    }
+
+    async fn get_pool_txs_inserted_since(
+        &self,
+        ctx: Context,
+        cursor: Option<u64>,
+    ) -> ProtocolResult<(u64, Vec<Hash>)> {
+        Ok(self.mempool.get_txs_inserted_since(ctx, cursor))
+    }
+}
+
+/// The lowest block up to `latest` for which `is_stored` holds. The blocks are
+/// kept from some height up to the latest one, so it is found by a binary
+/// search, after checking the genesis block which an archive node keeps.
+pub(crate) async fn lowest_stored_block<F, Fut>(
+    latest: BlockNumber,
+    is_stored: F,
+) -> ProtocolResult<BlockNumber>
+where
+    F: Fn(BlockNumber) -> Fut,
+    Fut: Future<Output = ProtocolResult<bool>>,
+{
+    if is_stored(0).await? {
+        return Ok(0);
+    }
+
+    let (mut low, mut high) = (1, latest);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if is_stored(mid).await? {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    Ok(high)
 }
@@ -0,0 +1,97 @@
+use std::time::{Duration, Instant};
+
+use protocol::types::{BlockNumber, Hash, Hasher};
+
+use crate::jsonrpc::lru::LruCache;
+use crate::jsonrpc::web3_types::Web3CallRequest;
+
+type CacheKey = (Hash, BlockNumber);
+
+/// A small LRU cache of `eth_call` results keyed by `(call_request_hash,
+/// block_number)`. Entries expire after `ttl`, and the whole cache is dropped
+/// once a newer block is observed.
+pub struct CallCache {
+    ttl:          Duration,
+    latest_block: BlockNumber,
+    entries:      LruCache<CacheKey, (Instant, Vec<u8>)>,
+}
+
+impl CallCache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        CallCache {
+            ttl,
+            latest_block: 0,
+            entries: LruCache::new(capacity),
+        }
+    }
+
+    pub fn call_request_hash(req: &Web3CallRequest) -> Hash {
+        Hasher::digest(serde_json::to_vec(req).unwrap_or_default())
+    }
+
+    /// Invalidate every cached result when a newer block than the ones seen
+    /// so far shows up.
+    pub fn observe_block(&mut self, number: BlockNumber) {
+        if number > self.latest_block {
+            self.latest_block = number;
+            self.entries.clear();
+        }
+    }
+
+    pub fn get(&mut self, key: &CacheKey) -> Option<Vec<u8>> {
+        let (inserted_at, _) = self.entries.peek(key)?;
+        if inserted_at.elapsed() > self.ttl {
+            self.entries.remove(key);
+            return None;
+        }
+
+        self.entries.get(key).map(|(_, ret)| ret.clone())
+    }
+
+    pub fn insert(&mut self, key: CacheKey, ret: Vec<u8>) {
+        self.entries.insert(key, (Instant::now(), ret));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(n: u8, number: BlockNumber) -> CacheKey {
+        (Hasher::digest([n]), number)
+    }
+
+    #[test]
+    fn test_evict_least_recently_used() {
+        let mut cache = CallCache::new(2, Duration::from_secs(60));
+        cache.insert(key(1, 1), vec![1]);
+        cache.insert(key(2, 1), vec![2]);
+        assert_eq!(cache.get(&key(1, 1)), Some(vec![1]));
+
+        cache.insert(key(3, 1), vec![3]);
+        assert_eq!(cache.entries.len(), 2);
+        assert_eq!(cache.get(&key(2, 1)), None);
+        assert_eq!(cache.get(&key(1, 1)), Some(vec![1]));
+    }
+
+    #[test]
+    fn test_invalidate_on_new_block() {
+        let mut cache = CallCache::new(2, Duration::from_secs(60));
+        cache.observe_block(1);
+        cache.insert(key(1, 1), vec![1]);
+        cache.observe_block(1);
+        assert_eq!(cache.get(&key(1, 1)), Some(vec![1]));
+
+        cache.observe_block(2);
+        assert_eq!(cache.get(&key(1, 1)), None);
+    }
+
+    #[test]
+    fn test_expire_by_ttl() {
+        let mut cache = CallCache::new(2, Duration::from_millis(0));
+        cache.insert(key(1, 1), vec![1]);
+        std::thread::sleep(Duration::from_millis(1));
+        assert_eq!(cache.get(&key(1, 1)), None);
+        assert!(cache.entries.is_empty());
+    }
+}
@@ -0,0 +1,33 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use common_metrics_derive::metrics_rpc;
+use core_consensus::{ConsensusStatus, CONSENSUS_STATUS};
+use protocol::async_trait;
+
+use crate::jsonrpc::web3_types::Web3ConsensusStatus;
+use crate::jsonrpc::{AxonConsensusRpcServer, RpcResult};
+
+pub struct ConsensusRpcImpl;
+
+#[async_trait]
+impl AxonConsensusRpcServer for ConsensusRpcImpl {
+    #[metrics_rpc("axon_consensusStatus")]
+    async fn consensus_status(&self) -> RpcResult<Web3ConsensusStatus> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+
+        Ok(to_web3_status(CONSENSUS_STATUS.read().clone(), now))
+    }
+}
+
+pub fn to_web3_status(status: ConsensusStatus, now: u64) -> Web3ConsensusStatus {
+    Web3ConsensusStatus {
+        height:                 status.height.into(),
+        round:                  status.round.into(),
+        last_committed_height:  status.last_committed_height.into(),
+        proposer:               status.last_proposer,
+        time_since_last_commit: now.saturating_sub(status.last_commit_time).into(),
+    }
+}
@@ -0,0 +1,183 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use jsonrpsee::core::Error;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use protocol::types::{Hash, Hasher, Hex, H160, H256, U256};
+
+use crate::jsonrpc::RpcResult;
+
+const DOMAIN_TYPE: &str = "EIP712Domain";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TypedDataField {
+    pub name:  String,
+    #[serde(rename = "type")]
+    pub type_: String,
+}
+
+/// The `eth_signTypedData_v4` payload, see
+/// [EIP-712](https://eips.ethereum.org/EIPS/eip-712).
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TypedData {
+    pub types:        BTreeMap<String, Vec<TypedDataField>>,
+    pub primary_type: String,
+    pub domain:       Value,
+    pub message:      Value,
+}
+
+impl TypedData {
+    /// `keccak256("\x19\x01" ‖ domainSeparator ‖ hashStruct(message))`
+    pub fn sign_hash(&self) -> RpcResult<Hash> {
+        let domain_separator = self.hash_struct(DOMAIN_TYPE, &self.domain)?;
+        let message_hash = self.hash_struct(&self.primary_type, &self.message)?;
+
+        let mut data = vec![0x19, 0x01];
+        data.extend_from_slice(domain_separator.as_bytes());
+        data.extend_from_slice(message_hash.as_bytes());
+        Ok(Hasher::digest(data))
+    }
+
+    pub fn hash_struct(&self, name: &str, value: &Value) -> RpcResult<Hash> {
+        Ok(Hasher::digest(self.encode_data(name, value)?))
+    }
+
+    /// The type itself followed by the types it references, sorted by name.
+    pub fn encode_type(&self, name: &str) -> RpcResult<String> {
+        let mut deps = BTreeSet::new();
+        self.find_dependencies(name, &mut deps)?;
+        deps.remove(name);
+
+        let mut ret = String::new();
+        for dep in std::iter::once(name).chain(deps.iter().map(String::as_str)) {
+            let fields = self
+                .fields(dep)?
+                .iter()
+                .map(|f| format!("{} {}", f.type_, f.name))
+                .collect::<Vec<_>>();
+            ret.push_str(&format!("{}({})", dep, fields.join(",")));
+        }
+
+        Ok(ret)
+    }
+
+    fn find_dependencies(&self, name: &str, deps: &mut BTreeSet<String>) -> RpcResult<()> {
+        if deps.contains(name) {
+            return Ok(());
+        }
+
+        deps.insert(name.to_string());
+        for field in self.fields(name)? {
+            let base = base_type(&field.type_);
+            if self.types.contains_key(base) {
+                self.find_dependencies(base, deps)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn fields(&self, name: &str) -> RpcResult<&Vec<TypedDataField>> {
+        self.types
+            .get(name)
+            .ok_or_else(|| Error::Custom(format!("Unknown type {}", name)))
+    }
+
+    fn encode_data(&self, name: &str, value: &Value) -> RpcResult<Vec<u8>> {
+        let mut ret = Hasher::digest(self.encode_type(name)?).as_bytes().to_vec();
+
+        for field in self.fields(name)? {
+            let field_value = value.get(&field.name).unwrap_or(&Value::Null);
+            let encoded = self.encode_value(&field.type_, field_value)?;
+            ret.extend_from_slice(encoded.as_bytes());
+        }
+
+        Ok(ret)
+    }
+
+    fn encode_value(&self, ty: &str, value: &Value) -> RpcResult<H256> {
+        let invalid = || Error::Custom(format!("Invalid {} value {}", ty, value));
+
+        if let Some(array) = ty.strip_suffix(']') {
+            let item_ty = &array[..array.rfind('[').ok_or_else(invalid)?];
+            let mut data = Vec::new();
+            for item in value.as_array().ok_or_else(invalid)? {
+                data.extend_from_slice(self.encode_value(item_ty, item)?.as_bytes());
+            }
+            return Ok(Hasher::digest(data));
+        }
+
+        if self.types.contains_key(ty) {
+            return self.hash_struct(ty, value);
+        }
+
+        match ty {
+            "string" => Ok(Hasher::digest(value.as_str().ok_or_else(invalid)?)),
+            "bytes" => Ok(Hasher::digest(parse_bytes(value).ok_or_else(invalid)?)),
+            "bool" => Ok(H256::from_low_u64_be(
+                value.as_bool().ok_or_else(invalid)? as u64
+            )),
+            "address" => {
+                let address: H160 = serde_json::from_value(value.clone()).map_err(|_| invalid())?;
+                Ok(address.into())
+            }
+            _ if ty.starts_with("uint") => Ok(u256_to_h256(parse_uint(value).ok_or_else(invalid)?)),
+            _ if ty.starts_with("int") => Ok(u256_to_h256(parse_int(value).ok_or_else(invalid)?)),
+            _ if ty.starts_with("bytes") => {
+                let bytes = parse_bytes(value).ok_or_else(invalid)?;
+                if bytes.len() > 32 {
+                    return Err(invalid());
+                }
+
+                let mut ret = H256::zero();
+                ret.as_bytes_mut()[..bytes.len()].copy_from_slice(&bytes);
+                Ok(ret)
+            }
+            _ => Err(Error::Custom(format!("Unknown type {}", ty))),
+        }
+    }
+}
+
+fn base_type(ty: &str) -> &str {
+    ty.split('[').next().unwrap_or(ty)
+}
+
+fn u256_to_h256(value: U256) -> H256 {
+    let mut ret = H256::zero();
+    value.to_big_endian(ret.as_bytes_mut());
+    ret
+}
+
+fn parse_bytes(value: &Value) -> Option<Vec<u8>> {
+    Hex::decode(value.as_str()?.to_string())
+        .ok()
+        .map(|b| b.to_vec())
+}
+
+fn parse_uint(value: &Value) -> Option<U256> {
+    match value {
+        Value::Number(n) => n.as_u64().map(U256::from),
+        Value::String(s) => match s.strip_prefix("0x") {
+            Some(hex) => U256::from_str_radix(hex, 16).ok(),
+            None => U256::from_dec_str(s).ok(),
+        },
+        _ => None,
+    }
+}
+
+/// Negative numbers are encoded in two's complement.
+fn parse_int(value: &Value) -> Option<U256> {
+    let negate = |abs: U256| (!abs).overflowing_add(U256::one()).0;
+
+    match value {
+        Value::Number(n) if n.as_i64().map(|i| i < 0).unwrap_or(false) => {
+            Some(negate(U256::from(n.as_i64()?.unsigned_abs())))
+        }
+        Value::String(s) if s.starts_with('-') => {
+            parse_uint(&Value::String(s[1..].to_string())).map(negate)
+        }
+        _ => parse_uint(value),
+    }
+}
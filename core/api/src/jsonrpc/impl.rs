@@ -1,73 +1,745 @@
-use std::collections::{BTreeSet, VecDeque};
+use std::collections::{BTreeMap, VecDeque};
 use std::sync::Arc;
+use std::time::Duration;
 
 use async_std::task::block_on;
 use jsonrpsee::core::Error;
+use jsonrpsee::types::error::CallError;
 use parking_lot::Mutex;
 
+use common_config_parser::types::{
+    ConfigApi, DefaultBlockTag, FeeHistoryInterpolation, TotalDifficultyMode,
+};
 use common_metrics_derive::metrics_rpc;
-use core_consensus::SYNC_STATUS;
+use core_consensus::{util::verify_block_proof, BLOCK_IMPORT_NOTIFIER, SYNC_STATUS};
+use core_executor::call_error_message;
+use core_mempool::{is_tx_broadcast, PoolTxEvent, POOL_TX_NOTIFIER, TX_BROADCAST_NOTIFIER};
+use protocol::tokio::{
+    self,
+    sync::{
+        broadcast::{
+            self,
+            error::{RecvError, TryRecvError},
+        },
+        Semaphore, SemaphorePermit,
+    },
+};
 use protocol::traits::{APIAdapter, Context};
 use protocol::types::{
-    Block, BlockNumber, Bytes, Hash, Hasher, Header, Hex, Receipt, SignedTransaction, TxResp,
-    UnverifiedTransaction, H160, H256, H64, U256,
+    Account, Block, BlockNumber, BundleCall, Bytes, Hash, Hasher, Header, Hex, Receipt,
+    SignedTransaction, TxResp, UnverifiedTransaction, H160, H256, H64, U256, U64,
 };
 use protocol::{async_trait, codec::ProtocolCodec, ProtocolResult};
 
-use crate::jsonrpc::poll_filter::{PollFilter, SyncPollFilter};
+use crate::jsonrpc::call_cache::CallCache;
+use crate::jsonrpc::nonce_cache::NonceCache;
+use crate::jsonrpc::pending_state::{PendingLog, PendingReplay, PendingState};
+use crate::jsonrpc::poll_filter::{remember_reported, BlockFilterHook, PollFilter, SyncPollFilter};
 use crate::jsonrpc::poll_manager::PollManager;
+use crate::jsonrpc::rate_limit::SenderRateLimiter;
 use crate::jsonrpc::web3_types::{
-    BlockId, ChangeWeb3Filter, Filter, FilterChanges, Index, RichTransactionOrHash, WEB3Work,
-    Web3Block, Web3CallRequest, Web3FeeHistory, Web3Filter, Web3Log, Web3Receipt, Web3SyncStatus,
-    Web3Transaction,
+    seal_fields, BlockId, ChangeWeb3Filter, Filter, FilterChanges, Index, LogOrder,
+    RichTransactionOrHash, TraceOptions, VariadicValue, Web3AccessSet, Web3AccountProof, Web3Block,
+    Web3CallRequest, Web3CallResult, Web3CallWithLogs, Web3FeeHistory, Web3Filter, Web3Log,
+    Web3PrestateAccount, Web3Receipt, Web3StorageKey, Web3StorageRange, Web3SyncStatus,
+    Web3Transaction, Web3TxPropagation, Web3TxStatus,
 };
 use crate::jsonrpc::{AxonJsonRpcServer, RpcResult};
 use crate::APIError;
 
+/// The EIP-1474 error code for a request over a limit.
+const LIMIT_EXCEEDED_CODE: i32 = -32005;
+const METHOD_NOT_SUPPORTED_CODE: i32 = -32004;
+const MAX_SUGGESTED_RANGES: usize = 100;
+/// The most blocks a single `eth_feeHistory` request covers, as in geth.
+const MAX_FEE_HISTORY_BLOCKS: u64 = 1024;
+/// The number of the recent blocks the fee suggestions are taken from.
+const FEE_SUGGESTION_BLOCKS: u64 = 20;
+/// The total difficulty is cached at every block whose number is a multiple
+/// of this, so a sum only walks the headers since the last such block.
+const TOTAL_DIFFICULTY_INTERVAL: u64 = 1024;
+/// The default and the longest wait of `axon_sendRawTransactionSync`, in
+/// milliseconds.
+const DEFAULT_PROPAGATION_TIMEOUT: u64 = 5_000;
+const MAX_PROPAGATION_TIMEOUT: u64 = 60_000;
+
 pub struct JsonRpcImpl<Adapter> {
-    adapter: Arc<Adapter>,
-    version: String,
-    polls:   Mutex<PollManager<SyncPollFilter>>,
+    adapter:     Arc<Adapter>,
+    version:     String,
+    polls:       Mutex<PollManager<SyncPollFilter>>,
+    block_hook:  Arc<BlockFilterHook>,
+    call_cache:  Option<Mutex<CallCache>>,
+    nonce_cache: Option<Mutex<NonceCache>>,
+
+    log_confirmations:   u64,
+    log_max_block_range: u64,
+    filter_max_logs:     usize,
+    sender_rate_limiter: Option<SenderRateLimiter>,
+    pending_state:       Mutex<Option<Arc<PendingState>>>,
+    pool_events:         Mutex<broadcast::Receiver<PoolTxEvent>>,
+    default_block:       BlockId,
+    trace_permits:       Option<Semaphore>,
+    max_storage_range:   usize,
+    chain_id:            Option<u64>,
+    total_difficulty:    TotalDifficultyMode,
+    // The hash and the total difficulty of a block by number, at every
+    // `TOTAL_DIFFICULTY_INTERVAL` blocks.
+    td_checkpoints:      Mutex<BTreeMap<BlockNumber, (Hash, U256)>>,
+    default_tip:         U256,
+    max_return_size:     usize,
+    fee_interpolation:   FeeHistoryInterpolation,
+    mining_compat:       bool,
+    max_bundle_calls:    usize,
 }
 
 impl<Adapter: APIAdapter> JsonRpcImpl<Adapter> {
-    pub fn new(adapter: Arc<Adapter>, version: &str, poll_lifetime: u32) -> Self {
+    pub fn new(adapter: Arc<Adapter>, config: &ConfigApi) -> Self {
+        let call_cache = (config.call_cache_size > 0).then(|| {
+            Mutex::new(CallCache::new(
+                config.call_cache_size,
+                Duration::from_millis(config.call_cache_ttl),
+            ))
+        });
+
         Self {
             adapter,
-            version: version.to_string(),
-            polls: Mutex::new(PollManager::new(poll_lifetime)),
+            version: config.client_version.clone(),
+            polls: Mutex::new(PollManager::new(config.life_time)),
+            block_hook: Arc::new(BlockFilterHook::default()),
+            call_cache,
+            nonce_cache: (config.nonce_cache_size > 0)
+                .then(|| Mutex::new(NonceCache::new(config.nonce_cache_size))),
+            log_confirmations: config.get_logs_confirmations,
+            log_max_block_range: config.get_logs_max_block_range,
+            filter_max_logs: config.filter_max_buffered_logs,
+            sender_rate_limiter: (config.max_txs_per_sender_per_sec > 0)
+                .then(|| SenderRateLimiter::new(config.max_txs_per_sender_per_sec)),
+            pending_state: Mutex::new(None),
+            pool_events: Mutex::new(POOL_TX_NOTIFIER.subscribe()),
+            default_block: match config.default_block_tag {
+                DefaultBlockTag::Latest => BlockId::Latest,
+                DefaultBlockTag::Pending => BlockId::Pending,
+            },
+            trace_permits: (config.max_concurrent_traces > 0)
+                .then(|| Semaphore::new(config.max_concurrent_traces)),
+            max_storage_range: config.storage_range_max_result,
+            chain_id: config.chain_id,
+            total_difficulty: config.total_difficulty,
+            td_checkpoints: Mutex::new(BTreeMap::new()),
+            default_tip: config.default_priority_fee.into(),
+            max_return_size: config.max_call_return_size,
+            fee_interpolation: config.fee_history_interpolation,
+            mining_compat: config.mining_rpc_compat,
+            max_bundle_calls: config.max_bundle_calls,
         }
     }
 
-    async fn call_evm(
-        &self,
-        req: Web3CallRequest,
-        data: Bytes,
-        number: Option<u64>,
-    ) -> ProtocolResult<TxResp> {
+    /// Decode the raw transaction and insert it into the pool, returning its
+    /// hash.
+    async fn submit_raw_tx(&self, tx: Hex) -> RpcResult<H256> {
+        let utx = UnverifiedTransaction::decode(&tx.as_bytes()[1..])
+            .map_err(|e| Error::Custom(e.to_string()))?
+            .hash();
+        let stx = SignedTransaction::try_from(utx).map_err(|e| Error::Custom(e.to_string()))?;
+
+        if let Some(limiter) = self.sender_rate_limiter.as_ref() {
+            if !limiter.try_acquire(stx.sender) {
+                return Err(Error::Custom(format!(
+                    "too many transactions from sender {:?}",
+                    stx.sender
+                )));
+            }
+        }
+
+        let hash = stx.transaction.hash;
+        self.adapter
+            .insert_signed_txs(Context::new(), stx)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+
+        Ok(hash)
+    }
+
+    async fn get_header(&self, number: Option<u64>) -> ProtocolResult<Header> {
         let header = self
             .adapter
             .get_block_header_by_number(Context::new(), number)
             .await?
             .ok_or_else(|| APIError::Storage(format!("Cannot get {:?} header", number)))?;
+        Ok(header)
+    }
+
+    /// Whether a transaction was inserted into or removed from the pool
+    /// since the last call. Missed events count as a change.
+    fn pool_changed(&self) -> bool {
+        let mut events = self.pool_events.lock();
+        let mut changed = false;
+        loop {
+            match events.try_recv() {
+                Ok(_) | Err(TryRecvError::Lagged(_)) => changed = true,
+                Err(TryRecvError::Empty) | Err(TryRecvError::Closed) => return changed,
+            }
+        }
+    }
+
+    /// Return the snapshot of the pool transactions on top of the latest
+    /// block. It is built lazily and rebuilt once a new block is committed or
+    /// the pool changes.
+    async fn pending_state(&self) -> RpcResult<Arc<PendingState>> {
+        let header = self
+            .get_header(None)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+        let block_hash = header.hash();
+
+        if self.pool_changed() {
+            self.pending_state.lock().take();
+        }
+
+        if let Some(state) = self.pending_state.lock().as_ref() {
+            if state.block_hash() == block_hash {
+                return Ok(Arc::clone(state));
+            }
+        }
+
+        let txs = self
+            .adapter
+            .get_pending_txs(Context::new())
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+        let state = Arc::new(PendingState::new(
+            block_hash,
+            header.number,
+            header.gas_limit,
+            &txs,
+        ));
+        *self.pending_state.lock() = Some(Arc::clone(&state));
+
+        Ok(state)
+    }
+
+    /// Return the code of `address` and the values of its `slots` once the
+    /// transactions of the pending block are executed. They are executed once
+    /// per pending snapshot, the code and slots they leave untouched are read
+    /// from the block the snapshot is built on.
+    async fn pending_account_state(
+        &self,
+        address: H160,
+        slots: Vec<H256>,
+    ) -> RpcResult<(Bytes, Vec<H256>)> {
+        let state = self.pending_state().await?;
+        let overlay = state
+            .replay(|| self.execute_pending(&state))
+            .await?
+            .overlay
+            .get(&address);
+
+        let pending_code = overlay.and_then(|account| account.code.clone());
+        let pending_slot = |slot: &H256| {
+            overlay.and_then(|account| match account.storage.get(slot) {
+                Some(value) => Some(*value),
+                None if account.reset_storage => Some(H256::zero()),
+                None => None,
+            })
+        };
+        if let Some(code) = pending_code.as_ref() {
+            if slots.iter().all(|slot| pending_slot(slot).is_some()) {
+                let values = slots.iter().filter_map(pending_slot).collect();
+                return Ok((Bytes::from(code.clone()), values));
+            }
+        }
+
+        let header = self
+            .get_header(Some(state.block_number()))
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+        let (code, values) = self
+            .adapter
+            .replay_account(
+                Context::new(),
+                Vec::new(),
+                address,
+                slots.clone(),
+                header.state_root,
+                header.into(),
+            )
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+        let values = slots
+            .iter()
+            .zip(values.into_iter())
+            .map(|(slot, value)| pending_slot(slot).unwrap_or(value))
+            .collect();
+
+        Ok((pending_code.map_or(code, Bytes::from), values))
+    }
+
+    /// Execute the transactions of the pending block one after another on top
+    /// of the state the snapshot is built on, so that a transaction sees the
+    /// changes of the preceding ones. It takes a trace permit as a trace does.
+    async fn execute_pending(&self, state: &PendingState) -> RpcResult<PendingReplay> {
+        let _permit = self.acquire_trace_permit()?;
+        let header = self
+            .get_header(Some(state.block_number()))
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+
+        let replay = self
+            .adapter
+            .replay_overlay(
+                Context::new(),
+                state.packed_txs().to_vec(),
+                header.state_root,
+                header.into(),
+            )
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+
+        let mut logs = Vec::new();
+        for (tx_index, (stx, tx_logs)) in state
+            .packed_txs()
+            .iter()
+            .zip(replay.logs.into_iter())
+            .enumerate()
+        {
+            for log in tx_logs.into_iter() {
+                logs.push(PendingLog {
+                    tx_hash: stx.transaction.hash,
+                    tx_index,
+                    log_index: logs.len(),
+                    log,
+                });
+            }
+        }
+
+        Ok(PendingReplay {
+            overlay: replay.overlay,
+            logs,
+        })
+    }
+
+    /// Return the pending snapshot along with the account read at the block
+    /// it is built on.
+    async fn pending_account(&self, address: H160) -> RpcResult<(Arc<PendingState>, Account)> {
+        let state = self.pending_state().await?;
+        let account = self
+            .adapter
+            .get_account(Context::new(), address, Some(state.block_number()))
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+
+        Ok((state, account))
+    }
+
+    async fn total_difficulty(&self, block: &Web3Block) -> RpcResult<Option<U256>> {
+        match self.total_difficulty {
+            TotalDifficultyMode::None => Ok(None),
+            TotalDifficultyMode::Constant(td) => Ok(Some(td)),
+            TotalDifficultyMode::Sum => {
+                let number = block.number.low_u64();
+                if number == 0 {
+                    return Ok(Some(block.difficulty));
+                }
+
+                let parent_td = self.canonical_total_difficulty(number - 1).await?;
+                Ok(Some(parent_td.saturating_add(block.difficulty)))
+            }
+        }
+    }
+
+    /// The sum of the difficulties of the canonical blocks up to `number`.
+    /// It starts from the nearest checkpoint still on the canonical chain,
+    /// or the genesis block, and adds the checkpoints it walks past.
+    async fn canonical_total_difficulty(&self, number: BlockNumber) -> RpcResult<U256> {
+        let read_header = |number: BlockNumber| async move {
+            self.get_header(Some(number))
+                .await
+                .map_err(|e| Error::Custom(e.to_string()))
+        };
+
+        let mut start = None;
+        loop {
+            let checkpoint = self
+                .td_checkpoints
+                .lock()
+                .range(..=number)
+                .next_back()
+                .map(|(n, checkpoint)| (*n, *checkpoint));
+            let (height, (hash, td)) = match checkpoint {
+                Some(checkpoint) => checkpoint,
+                None => break,
+            };
+
+            // A checkpoint of a block reorged out is dropped.
+            if read_header(height).await?.hash() == hash {
+                start = Some((height, td));
+                break;
+            }
+            self.td_checkpoints.lock().retain(|n, _| *n < height);
+        }
+
+        let (mut height, mut td) = match start {
+            Some(start) => start,
+            None => (0, read_header(0).await?.difficulty),
+        };
+        while height < number {
+            height += 1;
+            let header = read_header(height).await?;
+            td = td.saturating_add(header.difficulty);
+            if height % TOTAL_DIFFICULTY_INTERVAL == 0 {
+                self.td_checkpoints
+                    .lock()
+                    .insert(height, (header.hash(), td));
+            }
+        }
+
+        Ok(td)
+    }
+
+    /// The seal of block `number`, built from its own proof rather than the
+    /// one of its parent carried by its header.
+    async fn seal_fields(&self, number: BlockNumber) -> RpcResult<Vec<Bytes>> {
+        let proof = self
+            .adapter
+            .get_block_proof(Context::new(), number)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+
+        Ok(proof.as_ref().map(seal_fields).unwrap_or_default())
+    }
+
+    /// Return block `number`, which is `head` itself when the number is the
+    /// head's. A scan ending at a pinned head is then not affected by a reorg
+    /// after the head was read.
+    async fn block_at(&self, head: Block, number: BlockNumber) -> RpcResult<Block> {
+        if number >= head.header.number {
+            return Ok(head);
+        }
+
+        self.adapter
+            .get_block_by_number(Context::new(), Some(number))
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?
+            .ok_or_else(|| Error::Custom(format!("Cannot get block {}", number)))
+    }
+
+    /// Return the blocks numbered `start..=end` in ascending order. Only the
+    /// block `end` is resolved by number, the others by following the parent
+    /// hashes from it, so they always belong to one fork and the walk never
+    /// goes past the range.
+    async fn canonical_blocks(
+        &self,
+        head: Block,
+        start: BlockNumber,
+        end: BlockNumber,
+    ) -> RpcResult<Vec<Block>> {
+        if start > end {
+            return Ok(Vec::new());
+        }
+
+        let mut blocks = Vec::new();
+        let mut current = self.block_at(head, end).await?;
+
+        loop {
+            let number = current.header.number;
+            let prev_hash = current.header.prev_hash;
+            blocks.push(current);
+
+            if number <= start || number == 0 {
+                break;
+            }
+
+            current = self
+                .adapter
+                .get_block_by_hash(Context::new(), prev_hash)
+                .await
+                .map_err(|e| Error::Custom(e.to_string()))?
+                .ok_or_else(|| Error::Custom(format!("Cannot get block {:?}", prev_hash)))?;
+        }
 
+        blocks.reverse();
+        Ok(blocks)
+    }
+
+    async fn call_evm(
+        &self,
+        req: Web3CallRequest,
+        data: Bytes,
+        header: Header,
+    ) -> ProtocolResult<TxResp> {
         let mock_header = mock_header_by_call_req(header, &req);
 
         self.adapter
             .evm_call(
                 Context::new(),
+                req.from,
                 req.to,
+                req.value.unwrap_or_default(),
                 data.to_vec(),
+                req.access_list.unwrap_or_default(),
                 mock_header.state_root,
                 mock_header.into(),
             )
             .await
     }
 
+    /// Refuse to serve the `what` of a call or a trace, the return data or
+    /// its revert message included, when it is larger than the configured
+    /// limit. The check comes before the output is decoded or encoded.
+    fn check_return_size(&self, what: &str, size: usize) -> RpcResult<()> {
+        match self.return_size_error(what, size) {
+            Some(e) => Err(Error::Custom(e)),
+            None => Ok(()),
+        }
+    }
+
+    fn return_size_error(&self, what: &str, size: usize) -> Option<String> {
+        (self.max_return_size > 0 && size > self.max_return_size).then(|| {
+            format!(
+                "{} too large, {} bytes exceeds the limit of {} bytes",
+                what, size, self.max_return_size
+            )
+        })
+    }
+
+    /// Reject an `axon_multicall` or `axon_simulateBundle` of more calls than
+    /// `max_bundle_calls`.
+    fn check_bundle_size(&self, calls: usize) -> RpcResult<()> {
+        if self.max_bundle_calls > 0 && calls > self.max_bundle_calls {
+            return Err(Error::Call(CallError::Custom {
+                code:    LIMIT_EXCEEDED_CODE,
+                message: format!(
+                    "too many calls, {} exceeds the limit of {}",
+                    calls, self.max_bundle_calls
+                ),
+                data:    None,
+            }));
+        }
+
+        Ok(())
+    }
+
+    /// The result of one call of `axon_multicall` or `axon_simulateBundle`, a
+    /// failed call or one returning too much data is reported in the result
+    /// rather than failing the request.
+    fn call_result(&self, resp: TxResp) -> Web3CallResult {
+        let gas_used = resp.gas_used.into();
+        if let Some(e) = self.return_size_error("return data", resp.ret.len()) {
+            return Web3CallResult {
+                success: false,
+                return_data: Hex::empty(),
+                gas_used,
+                error: Some(e),
+            };
+        }
+
+        let error = call_error_message(&resp.exit_reason, &resp.ret);
+        Web3CallResult {
+            success: error.is_none(),
+            return_data: Hex::encode(resp.ret),
+            gas_used,
+            error,
+        }
+    }
+
+    /// Whether the call succeeds with `gas`.
+    async fn call_succeeds(
+        &self,
+        req: &Web3CallRequest,
+        gas: u64,
+        header: &Header,
+    ) -> RpcResult<bool> {
+        let mut req = req.clone();
+        req.gas = Some(gas.into());
+        let data_bytes = req.data.as_bytes();
+        let resp = self
+            .call_evm(req, data_bytes, header.clone())
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+
+        Ok(resp.exit_reason.is_succeed())
+    }
+
+    /// Synthesize the logs of the pending block transactions as if they were
+    /// packed into the block after the one the pending snapshot is built on.
+    /// The transactions are executed once per snapshot.
+    async fn pending_logs(
+        &self,
+        address: Option<H160>,
+        topics: &[Option<Vec<H256>>],
+        logs: &mut Vec<Web3Log>,
+    ) -> RpcResult<()> {
+        let state = self.pending_state().await?;
+        let replay = state.replay(|| self.execute_pending(&state)).await?;
+
+        for pending in replay.logs.iter() {
+            if address.map_or(false, |address| address != pending.log.address)
+                || !topics_match(topics, &pending.log.topics)
+            {
+                continue;
+            }
+
+            logs.push(Web3Log {
+                address:           pending.log.address,
+                topics:            pending.log.topics.clone(),
+                data:              Hex::encode(&pending.log.data),
+                block_hash:        None,
+                block_number:      Some((state.block_number() + 1).into()),
+                transaction_hash:  Some(pending.tx_hash),
+                transaction_index: Some(pending.tx_index.into()),
+                log_index:         Some(pending.log_index.into()),
+                removed:           false,
+                log_type:          "".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Fetch a committed transaction and its receipt. The transaction index
+    /// is consulted first so that an unknown hash is answered from a single
+    /// point read.
+    async fn get_committed_tx(
+        &self,
+        hash: H256,
+    ) -> RpcResult<Option<(SignedTransaction, Receipt)>> {
+        if self
+            .adapter
+            .get_transaction_position(Context::new(), hash)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?
+            .is_none()
+        {
+            return Ok(None);
+        }
+
+        let stx = match self
+            .adapter
+            .get_transaction_by_hash(Context::new(), hash)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?
+        {
+            Some(stx) => stx,
+            None => return Ok(None),
+        };
+
+        let receipt = self
+            .adapter
+            .get_receipt_by_tx_hash(Context::new(), hash)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?
+            .ok_or_else(|| Error::Custom(format!("can not get receipt by hash {:?}", hash)))?;
+
+        Ok(Some((stx, receipt)))
+    }
+
+    /// The effective priority fees paid in a block at the given percentiles,
+    /// each transaction weighted by the gas it used and interpolated as
+    /// configured.
+    async fn block_rewards(
+        &self,
+        number: BlockNumber,
+        tx_hashes: &[Hash],
+        base_fee: U256,
+        percentiles: &[f64],
+    ) -> RpcResult<Vec<U256>> {
+        let txs = self
+            .adapter
+            .get_transactions_by_hashes(Context::new(), number, tx_hashes)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+        let receipts = self
+            .adapter
+            .get_receipts_by_hashes(Context::new(), number, tx_hashes)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+
+        let tips = txs
+            .into_iter()
+            .zip(receipts)
+            .filter_map(|(tx, receipt)| {
+                let unsigned = tx?.transaction.unsigned;
+                let tip = unsigned
+                    .max_fee_per_gas
+                    .saturating_sub(base_fee)
+                    .min(unsigned.max_priority_fee_per_gas);
+                Some((tip, receipt?.used_gas.low_u64()))
+            })
+            .collect();
+
+        Ok(match self.fee_interpolation {
+            FeeHistoryInterpolation::NearestRank => gas_weighted_percentiles(tips, percentiles),
+            FeeHistoryInterpolation::Linear => linear_gas_weighted_percentiles(tips, percentiles),
+        })
+    }
+
+    /// The median of the median tips of the recent blocks, `None` if none of
+    /// them has a transaction.
+    async fn suggest_priority_fee(&self, latest: &Header) -> RpcResult<Option<U256>> {
+        let oldest = (latest.number + 1).saturating_sub(FEE_SUGGESTION_BLOCKS);
+        let mut tips = Vec::new();
+        for number in oldest..=latest.number {
+            let block = match self
+                .adapter
+                .get_block_by_number(Context::new(), Some(number))
+                .await
+                .map_err(|e| Error::Custom(e.to_string()))?
+            {
+                Some(block) if !block.tx_hashes.is_empty() => block,
+                _ => continue,
+            };
+
+            tips.extend(
+                self.block_rewards(number, &block.tx_hashes, block.header.base_fee_per_gas, &[
+                    50.0,
+                ])
+                .await?,
+            );
+        }
+
+        tips.sort_unstable();
+        Ok(tips.get(tips.len() / 2).copied())
+    }
+
+    /// Take one of the `max_concurrent_traces` slots for the lifetime of the
+    /// returned permit, a trace over the limit is rejected rather than queued.
+    fn acquire_trace_permit(&self) -> RpcResult<Option<SemaphorePermit<'_>>> {
+        match self.trace_permits.as_ref() {
+            Some(permits) => permits.try_acquire().map(Some).map_err(|_| {
+                Error::Call(CallError::Custom {
+                    code:    LIMIT_EXCEEDED_CODE,
+                    message: "too many concurrent traces".to_string(),
+                    data:    None,
+                })
+            }),
+            None => Ok(None),
+        }
+    }
+
+    /// The answer of a node that is not mining to the mining `method` in
+    /// compatibility mode, otherwise a method not supported error.
+    fn mining_placeholder<T>(&self, method: &str, placeholder: T) -> RpcResult<T> {
+        if self.mining_compat {
+            Ok(placeholder)
+        } else {
+            Err(mining_not_supported(method))
+        }
+    }
+
+    fn best_block_number(&self) -> u64 {
+        self.convert_block_number(BlockId::Latest)
+            .unwrap_or_default()
+    }
+
     fn polls(&self) -> &Mutex<PollManager<SyncPollFilter>> {
         &self.polls
     }
 
+    /// The hook pushing imported blocks to the block filters.
+    pub(crate) fn block_filter_hook(&self) -> &Arc<BlockFilterHook> {
+        &self.block_hook
+    }
+
     fn get_block_number_by_hash(&self, hash: Hash) -> ProtocolResult<u64> {
         let ret_number = block_on(self.adapter.get_number_by_hash(Context::new(), hash))?
             .ok_or_else(|| {
@@ -87,7 +759,7 @@ impl<Adapter: APIAdapter> JsonRpcImpl<Adapter> {
                 match ret_block {
                     Ok(op_block) => {
                         if let Some(block) = op_block {
-                            block_hash = Some(block.header.proof.block_hash);
+                            block_hash = Some(block.header_hash());
                         }
                     }
                     _ => {}
@@ -95,13 +767,13 @@ impl<Adapter: APIAdapter> JsonRpcImpl<Adapter> {
                 block_hash
             }
             // BlockId::Earliest => self.numbers.read().get(&0).cloned(),
-            BlockId::Latest => {
+            BlockId::Latest | BlockId::Pending => {
                 let mut block_hash: Option<Hash> = None;
                 let ret_block = block_on(self.adapter.get_block_by_number(Context::new(), None));
                 match ret_block {
                     Ok(op_block) => {
                         if let Some(block) = op_block {
-                            block_hash = Some(block.header.proof.block_hash);
+                            block_hash = Some(block.header_hash());
                         }
                     }
                     _ => {}
@@ -128,7 +800,7 @@ impl<Adapter: APIAdapter> JsonRpcImpl<Adapter> {
             }
             BlockId::Num(n) => Some(n),
             // BlockId::Earliest => self.numbers.read().get(&0).cloned(),
-            BlockId::Latest => {
+            BlockId::Latest | BlockId::Pending => {
                 let mut block_number: Option<u64> = None;
                 let ret_block = block_on(self.adapter.get_block_by_number(Context::new(), None));
                 match ret_block {
@@ -149,44 +821,61 @@ impl<Adapter: APIAdapter> JsonRpcImpl<Adapter> {
 impl<Adapter: APIAdapter + 'static> AxonJsonRpcServer for JsonRpcImpl<Adapter> {
     #[metrics_rpc("eth_sendRawTransaction")]
     async fn send_raw_transaction(&self, tx: Hex) -> RpcResult<H256> {
-        let utx = UnverifiedTransaction::decode(&tx.as_bytes()[1..])
-            .map_err(|e| Error::Custom(e.to_string()))?
-            .hash();
-        let stx = SignedTransaction::try_from(utx).map_err(|e| Error::Custom(e.to_string()))?;
-        let hash = stx.transaction.hash;
-        self.adapter
-            .insert_signed_txs(Context::new(), stx)
-            .await
-            .map_err(|e| Error::Custom(e.to_string()))?;
-
-        Ok(hash)
+        self.submit_raw_tx(tx).await
     }
 
-    #[metrics_rpc("eth_getTransactionByHash")]
-    async fn get_transaction_by_hash(&self, hash: H256) -> RpcResult<Option<Web3Transaction>> {
-        let res = self
-            .adapter
-            .get_transaction_by_hash(Context::new(), hash)
+    #[metrics_rpc("axon_sendRawTransactionSync")]
+    async fn send_raw_transaction_sync(
+        &self,
+        tx: Hex,
+        timeout: Option<u64>,
+    ) -> RpcResult<Web3TxPropagation> {
+        let timeout = timeout
+            .unwrap_or(DEFAULT_PROPAGATION_TIMEOUT)
+            .min(MAX_PROPAGATION_TIMEOUT);
+
+        // Subscribe before the insertion so the broadcast cannot be missed.
+        let mut broadcast_rx = TX_BROADCAST_NOTIFIER.subscribe();
+        let hash = self.submit_raw_tx(tx).await?;
+
+        let wait_broadcast = async {
+            loop {
+                match broadcast_rx.recv().await {
+                    Ok(event) if event.hashes.contains(&hash) => return true,
+                    Ok(_) => continue,
+                    // The event of the transaction may be among the missed
+                    // ones.
+                    Err(RecvError::Lagged(_)) if is_tx_broadcast(&hash) => return true,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return false,
+                }
+            }
+        };
+        let broadcast = tokio::time::timeout(Duration::from_millis(timeout), wait_broadcast)
             .await
-            .map_err(|e| Error::Custom(e.to_string()))?;
+            .unwrap_or(false);
 
-        if let Some(stx) = res {
-            if let Some(receipt) = self
+        // A broadcast without any connected peer reaches nobody.
+        let propagated = broadcast
+            && !self
                 .adapter
-                .get_receipt_by_tx_hash(Context::new(), hash)
+                .peer_count(Context::new())
                 .await
                 .map_err(|e| Error::Custom(e.to_string()))?
-            {
-                Ok(Some(Web3Transaction::create(receipt, stx)))
-            } else {
-                Err(Error::Custom(format!(
-                    "can not get receipt by hash {:?}",
-                    hash
-                )))
-            }
-        } else {
-            Ok(None)
-        }
+                .is_zero();
+
+        Ok(Web3TxPropagation {
+            transaction_hash: hash,
+            propagated,
+        })
+    }
+
+    #[metrics_rpc("eth_getTransactionByHash")]
+    async fn get_transaction_by_hash(&self, hash: H256) -> RpcResult<Option<Web3Transaction>> {
+        Ok(self
+            .get_committed_tx(hash)
+            .await?
+            .map(|(stx, receipt)| Web3Transaction::create(receipt, stx)))
     }
 
     #[metrics_rpc("eth_getBlockByNumber")]
@@ -204,7 +893,10 @@ impl<Adapter: APIAdapter + 'static> AxonJsonRpcServer for JsonRpcImpl<Adapter> {
         match block {
             Some(b) => {
                 let capacity = b.tx_hashes.len();
+                let number = b.header.number;
                 let mut ret = Web3Block::from(b);
+                ret.total_difficulty = self.total_difficulty(&ret).await?;
+                ret.seal_fields = self.seal_fields(number).await?;
                 if show_rich_tx {
                     let mut txs = Vec::with_capacity(capacity);
                     for tx in ret.transactions.iter() {
@@ -242,7 +934,10 @@ impl<Adapter: APIAdapter + 'static> AxonJsonRpcServer for JsonRpcImpl<Adapter> {
         match block {
             Some(b) => {
                 let capacity = b.tx_hashes.len();
+                let number = b.header.number;
                 let mut ret = Web3Block::from(b);
+                ret.total_difficulty = self.total_difficulty(&ret).await?;
+                ret.seal_fields = self.seal_fields(number).await?;
                 if show_rich_tx {
                     let mut txs = Vec::with_capacity(capacity);
                     for tx in ret.transactions.iter() {
@@ -266,13 +961,49 @@ impl<Adapter: APIAdapter + 'static> AxonJsonRpcServer for JsonRpcImpl<Adapter> {
     }
 
     #[metrics_rpc("eth_getTransactionCount")]
-    async fn get_transaction_count(&self, address: H160, number: BlockId) -> RpcResult<U256> {
+    async fn get_transaction_count(
+        &self,
+        address: H160,
+        number: Option<BlockId>,
+    ) -> RpcResult<U256> {
+        let number = number.unwrap_or_else(|| self.default_block.clone());
+        if number == BlockId::Pending {
+            let (state, account) = self.pending_account(address).await?;
+            return Ok(state.nonce(&address, &account));
+        }
+
+        // Committed nonces are served from the cache, pending ones depend on
+        // the pool and never are.
+        let cache_key = match (self.nonce_cache.as_ref(), &number) {
+            (Some(_), BlockId::Num(n)) => Some((address, *n)),
+            (Some(cache), BlockId::Latest) => {
+                let header = self
+                    .get_header(None)
+                    .await
+                    .map_err(|e| Error::Custom(e.to_string()))?;
+                cache.lock().observe_block(header.number);
+                Some((address, header.number))
+            }
+            _ => None,
+        };
+
+        if let (Some(cache), Some(key)) = (self.nonce_cache.as_ref(), cache_key.as_ref()) {
+            if let Some(nonce) = cache.lock().get(key) {
+                return Ok(nonce);
+            }
+        }
+
+        let number = cache_key.map_or_else(|| number.into(), |(_, n)| Some(n));
         let account = self
             .adapter
-            .get_account(Context::new(), address, number.into())
+            .get_account(Context::new(), address, number)
             .await
             .map_err(|e| Error::Custom(e.to_string()))?;
 
+        if let (Some(cache), Some(key)) = (self.nonce_cache.as_ref(), cache_key) {
+            cache.lock().insert(key, account.nonce);
+        }
+
         Ok(account.nonce)
     }
 
@@ -287,7 +1018,13 @@ impl<Adapter: APIAdapter + 'static> AxonJsonRpcServer for JsonRpcImpl<Adapter> {
     }
 
     #[metrics_rpc("eth_getBalance")]
-    async fn get_balance(&self, address: H160, number: BlockId) -> RpcResult<U256> {
+    async fn get_balance(&self, address: H160, number: Option<BlockId>) -> RpcResult<U256> {
+        let number = number.unwrap_or_else(|| self.default_block.clone());
+        if number == BlockId::Pending {
+            let (state, account) = self.pending_account(address).await?;
+            return Ok(state.balance(&address, &account));
+        }
+
         let account = self
             .adapter
             .get_account(Context::new(), address, number.into())
@@ -297,8 +1034,32 @@ impl<Adapter: APIAdapter + 'static> AxonJsonRpcServer for JsonRpcImpl<Adapter> {
         Ok(account.balance)
     }
 
+    #[metrics_rpc("eth_getProof")]
+    async fn get_proof(
+        &self,
+        address: H160,
+        storage_keys: Vec<Web3StorageKey>,
+        number: Option<BlockId>,
+    ) -> RpcResult<Web3AccountProof> {
+        let number = number.unwrap_or_else(|| self.default_block.clone());
+        let keys = storage_keys.into_iter().map(|key| key.0).collect();
+        let proof = self
+            .adapter
+            .get_proof(Context::new(), address, keys, number.into())
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+
+        Ok(Web3AccountProof::new(address, proof))
+    }
+
     #[metrics_rpc("eth_chainId")]
     async fn chain_id(&self) -> RpcResult<U256> {
+        // The configured chain id is a constant, serve it without touching the
+        // storage which may not hold the genesis block yet during startup.
+        if let Some(chain_id) = self.chain_id {
+            return Ok(U256::from(chain_id));
+        }
+
         self.adapter
             .get_block_header_by_number(Context::new(), None)
             .await
@@ -313,33 +1074,96 @@ impl<Adapter: APIAdapter + 'static> AxonJsonRpcServer for JsonRpcImpl<Adapter> {
     }
 
     #[metrics_rpc("eth_call")]
-    async fn call(&self, req: Web3CallRequest, number: BlockId) -> RpcResult<Hex> {
+    async fn call(&self, req: Web3CallRequest, number: Option<BlockId>) -> RpcResult<Hex> {
+        let number = number.unwrap_or_else(|| self.default_block.clone());
+        let header = self
+            .get_header(number.into())
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+
+        let cache_key = (CallCache::call_request_hash(&req), header.number);
+        if let Some(cache) = self.call_cache.as_ref() {
+            let mut cache = cache.lock();
+            cache.observe_block(header.number);
+            if let Some(ret) = cache.get(&cache_key) {
+                return Ok(Hex::encode(ret));
+            }
+        }
+
         let data_bytes = req.data.as_bytes();
         let resp = self
-            .call_evm(req, data_bytes, number.into())
+            .call_evm(req, data_bytes, header)
             .await
             .map_err(|e| Error::Custom(e.to_string()))?;
+        self.check_return_size("return data", resp.ret.len())?;
+        if let Some(message) = call_error_message(&resp.exit_reason, &resp.ret) {
+            return Err(Error::Custom(message));
+        }
+
+        if let Some(cache) = self.call_cache.as_ref() {
+            cache.lock().insert(cache_key, resp.ret.clone());
+        }
+
         let call_hex_result = Hex::encode(resp.ret);
         Ok(call_hex_result)
     }
 
     #[metrics_rpc("eth_estimateGas")]
     async fn estimate_gas(&self, req: Web3CallRequest, number: Option<BlockId>) -> RpcResult<U256> {
-        let num = match number {
-            Some(BlockId::Num(n)) => Some(n),
+        let num = match number.unwrap_or_else(|| self.default_block.clone()) {
+            BlockId::Num(n) => Some(n),
             _ => None,
         };
+        let header = self
+            .get_header(num)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+        let cap = req.gas.unwrap_or(header.gas_limit);
+        let cap = if cap > U256::from(u64::MAX) {
+            u64::MAX
+        } else {
+            cap.as_u64()
+        };
+
         let data_bytes = req.data.as_bytes();
         let resp = self
-            .call_evm(req, data_bytes, num)
+            .call_evm(req.clone(), data_bytes, header.clone())
             .await
             .map_err(|e| Error::Custom(e.to_string()))?;
+        if let Some(message) = call_error_message(&resp.exit_reason, &resp.ret) {
+            return Err(Error::Custom(message));
+        }
 
-        Ok(resp.gas_used.into())
+        // The gas used is net of the refund, e.g. for the storage cleared
+        // before a SELFDESTRUCT, which is only paid back after the execution.
+        // So the call may run out of its own gas used, search the least gas
+        // it succeeds with between that and the cap it succeeded with.
+        let mut lo = resp.gas_used;
+        if lo >= cap || self.call_succeeds(&req, lo, &header).await? {
+            return Ok(lo.min(cap).into());
+        }
+
+        let mut hi = cap;
+        while hi - lo > 1 {
+            let mid = lo + (hi - lo) / 2;
+            if self.call_succeeds(&req, mid, &header).await? {
+                hi = mid;
+            } else {
+                lo = mid;
+            }
+        }
+
+        Ok(hi.into())
     }
 
     #[metrics_rpc("eth_getCode")]
-    async fn get_code(&self, address: H160, number: BlockId) -> RpcResult<Hex> {
+    async fn get_code(&self, address: H160, number: Option<BlockId>) -> RpcResult<Hex> {
+        let number = number.unwrap_or_else(|| self.default_block.clone());
+        if number == BlockId::Pending {
+            let (code, _) = self.pending_account_state(address, Vec::new()).await?;
+            return Ok(Hex::encode(code));
+        }
+
         let account = self
             .adapter
             .get_account(Context::new(), address, number.into())
@@ -358,8 +1182,44 @@ impl<Adapter: APIAdapter + 'static> AxonJsonRpcServer for JsonRpcImpl<Adapter> {
         }
     }
 
+    #[metrics_rpc("eth_getStorageAt")]
+    async fn get_storage_at(
+        &self,
+        address: H160,
+        position: Web3StorageKey,
+        number: Option<BlockId>,
+    ) -> RpcResult<H256> {
+        let number = number.unwrap_or_else(|| self.default_block.clone());
+        let slots = vec![position.0];
+        let (_, values) = if number == BlockId::Pending {
+            self.pending_account_state(address, slots).await?
+        } else {
+            let header = self
+                .get_header(number.into())
+                .await
+                .map_err(|e| Error::Custom(e.to_string()))?;
+            self.adapter
+                .replay_account(
+                    Context::new(),
+                    Vec::new(),
+                    address,
+                    slots,
+                    header.state_root,
+                    header.into(),
+                )
+                .await
+                .map_err(|e| Error::Custom(e.to_string()))?
+        };
+
+        Ok(values.into_iter().next().unwrap_or_default())
+    }
+
     #[metrics_rpc("eth_getBlockTransactionCountByNumber")]
     async fn get_transaction_count_by_number(&self, number: BlockId) -> RpcResult<U256> {
+        if number == BlockId::Pending {
+            return Ok(self.pending_state().await?.packed_txs().len().into());
+        }
+
         let block = self
             .adapter
             .get_block_by_number(Context::new(), number.into())
@@ -374,34 +1234,35 @@ impl<Adapter: APIAdapter + 'static> AxonJsonRpcServer for JsonRpcImpl<Adapter> {
 
     #[metrics_rpc("eth_getTransactionReceipt")]
     async fn get_transaction_receipt(&self, hash: H256) -> RpcResult<Option<Web3Receipt>> {
-        let res = self
-            .adapter
-            .get_transaction_by_hash(Context::new(), hash)
-            .await
-            .map_err(|e| Error::Custom(e.to_string()))?;
-
-        if let Some(stx) = res {
-            if let Some(receipt) = self
-                .adapter
-                .get_receipt_by_tx_hash(Context::new(), hash)
-                .await
-                .map_err(|e| Error::Custom(e.to_string()))?
-            {
-                Ok(Some(Web3Receipt::new(receipt, stx)))
-            } else {
-                Err(Error::Custom(format!(
-                    "can not get receipt by hash {:?}",
-                    hash
-                )))
-            }
-        } else {
-            Ok(None)
-        }
+        Ok(self
+            .get_committed_tx(hash)
+            .await?
+            .map(|(stx, receipt)| Web3Receipt::new(receipt, stx)))
     }
 
     #[metrics_rpc("eth_gasPrice")]
     async fn gas_price(&self) -> RpcResult<U256> {
-        Ok(U256::from(8u64))
+        let latest = self
+            .get_header(None)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+        let tip = self
+            .suggest_priority_fee(&latest)
+            .await?
+            .unwrap_or(self.default_tip);
+        Ok(latest.base_fee_per_gas.saturating_add(tip))
+    }
+
+    #[metrics_rpc("eth_maxPriorityFeePerGas")]
+    async fn max_priority_fee_per_gas(&self) -> RpcResult<U256> {
+        let latest = self
+            .get_header(None)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+        Ok(self
+            .suggest_priority_fee(&latest)
+            .await?
+            .unwrap_or(self.default_tip))
     }
 
     #[metrics_rpc("net_listening")]
@@ -423,33 +1284,40 @@ impl<Adapter: APIAdapter + 'static> AxonJsonRpcServer for JsonRpcImpl<Adapter> {
     }
 
     async fn get_logs(&self, filter: Web3Filter) -> RpcResult<Vec<Web3Log>> {
-        if filter.topics.is_none() {
-            return Ok(Vec::new());
-        }
-
-        let topics = filter.topics.unwrap();
+        let topics = filter.topics.map_or_else(Vec::new, |topics| {
+            topics
+                .into_iter()
+                .map(VariadicValue::into_option)
+                .collect::<Vec<_>>()
+        });
 
         #[allow(clippy::large_enum_variant)]
         enum BlockPosition {
             Hash(H256),
-            Num(BlockNumber),
             Block(Block),
         }
 
         async fn get_logs<T: APIAdapter>(
             adapter: &T,
             position: BlockPosition,
-            topics: &[H256],
+            topics: &[Option<Vec<H256>>],
             logs: &mut Vec<Web3Log>,
         ) -> RpcResult<()> {
-            let extend_logs = |logs: &mut Vec<Web3Log>, receipts: Vec<Option<Receipt>>| {
-                let mut index = 0;
-                for receipt in receipts.into_iter().flatten() {
-                    let log_len = receipt.logs.len();
-                    from_receipt_to_web3_log(index, topics, receipt, logs);
-                    index += log_len;
-                }
-            };
+            // A receipt of another block is left by an orphaned block which
+            // included the same transaction.
+            let extend_logs =
+                |logs: &mut Vec<Web3Log>, block_hash: H256, receipts: Vec<Option<Receipt>>| {
+                    let mut index = 0;
+                    for receipt in receipts
+                        .into_iter()
+                        .flatten()
+                        .filter(|r| r.block_hash == block_hash)
+                    {
+                        let log_len = receipt.logs.len();
+                        from_receipt_to_web3_log(index, topics, receipt, logs);
+                        index += log_len;
+                    }
+                };
 
             match position {
                 BlockPosition::Hash(hash) => {
@@ -459,6 +1327,19 @@ impl<Adapter: APIAdapter + 'static> AxonJsonRpcServer for JsonRpcImpl<Adapter> {
                         .map_err(|e| Error::Custom(e.to_string()))?
                     {
                         Some(block) => {
+                            // An orphaned block is still stored but its logs
+                            // are not on the chain.
+                            let canonical = adapter
+                                .get_block_header_by_number(
+                                    Context::new(),
+                                    Some(block.header.number),
+                                )
+                                .await
+                                .map_err(|e| Error::Custom(e.to_string()))?;
+                            if canonical.map(|h| h.hash()) != Some(hash) {
+                                return Ok(());
+                            }
+
                             let receipts = adapter
                                 .get_receipts_by_hashes(
                                     Context::new(),
@@ -467,7 +1348,7 @@ impl<Adapter: APIAdapter + 'static> AxonJsonRpcServer for JsonRpcImpl<Adapter> {
                                 )
                                 .await
                                 .map_err(|e| Error::Custom(e.to_string()))?;
-                            extend_logs(logs, receipts);
+                            extend_logs(logs, hash, receipts);
                             Ok(())
                         }
                         None => Err(Error::Custom(format!(
@@ -477,24 +1358,6 @@ impl<Adapter: APIAdapter + 'static> AxonJsonRpcServer for JsonRpcImpl<Adapter> {
                         ))),
                     }
                 }
-                BlockPosition::Num(n) => {
-                    let block = adapter
-                        .get_block_by_number(Context::new(), Some(n))
-                        .await
-                        .map_err(|e| Error::Custom(e.to_string()))?
-                        .unwrap();
-                    let receipts = adapter
-                        .get_receipts_by_hashes(
-                            Context::new(),
-                            block.header.number,
-                            &block.tx_hashes,
-                        )
-                        .await
-                        .map_err(|e| Error::Custom(e.to_string()))?;
-
-                    extend_logs(logs, receipts);
-                    Ok(())
-                }
                 BlockPosition::Block(block) => {
                     let receipts = adapter
                         .get_receipts_by_hashes(
@@ -505,7 +1368,7 @@ impl<Adapter: APIAdapter + 'static> AxonJsonRpcServer for JsonRpcImpl<Adapter> {
                         .await
                         .map_err(|e| Error::Custom(e.to_string()))?;
 
-                    extend_logs(logs, receipts);
+                    extend_logs(logs, block.header_hash(), receipts);
                     Ok(())
                 }
             }
@@ -530,11 +1393,18 @@ impl<Adapter: APIAdapter + 'static> AxonJsonRpcServer for JsonRpcImpl<Adapter> {
                     .map_err(|e| Error::Custom(e.to_string()))?
                     .unwrap();
                 let latest_number = latest_block.header.number;
+                // `latest` and an omitted block mean the newest block with
+                // enough confirmations, an explicit number is taken as is.
+                // `pending` runs up to the newest block, so that the pool
+                // logs follow the mined ones without a gap.
+                let confirmed_number = latest_number
+                    .saturating_sub(filter.confirmations.unwrap_or(self.log_confirmations));
                 let (start, end) = {
                     let convert = |id: BlockId| -> BlockNumber {
                         match id {
                             BlockId::Num(n) => n,
-                            BlockId::Latest => latest_number,
+                            BlockId::Latest => confirmed_number,
+                            BlockId::Pending => latest_number,
                             BlockId::Hash(ha) => {
                                 let ret_num = self.get_block_number_by_hash(ha);
                                 match ret_num {
@@ -546,8 +1416,12 @@ impl<Adapter: APIAdapter + 'static> AxonJsonRpcServer for JsonRpcImpl<Adapter> {
                     };
 
                     (
-                        filter.from_block.map(convert).unwrap_or(latest_number),
-                        filter.to_block.map(convert).unwrap_or(latest_number),
+                        filter.from_block.map(convert).unwrap_or(confirmed_number),
+                        filter
+                            .to_block
+                            .clone()
+                            .map(convert)
+                            .unwrap_or(confirmed_number),
                     )
                 };
 
@@ -555,14 +1429,66 @@ impl<Adapter: APIAdapter + 'static> AxonJsonRpcServer for JsonRpcImpl<Adapter> {
                     return Err(Error::Custom(format!("Invalid from_block {}", start)));
                 }
 
-                let mut visiter_last_block = false;
-                for n in start..=end {
-                    if n == latest_number {
-                        visiter_last_block = true;
+                let end = end.min(latest_number);
+                if self.log_max_block_range > 0
+                    && end >= start
+                    && end - start >= self.log_max_block_range
+                {
+                    return Err(block_range_exceeded(start, end, self.log_max_block_range));
+                }
+
+                if let (Some(limit), Some(LogOrder::Desc)) = (filter.limit, filter.order) {
+                    if filter.to_block == Some(BlockId::Pending) {
+                        self.pending_logs(filter.address, &topics, &mut all_logs)
+                            .await?;
+                        all_logs.reverse();
+                    }
+
+                    // Walk back from the end of the range and stop as soon as
+                    // enough logs are found.
+                    let mut current = if start <= end {
+                        Some(self.block_at(latest_block, end).await?)
                     } else {
+                        None
+                    };
+                    while let Some(block) = current.take() {
+                        if all_logs.len() >= limit {
+                            break;
+                        }
+
+                        let number = block.header.number;
+                        let prev_hash = block.header.prev_hash;
+                        let mut block_logs = Vec::new();
+                        get_logs(
+                            &*self.adapter,
+                            BlockPosition::Block(block),
+                            &topics,
+                            &mut block_logs,
+                        )
+                        .await?;
+                        all_logs.extend(block_logs.into_iter().rev());
+
+                        if number > start && number > 0 {
+                            current = self
+                                .adapter
+                                .get_block_by_hash(Context::new(), prev_hash)
+                                .await
+                                .map_err(|e| Error::Custom(e.to_string()))?;
+                        }
+                    }
+
+                    all_logs.truncate(limit);
+                    return Ok(all_logs);
+                }
+
+                if start <= end {
+                    // Scan against the chain ending at the head pinned above, so
+                    // a reorg during the scan can not mix blocks of different
+                    // forks.
+                    for block in self.canonical_blocks(latest_block, start, end).await? {
                         get_logs(
                             &*self.adapter,
-                            BlockPosition::Num(n),
+                            BlockPosition::Block(block),
                             &topics,
                             &mut all_logs,
                         )
@@ -570,31 +1496,102 @@ impl<Adapter: APIAdapter + 'static> AxonJsonRpcServer for JsonRpcImpl<Adapter> {
                     }
                 }
 
-                if visiter_last_block {
-                    get_logs(
-                        &*self.adapter,
-                        BlockPosition::Block(latest_block),
-                        &topics,
-                        &mut all_logs,
+                // The logs of the pool transactions are only taken in when
+                // `pending` is asked for explicitly, `latest` and numbers end
+                // at the mined blocks.
+                if filter.to_block == Some(BlockId::Pending) {
+                    self.pending_logs(filter.address, &topics, &mut all_logs)
+                        .await?;
+                }
+            }
+        }
+        Ok(all_logs)
+    }
+
+    async fn fee_history(
+        &self,
+        block_count: u64,
+        newest_block: BlockId,
+        reward_percentiles: Option<Vec<f64>>,
+    ) -> RpcResult<Web3FeeHistory> {
+        let percentiles = reward_percentiles.unwrap_or_default();
+        check_reward_percentiles(&percentiles)?;
+
+        let newest = match newest_block.clone() {
+            BlockId::Hash(hash) => self
+                .adapter
+                .get_block_by_hash(Context::new(), hash)
+                .await
+                .map(|b| b.map(|b| b.header.number)),
+            id => self
+                .adapter
+                .get_block_header_by_number(Context::new(), id.into())
+                .await
+                .map(|h| h.map(|h| h.number)),
+        }
+        .map_err(|e| Error::Custom(e.to_string()))?
+        .ok_or_else(|| Error::Custom(format!("Cannot get block {:?}", newest_block)))?;
+
+        let block_count = block_count.min(MAX_FEE_HISTORY_BLOCKS).min(newest + 1);
+        if block_count == 0 {
+            return Ok(Web3FeeHistory {
+                oldest_block:     U256::zero(),
+                reward:           None,
+                base_fee_per_gas: Vec::new(),
+                gas_used_ratio:   Vec::new(),
+            });
+        }
+
+        let oldest = newest + 1 - block_count;
+        let mut base_fee_per_gas = Vec::with_capacity(block_count as usize + 1);
+        let mut gas_used_ratio = Vec::with_capacity(block_count as usize);
+        let mut reward = Vec::with_capacity(block_count as usize);
+
+        for number in oldest..=newest {
+            let block = self
+                .adapter
+                .get_block_by_number(Context::new(), Some(number))
+                .await
+                .map_err(|e| Error::Custom(e.to_string()))?
+                .ok_or_else(|| Error::Custom(format!("Cannot get block {}", number)))?;
+            let header = &block.header;
+
+            base_fee_per_gas.push(header.base_fee_per_gas);
+            gas_used_ratio.push(if header.gas_limit.is_zero() {
+                0.0
+            } else {
+                header.gas_used.low_u64() as f64 / header.gas_limit.low_u64() as f64
+            });
+
+            if !percentiles.is_empty() {
+                reward.push(
+                    self.block_rewards(
+                        number,
+                        &block.tx_hashes,
+                        header.base_fee_per_gas,
+                        &percentiles,
                     )
-                    .await?;
-                }
+                    .await?,
+                );
             }
         }
-        Ok(all_logs)
-    }
 
-    async fn fee_history(
-        &self,
-        _block_count: u64,
-        _newest_block: BlockId,
-        _reward_percentiles: Option<Vec<u64>>,
-    ) -> RpcResult<Web3FeeHistory> {
+        // The base fee of the block after the newest one, which the next
+        // proposal carries over from the latest block if it is not committed
+        // yet.
+        let next_base_fee = self
+            .adapter
+            .get_block_header_by_number(Context::new(), Some(newest + 1))
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?
+            .map(|header| header.base_fee_per_gas);
+        base_fee_per_gas.push(next_base_fee.unwrap_or(*base_fee_per_gas.last().unwrap()));
+
         Ok(Web3FeeHistory {
-            oldest_block:     U256::from(0),
-            reward:           None,
-            base_fee_per_gas: Vec::new(),
-            gas_used_ratio:   Vec::new(),
+            oldest_block: oldest.into(),
+            reward: (!percentiles.is_empty()).then(|| reward),
+            base_fee_per_gas,
+            gas_used_ratio,
         })
     }
 
@@ -614,35 +1611,30 @@ impl<Adapter: APIAdapter + 'static> AxonJsonRpcServer for JsonRpcImpl<Adapter> {
     }
 
     async fn coinbase(&self) -> RpcResult<H160> {
-        // fixme: how to get the the coinbase value
-        Ok(H160::default())
+        self.mining_placeholder("eth_coinbase", H160::default())
     }
 
     async fn hashrate(&self) -> RpcResult<U256> {
-        Ok(U256::from(1u64))
+        self.mining_placeholder("eth_hashrate", U256::zero())
     }
 
     async fn get_work(&self) -> RpcResult<(Hash, Hash, Hash)> {
-        let work = WEB3Work {
-            pow_hash:  H256::default(), // how to get the pow_hash
-            seed_hash: H256::default(),
-            target:    H256::default(),
-            number:    None,
-        };
-        Ok((work.pow_hash, work.pow_hash, work.target))
+        // Even a node not mining has no work to hand out.
+        Err(mining_not_supported("eth_getWork"))
     }
 
     async fn submit_work(&self, _nc: U256, _hash: H256, _summary: Hex) -> RpcResult<bool> {
-        Ok(true)
+        self.mining_placeholder("eth_submitWork", false)
     }
 
     async fn submit_hashrate(&self, _hash_rate: Hex, _client_id: Hex) -> RpcResult<bool> {
-        Ok(true)
+        self.mining_placeholder("eth_submitHashrate", false)
     }
 
     async fn new_filter(&self, filter: ChangeWeb3Filter) -> RpcResult<U256> {
+        filter.validate().map_err(Error::Custom)?;
         let mut polls = self.polls.lock();
-        let block_number = best_block_number();
+        let block_number = self.best_block_number();
         let include_pending = false;
         let filter = filter.try_into();
         let id = polls.create_poll(SyncPollFilter::new(PollFilter::Logs {
@@ -656,21 +1648,49 @@ impl<Adapter: APIAdapter + 'static> AxonJsonRpcServer for JsonRpcImpl<Adapter> {
     }
 
     async fn new_block_filter(&self) -> RpcResult<U256> {
-        let mut polls = self.polls.lock();
+        // Listen before reading the latest block, a block imported in between
+        // breaks the parent chain and is read from storage by the next poll.
+        self.block_hook.start(|| BLOCK_IMPORT_NOTIFIER.subscribe());
+        let latest = self
+            .adapter
+            .get_block_by_number(Context::new(), None)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+        let mut recent_reported_hashes =
+            VecDeque::with_capacity(PollFilter::max_block_history_size());
         // +1, since we don't want to include the current block
-        let id = polls.create_poll(SyncPollFilter::new(PollFilter::Block {
-            last_block_number:      best_block_number(),
-            recent_reported_hashes: VecDeque::with_capacity(PollFilter::max_block_history_size()),
-        }));
+        let last_block_number = match latest {
+            Some(block) => {
+                remember_reported(
+                    &mut recent_reported_hashes,
+                    block.header.number,
+                    block.header_hash(),
+                );
+                block.header.number
+            }
+            None => 0,
+        };
+        let filter = SyncPollFilter::new(PollFilter::Block {
+            last_block_number,
+            recent_reported_hashes,
+            imported: VecDeque::new(),
+            missed: false,
+        });
+        self.block_hook.register(&filter);
+        let id = self.polls.lock().create_poll(filter);
         Ok(id.into())
     }
 
     async fn new_pending_transaction_filter(&self) -> RpcResult<U256> {
-        let mut polls = self.polls.lock();
-        let pending_transactions = pending_transaction_hashes();
-        let id = polls.create_poll(SyncPollFilter::new(PollFilter::PendingTransaction(
-            pending_transactions,
-        )));
+        let (cursor, _) = self
+            .adapter
+            .get_pool_txs_inserted_since(Context::new(), None)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+        let id = self
+            .polls
+            .lock()
+            .create_poll(SyncPollFilter::new(PollFilter::PendingTransaction(cursor)));
         Ok(id.into())
     }
 
@@ -684,51 +1704,78 @@ impl<Adapter: APIAdapter + 'static> AxonJsonRpcServer for JsonRpcImpl<Adapter> {
             PollFilter::Block {
                 ref mut last_block_number,
                 ref mut recent_reported_hashes,
+                ref mut imported,
+                ref mut missed,
             } => {
+                // Serve the blocks pushed by the import hook while each one
+                // extends the last reported block
+                let mut hashes = Vec::new();
+                while !*missed {
+                    let (number, parent, hash) = match imported.front() {
+                        Some(block) => *block,
+                        None => return Ok(FilterChanges::Hashes(hashes)),
+                    };
+                    let extends = number == *last_block_number + 1
+                        && recent_reported_hashes
+                            .front()
+                            .map_or(true, |(_, last)| *last == parent);
+                    if !extends {
+                        *missed = true;
+                        break;
+                    }
+
+                    imported.pop_front();
+                    *last_block_number = number;
+                    hashes.push(hash);
+                    remember_reported(recent_reported_hashes, number, hash);
+                }
+
                 // Check validity of recently reported blocks -- in case of re-org, rewind block
-                // to last valid
+                // to last valid, so the blocks replacing them are reported as new ones
                 while let Some((num, hash)) = recent_reported_hashes.front().cloned() {
                     if self.convert_block_hash(BlockId::Num(num)) == Some(hash) {
                         break;
                     }
-                    *last_block_number = num - 1;
+                    *last_block_number = num.saturating_sub(1);
                     recent_reported_hashes.pop_front();
                 }
-                let current_number = best_block_number();
-                let mut hashes = Vec::new();
+                let current_number = self.best_block_number();
+                *missed = false;
                 for n in (*last_block_number + 1)..=current_number {
-                    let block_number = BlockId::Num(n);
-                    if let Some(hash) = self.convert_block_hash(block_number) {
-                        *last_block_number = n;
-                        hashes.push(hash);
-                        // Only keep the most recent history
-                        if recent_reported_hashes.len() >= PollFilter::max_block_history_size() {
-                            recent_reported_hashes.pop_back();
+                    // Stop at a missing block rather than skip it, the cursor
+                    // only moves past delivered blocks
+                    let hash = match self.convert_block_hash(BlockId::Num(n)) {
+                        Some(hash) => hash,
+                        None => {
+                            *missed = true;
+                            break;
                         }
-                        recent_reported_hashes.push_front((n, hash));
-                    }
+                    };
+
+                    *last_block_number = n;
+                    hashes.push(hash);
+                    remember_reported(recent_reported_hashes, n, hash);
                 }
+                imported.retain(|(num, ..)| *num > *last_block_number);
 
-                FilterChanges::Hashes(hashes)
+                Ok(FilterChanges::Hashes(hashes))
             }
-            PollFilter::PendingTransaction(ref mut previous_hashes) => {
-                // get hashes of pending transactions
-                let current_hashes = pending_transaction_hashes();
-
-                let new_hashes = {
-                    // find all new hashes
-                    current_hashes
-                        .difference(previous_hashes)
-                        .cloned()
-                        .map(Into::into)
-                        .collect()
+            PollFilter::PendingTransaction(ref mut cursor) => {
+                // get hashes of transactions pooled since the last poll, the
+                // ones mined in between included
+                let new_hashes = match block_on(
+                    self.adapter
+                        .get_pool_txs_inserted_since(Context::new(), Some(*cursor)),
+                ) {
+                    Ok((last_seq, hashes)) => {
+                        *cursor = last_seq;
+                        hashes
+                    }
+                    Err(_) => Vec::new(),
                 };
 
-                // save all hashes of pending transactions
-                *previous_hashes = current_hashes;
-
                 // return new hashes
-                FilterChanges::Hashes(new_hashes)
+                Ok(FilterChanges::Hashes(new_hashes))
             }
             PollFilter::Logs {
                 ref mut block_number,
@@ -738,90 +1785,112 @@ impl<Adapter: APIAdapter + 'static> AxonJsonRpcServer for JsonRpcImpl<Adapter> {
                 include_pending: _,
             } => {
                 // retrive the current block number
-                let current_number = best_block_number();
+                let current_number = self.best_block_number();
 
                 let mut filter = filter.clone();
 
-                // fixme
                 // retrieve reorg logs
-                let (reorg, reorg_len) = last_block_hash.map_or_else(
-                    || (Vec::new(), 0),
-                    |h| {
-                        let ret = block_on(self.removed_logs(h, filter.clone()));
-                        let r = match ret {
-                            Ok(inter_ret) => inter_ret,
-                            _ => {
-                                let empty_logs: Vec<Web3Log> = vec![];
-                                (empty_logs, 0u64)
-                            }
-                        };
-                        r
-                    },
-                );
-                *block_number -= reorg_len as u64;
+                let (reorg, reorg_len) = match *last_block_hash {
+                    Some(h) => block_on(self.removed_logs(h, filter.clone()))?,
+                    None => (Vec::new(), 0),
+                };
 
-                filter.from_block = BlockId::Num(*block_number);
-                filter.to_block = BlockId::Num(3u64); // BlockId::Latest;
+                // Nothing was mined or replaced since the last poll.
+                let from_number = block_number.saturating_sub(reorg_len);
+                if from_number > current_number && reorg.is_empty() {
+                    return Ok(FilterChanges::Logs(Vec::new()));
+                }
 
-                // save the number of the next block as a first block from which
-                // we want to get logs
-                *block_number = current_number + 1;
+                // A poll over too many blocks or logs fails once and moves the
+                // filter to the head, the skipped blocks are left to
+                // eth_getLogs rather than failing every later poll.
+                if self.log_max_block_range > 0
+                    && current_number >= from_number
+                    && current_number - from_number >= self.log_max_block_range
+                {
+                    *block_number = current_number + 1;
+                    *last_block_hash = self.convert_block_hash(BlockId::Num(current_number));
+                    return Err(block_range_exceeded(
+                        from_number,
+                        current_number,
+                        self.log_max_block_range,
+                    ));
+                }
 
-                // save the current block hash, which we used to get back to the
-                // canon chain in case of reorg.
-                *last_block_hash = self.convert_block_hash(BlockId::Num(current_number));
+                filter.from_block = BlockId::Num(from_number);
+                filter.to_block = BlockId::Num(current_number);
 
                 // retrieve logs in range from_block..min(BlockId::Latest..to_block)
                 let limit = filter.limit;
-                let mut web3_logs: Vec<Web3Log> = vec![];
-
-                for topic in filter.topics {
-                    if let Some(addrs) = filter.address.clone() {
-                        for addr in addrs {
-                            let logs = block_on(self.get_logs(Web3Filter {
-                                from_block: Some(filter.from_block.clone()),
-                                to_block:   Some(filter.to_block.clone()),
-                                block_hash: None,
-                                address:    Some(addr),
-                                topics:     topic.clone(),
-                                limit:      None,
-                            }));
-
-                            match logs {
-                                Ok(ret) => {
-                                    for log in ret {
-                                        web3_logs.push(log)
-                                    }
-                                }
-                                _ => {}
-                            }
-                        }
-                    } else {
+                // The removed logs go in the front, so they stay ahead of the
+                // logs replacing them after sorting.
+                let mut web3_logs: Vec<Web3Log> = reorg;
+                if from_number > current_number {
+                    *block_number = current_number + 1;
+                    *last_block_hash = self.convert_block_hash(BlockId::Num(current_number));
+                    return Ok(FilterChanges::Logs(limit_logs(web3_logs, limit)));
+                }
+
+                let topics = Some(
+                    filter
+                        .topics
+                        .iter()
+                        .cloned()
+                        .map(VariadicValue::from)
+                        .collect::<Vec<_>>(),
+                );
+                if let Some(addrs) = filter.address.clone() {
+                    for addr in addrs {
                         let logs = block_on(self.get_logs(Web3Filter {
-                            from_block: Some(filter.from_block.clone()),
-                            to_block:   Some(filter.to_block.clone()),
-                            block_hash: None,
-                            address:    None,
-                            topics:     topic.clone(),
-                            limit:      None,
-                        }));
-
-                        match logs {
-                            Ok(ret) => {
-                                for log in ret {
-                                    web3_logs.push(log)
-                                }
-                            }
-                            _ => {}
-                        }
+                            from_block:    Some(filter.from_block.clone()),
+                            to_block:      Some(filter.to_block.clone()),
+                            block_hash:    None,
+                            address:       Some(addr),
+                            topics:        topics.clone(),
+                            limit:         None,
+                            confirmations: Some(0),
+                            order:         None,
+                        }))?;
+                        web3_logs.extend(logs);
                     }
+                } else {
+                    let logs = block_on(self.get_logs(Web3Filter {
+                        from_block:    Some(filter.from_block.clone()),
+                        to_block:      Some(filter.to_block.clone()),
+                        block_hash:    None,
+                        address:       None,
+                        topics:        topics.clone(),
+                        limit:         None,
+                        confirmations: Some(0),
+                        order:         None,
+                    }))?;
+                    web3_logs.extend(logs);
                 }
-                // append reorg logs in the front
-                web3_logs.extend(reorg);
+                if self.filter_max_logs > 0 && web3_logs.len() > self.filter_max_logs {
+                    *block_number = current_number + 1;
+                    *last_block_hash = self.convert_block_hash(BlockId::Num(current_number));
+                    return Err(Error::Custom(format!(
+                        "filter buffer overflow, {} logs in blocks {} to {} skipped, get them \
+                         with eth_getLogs and poll more frequently",
+                        web3_logs.len(),
+                        from_number,
+                        current_number
+                    )));
+                }
+
+                // Only move the cursor once the logs are delivered, so a poll
+                // failing to read them can be retried. Save the number of
+                // the next block to get logs from, and the current block hash,
+                // which we used to get back to the canon chain in case of
+                // reorg.
+                *block_number = current_number + 1;
+                *last_block_hash = self.convert_block_hash(BlockId::Num(current_number));
+
+                web3_logs.sort_by_key(|log| (log.block_number, log.log_index));
                 let limit_logs = limit_logs(web3_logs, limit);
-                FilterChanges::Logs(limit_logs)
+                Ok(FilterChanges::Logs(limit_logs))
             }
-        });
+        })?;
         Ok(ret_filter_changes)
     }
 
@@ -832,24 +1901,23 @@ impl<Adapter: APIAdapter + 'static> AxonJsonRpcServer for JsonRpcImpl<Adapter> {
     ) -> RpcResult<(Vec<Web3Log>, u64)> {
         let filter = &web3_filter;
 
+        // Walk back from `block_hash` until reaching a block which is still
+        // canonical, the blocks on the way are removed by a reorg.
         let inner = || -> Option<Vec<H256>> {
             let mut route = Vec::new();
             let mut current_block_hash = block_hash;
-            let current_block_header = block_on(self.adapter.get_block_header_by_number(
-                Context::new(),
-                self.convert_block_number(BlockId::Hash(current_block_hash)),
-            ));
-            if current_block_header.is_ok() {
-                let ret_header = current_block_header.unwrap();
-                if let Some(hea) = ret_header {
-                    while Some(current_block_hash)
-                        != self.convert_block_hash(BlockId::Num(hea.number))
-                    {
-                        route.push(current_block_hash);
-
-                        current_block_hash = hea.prev_hash;
-                    }
+            while let Ok(Some(block)) = block_on(
+                self.adapter
+                    .get_block_by_hash(Context::new(), current_block_hash),
+            ) {
+                if Some(current_block_hash)
+                    == self.convert_block_hash(BlockId::Num(block.header.number))
+                {
+                    break;
                 }
+
+                route.push(current_block_hash);
+                current_block_hash = block.header.prev_hash;
             }
             Some(route)
         };
@@ -863,12 +1931,14 @@ impl<Adapter: APIAdapter + 'static> AxonJsonRpcServer for JsonRpcImpl<Adapter> {
             filter.to_block = filter.from_block.clone();
 
             let logs = block_on(self.get_logs(Web3Filter {
-                from_block: Some(filter.from_block.clone()),
-                to_block:   Some(filter.to_block.clone()),
-                block_hash: None,
-                address:    None,
-                topics:     None,
-                limit:      None,
+                from_block:    Some(filter.from_block.clone()),
+                to_block:      Some(filter.to_block.clone()),
+                block_hash:    None,
+                address:       None,
+                topics:        None,
+                limit:         None,
+                confirmations: Some(0),
+                order:         None,
             }));
             match logs {
                 Ok(ret) => {
@@ -889,15 +1959,504 @@ impl<Adapter: APIAdapter + 'static> AxonJsonRpcServer for JsonRpcImpl<Adapter> {
     async fn uninstall_filter(&self, idx: Index) -> RpcResult<bool> {
         Ok(self.polls.lock().remove_poll(&idx.value()))
     }
+
+    #[metrics_rpc("axon_getTransactionStatus")]
+    async fn get_transaction_status(&self, hash: H256) -> RpcResult<Web3TxStatus> {
+        if let Some(receipt) = self
+            .adapter
+            .get_receipt_by_tx_hash(Context::new(), hash)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?
+        {
+            let latest_number = self.block_number().await?;
+            let block_number = U256::from(receipt.block_number);
+            return Ok(Web3TxStatus::Mined {
+                block_number,
+                confirmations: latest_number.saturating_sub(block_number) + 1,
+            });
+        }
+
+        let status = self
+            .adapter
+            .get_pool_tx_status(Context::new(), hash)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+
+        Ok(status.map_or(Web3TxStatus::Unknown, Into::into))
+    }
+
+    #[metrics_rpc("axon_oldestAvailableBlock")]
+    async fn oldest_available_block(&self) -> RpcResult<U256> {
+        self.adapter
+            .get_oldest_available_block(Context::new())
+            .await
+            .map(U256::from)
+            .map_err(|e| Error::Custom(e.to_string()))
+    }
+
+    #[metrics_rpc("axon_simulateAccessSet")]
+    async fn simulate_access_set(
+        &self,
+        req: Web3CallRequest,
+        number: Option<BlockId>,
+    ) -> RpcResult<Web3AccessSet> {
+        let _permit = self.acquire_trace_permit()?;
+        let number = number.unwrap_or_else(|| self.default_block.clone());
+        let header = self
+            .get_header(number.into())
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+        let mock_header = mock_header_by_call_req(header, &req);
+
+        let set = self
+            .adapter
+            .simulate_access_set(
+                Context::new(),
+                req.from,
+                req.to,
+                req.value.unwrap_or_default(),
+                req.data.as_bytes().to_vec(),
+                req.access_list.unwrap_or_default(),
+                mock_header.state_root,
+                mock_header.into(),
+            )
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+
+        Ok(set.into())
+    }
+
+    #[metrics_rpc("axon_callWithLogs")]
+    async fn call_with_logs(
+        &self,
+        req: Web3CallRequest,
+        number: Option<BlockId>,
+    ) -> RpcResult<Web3CallWithLogs> {
+        let number = number.unwrap_or_else(|| self.default_block.clone());
+        let header = self
+            .get_header(number.into())
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+
+        let data_bytes = req.data.as_bytes();
+        let resp = self
+            .call_evm(req, data_bytes, header)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+        self.check_return_size("return data", resp.ret.len())?;
+        if let Some(message) = call_error_message(&resp.exit_reason, &resp.ret) {
+            return Err(Error::Custom(message));
+        }
+
+        let logs = resp
+            .logs
+            .into_iter()
+            .enumerate()
+            .map(|(idx, log)| Web3Log {
+                address:           log.address,
+                topics:            log.topics,
+                data:              Hex::encode(&log.data),
+                block_hash:        None,
+                block_number:      None,
+                transaction_hash:  None,
+                transaction_index: None,
+                log_index:         Some(idx.into()),
+                removed:           false,
+                log_type:          "".to_string(),
+            })
+            .collect();
+
+        Ok(Web3CallWithLogs {
+            return_data: Hex::encode(resp.ret),
+            logs,
+            gas_used: resp.gas_used.into(),
+        })
+    }
+
+    #[metrics_rpc("axon_multicall")]
+    async fn multicall(
+        &self,
+        reqs: Vec<Web3CallRequest>,
+        number: Option<BlockId>,
+    ) -> RpcResult<Vec<Web3CallResult>> {
+        self.check_bundle_size(reqs.len())?;
+        let _permit = self.acquire_trace_permit()?;
+        let number = number.unwrap_or_else(|| self.default_block.clone());
+        let header = self
+            .get_header(number.into())
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+
+        let mut results = Vec::with_capacity(reqs.len());
+        for req in reqs.into_iter() {
+            let data_bytes = req.data.as_bytes();
+            let resp = self
+                .call_evm(req, data_bytes, header.clone())
+                .await
+                .map_err(|e| Error::Custom(e.to_string()))?;
+            results.push(self.call_result(resp));
+        }
+
+        Ok(results)
+    }
+
+    #[metrics_rpc("axon_simulateBundle")]
+    async fn simulate_bundle(
+        &self,
+        reqs: Vec<Web3CallRequest>,
+        number: Option<BlockId>,
+    ) -> RpcResult<Vec<Web3CallResult>> {
+        self.check_bundle_size(reqs.len())?;
+        let _permit = self.acquire_trace_permit()?;
+        let number = number.unwrap_or_else(|| self.default_block.clone());
+        let header = self
+            .get_header(number.into())
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+
+        let calls = reqs
+            .into_iter()
+            .map(|req| {
+                let gas_limit = req.gas.unwrap_or(header.gas_limit);
+                let gas_limit = if gas_limit > U256::from(u64::MAX) {
+                    u64::MAX
+                } else {
+                    gas_limit.as_u64()
+                };
+
+                BundleCall {
+                    from: req.from,
+                    to: req.to,
+                    value: req.value.unwrap_or_default(),
+                    gas_limit,
+                    data: req.data.as_bytes().to_vec(),
+                    access_list: req.access_list.unwrap_or_default(),
+                }
+            })
+            .collect();
+        let resps = self
+            .adapter
+            .simulate_bundle(Context::new(), calls, header.state_root, header.into())
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+
+        Ok(resps
+            .into_iter()
+            .map(|resp| self.call_result(resp))
+            .collect())
+    }
+
+    #[metrics_rpc("axon_isBlockCanonical")]
+    async fn is_block_canonical(&self, hash: H256) -> RpcResult<bool> {
+        let number = self
+            .adapter
+            .get_block_by_hash(Context::new(), hash)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?
+            .ok_or_else(|| Error::Custom(format!("Cannot get block {:?}", hash)))?
+            .header
+            .number;
+
+        let canonical = self
+            .adapter
+            .get_block_by_number(Context::new(), Some(number))
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+
+        Ok(canonical.map_or(false, |b| b.header_hash() == hash))
+    }
+
+    #[metrics_rpc("axon_verifyBlockProof")]
+    async fn verify_block_proof(&self, block_hash: H256) -> RpcResult<bool> {
+        let block = self
+            .adapter
+            .get_block_by_hash(Context::new(), block_hash)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?
+            .ok_or_else(|| Error::Custom(format!("Cannot get block {:?}", block_hash)))?;
+        let number = block.header.number;
+        // The genesis block is agreed on by the community instead of a proof.
+        if number == 0 {
+            return Ok(true);
+        }
+
+        let proof = match self
+            .adapter
+            .get_block_proof(Context::new(), number)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?
+        {
+            Some(proof) => proof,
+            None => return Ok(false),
+        };
+        let metadata = self
+            .adapter
+            .get_metadata(Context::new(), number)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?
+            .ok_or_else(|| {
+                Error::Custom(format!("Cannot get the validators of block {}", number))
+            })?;
+
+        Ok(verify_block_proof(&block, &proof, &metadata).is_ok())
+    }
+
+    #[metrics_rpc("debug_traceTransaction")]
+    async fn trace_transaction(
+        &self,
+        hash: H256,
+        options: Option<TraceOptions>,
+    ) -> RpcResult<BTreeMap<H160, Web3PrestateAccount>> {
+        let tracer = options.unwrap_or_default().tracer;
+        if tracer.as_deref() != Some("prestateTracer") {
+            return Err(Error::Custom(format!(
+                "Unsupported tracer {:?}, only prestateTracer is supported",
+                tracer
+            )));
+        }
+        let _permit = self.acquire_trace_permit()?;
+
+        let receipt = self
+            .adapter
+            .get_receipt_by_tx_hash(Context::new(), hash)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?
+            .ok_or_else(|| Error::Custom(format!("Cannot get receipt of {:?}", hash)))?;
+        let number = receipt.block_number;
+        if number == 0 {
+            return Err(Error::Custom("Cannot trace the genesis block".to_string()));
+        }
+
+        let block = self
+            .adapter
+            .get_block_by_number(Context::new(), Some(number))
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?
+            .ok_or_else(|| Error::Custom(format!("Cannot get block {}", number)))?;
+        let parent = self
+            .get_header(Some(number - 1))
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+
+        // The transactions before the traced one in the same block are replayed
+        // on top of the parent state first.
+        let index = block
+            .tx_hashes
+            .iter()
+            .position(|h| *h == hash)
+            .ok_or_else(|| Error::Custom(format!("Cannot find {:?} in block {}", hash, number)))?;
+        let mut txs = self
+            .adapter
+            .get_transactions_by_hashes(Context::new(), number, &block.tx_hashes[..=index])
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| Error::Custom(format!("Cannot get transactions of block {}", number)))?;
+        let tx = txs.pop().unwrap();
+
+        let prestate = self
+            .adapter
+            .trace_prestate(
+                Context::new(),
+                txs,
+                tx,
+                parent.state_root,
+                block.header.into(),
+            )
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+        // The raw size of the codes and slots, the hex output is about twice
+        // as large.
+        let size = prestate
+            .values()
+            .map(|account| account.code.len() + account.storage.len() * 64)
+            .sum();
+        self.check_return_size("trace", size)?;
+
+        Ok(prestate
+            .into_iter()
+            .map(|(address, account)| (address, account.into()))
+            .collect())
+    }
+
+    #[metrics_rpc("debug_storageRangeAt")]
+    async fn storage_range_at(
+        &self,
+        block_hash: H256,
+        tx_index: usize,
+        address: H160,
+        start_key: H256,
+        max_result: usize,
+    ) -> RpcResult<Web3StorageRange> {
+        let _permit = self.acquire_trace_permit()?;
+
+        let block = self
+            .adapter
+            .get_block_by_hash(Context::new(), block_hash)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?
+            .ok_or_else(|| Error::Custom(format!("Cannot get block {:?}", block_hash)))?;
+        let number = block.header.number;
+        if number == 0 {
+            return Err(Error::Custom("Cannot trace the genesis block".to_string()));
+        }
+        if tx_index > block.tx_hashes.len() {
+            return Err(Error::Custom(format!(
+                "Transaction index {} out of range for block {}",
+                tx_index, number
+            )));
+        }
+
+        let parent = self
+            .get_header(Some(number - 1))
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+        let preceding = self
+            .adapter
+            .get_transactions_by_hashes(Context::new(), number, &block.tx_hashes[..tx_index])
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| Error::Custom(format!("Cannot get transactions of block {}", number)))?;
+
+        self.adapter
+            .storage_range(
+                Context::new(),
+                preceding,
+                address,
+                start_key,
+                max_result.min(self.max_storage_range),
+                parent.state_root,
+                block.header.into(),
+            )
+            .await
+            .map(Into::into)
+            .map_err(|e| Error::Custom(e.to_string()))
+    }
+}
+
+/// The error of a logs query over `max_range` blocks, its data suggests the
+/// sub-ranges to split the query into, at most `MAX_SUGGESTED_RANGES` of them.
+fn block_range_exceeded(start: BlockNumber, end: BlockNumber, max_range: u64) -> Error {
+    let ranges = split_block_range(start, end, max_range);
+    let data = serde_json::value::to_raw_value(&ranges).ok();
+
+    Error::Call(CallError::Custom {
+        code: LIMIT_EXCEEDED_CODE,
+        message: format!(
+            "query exceeds max block range {}, split it into the ranges in data",
+            max_range
+        ),
+        data,
+    })
+}
+
+fn mining_not_supported(method: &str) -> Error {
+    Error::Call(CallError::Custom {
+        code:    METHOD_NOT_SUPPORTED_CODE,
+        message: format!(
+            "{} is not supported, blocks are produced by consensus rather than mined",
+            method
+        ),
+        data:    None,
+    })
+}
+
+fn split_block_range(start: BlockNumber, end: BlockNumber, max_range: u64) -> Vec<(U64, U64)> {
+    let mut ranges = Vec::new();
+    let mut from = start;
+
+    while from <= end && ranges.len() < MAX_SUGGESTED_RANGES {
+        let to = from.saturating_add(max_range - 1).min(end);
+        ranges.push((from.into(), to.into()));
+        if to == end {
+            break;
+        }
+        from = to + 1;
+    }
+
+    ranges
+}
+
+fn check_reward_percentiles(percentiles: &[f64]) -> RpcResult<()> {
+    let mut prev = 0.0;
+    for (idx, p) in percentiles.iter().enumerate() {
+        if !(0.0..=100.0).contains(p) || *p < prev {
+            return Err(Error::Custom(format!(
+                "invalid reward percentile #{}: {}",
+                idx, p
+            )));
+        }
+        prev = *p;
+    }
+    Ok(())
 }
 
-fn best_block_number() -> u64 {
-    0u64
+/// Select the rewards at ascending `percentiles` from `(tip, gas used)`
+/// pairs. The transactions are sorted by tip and a percentile picks the first
+/// one at which the cumulative gas used reaches that share of the total, as
+/// geth does. An empty block rewards nothing.
+fn gas_weighted_percentiles(mut tips: Vec<(U256, u64)>, percentiles: &[f64]) -> Vec<U256> {
+    if tips.is_empty() {
+        return vec![U256::zero(); percentiles.len()];
+    }
+
+    tips.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    let total_gas = tips.iter().map(|(_, gas)| *gas).sum::<u64>();
+    let mut idx = 0;
+    let mut sum_gas = tips[0].1;
+
+    percentiles
+        .iter()
+        .map(|p| {
+            let threshold = (total_gas as f64 * p / 100.0) as u64;
+            while sum_gas < threshold && idx < tips.len() - 1 {
+                idx += 1;
+                sum_gas += tips[idx].1;
+            }
+            tips[idx].0
+        })
+        .collect()
 }
 
-fn pending_transaction_hashes() -> BTreeSet<H256> {
-    let btree: BTreeSet<H256> = BTreeSet::new();
-    btree
+/// Like `gas_weighted_percentiles`, but each transaction stands at the middle
+/// of its share of the total gas and a percentile between two of them gets a
+/// reward interpolated linearly from their tips. Below the first or above the
+/// last transaction the reward is its tip.
+fn linear_gas_weighted_percentiles(mut tips: Vec<(U256, u64)>, percentiles: &[f64]) -> Vec<U256> {
+    const PRECISION: u64 = 1_000_000;
+
+    let total_gas = tips.iter().map(|(_, gas)| *gas).sum::<u64>();
+    if total_gas == 0 {
+        return gas_weighted_percentiles(tips, percentiles);
+    }
+
+    tips.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    let mut sum_gas = 0;
+    let points = tips
+        .iter()
+        .map(|(tip, gas)| {
+            let mid = (sum_gas as f64 + *gas as f64 / 2.0) * 100.0 / total_gas as f64;
+            sum_gas += gas;
+            (mid, *tip)
+        })
+        .collect::<Vec<_>>();
+
+    percentiles
+        .iter()
+        .map(|p| match points.iter().position(|(mid, _)| mid > p) {
+            Some(0) => points[0].1,
+            None => points[points.len() - 1].1,
+            Some(idx) => {
+                let (lo_mid, lo) = points[idx - 1];
+                let (hi_mid, hi) = points[idx];
+                let frac = ((p - lo_mid) / (hi_mid - lo_mid) * PRECISION as f64) as u64;
+                lo + (hi - lo) * frac / PRECISION
+            }
+        })
+        .collect()
 }
 
 fn limit_logs(mut logs: Vec<Web3Log>, limit: Option<usize>) -> Vec<Web3Log> {
@@ -944,29 +2503,47 @@ fn mock_header_by_call_req(latest_header: Header, call_req: &Web3CallRequest) ->
     }
 }
 
-fn from_receipt_to_web3_log(
+pub(crate) fn from_receipt_to_web3_log(
     index: usize,
-    topics: &[H256],
+    topics: &[Option<Vec<H256>>],
     receipt: Receipt,
     logs: &mut Vec<Web3Log>,
 ) {
-    for log in receipt.logs {
-        for (idx, topic) in log.topics.iter().enumerate() {
-            if topics.contains(topic) {
-                let web3_log = Web3Log {
-                    address:           receipt.sender,
-                    topics:            log.topics.clone(),
-                    data:              Hex::encode(&log.data),
-                    block_hash:        Some(receipt.block_hash),
-                    block_number:      Some(receipt.block_number.into()),
-                    transaction_hash:  Some(receipt.tx_hash),
-                    transaction_index: Some(receipt.tx_index.into()),
-                    log_index:         Some((index + idx).into()),
-                    removed:           false,
-                    log_type:          "".to_string(),
-                };
-                logs.push(web3_log);
-            }
+    for (idx, log) in receipt.logs.into_iter().enumerate() {
+        if !topics_match(topics, &log.topics) {
+            continue;
         }
+
+        let web3_log = Web3Log {
+            address:           log.address,
+            topics:            log.topics,
+            data:              Hex::encode(&log.data),
+            block_hash:        Some(receipt.block_hash),
+            block_number:      Some(receipt.block_number.into()),
+            transaction_hash:  Some(receipt.tx_hash),
+            transaction_index: Some(receipt.tx_index.into()),
+            log_index:         Some((index + idx).into()),
+            removed:           false,
+            log_type:          "".to_string(),
+        };
+        logs.push(web3_log);
+    }
+}
+
+/// Whether the topics of a log match the topics of a filter by position, a
+/// `None` or empty position matches any topic. Like geth a log with fewer
+/// topics than the filter never matches, so the logs of anonymous events,
+/// which may have no topic at all, only match a filter without topics.
+fn topics_match(filter: &[Option<Vec<H256>>], topics: &[H256]) -> bool {
+    if filter.len() > topics.len() {
+        return false;
     }
+
+    filter
+        .iter()
+        .zip(topics.iter())
+        .all(|(wanted, topic)| match wanted {
+            Some(wanted) if !wanted.is_empty() => wanted.contains(topic),
+            _ => true,
+        })
 }
@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use parking_lot::RwLock;
+
+use common_config_parser::types::ConfigKeystoreAccount;
+use common_crypto::{
+    Crypto, PrivateKey, Secp256k1Recoverable, Secp256k1RecoverablePrivateKey, Signature,
+    UncompressedPublicKey,
+};
+use protocol::types::{public_to_address, Bytes, Hash, Public, SignatureComponents, H160};
+use protocol::ProtocolResult;
+
+use crate::APIError;
+
+/// The signing backend of `eth_sendTransaction`. An account has to be
+/// unlocked before it can sign.
+pub trait KeyStore: Send + Sync {
+    fn accounts(&self) -> Vec<H160>;
+
+    /// Unlock the account for `duration`, or until it is locked again if
+    /// `None`. Return false if the account is unknown or the password is
+    /// wrong.
+    fn unlock(&self, address: H160, password: &str, duration: Option<Duration>) -> bool;
+
+    /// Return false if the account is unknown.
+    fn lock(&self, address: H160) -> bool;
+
+    fn sign(&self, address: H160, hash: Hash) -> ProtocolResult<SignatureComponents>;
+}
+
+struct LocalAccount {
+    privkey:  Bytes,
+    password: String,
+}
+
+struct UnlockedAccount {
+    privkey:  Bytes,
+    deadline: Option<Instant>,
+}
+
+/// Keeps the private keys of the `[[rpc.keystore]]` accounts in memory in
+/// plain text, as they are in the config. The password only gates the
+/// signing through the RPC, it does not protect the key.
+pub struct LocalKeyStore {
+    accounts: HashMap<H160, LocalAccount>,
+    unlocked: RwLock<HashMap<H160, UnlockedAccount>>,
+}
+
+impl LocalKeyStore {
+    pub fn new(accounts: &[ConfigKeystoreAccount]) -> ProtocolResult<Self> {
+        let mut ret = HashMap::with_capacity(accounts.len());
+
+        for account in accounts.iter() {
+            let privkey = account.privkey.as_bytes();
+            let address = privkey_address(&privkey)?;
+
+            ret.insert(address, LocalAccount {
+                privkey,
+                password: account.password.clone(),
+            });
+        }
+
+        Ok(LocalKeyStore {
+            accounts: ret,
+            unlocked: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// The private key of `address` if it is unlocked. An expired unlock is
+    /// dropped along with its key.
+    fn unlocked_privkey(&self, address: &H160) -> Option<Bytes> {
+        {
+            let unlocked = self.unlocked.read();
+            let account = unlocked.get(address)?;
+            if account.deadline.map_or(true, |d| Instant::now() < d) {
+                return Some(account.privkey.clone());
+            }
+        }
+
+        self.unlocked.write().remove(address);
+        None
+    }
+}
+
+fn privkey_address(privkey: &[u8]) -> ProtocolResult<H160> {
+    let pubkey = Secp256k1RecoverablePrivateKey::try_from(privkey)
+        .map_err(|e| APIError::KeyStore(e.to_string()))?
+        .pub_key();
+    Ok(public_to_address(&Public::from_slice(
+        &pubkey.to_uncompressed_bytes()[1..65],
+    )))
+}
+
+impl KeyStore for LocalKeyStore {
+    fn accounts(&self) -> Vec<H160> {
+        self.accounts.keys().copied().collect()
+    }
+
+    fn unlock(&self, address: H160, password: &str, duration: Option<Duration>) -> bool {
+        let account = match self.accounts.get(&address) {
+            Some(account) if account.password == password => account,
+            _ => return false,
+        };
+
+        self.unlocked.write().insert(address, UnlockedAccount {
+            privkey:  account.privkey.clone(),
+            deadline: duration.map(|d| Instant::now() + d),
+        });
+        true
+    }
+
+    fn lock(&self, address: H160) -> bool {
+        self.unlocked.write().remove(&address);
+        self.accounts.contains_key(&address)
+    }
+
+    fn sign(&self, address: H160, hash: Hash) -> ProtocolResult<SignatureComponents> {
+        if !self.accounts.contains_key(&address) {
+            return Err(APIError::KeyStore(format!("unknown account {:?}", address)).into());
+        }
+
+        let privkey = self
+            .unlocked_privkey(&address)
+            .ok_or_else(|| APIError::KeyStore(format!("account {:?} is locked", address)))?;
+
+        let signature = Secp256k1Recoverable::sign_message(hash.as_bytes(), &privkey)
+            .map_err(|e| APIError::KeyStore(e.to_string()))?
+            .to_bytes();
+        Ok(signature.into())
+    }
+}
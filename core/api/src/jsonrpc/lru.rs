@@ -0,0 +1,132 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+/// A map holding at most `capacity` entries, which evicts the least recently
+/// used one to make room. Each access stamps the entry with a tick, and the
+/// entries are ordered by their ticks, so every operation takes logarithmic
+/// time in the number of entries.
+pub struct LruCache<K, V> {
+    capacity: usize,
+    tick:     u64,
+    entries:  HashMap<K, (u64, V)>,
+    order:    BTreeMap<u64, K>,
+}
+
+impl<K: Hash + Eq + Clone, V> LruCache<K, V> {
+    pub fn new(capacity: usize) -> Self {
+        LruCache {
+            capacity,
+            tick: 0,
+            entries: HashMap::with_capacity(capacity),
+            order: BTreeMap::new(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    #[cfg(test)]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Return the value of `key` without marking it as used.
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        self.entries.get(key).map(|(_, value)| value)
+    }
+
+    /// Return the value of `key` and mark it as the most recently used.
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let tick = self.next_tick();
+        let (used_at, value) = self.entries.get_mut(key)?;
+        let key = self.order.remove(used_at).expect("lru order");
+        self.order.insert(tick, key);
+        *used_at = tick;
+        Some(value)
+    }
+
+    /// Insert or replace the value of `key`, evicting the least recently used
+    /// entry when the cache is full. Nothing is kept when the capacity is 0.
+    pub fn insert(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        self.remove(&key);
+        while self.entries.len() >= self.capacity {
+            let oldest = match self.order.keys().next() {
+                Some(tick) => *tick,
+                None => break,
+            };
+            let evicted = self.order.remove(&oldest).expect("lru order");
+            self.entries.remove(&evicted);
+        }
+
+        let tick = self.next_tick();
+        self.order.insert(tick, key.clone());
+        self.entries.insert(key, (tick, value));
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        let (used_at, value) = self.entries.remove(key)?;
+        self.order.remove(&used_at);
+        Some(value)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.order.clear();
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evict_least_recently_used() {
+        let mut cache = LruCache::new(2);
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+        assert_eq!(cache.get(&1), Some(&"a"));
+
+        cache.insert(3, "c");
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(&2), None);
+        assert_eq!(cache.get(&1), Some(&"a"));
+        assert_eq!(cache.get(&3), Some(&"c"));
+
+        // Replacing a value marks it as used.
+        cache.insert(1, "d");
+        cache.insert(4, "e");
+        assert_eq!(cache.peek(&3), None);
+        assert_eq!(cache.peek(&1), Some(&"d"));
+    }
+
+    #[test]
+    fn test_peek_does_not_touch() {
+        let mut cache = LruCache::new(2);
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+        assert_eq!(cache.peek(&1), Some(&"a"));
+
+        cache.insert(3, "c");
+        assert_eq!(cache.peek(&1), None);
+        assert_eq!(cache.remove(&2), Some("b"));
+        assert_eq!(cache.len(), 1);
+    }
+
+    #[test]
+    fn test_zero_capacity() {
+        let mut cache = LruCache::new(0);
+        cache.insert(1, "a");
+        assert!(cache.is_empty());
+    }
+}
@@ -1,22 +1,41 @@
-mod r#impl;
-mod poll_filter;
+mod call_cache;
+pub(crate) mod consensus_rpc;
+pub(crate) mod eip712;
+pub(crate) mod r#impl;
+pub(crate) mod keystore;
+mod lru;
+mod nonce_cache;
+mod pending_state;
+pub(crate) mod personal_rpc;
+pub(crate) mod poll_filter;
 mod poll_manager;
-mod web3_types;
-
+mod rate_limit;
+pub(crate) mod request_log;
+pub(crate) mod web3_types;
+pub(crate) mod ws_conn_limit;
+pub(crate) mod ws_subscription;
+
+use std::collections::BTreeMap;
+use std::net::{Ipv4Addr, SocketAddr};
 use std::sync::Arc;
 
 use jsonrpsee::http_server::{HttpServerBuilder, HttpServerHandle};
 use jsonrpsee::ws_server::{WsServerBuilder, WsServerHandle};
-use jsonrpsee::{core::Error, proc_macros::rpc};
+use jsonrpsee::{core::Error, proc_macros::rpc, RpcModule};
 
 use common_config_parser::types::ConfigApi;
+use protocol::tokio::{self, net::TcpListener};
 use protocol::traits::APIAdapter;
 use protocol::types::{Hash, Hex, H160, H256, U256};
 use protocol::ProtocolResult;
 
+use crate::jsonrpc::eip712::TypedData;
 use crate::jsonrpc::web3_types::{
-    BlockId, ChangeWeb3Filter, Filter, FilterChanges, Index, Web3Block, Web3CallRequest,
-    Web3FeeHistory, Web3Filter, Web3Log, Web3Receipt, Web3SyncStatus, Web3Transaction,
+    BlockId, ChangeWeb3Filter, Filter, FilterChanges, Index, SubscriptionKind, SubscriptionParams,
+    TraceOptions, Web3AccessSet, Web3AccountProof, Web3Block, Web3CallRequest, Web3CallResult,
+    Web3CallWithLogs, Web3ConsensusStatus, Web3FeeHistory, Web3Filter, Web3Log,
+    Web3PrestateAccount, Web3Receipt, Web3StorageKey, Web3StorageRange, Web3SyncStatus,
+    Web3Transaction, Web3TxPropagation, Web3TxStatus,
 };
 
 use crate::APIError;
@@ -29,6 +48,15 @@ pub trait AxonJsonRpc {
     #[method(name = "eth_sendRawTransaction")]
     async fn send_raw_transaction(&self, tx: Hex) -> RpcResult<H256>;
 
+    /// Like `eth_sendRawTransaction`, but returns only after the transaction
+    /// has been gossiped to the peers or `timeout` milliseconds have passed.
+    #[method(name = "axon_sendRawTransactionSync")]
+    async fn send_raw_transaction_sync(
+        &self,
+        tx: Hex,
+        timeout: Option<u64>,
+    ) -> RpcResult<Web3TxPropagation>;
+
     /// Get transaction by its hash.
     #[method(name = "eth_getTransactionByHash")]
     async fn get_transaction_by_hash(&self, hash: H256) -> RpcResult<Option<Web3Transaction>>;
@@ -51,18 +79,37 @@ pub trait AxonJsonRpc {
     #[method(name = "eth_blockNumber")]
     async fn block_number(&self) -> RpcResult<U256>;
 
+    /// The block parameter of the state methods may be omitted, the
+    /// `default_block_tag` of the config, `latest` unless configured, is
+    /// used then.
     #[method(name = "eth_getTransactionCount")]
-    async fn get_transaction_count(&self, address: H160, number: BlockId) -> RpcResult<U256>;
+    async fn get_transaction_count(
+        &self,
+        address: H160,
+        number: Option<BlockId>,
+    ) -> RpcResult<U256>;
 
     #[method(name = "eth_getBlockTransactionCountByNumber")]
     async fn get_transaction_count_by_number(&self, number: BlockId) -> RpcResult<U256>;
 
     #[method(name = "eth_getBalance")]
-    async fn get_balance(&self, address: H160, number: BlockId) -> RpcResult<U256>;
+    async fn get_balance(&self, address: H160, number: Option<BlockId>) -> RpcResult<U256>;
+
+    /// The Merkle proofs of an account and of some of its storage slots, a
+    /// slot may be given in full or as a quantity such as `0x1`.
+    #[method(name = "eth_getProof")]
+    async fn get_proof(
+        &self,
+        address: H160,
+        storage_keys: Vec<Web3StorageKey>,
+        number: Option<BlockId>,
+    ) -> RpcResult<Web3AccountProof>;
 
     #[method(name = "eth_call")]
-    async fn call(&self, req: Web3CallRequest, number: BlockId) -> RpcResult<Hex>;
+    async fn call(&self, req: Web3CallRequest, number: Option<BlockId>) -> RpcResult<Hex>;
 
+    /// Returns the least gas the call succeeds with, which may be more than
+    /// the gas it uses once the refund is paid back.
     #[method(name = "eth_estimateGas")]
     async fn estimate_gas(&self, req: Web3CallRequest, number: Option<BlockId>) -> RpcResult<U256>;
 
@@ -73,7 +120,17 @@ pub trait AxonJsonRpc {
     async fn net_version(&self) -> RpcResult<U256>;
 
     #[method(name = "eth_getCode")]
-    async fn get_code(&self, address: H160, number: BlockId) -> RpcResult<Hex>;
+    async fn get_code(&self, address: H160, number: Option<BlockId>) -> RpcResult<Hex>;
+
+    /// At `pending` the transactions of the pending block are executed on
+    /// top of the latest state, like the code of `eth_getCode`.
+    #[method(name = "eth_getStorageAt")]
+    async fn get_storage_at(
+        &self,
+        address: H160,
+        position: Web3StorageKey,
+        number: Option<BlockId>,
+    ) -> RpcResult<H256>;
 
     #[method(name = "eth_getTransactionReceipt")]
     async fn get_transaction_receipt(&self, hash: H256) -> RpcResult<Option<Web3Receipt>>;
@@ -90,6 +147,9 @@ pub trait AxonJsonRpc {
     #[method(name = "eth_gasPrice")]
     async fn gas_price(&self) -> RpcResult<U256>;
 
+    #[method(name = "eth_maxPriorityFeePerGas")]
+    async fn max_priority_fee_per_gas(&self) -> RpcResult<U256>;
+
     #[method(name = "eth_getLogs")]
     async fn get_logs(&self, filter: Web3Filter) -> RpcResult<Vec<Web3Log>>;
 
@@ -98,7 +158,7 @@ pub trait AxonJsonRpc {
         &self,
         block_count: u64,
         newest_block: BlockId,
-        reward_percentiles: Option<Vec<u64>>,
+        reward_percentiles: Option<Vec<f64>>,
     ) -> RpcResult<Web3FeeHistory>;
 
     #[method(name = "web3_clientVersion")]
@@ -128,18 +188,27 @@ pub trait AxonJsonRpc {
     #[method(name = "eth_uninstallFilter")]
     async fn uninstall_filter(&self, index: Index) -> RpcResult<bool>;
 
+    /// Axon blocks are produced by the BFT consensus rather than mined, so
+    /// the mining methods fail with a method not supported error. With
+    /// `mining_rpc_compat` they answer like a node that is not mining
+    /// instead: the zero address here.
     #[method(name = "eth_coinbase")]
     async fn coinbase(&self) -> RpcResult<H160>;
 
+    /// Not supported, or a zero hashrate in compatibility mode.
     #[method(name = "eth_hashrate")]
     async fn hashrate(&self) -> RpcResult<U256>;
 
+    /// Not supported even in compatibility mode, there is no work to mine.
     #[method(name = "eth_getWork")]
     async fn get_work(&self) -> RpcResult<(Hash, Hash, Hash)>;
 
-    #[method(name = "eth_submitWork ")]
+    /// Not supported, or false in compatibility mode as no work is accepted.
+    #[method(name = "eth_submitWork")]
     async fn submit_work(&self, _nc: U256, _hash: H256, _summary: Hex) -> RpcResult<bool>;
 
+    /// Not supported, or false in compatibility mode as the hashrate is
+    /// ignored.
     #[method(name = "eth_submitHashrate")]
     async fn submit_hashrate(&self, _hash_rate: Hex, _client_id: Hex) -> RpcResult<bool>;
 
@@ -149,12 +218,152 @@ pub trait AxonJsonRpc {
         block_hash: H256,
         web3_filter: Filter,
     ) -> RpcResult<(Vec<Web3Log>, u64)>;
+
+    /// Returns whether a transaction is unknown, in the mempool, dropped or
+    /// mined with its confirmations.
+    #[method(name = "axon_getTransactionStatus")]
+    async fn get_transaction_status(&self, hash: H256) -> RpcResult<Web3TxStatus>;
+
+    /// Returns the lowest block number whose body and state this node still
+    /// has, 0 for an archive node. Older history has to be queried from
+    /// another node.
+    #[method(name = "axon_oldestAvailableBlock")]
+    async fn oldest_available_block(&self) -> RpcResult<U256>;
+
+    /// Like `eth_call`, and also returns the logs emitted by the call and the
+    /// gas it used.
+    #[method(name = "axon_callWithLogs")]
+    async fn call_with_logs(
+        &self,
+        req: Web3CallRequest,
+        number: Option<BlockId>,
+    ) -> RpcResult<Web3CallWithLogs>;
+
+    /// Simulate the call and return the accounts and storage slots it reads
+    /// and writes. A written account or slot is not among the reads.
+    #[method(name = "axon_simulateAccessSet")]
+    async fn simulate_access_set(
+        &self,
+        req: Web3CallRequest,
+        number: Option<BlockId>,
+    ) -> RpcResult<Web3AccessSet>;
+
+    /// Run the calls on the state of one block. Every call starts from that
+    /// state, the changes of a call are dropped before the next one. Takes at
+    /// most `max_bundle_calls` calls and one of the trace slots.
+    #[method(name = "axon_multicall")]
+    async fn multicall(
+        &self,
+        reqs: Vec<Web3CallRequest>,
+        number: Option<BlockId>,
+    ) -> RpcResult<Vec<Web3CallResult>>;
+
+    /// Run the calls on the state of one block one after the other, unlike
+    /// `axon_multicall` each call sees the changes of the successful calls
+    /// before it. Nothing is committed.
+    #[method(name = "axon_simulateBundle")]
+    async fn simulate_bundle(
+        &self,
+        reqs: Vec<Web3CallRequest>,
+        number: Option<BlockId>,
+    ) -> RpcResult<Vec<Web3CallResult>>;
+
+    /// Returns whether the block of the hash is on the canonical chain, false
+    /// for an orphaned block the node still stores. `eth_getBlockByHash`
+    /// returns both.
+    #[method(name = "axon_isBlockCanonical")]
+    async fn is_block_canonical(&self, hash: H256) -> RpcResult<bool>;
+
+    /// Whether the consensus proof of the block is signed by more than two
+    /// thirds of the vote weight of the validators at its height. The latest
+    /// block may have no proof yet, and so is not final.
+    #[method(name = "axon_verifyBlockProof")]
+    async fn verify_block_proof(&self, block_hash: H256) -> RpcResult<bool>;
+
+    /// Replays the transaction and returns the state it touched as it was
+    /// before execution. Only the `prestateTracer` is supported.
+    #[method(name = "debug_traceTransaction")]
+    async fn trace_transaction(
+        &self,
+        hash: H256,
+        options: Option<TraceOptions>,
+    ) -> RpcResult<BTreeMap<H160, Web3PrestateAccount>>;
+
+    /// Returns up to `max_result` non-zero storage slots of the account from
+    /// `start_key` on, as they are before the transaction at `tx_index` of the
+    /// block executes, and the key to continue from.
+    #[method(name = "debug_storageRangeAt")]
+    async fn storage_range_at(
+        &self,
+        block_hash: H256,
+        tx_index: usize,
+        address: H160,
+        start_key: H256,
+        max_result: usize,
+    ) -> RpcResult<Web3StorageRange>;
+}
+
+#[rpc(server)]
+pub trait AxonConsensusRpc {
+    /// Returns the overlord height, round and the last commit of this node.
+    #[method(name = "axon_consensusStatus")]
+    async fn consensus_status(&self) -> RpcResult<Web3ConsensusStatus>;
+}
+
+#[rpc(server)]
+pub trait AxonPersonalRpc {
+    /// Signs the transaction with an unlocked keystore account and sends it,
+    /// returning its hash.
+    #[method(name = "eth_sendTransaction")]
+    async fn send_transaction(&self, req: Web3CallRequest) -> RpcResult<H256>;
+
+    /// Unlocks the account for `duration` seconds, 300 by default and until
+    /// locked again if 0.
+    #[method(name = "personal_unlockAccount")]
+    async fn unlock_account(
+        &self,
+        address: H160,
+        password: String,
+        duration: Option<u64>,
+    ) -> RpcResult<bool>;
+
+    #[method(name = "personal_lockAccount")]
+    async fn lock_account(&self, address: H160) -> RpcResult<bool>;
+
+    /// Signs the EIP-712 typed data with an unlocked keystore account,
+    /// returning the 65 bytes signature.
+    #[method(name = "eth_signTypedData_v4")]
+    async fn sign_typed_data(&self, address: H160, data: TypedData) -> RpcResult<Hex>;
+}
+
+#[rpc(server)]
+pub trait AxonPubSub {
+    /// Subscribe to `newHeads`, `newPendingTransactions` or `logs`, only
+    /// available on the web socket server. `newPendingTransactions` takes an
+    /// optional `fullTx` flag, see `Web3PendingTransaction` for its items.
+    /// `logs` takes an optional filter, which is rejected right away if it is
+    /// invalid like for `eth_newFilter` or names a block by hash. Its
+    /// `fromBlock` and `toBlock` bound the blocks whose logs are pushed.
+    #[subscription(name = "eth_subscribe", unsubscribe = "eth_unsubscribe", item = Web3Block)]
+    fn subscribe(
+        &self,
+        kind: SubscriptionKind,
+        params: Option<SubscriptionParams>,
+    ) -> RpcResult<()>;
+}
+
+/// The handle of the web socket server, with the task forwarding the
+/// connections to it when they are limited. The forwarding stops once this is
+/// dropped, stopping the server does not need to wait for it.
+pub struct WsServer {
+    pub handle: WsServerHandle,
+    _forward:   Option<ws_conn_limit::ForwardTask>,
 }
 
 pub async fn run_jsonrpc_server<Adapter: APIAdapter + 'static>(
     config: ConfigApi,
     adapter: Arc<Adapter>,
-) -> ProtocolResult<(Option<HttpServerHandle>, Option<WsServerHandle>)> {
+) -> ProtocolResult<(Option<HttpServerHandle>, Option<WsServer>)> {
     let mut ret = (None, None);
 
     if let Some(addr) = config.http_listening_address {
@@ -163,37 +372,156 @@ pub async fn run_jsonrpc_server<Adapter: APIAdapter + 'static>(
             .build(addr)
             .map_err(|e| APIError::HttpServer(e.to_string()))?;
 
+        let rpc = rpc_module(
+            Arc::clone(&adapter),
+            &config,
+            serves_personal_rpc(&config, addr),
+        )
+        .map_err(|e| APIError::HttpServer(e.to_string()))?;
+        let rpc = request_log::log_requests(rpc, config.request_log)
+            .map_err(|e| APIError::HttpServer(e.to_string()))?;
+
         ret.0 = Some(
             server
-                .start(
-                    r#impl::JsonRpcImpl::new(
-                        Arc::clone(&adapter),
-                        &config.client_version,
-                        config.life_time,
-                    )
-                    .into_rpc(),
-                )
+                .start(rpc)
                 .map_err(|e| APIError::HttpServer(e.to_string()))?,
         );
     }
 
     if let Some(addr) = config.ws_listening_address {
-        let server = WsServerBuilder::new()
+        let mut builder = WsServerBuilder::new()
             .max_request_body_size(config.max_payload_size as u32)
-            .max_connections(config.maxconn as u64)
-            .build(addr)
+            .max_connections(config.maxconn as u64);
+
+        // With a per IP or an unread bytes limit the server listens on a
+        // local port behind a listener counting the connections of every IP
+        // and buffering what the clients leave unread, since the server
+        // queues it without bound. The server only accepts the host the
+        // listener rewrites the requests to, so it can't be reached around
+        // the listener.
+        let limited = config.ws_max_conn_per_ip > 0 || config.ws_max_buffered_bytes > 0;
+        let (server_addr, upstream_host) = if limited {
+            let host = ws_conn_limit::upstream_host()
+                .map_err(|e| APIError::WebSocketServer(e.to_string()))?;
+            builder = builder
+                .set_allowed_hosts([host.clone()])
+                .map_err(|e| APIError::WebSocketServer(e.to_string()))?;
+            (SocketAddr::new(Ipv4Addr::LOCALHOST.into(), 0), Some(host))
+        } else {
+            (addr, None)
+        };
+
+        let server = builder
+            .build(server_addr)
             .await
             .map_err(|e| APIError::WebSocketServer(e.to_string()))?;
 
-        ret.1 = Some(
-            server
-                .start(
-                    r#impl::JsonRpcImpl::new(adapter, &config.client_version, config.life_time)
-                        .into_rpc(),
-                )
+        let forward = match upstream_host {
+            Some(host) => {
+                let upstream = server
+                    .local_addr()
+                    .map_err(|e| APIError::WebSocketServer(e.to_string()))?;
+                let listener = TcpListener::bind(addr)
+                    .await
+                    .map_err(|e| APIError::WebSocketServer(e.to_string()))?;
+                let limiter =
+                    Arc::new(ws_conn_limit::IpConnLimiter::new(config.ws_max_conn_per_ip));
+                Some(ws_conn_limit::spawn_forward(
+                    listener,
+                    upstream,
+                    host,
+                    limiter,
+                    config.ws_max_buffered_bytes,
+                ))
+            }
+            None => None,
+        };
+
+        let mut pubsub = ws_subscription::AxonPubSub::new(Arc::clone(&adapter), &config).into_rpc();
+        check_method_names(&pubsub).map_err(|e| APIError::WebSocketServer(e.to_string()))?;
+        let rpc = rpc_module(adapter, &config, serves_personal_rpc(&config, addr))
+            .map_err(|e| APIError::WebSocketServer(e.to_string()))?;
+        pubsub
+            .merge(
+                request_log::log_requests(rpc, config.request_log)
+                    .map_err(|e| APIError::WebSocketServer(e.to_string()))?,
+            )
+            .map_err(|e| APIError::WebSocketServer(e.to_string()))?;
+
+        ret.1 = Some(WsServer {
+            handle:   server
+                .start(pubsub)
                 .map_err(|e| APIError::WebSocketServer(e.to_string()))?,
-        )
+            _forward: forward,
+        })
     }
 
     Ok(ret)
 }
+
+/// The personal namespace signs with the keystore accounts, so it is only
+/// served by a server listening on a loopback address.
+pub(crate) fn serves_personal_rpc(config: &ConfigApi, addr: SocketAddr) -> bool {
+    if !config.enable_personal_rpc {
+        return false;
+    }
+
+    if !addr.ip().is_loopback() {
+        log::warn!(
+            "[jsonrpc] personal rpc is only served on a loopback address, not on {}",
+            addr
+        );
+        return false;
+    }
+
+    true
+}
+
+pub(crate) fn rpc_module<Adapter: APIAdapter + 'static>(
+    adapter: Arc<Adapter>,
+    config: &ConfigApi,
+    personal: bool,
+) -> Result<RpcModule<r#impl::JsonRpcImpl<Adapter>>, Error> {
+    let mut rpc = r#impl::JsonRpcImpl::new(Arc::clone(&adapter), config).into_rpc();
+
+    if config.enable_consensus_rpc {
+        rpc.merge(consensus_rpc::ConsensusRpcImpl.into_rpc())?;
+    }
+
+    if personal {
+        let keystore = keystore::LocalKeyStore::new(&config.keystore)
+            .map_err(|e| Error::Custom(e.to_string()))?;
+        rpc.merge(personal_rpc::PersonalRpcImpl::new(adapter, Arc::new(keystore)).into_rpc())?;
+    }
+
+    check_method_names(&rpc)?;
+    Ok(rpc)
+}
+
+/// Method names are matched exactly, so a name with stray whitespace or an
+/// unexpected character can never be called. Every name must be a lowercase
+/// namespace and a method made of ASCII letters, digits and underscores,
+/// like `eth_submitWork`.
+pub(crate) fn check_method_names<Context>(rpc: &RpcModule<Context>) -> Result<(), Error> {
+    for name in rpc.method_names() {
+        let valid = match name.split_once('_') {
+            Some((namespace, method)) => {
+                !namespace.is_empty()
+                    && !method.is_empty()
+                    && namespace
+                        .chars()
+                        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+                    && method
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '_')
+            }
+            None => false,
+        };
+
+        if !valid {
+            return Err(Error::Custom(format!("Invalid method name {:?}", name)));
+        }
+    }
+
+    Ok(())
+}
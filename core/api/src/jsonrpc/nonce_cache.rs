@@ -0,0 +1,59 @@
+use protocol::types::{BlockNumber, H160, U256};
+
+use crate::jsonrpc::lru::LruCache;
+
+type CacheKey = (H160, BlockNumber);
+
+/// A small LRU cache of committed nonces keyed by `(address, block_number)`,
+/// serving the `eth_getTransactionCount` polls of wallets and relayers
+/// without a trie lookup. The whole cache is dropped once a newer block is
+/// observed.
+pub struct NonceCache {
+    latest_block: BlockNumber,
+    entries:      LruCache<CacheKey, U256>,
+}
+
+impl NonceCache {
+    pub fn new(capacity: usize) -> Self {
+        NonceCache {
+            latest_block: 0,
+            entries:      LruCache::new(capacity),
+        }
+    }
+
+    pub fn observe_block(&mut self, number: BlockNumber) {
+        if number > self.latest_block {
+            self.latest_block = number;
+            self.entries.clear();
+        }
+    }
+
+    pub fn get(&mut self, key: &CacheKey) -> Option<U256> {
+        self.entries.get(key).copied()
+    }
+
+    pub fn insert(&mut self, key: CacheKey, nonce: U256) {
+        self.entries.insert(key, nonce);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(n: u8, number: BlockNumber) -> CacheKey {
+        (H160::repeat_byte(n), number)
+    }
+
+    #[test]
+    fn test_invalidate_on_new_block() {
+        let mut cache = NonceCache::new(2);
+        cache.observe_block(1);
+        cache.insert(key(1, 1), U256::one());
+        cache.observe_block(1);
+        assert_eq!(cache.get(&key(1, 1)), Some(U256::one()));
+
+        cache.observe_block(2);
+        assert_eq!(cache.get(&key(1, 1)), None);
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+
+use protocol::tokio::sync::OnceCell;
+use protocol::types::{
+    Account, AccountOverlay, BlockNumber, Hash, Log, SignedTransaction, TransactionAction, H160,
+    U256,
+};
+
+#[derive(Default, Clone, Debug)]
+struct PendingChange {
+    txs:      u64,
+    spent:    U256,
+    received: U256,
+}
+
+/// A log emitted by a transaction of the pending block.
+pub struct PendingLog {
+    pub tx_hash:   Hash,
+    pub tx_index:  usize,
+    pub log_index: usize,
+    pub log:       Log,
+}
+
+/// The pending block transactions executed one after another on top of the
+/// block the snapshot is built on.
+pub struct PendingReplay {
+    /// The code and storage they leave the touched accounts with.
+    pub overlay: BTreeMap<H160, AccountOverlay>,
+    pub logs:    Vec<PendingLog>,
+}
+
+/// The effects of the executable pool transactions on top of one block. The
+/// `pending` queries all read through the same snapshot so that, for example,
+/// the pending nonce and the pending balance of an account agree with each
+/// other. The fee of a transaction is reserved at its full gas limit.
+pub struct PendingState {
+    block_hash:   Hash,
+    block_number: BlockNumber,
+    changes:      HashMap<H160, PendingChange>,
+    packed_txs:   Vec<SignedTransaction>,
+    replay:       OnceCell<PendingReplay>,
+}
+
+impl PendingState {
+    pub fn new(
+        block_hash: Hash,
+        block_number: BlockNumber,
+        block_gas_limit: U256,
+        txs: &[SignedTransaction],
+    ) -> Self {
+        let mut changes: HashMap<H160, PendingChange> = HashMap::new();
+        let mut packed_txs = Vec::new();
+        let mut packed_gas = U256::zero();
+        for stx in txs.iter() {
+            packed_gas = packed_gas.saturating_add(stx.transaction.unsigned.gas_limit);
+            if packed_gas > block_gas_limit {
+                break;
+            }
+            packed_txs.push(stx.clone());
+        }
+
+        for stx in txs.iter() {
+            let tx = &stx.transaction.unsigned;
+            let fee = tx.gas_limit.saturating_mul(tx.max_fee_per_gas);
+
+            let sender = changes.entry(stx.sender).or_default();
+            sender.txs += 1;
+            sender.spent = sender.spent.saturating_add(tx.value).saturating_add(fee);
+
+            if let TransactionAction::Call(to) = tx.action {
+                let recipient = changes.entry(to).or_default();
+                recipient.received = recipient.received.saturating_add(tx.value);
+            }
+        }
+
+        PendingState {
+            block_hash,
+            block_number,
+            changes,
+            packed_txs,
+            replay: OnceCell::new(),
+        }
+    }
+
+    /// The transactions of the pending block, the pool ones are taken in
+    /// order until the next one does not fit in the block gas limit.
+    pub fn packed_txs(&self) -> &[SignedTransaction] {
+        &self.packed_txs
+    }
+
+    /// The replay of the pending block transactions, produced by `execute`
+    /// on the first call and shared by the later ones.
+    pub async fn replay<F, Fut, E>(&self, execute: F) -> Result<&PendingReplay, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<PendingReplay, E>>,
+    {
+        self.replay.get_or_try_init(execute).await
+    }
+
+    pub fn block_hash(&self) -> Hash {
+        self.block_hash
+    }
+
+    /// The block the snapshot is built on, accounts must be read at this
+    /// height before being passed to [`PendingState::nonce`] and
+    /// [`PendingState::balance`].
+    pub fn block_number(&self) -> BlockNumber {
+        self.block_number
+    }
+
+    pub fn nonce(&self, address: &H160, account: &Account) -> U256 {
+        match self.changes.get(address) {
+            Some(change) => account.nonce + change.txs,
+            None => account.nonce,
+        }
+    }
+
+    pub fn balance(&self, address: &H160, account: &Account) -> U256 {
+        match self.changes.get(address) {
+            Some(change) => account
+                .balance
+                .saturating_add(change.received)
+                .saturating_sub(change.spent),
+            None => account.balance,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use protocol::tokio;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_replay_is_executed_once() {
+        let state = PendingState::new(Hash::default(), 1, U256::from(30_000_000u64), &[]);
+        let executed = AtomicUsize::new(0);
+        let execute = || async {
+            executed.fetch_add(1, Ordering::SeqCst);
+            let mut overlay = BTreeMap::new();
+            overlay.insert(H160::repeat_byte(0x11), AccountOverlay::default());
+            Ok::<_, ()>(PendingReplay {
+                overlay,
+                logs: Vec::new(),
+            })
+        };
+
+        assert_eq!(state.replay(execute).await.unwrap().overlay.len(), 1);
+        assert_eq!(state.replay(execute).await.unwrap().overlay.len(), 1);
+        assert_eq!(executed.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,144 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use jsonrpsee::core::Error;
+
+use common_metrics_derive::metrics_rpc;
+use protocol::async_trait;
+use protocol::traits::{APIAdapter, Context};
+use protocol::types::{
+    Hex, SignedTransaction, Transaction, TransactionAction, UnverifiedTransaction, H160, H256, U256,
+};
+
+use crate::jsonrpc::eip712::TypedData;
+use crate::jsonrpc::keystore::KeyStore;
+use crate::jsonrpc::web3_types::Web3CallRequest;
+use crate::jsonrpc::{AxonPersonalRpcServer, RpcResult};
+
+const DEFAULT_GAS_LIMIT: u64 = 90_000;
+const DEFAULT_UNLOCK_DURATION: u64 = 300;
+
+pub struct PersonalRpcImpl<Adapter> {
+    adapter:  Arc<Adapter>,
+    keystore: Arc<dyn KeyStore>,
+}
+
+impl<Adapter: APIAdapter> PersonalRpcImpl<Adapter> {
+    pub fn new(adapter: Arc<Adapter>, keystore: Arc<dyn KeyStore>) -> Self {
+        PersonalRpcImpl { adapter, keystore }
+    }
+
+    /// The nonce after the committed transactions of `address` and the ones
+    /// waiting in the pool, so that consecutive sends do not reuse a nonce.
+    async fn pending_nonce(&self, address: H160) -> RpcResult<U256> {
+        let committed = self
+            .adapter
+            .get_account(Context::new(), address, None)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?
+            .nonce;
+        let pooled = self
+            .adapter
+            .get_pending_txs(Context::new())
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?
+            .into_iter()
+            .filter(|stx| stx.sender == address)
+            .map(|stx| stx.transaction.unsigned.nonce + 1)
+            .max();
+
+        Ok(pooled.map_or(committed, |nonce| nonce.max(committed)))
+    }
+}
+
+#[async_trait]
+impl<Adapter: APIAdapter + 'static> AxonPersonalRpcServer for PersonalRpcImpl<Adapter> {
+    #[metrics_rpc("eth_sendTransaction")]
+    async fn send_transaction(&self, req: Web3CallRequest) -> RpcResult<H256> {
+        let from = req
+            .from
+            .ok_or_else(|| Error::Custom("Missing from address".to_string()))?;
+        let header = self
+            .adapter
+            .get_block_header_by_number(Context::new(), None)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?
+            .ok_or_else(|| Error::Custom("Cannot get the latest header".to_string()))?;
+        let nonce = match req.nonce {
+            Some(nonce) => nonce,
+            None => self.pending_nonce(from).await?,
+        };
+        // The base fee is carried over unchanged from block to block, so the
+        // tip on top of it is enough for the next one.
+        let max_priority_fee_per_gas = req.max_priority_fee_per_gas.unwrap_or_default();
+        let max_fee_per_gas = req
+            .max_fee_per_gas
+            .or(req.gas_price)
+            .unwrap_or_else(|| header.base_fee_per_gas + max_priority_fee_per_gas);
+
+        let mut utx = UnverifiedTransaction {
+            unsigned:  Transaction {
+                nonce,
+                max_priority_fee_per_gas,
+                max_fee_per_gas,
+                gas_limit: req.gas.unwrap_or_else(|| U256::from(DEFAULT_GAS_LIMIT)),
+                action: TransactionAction::Call(req.to),
+                value: req.value.unwrap_or_default(),
+                data: req.data.as_bytes(),
+                access_list: req.access_list.unwrap_or_default(),
+            },
+            signature: None,
+            chain_id:  header.chain_id,
+            hash:      Default::default(),
+        };
+        let signature = self
+            .keystore
+            .sign(from, utx.signature_hash())
+            .map_err(|e| Error::Custom(e.to_string()))?;
+        utx.signature = Some(signature);
+
+        let stx =
+            SignedTransaction::try_from(utx.hash()).map_err(|e| Error::Custom(e.to_string()))?;
+        let hash = stx.transaction.hash;
+        self.adapter
+            .insert_signed_txs(Context::new(), stx)
+            .await
+            .map_err(|e| Error::Custom(e.to_string()))?;
+
+        Ok(hash)
+    }
+
+    #[metrics_rpc("personal_unlockAccount")]
+    async fn unlock_account(
+        &self,
+        address: H160,
+        password: String,
+        duration: Option<u64>,
+    ) -> RpcResult<bool> {
+        // Same as geth, a zero duration keeps the account unlocked until it
+        // is locked explicitly.
+        let duration = match duration.unwrap_or(DEFAULT_UNLOCK_DURATION) {
+            0 => None,
+            secs => Some(Duration::from_secs(secs)),
+        };
+
+        Ok(self.keystore.unlock(address, &password, duration))
+    }
+
+    #[metrics_rpc("personal_lockAccount")]
+    async fn lock_account(&self, address: H160) -> RpcResult<bool> {
+        Ok(self.keystore.lock(address))
+    }
+
+    #[metrics_rpc("eth_signTypedData_v4")]
+    async fn sign_typed_data(&self, address: H160, data: TypedData) -> RpcResult<Hex> {
+        let mut signature = self
+            .keystore
+            .sign(address, data.sign_hash()?)
+            .map_err(|e| Error::Custom(e.to_string()))?;
+        // The recovery id is returned as 27 or 28, same as geth.
+        signature.standard_v += 27;
+
+        Ok(Hex::encode(signature.as_bytes()))
+    }
+}
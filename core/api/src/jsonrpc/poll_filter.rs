@@ -1,22 +1,31 @@
 use parking_lot::Mutex;
-use protocol::types::H256;
+use protocol::tokio::{self, sync::broadcast};
+use protocol::types::{Block, H256};
 use std::{
-    collections::{BTreeSet, HashSet, VecDeque},
-    sync::Arc,
+    collections::{HashSet, VecDeque},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Weak,
+    },
 };
 
 use super::web3_types::{Filter, Web3Log};
 
 pub type BlockNumber = u64;
 const MAX_BLOCK_HISTORY_SIZE: usize = 32;
+
 /// Thread-safe filter state.
 #[derive(Clone)]
-pub struct SyncPollFilter(Arc<Mutex<PollFilter>>);
+pub struct SyncPollFilter {
+    inner: Arc<Mutex<PollFilter>>,
+}
 
 impl SyncPollFilter {
     /// New `SyncPollFilter`
     pub fn new(f: PollFilter) -> Self {
-        SyncPollFilter(Arc::new(Mutex::new(f)))
+        SyncPollFilter {
+            inner: Arc::new(Mutex::new(f)),
+        }
     }
 
     /// Modify underlying filter
@@ -24,7 +33,7 @@ impl SyncPollFilter {
     where
         F: FnOnce(&mut PollFilter) -> R,
     {
-        f(&mut self.0.lock())
+        f(&mut self.inner.lock())
     }
 }
 
@@ -36,9 +45,16 @@ pub enum PollFilter {
         last_block_number:      BlockNumber,
         #[doc(hidden)]
         recent_reported_hashes: VecDeque<(BlockNumber, H256)>,
+        /// Number, parent hash and hash of the blocks pushed by the
+        /// [`BlockFilterHook`] since the last poll.
+        imported:               VecDeque<(BlockNumber, H256, H256)>,
+        /// Set when the hook may have missed a block, the next poll reads the
+        /// new blocks from storage instead.
+        missed:                 bool,
     },
-    /// Hashes of all pending transactions the client knows about.
-    PendingTransaction(BTreeSet<H256>),
+    /// Pool insertion sequence number of the last transaction the client
+    /// was notified about.
+    PendingTransaction(u64),
     /// Number of From block number, last seen block hash, pending logs and log
     /// filter itself.
     Logs {
@@ -54,4 +70,115 @@ impl PollFilter {
     pub fn max_block_history_size() -> usize {
         MAX_BLOCK_HISTORY_SIZE
     }
+
+    /// Queue an imported block on a block filter. A block at or below the
+    /// last reported one replaces a reported block, so the next poll goes
+    /// through storage to rewind. Other filters ignore it.
+    pub fn push_block(&mut self, number: BlockNumber, parent: H256, hash: H256) {
+        if let PollFilter::Block {
+            last_block_number,
+            imported,
+            missed,
+            ..
+        } = self
+        {
+            if number <= *last_block_number || imported.len() >= MAX_BLOCK_HISTORY_SIZE {
+                *missed = true;
+                imported.clear();
+                return;
+            }
+
+            imported.push_back((number, parent, hash));
+        }
+    }
+
+    /// Make a block filter read the next poll from storage.
+    pub fn miss_blocks(&mut self) {
+        if let PollFilter::Block { missed, .. } = self {
+            *missed = true;
+        }
+    }
+}
+
+/// Remember a reported block, so that a re-org replacing it is noticed. Only
+/// the most recent blocks are kept.
+pub fn remember_reported(
+    recent_reported_hashes: &mut VecDeque<(BlockNumber, H256)>,
+    number: BlockNumber,
+    hash: H256,
+) {
+    if recent_reported_hashes.len() >= MAX_BLOCK_HISTORY_SIZE {
+        recent_reported_hashes.pop_back();
+    }
+    recent_reported_hashes.push_front((number, hash));
+}
+
+/// Pushes every imported block to the block filters, so a poll serves them
+/// without reading storage. A filter dropped by the poll manager is dropped
+/// here on the next block.
+#[derive(Default)]
+pub struct BlockFilterHook {
+    filters: Mutex<Vec<Weak<Mutex<PollFilter>>>>,
+    started: AtomicBool,
+}
+
+impl BlockFilterHook {
+    /// Start pushing the blocks of `rx` the first time it is called, later
+    /// calls do nothing.
+    pub fn start(self: &Arc<Self>, rx: impl FnOnce() -> broadcast::Receiver<Block>) {
+        if !self.started.swap(true, Ordering::SeqCst) {
+            tokio::spawn(notify_block_filters(rx(), Arc::downgrade(self)));
+        }
+    }
+
+    pub fn register(&self, filter: &SyncPollFilter) {
+        self.filters.lock().push(Arc::downgrade(&filter.inner));
+    }
+
+    /// Push the block to every live filter and return how many got it.
+    pub fn block_imported(&self, block: &Block) -> usize {
+        let header = &block.header;
+        let hash = block.header_hash();
+        self.for_each_filter(|filter| filter.push_block(header.number, header.prev_hash, hash))
+    }
+
+    /// Tell every live filter that some blocks were not pushed.
+    pub fn blocks_missed(&self) {
+        self.for_each_filter(PollFilter::miss_blocks);
+    }
+
+    fn for_each_filter(&self, mut f: impl FnMut(&mut PollFilter)) -> usize {
+        let mut filters = self.filters.lock();
+        filters.retain(|filter| match filter.upgrade() {
+            Some(filter) => {
+                f(&mut filter.lock());
+                true
+            }
+            None => false,
+        });
+        filters.len()
+    }
+}
+
+/// Push the blocks of `rx` to the filters of `hook` until either is gone. The
+/// blocks missed on lag are read from storage by the next poll instead.
+pub async fn notify_block_filters(mut rx: broadcast::Receiver<Block>, hook: Weak<BlockFilterHook>) {
+    loop {
+        match rx.recv().await {
+            Ok(block) => match hook.upgrade() {
+                Some(hook) => {
+                    hook.block_imported(&block);
+                }
+                None => break,
+            },
+            Err(broadcast::error::RecvError::Lagged(n)) => {
+                log::warn!("[jsonrpc] block filters lagging, skip {} blocks", n);
+                match hook.upgrade() {
+                    Some(hook) => hook.blocks_missed(),
+                    None => break,
+                }
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
+    }
 }
@@ -0,0 +1,49 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use parking_lot::Mutex;
+
+use protocol::types::H160;
+
+const WINDOW: Duration = Duration::from_secs(1);
+const MAX_TRACKED_SENDERS: usize = 100_000;
+
+/// Limits the transactions each sender can submit per second, counted in a
+/// fixed window of one second.
+pub struct SenderRateLimiter {
+    max_per_sec: u64,
+    windows:     Mutex<HashMap<H160, (Instant, u64)>>,
+}
+
+impl SenderRateLimiter {
+    pub fn new(max_per_sec: u64) -> Self {
+        SenderRateLimiter {
+            max_per_sec,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Return false if the sender has used up the transactions of the
+    /// current window.
+    pub fn try_acquire(&self, sender: H160) -> bool {
+        let now = Instant::now();
+        let mut windows = self.windows.lock();
+
+        if windows.len() >= MAX_TRACKED_SENDERS {
+            windows.retain(|_, (start, _)| now.duration_since(*start) < WINDOW);
+        }
+
+        let (start, count) = windows.entry(sender).or_insert((now, 0));
+        if now.duration_since(*start) >= WINDOW {
+            *start = now;
+            *count = 0;
+        }
+
+        if *count >= self.max_per_sec {
+            return false;
+        }
+
+        *count += 1;
+        true
+    }
+}
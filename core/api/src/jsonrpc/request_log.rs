@@ -0,0 +1,159 @@
+use std::time::{Duration, Instant};
+
+use jsonrpsee::core::server::rpc_module::Methods;
+use jsonrpsee::core::Error;
+use jsonrpsee::types::{error::CallError, Params};
+use jsonrpsee::RpcModule;
+use serde_json::{Map, Value};
+
+use common_config_parser::types::RequestLogLevel;
+
+/// The params of these methods carry raw transactions or passwords, only
+/// their size is logged.
+const REDACTED_METHODS: [&str; 2] = ["eth_sendRawTransaction", "axon_sendRawTransactionSync"];
+const REDACTED_NAMESPACES: [&str; 1] = ["personal_"];
+/// The logged params are cut to this many bytes.
+const MAX_LOGGED_PARAMS: usize = 256;
+
+/// Logs the method, params size, status and duration of every call, and the
+/// params themselves unless the method is redacted.
+#[derive(Clone, Copy, Debug)]
+pub struct RequestLogger {
+    level: RequestLogLevel,
+}
+
+impl RequestLogger {
+    pub fn new(level: RequestLogLevel) -> Self {
+        RequestLogger { level }
+    }
+
+    /// The log line of a call, `None` if the level skips it.
+    pub fn entry(
+        &self,
+        method: &str,
+        params: Option<&Value>,
+        success: bool,
+        elapsed: Duration,
+    ) -> Option<String> {
+        match self.level {
+            RequestLogLevel::Off => return None,
+            RequestLogLevel::Failed if success => return None,
+            _ => (),
+        }
+
+        let params = params.map(Value::to_string).unwrap_or_default();
+        let logged = if is_redacted(method) {
+            "<redacted>".to_string()
+        } else {
+            truncate(&params, MAX_LOGGED_PARAMS)
+        };
+
+        Some(format!(
+            "[jsonrpc] method={} params_size={} params={} status={} duration={}us",
+            method,
+            params.len(),
+            logged,
+            if success { "ok" } else { "error" },
+            elapsed.as_micros()
+        ))
+    }
+}
+
+/// Wrap every method of `rpc` to log its calls. The middleware of the
+/// servers never sees the params, so the methods are registered again in
+/// front of the original ones, which they forward the calls to. Nothing is
+/// wrapped when the logs are off, and the subscriptions are left out, they
+/// are merged after.
+pub fn log_requests<Context: Send + Sync + 'static>(
+    rpc: RpcModule<Context>,
+    level: RequestLogLevel,
+) -> Result<Methods, Error> {
+    if level == RequestLogLevel::Off {
+        return Ok(rpc.into());
+    }
+
+    let names = rpc.method_names().collect::<Vec<_>>();
+    let mut logged = RpcModule::new(LoggedMethods {
+        methods: rpc.into(),
+        logger:  RequestLogger::new(level),
+    });
+    for name in names {
+        logged.register_async_method(name, move |params, logged| async move {
+            logged.call(name, params).await
+        })?;
+    }
+
+    Ok(logged.into())
+}
+
+struct LoggedMethods {
+    methods: Methods,
+    logger:  RequestLogger,
+}
+
+impl LoggedMethods {
+    async fn call(&self, method: &str, params: Params<'static>) -> Result<Value, Error> {
+        let started_at = Instant::now();
+        let params = params.parse::<Option<Value>>()?;
+
+        let mut request = Map::new();
+        request.insert("jsonrpc".to_string(), "2.0".into());
+        request.insert("id".to_string(), 0.into());
+        request.insert("method".to_string(), method.into());
+        if let Some(params) = params.clone() {
+            request.insert("params".to_string(), params);
+        }
+
+        let (response, _) = self
+            .methods
+            .raw_json_request(&Value::Object(request).to_string())
+            .await?;
+        let mut response =
+            serde_json::from_str::<Value>(&response).map_err(|e| Error::Custom(e.to_string()))?;
+        let ret = match response.get_mut("error") {
+            Some(error) => Err(forwarded_error(error.take())),
+            None => Ok(response["result"].take()),
+        };
+
+        if let Some(entry) =
+            self.logger
+                .entry(method, params.as_ref(), ret.is_ok(), started_at.elapsed())
+        {
+            log::info!("{}", entry);
+        }
+
+        ret
+    }
+}
+
+/// Return the error of the original method as is, code and data included.
+fn forwarded_error(error: Value) -> Error {
+    let data = error
+        .get("data")
+        .and_then(|data| serde_json::value::to_raw_value(data).ok());
+
+    Error::Call(CallError::Custom {
+        code: error["code"].as_i64().unwrap_or(-32603) as i32,
+        message: error["message"].as_str().unwrap_or_default().to_string(),
+        data,
+    })
+}
+
+fn is_redacted(method: &str) -> bool {
+    REDACTED_METHODS.contains(&method)
+        || REDACTED_NAMESPACES
+            .iter()
+            .any(|namespace| method.starts_with(namespace))
+}
+
+fn truncate(params: &str, max_len: usize) -> String {
+    if params.len() <= max_len {
+        return params.to_string();
+    }
+
+    let mut end = max_len;
+    while !params.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}...", &params[..end])
+}
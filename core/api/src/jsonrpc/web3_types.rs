@@ -1,3 +1,4 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 
 use jsonrpsee::core::DeserializeOwned;
@@ -8,10 +9,12 @@ use serde_json::{from_value, Value};
 use core_consensus::SyncStatus as InnerSyncStatus;
 use protocol::codec::ProtocolCodec;
 use protocol::types::{
-    AccessList, Block, Bloom, Bytes, Hash, Hex, Public, Receipt, SignedTransaction, H160, H256,
-    U256, U64,
+    AccessList, AccessSet, AccountProof, Block, Bloom, Bytes, Hash, Hex, PrestateAccount, Proof,
+    Public, Receipt, SignedTransaction, StorageRange, TxPoolStatus, H160, H256, H64, U256, U64,
 };
 
+pub const EIP1559_TX_TYPE: u64 = 0x02;
+
 #[allow(clippy::large_enum_variant)]
 #[derive(Deserialize, Clone, Debug, PartialEq, Eq)]
 pub enum RichTransactionOrHash {
@@ -67,6 +70,9 @@ pub struct Web3Transaction {
     pub access_list:              Option<AccessList>,
     pub chain_id:                 Option<U256>,
     pub standard_v:               Option<U256>,
+    pub v:                        U256,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub y_parity:                 Option<U64>,
     pub r:                        U256,
     pub s:                        U256,
 }
@@ -77,7 +83,7 @@ impl Web3Transaction {
         let mut web3_transaction_out_tx = Web3Transaction {
             block_number:             receipt.block_number.into(),
             block_hash:               receipt.block_hash,
-            from:                     receipt.sender,
+            from:                     transaction_sender(&stx),
             contract_address:         receipt.code_address.map(Into::into),
             cumulative_gas_used:      receipt.used_gas,
             effective_gas_price:      receipt.used_gas,
@@ -85,8 +91,8 @@ impl Web3Transaction {
             raw:                      Hex::encode(stx.transaction.encode().unwrap()),
             public_key:               stx.public,
             gas:                      receipt.used_gas,
-            gas_price:                stx.transaction.unsigned.gas_price,
-            max_fee_per_gas:          U256::from(1337u64),
+            gas_price:                stx.transaction.unsigned.max_fee_per_gas,
+            max_fee_per_gas:          stx.transaction.unsigned.max_fee_per_gas,
             max_priority_fee_per_gas: stx.transaction.unsigned.max_priority_fee_per_gas,
             hash:                     receipt.tx_hash,
             to:                       stx.get_to(),
@@ -94,15 +100,20 @@ impl Web3Transaction {
             nonece:                   stx.transaction.unsigned.value,
             transaction_index:        Some(receipt.tx_index.into()),
             value:                    stx.transaction.unsigned.value,
-            type_:                    Some(0x02u64.into()),
+            type_:                    Some(transaction_type(&stx).into()),
             access_list:              Some(stx.transaction.unsigned.access_list.clone()),
             chain_id:                 Some(stx.transaction.chain_id.into()),
             standard_v:               Some(U256::default()),
+            v:                        U256::default(),
+            y_parity:                 None,
             r:                        U256::default(),
             s:                        U256::default(),
         };
         if let Some(sc) = signature {
             web3_transaction_out_tx.standard_v = Some(sc.standard_v.into());
+            // The `v` of a typed transaction is its y parity.
+            web3_transaction_out_tx.v = sc.standard_v.into();
+            web3_transaction_out_tx.y_parity = Some(sc.standard_v.into());
             web3_transaction_out_tx.r = sc.r.as_ref().into();
             web3_transaction_out_tx.s = sc.s.as_ref().into();
         }
@@ -110,6 +121,12 @@ impl Web3Transaction {
     }
 }
 
+/// The EIP-2718 type of the transaction. Axon only accepts EIP-1559
+/// transactions so far.
+pub fn transaction_type(_stx: &SignedTransaction) -> u64 {
+    EIP1559_TX_TYPE
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct Web3Receipt {
@@ -154,7 +171,7 @@ impl Web3Receipt {
             contract_address:    receipt.code_address.map(Into::into),
             cumulative_gas_used: receipt.used_gas,
             effective_gas_price: receipt.used_gas,
-            from:                receipt.sender,
+            from:                transaction_sender(&stx),
             status:              receipt.status(),
             gas_used:            receipt.used_gas,
             logs:                vec![],
@@ -163,7 +180,7 @@ impl Web3Receipt {
             to:                  stx.get_to(),
             transaction_hash:    receipt.tx_hash,
             transaction_index:   Some(receipt.tx_index.into()),
-            transaction_type:    Some(0x02u64.into()),
+            transaction_type:    Some(transaction_type(&stx).into()),
         };
         for item in receipt.logs.into_iter() {
             web3_receipt.logs.push(Web3ReceiptLog {
@@ -184,6 +201,19 @@ impl Web3Receipt {
     }
 }
 
+/// The `from` of both `Web3Transaction` and `Web3Receipt`. It only depends on
+/// the transaction, whose signature is recovered if it does not carry the
+/// sender, since the `sender` of a receipt may be stale.
+pub fn transaction_sender(stx: &SignedTransaction) -> H160 {
+    if !stx.sender.is_zero() {
+        return stx.sender;
+    }
+
+    SignedTransaction::try_from(stx.transaction.clone())
+        .map(|recovered| recovered.sender)
+        .unwrap_or(stx.sender)
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct Web3Block {
@@ -199,18 +229,30 @@ pub struct Web3Block {
     pub number:            U256,
     pub gas_used:          U256,
     pub gas_limit:         U256,
+    /// The header's extra data byte for byte. Unlike Ethereum, Axon does not
+    /// limit it to 32 bytes, so it is returned uncut whatever its length.
     pub extra_data:        Hex,
     pub logs_bloom:        Option<Bloom>,
     pub timestamp:         U256,
     pub difficulty:        U256,
     pub total_difficulty:  Option<U256>,
+    /// The consensus seal of the block, encoded as `[round, signature,
+    /// bitmap]` where `round` is the 8 bytes big-endian overlord round,
+    /// `signature` is the aggregated BLS signature and `bitmap` marks the
+    /// validators who signed. The header carries the proof of its parent,
+    /// so the seal is filled from the proof of the block itself, carried by
+    /// the next block or the latest proof. It is empty until that proof
+    /// exists.
     pub seal_fields:       Vec<Bytes>,
     pub base_fee_per_gas:  U256,
     pub uncles:            Vec<H256>,
     pub transactions:      Vec<RichTransactionOrHash>,
     pub size:              Option<U256>,
+    /// Axon is a BFT chain without RANDAO, so `mixHash` is the header's
+    /// `mixed_hash` if present, otherwise the zero hash.
     pub mix_hash:          H256,
-    pub nonce:             U256,
+    /// Always eight zero bytes, following the post-merge convention.
+    pub nonce:             H64,
 }
 
 impl From<Block> for Web3Block {
@@ -241,12 +283,24 @@ impl From<Block> for Web3Block {
                 .map(|hash| RichTransactionOrHash::Hash(*hash))
                 .collect(),
             uncles:            vec![],
-            mix_hash:          H256::default(),
-            nonce:             U256::default(),
+            mix_hash:          b.header.mixed_hash.unwrap_or_default(),
+            nonce:             H64::zero(),
         }
     }
 }
 
+pub fn seal_fields(proof: &Proof) -> Vec<Bytes> {
+    if proof.signature.is_empty() {
+        return vec![];
+    }
+
+    vec![
+        Bytes::copy_from_slice(&proof.round.to_be_bytes()),
+        proof.signature.clone(),
+        proof.bitmap.clone(),
+    ]
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
 pub enum TransactionCondition {
     #[serde(rename = "block")]
@@ -274,24 +328,12 @@ pub struct Web3CallRequest {
     pub max_priority_fee_per_gas: Option<U256>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
-#[serde(deny_unknown_fields, rename_all = "camelCase")]
-pub struct WEB3Work {
-    /// The proof-of-work hash.
-    pub pow_hash:  H256,
-    /// The seed hash.
-    pub seed_hash: H256,
-    /// The target.
-    pub target:    H256,
-    /// The block number: this isn't always stored.
-    pub number:    Option<u64>,
-}
-
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum BlockId {
     Num(u64),
     Hash(H256),
     Latest,
+    Pending,
 }
 
 impl Default for BlockId {
@@ -304,7 +346,7 @@ impl From<BlockId> for Option<u64> {
     fn from(id: BlockId) -> Self {
         match id {
             BlockId::Num(num) => Some(num),
-            BlockId::Latest => None,
+            BlockId::Latest | BlockId::Pending => None,
             BlockId::Hash(_h) => None,
         }
     }
@@ -341,6 +383,7 @@ impl Serialize for BlockId {
         match *self {
             BlockId::Num(ref x) => serializer.serialize_str(&format!("0x{:x}", x)),
             BlockId::Latest => serializer.serialize_str("latest"),
+            BlockId::Pending => serializer.serialize_str("pending"),
             BlockId::Hash(hash) => serializer.serialize_str(&format!(
                 "{{ 'hash': '{}', 'requireCanonical': '{}'  }}",
                 hash, false
@@ -355,7 +398,7 @@ impl<'a> Visitor<'a> for BlockIdVisitor {
     type Value = BlockId;
 
     fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        write!(formatter, "a block number or 'latest' ")
+        write!(formatter, "a block number, 'latest' or 'pending' ")
     }
 
     #[allow(clippy::never_loop)]
@@ -404,6 +447,7 @@ impl<'a> Visitor<'a> for BlockIdVisitor {
     {
         match value {
             "latest" => Ok(BlockId::Latest),
+            "pending" => Ok(BlockId::Pending),
             _ if value.starts_with("0x") => u64::from_str_radix(&value[2..], 16)
                 .map(BlockId::Num)
                 .map_err(|e| Error::custom(format!("Invalid block number: {}", e))),
@@ -474,12 +518,29 @@ impl<'a> Visitor<'a> for IndexVisitor {
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 #[serde(deny_unknown_fields, rename_all = "camelCase")]
 pub struct Web3Filter {
-    pub from_block: Option<BlockId>,
-    pub to_block:   Option<BlockId>,
-    pub block_hash: Option<H256>,
-    pub address:    Option<H160>,
-    pub topics:     Option<Vec<H256>>,
-    pub limit:      Option<usize>,
+    pub from_block:    Option<BlockId>,
+    pub to_block:      Option<BlockId>,
+    pub block_hash:    Option<H256>,
+    pub address:       Option<H160>,
+    /// The topics of a log by position, a log matches if it has at least as
+    /// many topics as given and each one is among the topics of its
+    /// position. A null or empty position matches any topic, so `[]` matches
+    /// all logs while `[null]` matches the logs with at least one topic.
+    pub topics:        Option<Vec<VariadicValue<H256>>>,
+    pub limit:         Option<usize>,
+    /// Overrides the `get_logs_confirmations` of the config.
+    pub confirmations: Option<u64>,
+    /// With `desc` and a `limit`, the blocks are scanned from `toBlock`
+    /// backward until `limit` logs are found, which are returned newest
+    /// first.
+    pub order:         Option<LogOrder>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogOrder {
+    Asc,
+    Desc,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
@@ -499,6 +560,36 @@ pub struct Web3Log {
     pub log_type:          String,
 }
 
+/// The result of `axon_callWithLogs`, the logs are not in any block so only
+/// their index in the call is set.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Web3CallWithLogs {
+    pub return_data: Hex,
+    pub logs:        Vec<Web3Log>,
+    pub gas_used:    U256,
+}
+
+/// The result of one call of `axon_multicall` or `axon_simulateBundle`. A
+/// failed call has no effect on the others and carries its error.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Web3CallResult {
+    pub success:     bool,
+    pub return_data: Hex,
+    pub gas_used:    U256,
+    pub error:       Option<String>,
+}
+
+/// The result of `axon_sendRawTransactionSync`. The transaction is in the
+/// local pool either way, `propagated` tells whether it reached a peer.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Web3TxPropagation {
+    pub transaction_hash: H256,
+    pub propagated:       bool,
+}
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum Web3SyncStatus {
     Doing(SyncStatus),
@@ -546,13 +637,17 @@ pub struct SyncStatus {
     pub pulled_states:  U256,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 #[serde(deny_unknown_fields, rename_all = "camelCase")]
 pub struct Web3FeeHistory {
     pub oldest_block:     U256,
-    pub reward:           Option<Vec<U256>>,
+    /// The effective priority fees at the requested percentiles, one row per
+    /// block. Omitted when no percentiles are requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reward:           Option<Vec<Vec<U256>>>,
+    /// One entry per block plus the base fee of the block after the newest.
     pub base_fee_per_gas: Vec<U256>,
-    pub gas_used_ratio:   Vec<U256>,
+    pub gas_used_ratio:   Vec<f64>,
 }
 
 impl Default for Web3BlockNumber {
@@ -702,6 +797,25 @@ where
     /// None
     Null,
 }
+impl<T: DeserializeOwned> VariadicValue<T> {
+    pub fn into_option(self) -> Option<Vec<T>> {
+        match self {
+            VariadicValue::Null => None,
+            VariadicValue::Single(t) => Some(vec![t]),
+            VariadicValue::Multiple(t) => Some(t),
+        }
+    }
+}
+
+impl<T: DeserializeOwned> From<Option<Vec<T>>> for VariadicValue<T> {
+    fn from(value: Option<Vec<T>>) -> Self {
+        match value {
+            Some(t) => VariadicValue::Multiple(t),
+            None => VariadicValue::Null,
+        }
+    }
+}
+
 impl<'a, T> Deserialize<'a> for VariadicValue<T>
 where
     T: DeserializeOwned,
@@ -732,8 +846,10 @@ pub struct Filter {
     pub limit:      Option<usize>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
-#[serde(deny_unknown_fields, rename_all = "camelCase")]
+/// The errors of deserializing a filter name the offending field, and a filter
+/// failing [`validate`](Self::validate) is rejected as well.
+#[derive(Serialize, Default, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
 pub struct ChangeWeb3Filter {
     pub from_block: Option<Web3BlockNumber>,
     pub to_block:   Option<Web3BlockNumber>,
@@ -744,6 +860,36 @@ pub struct ChangeWeb3Filter {
 }
 
 impl ChangeWeb3Filter {
+    /// Reject a filter that could never match what the client meant, the
+    /// error describes the offending part.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.block_hash.is_some() && (self.from_block.is_some() || self.to_block.is_some()) {
+            return Err("blockHash can not be combined with fromBlock or toBlock".to_string());
+        }
+
+        if let Some(topics) = self.topics.as_ref() {
+            if topics.len() > 4 {
+                return Err(format!(
+                    "too many topics, {} positions given but a log has at most 4",
+                    topics.len()
+                ));
+            }
+        }
+
+        if let (Some(Web3BlockNumber::Num(from)), Some(Web3BlockNumber::Num(to))) =
+            (self.from_block.as_ref(), self.to_block.as_ref())
+        {
+            if from > to {
+                return Err(format!(
+                    "invalid block range, fromBlock {} is after toBlock {}",
+                    from, to
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn try_into(self) -> Filter {
         let num_to_id = |num| match num {
             Web3BlockNumber::Hash { hash, .. } => BlockId::Hash(hash),
@@ -763,39 +909,90 @@ impl ChangeWeb3Filter {
         Filter {
             from_block,
             to_block,
-            address: self.address.and_then(|address| match address {
-                VariadicValue::Null => None,
-                VariadicValue::Single(a) => Some(vec![a]),
-                VariadicValue::Multiple(a) => Some(a),
+            address: self.address.and_then(VariadicValue::into_option),
+            topics: self.topics.map_or_else(Vec::new, |topics| {
+                topics
+                    .into_iter()
+                    .take(4)
+                    .map(VariadicValue::into_option)
+                    .collect()
             }),
-            topics: {
-                let mut iter = self
-                    .topics
-                    .map_or_else(Vec::new, |topics| {
-                        topics
-                            .into_iter()
-                            .take(4)
-                            .map(|topic| match topic {
-                                VariadicValue::Null => None,
-                                VariadicValue::Single(t) => Some(vec![t]),
-                                VariadicValue::Multiple(t) => Some(t),
-                            })
-                            .collect()
-                    })
-                    .into_iter();
-
-                vec![
-                    iter.next().unwrap_or(None),
-                    iter.next().unwrap_or(None),
-                    iter.next().unwrap_or(None),
-                    iter.next().unwrap_or(None),
-                ]
-            },
             limit: self.limit,
         }
     }
 }
 
+const CHANGE_WEB3_FILTER_FIELDS: &[&str] = &[
+    "fromBlock",
+    "toBlock",
+    "blockHash",
+    "address",
+    "topics",
+    "limit",
+];
+
+impl<'a> Deserialize<'a> for ChangeWeb3Filter {
+    fn deserialize<D>(deserializer: D) -> Result<ChangeWeb3Filter, D::Error>
+    where
+        D: Deserializer<'a>,
+    {
+        deserializer.deserialize_map(ChangeWeb3FilterVisitor)
+    }
+}
+
+struct ChangeWeb3FilterVisitor;
+
+impl ChangeWeb3FilterVisitor {
+    fn field<'a, V, T>(
+        visitor: &mut V,
+        slot: &mut Option<T>,
+        name: &'static str,
+    ) -> Result<(), V::Error>
+    where
+        V: MapAccess<'a>,
+        T: Deserialize<'a>,
+    {
+        if slot.is_some() {
+            return Err(Error::duplicate_field(name));
+        }
+
+        *slot = visitor
+            .next_value::<Option<T>>()
+            .map_err(|e| V::Error::custom(format!("invalid field `{}`: {}", name, e)))?;
+        Ok(())
+    }
+}
+
+impl<'a> Visitor<'a> for ChangeWeb3FilterVisitor {
+    type Value = ChangeWeb3Filter;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a filter object")
+    }
+
+    fn visit_map<V>(self, mut visitor: V) -> Result<Self::Value, V::Error>
+    where
+        V: MapAccess<'a>,
+    {
+        let mut filter = ChangeWeb3Filter::default();
+
+        while let Some(key) = visitor.next_key::<String>()? {
+            match key.as_str() {
+                "fromBlock" => Self::field(&mut visitor, &mut filter.from_block, "fromBlock")?,
+                "toBlock" => Self::field(&mut visitor, &mut filter.to_block, "toBlock")?,
+                "blockHash" => Self::field(&mut visitor, &mut filter.block_hash, "blockHash")?,
+                "address" => Self::field(&mut visitor, &mut filter.address, "address")?,
+                "topics" => Self::field(&mut visitor, &mut filter.topics, "topics")?,
+                "limit" => Self::field(&mut visitor, &mut filter.limit, "limit")?,
+                key => return Err(Error::unknown_field(key, CHANGE_WEB3_FILTER_FIELDS)),
+            }
+        }
+
+        filter.validate().map_err(V::Error::custom)?;
+        Ok(filter)
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub enum FilterChanges {
     Logs(Vec<Web3Log>),
@@ -816,8 +1013,278 @@ impl Serialize for FilterChanges {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum Web3TxStatus {
+    Unknown,
+    Pending,
+    Queued,
+    #[serde(rename_all = "camelCase")]
+    Mined {
+        block_number:  U256,
+        confirmations: U256,
+    },
+    Dropped,
+}
+
+impl From<TxPoolStatus> for Web3TxStatus {
+    fn from(status: TxPoolStatus) -> Self {
+        match status {
+            TxPoolStatus::Pending => Web3TxStatus::Pending,
+            TxPoolStatus::Queued => Web3TxStatus::Queued,
+            TxPoolStatus::Dropped => Web3TxStatus::Dropped,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Web3ConsensusStatus {
+    pub height:                 U256,
+    pub round:                  U256,
+    pub last_committed_height:  U256,
+    pub proposer:               H160,
+    /// Seconds elapsed since the last commit.
+    pub time_since_last_commit: U256,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SubscriptionKind {
+    NewHeads,
+    NewPendingTransactions,
+    Logs,
+}
+
+/// The optional second parameter of `eth_subscribe`. For
+/// `newPendingTransactions` it is the `fullTx` flag, for `logs` the filter of
+/// the logs.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum SubscriptionParams {
+    FullTx(bool),
+    Logs(ChangeWeb3Filter),
+}
+
+// Not derived, an untagged enum would hide why a logs filter is invalid.
+impl<'a> Deserialize<'a> for SubscriptionParams {
+    fn deserialize<D>(deserializer: D) -> Result<SubscriptionParams, D::Error>
+    where
+        D: Deserializer<'a>,
+    {
+        match Value::deserialize(deserializer)? {
+            Value::Bool(full_tx) => Ok(SubscriptionParams::FullTx(full_tx)),
+            value @ Value::Object(_) => from_value(value)
+                .map(SubscriptionParams::Logs)
+                .map_err(Error::custom),
+            _ => Err(Error::custom("expected the fullTx flag or a logs filter")),
+        }
+    }
+}
+
+/// The `newPendingTransactions` item. Without `fullTx` it is the hash of the
+/// transaction. With `fullTx` it is the transaction object, whose block
+/// fields are zero and `transactionIndex` is null, plus a `replaces` field
+/// holding the hash of the pending transaction with the same sender and
+/// nonce that it supersedes, or null if it replaces nothing.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum Web3PendingTransaction {
+    Hash(H256),
+    Full(Box<Web3FullPendingTransaction>),
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct Web3FullPendingTransaction {
+    #[serde(flatten)]
+    pub transaction: Web3Transaction,
+    pub replaces:    Option<H256>,
+}
+
+impl Web3PendingTransaction {
+    pub fn new(stx: SignedTransaction, replaces: Option<H256>, full_tx: bool) -> Self {
+        if !full_tx {
+            return Web3PendingTransaction::Hash(stx.transaction.hash);
+        }
+
+        let receipt = Receipt {
+            tx_hash: stx.transaction.hash,
+            sender: stx.sender,
+            ..Default::default()
+        };
+        let mut transaction = Web3Transaction::create(receipt, stx);
+        transaction.transaction_index = None;
+        Web3PendingTransaction::Full(Box::new(Web3FullPendingTransaction {
+            transaction,
+            replaces,
+        }))
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceOptions {
+    pub tracer: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Web3PrestateAccount {
+    pub balance: U256,
+    pub nonce:   u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code:    Option<Hex>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub storage: BTreeMap<H256, H256>,
+}
+
+impl From<PrestateAccount> for Web3PrestateAccount {
+    fn from(account: PrestateAccount) -> Self {
+        Web3PrestateAccount {
+            balance: account.balance,
+            nonce:   account.nonce.low_u64(),
+            code:    (!account.code.is_empty()).then(|| Hex::encode(&account.code)),
+            storage: account.storage,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Web3StorageRange {
+    /// geth keys the entries by the hash of the slot, the state trie of Axon
+    /// is keyed by the slot itself so it is used for both.
+    pub storage:  BTreeMap<H256, Web3StorageEntry>,
+    pub next_key: Option<H256>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Web3StorageEntry {
+    pub key:   Option<H256>,
+    pub value: H256,
+}
+
+impl From<StorageRange> for Web3StorageRange {
+    fn from(range: StorageRange) -> Self {
+        Web3StorageRange {
+            storage:  range
+                .storage
+                .into_iter()
+                .map(|(key, value)| {
+                    (key, Web3StorageEntry {
+                        key: Some(key),
+                        value,
+                    })
+                })
+                .collect(),
+            next_key: range.next_key,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Web3AccessSet {
+    pub reads_accounts:  BTreeSet<H160>,
+    pub writes_accounts: BTreeSet<H160>,
+    /// The slots by the address of their account.
+    pub reads_slots:     BTreeMap<H160, BTreeSet<H256>>,
+    pub writes_slots:    BTreeMap<H160, BTreeSet<H256>>,
+}
+
+impl From<AccessSet> for Web3AccessSet {
+    fn from(set: AccessSet) -> Self {
+        Web3AccessSet {
+            reads_accounts:  set.reads_accounts,
+            writes_accounts: set.writes_accounts,
+            reads_slots:     set.reads_slots,
+            writes_slots:    set.writes_slots,
+        }
+    }
+}
+
+/// A storage slot of `eth_getProof`. Clients send the slot as a quantity as
+/// often as a full word, so a shorter or odd-length key is left-padded to 32
+/// bytes, e.g. `0x1` is the same slot as `0x00..01`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Web3StorageKey(pub H256);
+
+impl<'a> Deserialize<'a> for Web3StorageKey {
+    fn deserialize<D>(deserializer: D) -> Result<Web3StorageKey, D::Error>
+    where
+        D: Deserializer<'a>,
+    {
+        let key = String::deserialize(deserializer)?;
+        let digits = key
+            .strip_prefix("0x")
+            .or_else(|| key.strip_prefix("0X"))
+            .ok_or_else(|| Error::custom(format!("Invalid storage key: {}", key)))?;
+        let padded = if digits.len() % 2 == 0 {
+            format!("0x{}", digits)
+        } else {
+            format!("0x0{}", digits)
+        };
+        let bytes = Hex::decode(padded)
+            .map_err(|e| Error::custom(format!("Invalid storage key: {}", e)))?;
+        if bytes.len() > 32 {
+            return Err(Error::custom(format!(
+                "Invalid storage key: {} is longer than 32 bytes",
+                key
+            )));
+        }
+
+        let mut slot = H256::zero();
+        slot.as_bytes_mut()[32 - bytes.len()..].copy_from_slice(&bytes);
+        Ok(Web3StorageKey(slot))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Web3AccountProof {
+    pub address:       H160,
+    pub account_proof: Vec<Hex>,
+    pub balance:       U256,
+    pub code_hash:     H256,
+    pub nonce:         U256,
+    pub storage_hash:  H256,
+    pub storage_proof: Vec<Web3StorageProof>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Web3StorageProof {
+    pub key:   H256,
+    pub value: U256,
+    pub proof: Vec<Hex>,
+}
+
+impl Web3AccountProof {
+    pub fn new(address: H160, proof: AccountProof) -> Self {
+        Web3AccountProof {
+            address,
+            account_proof: proof.account_proof.iter().map(Hex::encode).collect(),
+            balance: proof.account.balance,
+            code_hash: proof.account.code_hash,
+            nonce: proof.account.nonce,
+            storage_hash: proof.account.storage_root,
+            storage_proof: proof
+                .storage_proof
+                .into_iter()
+                .map(|slot| Web3StorageProof {
+                    key:   slot.key,
+                    value: U256::from_big_endian(slot.value.as_bytes()),
+                    proof: slot.proof.iter().map(Hex::encode).collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use protocol::types::{
+        AccessListItem, SignatureComponents, Transaction, TransactionAction, UnverifiedTransaction,
+    };
+
     use super::*;
 
     #[test]
@@ -836,4 +1303,158 @@ mod tests {
         let json = json::parse(&serde_json::to_string(&status).unwrap()).unwrap();
         assert!(json.is_object());
     }
+
+    fn mock_stx(access_list: AccessList) -> SignedTransaction {
+        let utx = UnverifiedTransaction {
+            unsigned:  Transaction {
+                nonce: U256::one(),
+                max_priority_fee_per_gas: U256::one(),
+                max_fee_per_gas: U256::one(),
+                gas_limit: U256::from(21000u64),
+                action: TransactionAction::Call(H160::repeat_byte(0x22)),
+                value: U256::one(),
+                data: Bytes::new(),
+                access_list,
+            },
+            signature: Some(SignatureComponents {
+                standard_v: 1,
+                r:          H256::repeat_byte(0x01),
+                s:          H256::repeat_byte(0x02),
+            }),
+            chain_id:  5,
+            hash:      Default::default(),
+        };
+
+        SignedTransaction {
+            transaction: utx.hash(),
+            sender:      H160::repeat_byte(0x11),
+            public:      None,
+        }
+    }
+
+    #[test]
+    fn test_access_list_of_1559_transaction() {
+        let access_list = vec![AccessListItem {
+            address: H160::repeat_byte(0x01),
+            slots:   vec![H256::repeat_byte(0x02)],
+        }];
+        let tx = Web3Transaction::create(Receipt::default(), mock_stx(access_list.clone()));
+        let json = serde_json::to_value(&tx).unwrap();
+        assert_eq!(json["type"], "0x2");
+        assert_eq!(
+            json["accessList"],
+            serde_json::to_value(&access_list).unwrap()
+        );
+
+        let tx = Web3Transaction::create(Receipt::default(), mock_stx(vec![]));
+        let json = serde_json::to_value(&tx).unwrap();
+        assert_eq!(json["accessList"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_change_filter_errors_name_the_field() {
+        let err = |value: Value| {
+            from_value::<ChangeWeb3Filter>(value)
+                .unwrap_err()
+                .to_string()
+        };
+
+        let typo = err(serde_json::json!({"frmBlock": "0x1"}));
+        assert!(typo.contains("unknown field `frmBlock`, expected one of `fromBlock`"));
+        assert!(
+            err(serde_json::json!({"toBlock": "0x1", "limt": 1})).contains("unknown field `limt`")
+        );
+        assert!(err(serde_json::json!({"toBlock": 2})).contains("invalid field `toBlock`"));
+        assert!(err(serde_json::json!({"address": "0x12"})).contains("invalid field `address`"));
+        assert!(
+            err(serde_json::json!({"fromBlock": "0x10", "toBlock": "0x2"}))
+                .contains("fromBlock 16 is after toBlock 2")
+        );
+
+        let filter: ChangeWeb3Filter = from_value(serde_json::json!({
+            "fromBlock": "0x1",
+            "toBlock": null,
+            "topics": [],
+        }))
+        .unwrap();
+        assert_eq!(filter.from_block, Some(Web3BlockNumber::Num(1)));
+        assert_eq!(filter.to_block, None);
+        assert_eq!(filter.topics, Some(vec![]));
+
+        let params = from_value::<SubscriptionParams>(serde_json::json!({"frmBlock": "0x1"}))
+            .unwrap_err()
+            .to_string();
+        assert!(params.contains("unknown field `frmBlock`"));
+        assert_eq!(
+            from_value::<SubscriptionParams>(serde_json::json!(true)).unwrap(),
+            SubscriptionParams::FullTx(true)
+        );
+    }
+
+    #[test]
+    fn test_y_parity_of_typed_transaction() {
+        let tx = Web3Transaction::create(Receipt::default(), mock_stx(vec![]));
+        let json = serde_json::to_value(&tx).unwrap();
+        assert_eq!(json["type"], "0x2");
+        // The recovery id of the mock signature is 1.
+        assert_eq!(json["yParity"], "0x1");
+        assert_eq!(json["yParity"], json["v"]);
+        assert_eq!(tx.v, U256::one());
+
+        let mut stx = mock_stx(vec![]);
+        stx.transaction.signature.as_mut().unwrap().standard_v = 0;
+        let json = serde_json::to_value(&Web3Transaction::create(Receipt::default(), stx)).unwrap();
+        assert_eq!(json["yParity"], "0x0");
+        assert_eq!(json["v"], "0x0");
+
+        // An unsigned transaction has no recovery id.
+        let mut stx = mock_stx(vec![]);
+        stx.transaction.signature = None;
+        let json = serde_json::to_value(&Web3Transaction::create(Receipt::default(), stx)).unwrap();
+        assert!(json.as_object().unwrap().get("yParity").is_none());
+    }
+
+    #[test]
+    fn test_max_fee_per_gas_of_1559_transaction() {
+        let mut stx = mock_stx(vec![]);
+        stx.transaction.unsigned.max_fee_per_gas = U256::from(30_000_000_000u64);
+        stx.transaction.unsigned.max_priority_fee_per_gas = U256::from(2_000_000_000u64);
+
+        let tx = Web3Transaction::create(Receipt::default(), stx);
+        assert_eq!(tx.max_fee_per_gas, U256::from(30_000_000_000u64));
+        assert_eq!(tx.max_priority_fee_per_gas, U256::from(2_000_000_000u64));
+        assert_eq!(
+            serde_json::to_value(&tx).unwrap()["maxFeePerGas"],
+            "0x6fc23ac00"
+        );
+    }
+
+    #[test]
+    fn test_pos_mix_hash_and_nonce() {
+        let json = serde_json::to_value(Web3Block::from(Block::default())).unwrap();
+        assert_eq!(json["nonce"], "0x0000000000000000");
+        assert_eq!(json["mixHash"], format!("{:?}", H256::zero()));
+    }
+
+    #[test]
+    fn test_seal_fields_from_proof() {
+        assert!(seal_fields(&Proof::default()).is_empty());
+
+        // The proof in the header belongs to the parent block.
+        let mut block = Block::default();
+        block.header.proof = Proof {
+            number:     1,
+            round:      2,
+            block_hash: Hash::default(),
+            signature:  Bytes::from(vec![1u8; 96]),
+            bitmap:     Bytes::from(vec![0b1010_0000]),
+        };
+        assert!(Web3Block::from(block.clone()).seal_fields.is_empty());
+
+        let seal_fields = seal_fields(&block.header.proof);
+        assert_eq!(seal_fields.len(), 3);
+        assert_eq!(seal_fields[0], Bytes::copy_from_slice(&2u64.to_be_bytes()));
+        assert_eq!(seal_fields[1].len(), 96);
+        assert_eq!(seal_fields[2], Bytes::from(vec![0b1010_0000]));
+    }
 }
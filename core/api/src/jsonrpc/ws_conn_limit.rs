@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+
+use protocol::tokio::{
+    self,
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+use protocol::types::Hex;
+
+const MAX_HANDSHAKE_SIZE: usize = 8 * 1024;
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+const MIN_ACCEPT_BACKOFF: Duration = Duration::from_millis(10);
+const MAX_ACCEPT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Counts the open connections of every remote IP, no limit when
+/// `max_per_ip` is 0.
+pub struct IpConnLimiter {
+    max_per_ip: usize,
+    conns:      Mutex<HashMap<IpAddr, usize>>,
+}
+
+impl IpConnLimiter {
+    pub fn new(max_per_ip: usize) -> Self {
+        IpConnLimiter {
+            max_per_ip,
+            conns: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Return None if the IP already has `max_per_ip` open connections, the
+    /// connection is counted until the returned guard is dropped.
+    pub fn try_acquire(self: &Arc<Self>, ip: IpAddr) -> Option<IpConnGuard> {
+        let mut conns = self.conns.lock();
+        let count = conns.entry(ip).or_insert(0);
+        if self.max_per_ip > 0 && *count >= self.max_per_ip {
+            return None;
+        }
+
+        *count += 1;
+        Some(IpConnGuard {
+            limiter: Arc::clone(self),
+            ip,
+        })
+    }
+}
+
+pub struct IpConnGuard {
+    limiter: Arc<IpConnLimiter>,
+    ip:      IpAddr,
+}
+
+impl Drop for IpConnGuard {
+    fn drop(&mut self) {
+        let mut conns = self.limiter.conns.lock();
+        if let Some(count) = conns.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                conns.remove(&self.ip);
+            }
+        }
+    }
+}
+
+/// The forwarding task, aborted when dropped so that it stops with the
+/// server behind it.
+pub struct ForwardTask(JoinHandle<()>);
+
+impl Drop for ForwardTask {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// A random `Host` the web socket server behind the limiter only accepts, so
+/// that a local client connecting to the server directly is rejected rather
+/// than slipping past the limit.
+pub fn upstream_host() -> Result<String, getrandom::Error> {
+    let mut nonce = [0u8; 16];
+    getrandom::getrandom(&mut nonce)?;
+    Ok(format!("axon-ws-{}", Hex::encode(nonce).as_string_trim0x()))
+}
+
+/// Accept the connections on `listener` and forward them to the web socket
+/// server listening on `upstream` with their `Host` replaced by
+/// `upstream_host`, closing the ones over the per IP limit right away. What
+/// the server sends is read as soon as it is sent, a connection whose client
+/// leaves more than `max_buffered` bytes unread is closed.
+pub fn spawn_forward(
+    listener: TcpListener,
+    upstream: SocketAddr,
+    upstream_host: String,
+    limiter: Arc<IpConnLimiter>,
+    max_buffered: usize,
+) -> ForwardTask {
+    ForwardTask(tokio::spawn(forward_limited_conns(
+        listener,
+        upstream,
+        Arc::new(upstream_host),
+        limiter,
+        max_buffered,
+    )))
+}
+
+async fn forward_limited_conns(
+    listener: TcpListener,
+    upstream: SocketAddr,
+    upstream_host: Arc<String>,
+    limiter: Arc<IpConnLimiter>,
+    max_buffered: usize,
+) {
+    let mut backoff = MIN_ACCEPT_BACKOFF;
+    loop {
+        let (inbound, remote) = match listener.accept().await {
+            Ok(conn) => {
+                backoff = MIN_ACCEPT_BACKOFF;
+                conn
+            }
+            Err(e) => {
+                // Errors like running out of file descriptors persist for a
+                // while, retrying right away would spin.
+                log::warn!("[jsonrpc] accept web socket connection error {:?}", e);
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_ACCEPT_BACKOFF);
+                continue;
+            }
+        };
+
+        let guard = match limiter.try_acquire(remote.ip()) {
+            Some(guard) => guard,
+            None => {
+                log::warn!(
+                    "[jsonrpc] too many web socket connections from {}",
+                    remote.ip()
+                );
+                continue;
+            }
+        };
+
+        let upstream_host = Arc::clone(&upstream_host);
+        tokio::spawn(async move {
+            let _guard = guard;
+            if let Err(e) = forward_conn(inbound, upstream, &upstream_host, max_buffered).await {
+                log::debug!("[jsonrpc] forward web socket connection error {:?}", e);
+            }
+        });
+    }
+}
+
+async fn forward_conn(
+    mut inbound: TcpStream,
+    upstream: SocketAddr,
+    upstream_host: &str,
+    max_buffered: usize,
+) -> std::io::Result<()> {
+    let (head, rest) = tokio::time::timeout(HANDSHAKE_TIMEOUT, read_handshake(&mut inbound))
+        .await
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::TimedOut, "handshake timeout"))??;
+
+    let mut outbound = TcpStream::connect(upstream).await?;
+    outbound
+        .write_all(replace_host(&head, upstream_host).as_bytes())
+        .await?;
+    outbound.write_all(&rest).await?;
+
+    let (mut client_read, mut client_write) = inbound.split();
+    let (mut server_read, mut server_write) = outbound.split();
+    let requests = async {
+        tokio::io::copy(&mut client_read, &mut server_write).await?;
+        server_write.shutdown().await
+    };
+    let responses = copy_buffered(&mut server_read, &mut client_write, max_buffered);
+    tokio::try_join!(requests, responses)?;
+    Ok(())
+}
+
+/// Copy `from` to `to`, reading `from` whenever it has data rather than when
+/// `to` takes it, so the server never queues what a slow client leaves
+/// unread. Fails once more than `max_buffered` bytes wait, no limit when it
+/// is 0.
+async fn copy_buffered<R, W>(from: &mut R, to: &mut W, max_buffered: usize) -> std::io::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut buffered = Vec::new();
+    let mut chunk = [0u8; 8 * 1024];
+    let mut eof = false;
+    loop {
+        if eof && buffered.is_empty() {
+            return to.shutdown().await;
+        }
+
+        // Both reading and writing are cancel safe, the branch not taken
+        // has not moved any byte.
+        tokio::select! {
+            n = from.read(&mut chunk), if !eof => {
+                let n = n?;
+                if n == 0 {
+                    eof = true;
+                    continue;
+                }
+
+                buffered.extend_from_slice(&chunk[..n]);
+                if max_buffered > 0 && buffered.len() > max_buffered {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::Other,
+                        format!("client left more than {} bytes unread", max_buffered),
+                    ));
+                }
+            }
+            n = to.write(&buffered), if !buffered.is_empty() => {
+                let n = n?;
+                if n == 0 {
+                    return Err(std::io::ErrorKind::WriteZero.into());
+                }
+                buffered.drain(..n);
+            }
+        }
+    }
+}
+
+/// Read the HTTP upgrade request up to its blank line, returning it and the
+/// bytes read past it.
+async fn read_handshake(inbound: &mut TcpStream) -> std::io::Result<(String, Vec<u8>)> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 1024];
+    loop {
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            let rest = buf.split_off(pos + 4);
+            let head = String::from_utf8(buf).map_err(|_| {
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "handshake is not utf8")
+            })?;
+            return Ok((head, rest));
+        }
+        if buf.len() > MAX_HANDSHAKE_SIZE {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "handshake is too large",
+            ));
+        }
+
+        let n = inbound.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+}
+
+/// Replace the `Host` header of the request head, adding it if missing.
+pub fn replace_host(head: &str, host: &str) -> String {
+    let mut lines = head
+        .trim_end_matches("\r\n")
+        .split("\r\n")
+        .filter(|line| {
+            !line
+                .split(':')
+                .next()
+                .map_or(false, |name| name.trim().eq_ignore_ascii_case("host"))
+        })
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    lines.insert(1.min(lines.len()), format!("Host: {}", host));
+    lines.join("\r\n") + "\r\n\r\n"
+}
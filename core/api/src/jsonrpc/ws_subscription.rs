@@ -0,0 +1,316 @@
+use std::future::{self, Future};
+use std::sync::Arc;
+
+use jsonrpsee::{core::Error, SubscriptionSink};
+use parking_lot::Mutex;
+
+use common_config_parser::types::{ConfigApi, SubscriptionLagPolicy};
+use core_consensus::BLOCK_IMPORT_NOTIFIER;
+use core_mempool::{PoolTxEvent, POOL_TX_NOTIFIER};
+use protocol::tokio::{
+    self,
+    sync::{broadcast, mpsc},
+};
+use protocol::traits::{APIAdapter, Context};
+use protocol::types::Block;
+
+use crate::jsonrpc::r#impl::from_receipt_to_web3_log;
+use crate::jsonrpc::web3_types::{
+    BlockId, Filter, SubscriptionKind, SubscriptionParams, Web3Block, Web3Log,
+    Web3PendingTransaction,
+};
+use crate::jsonrpc::{AxonPubSubServer, RpcResult};
+
+pub struct AxonPubSub<Adapter> {
+    adapter:     Arc<Adapter>,
+    buffer_size: usize,
+    lag_policy:  SubscriptionLagPolicy,
+}
+
+impl<Adapter: APIAdapter> AxonPubSub<Adapter> {
+    pub fn new(adapter: Arc<Adapter>, config: &ConfigApi) -> Self {
+        AxonPubSub {
+            adapter,
+            buffer_size: config.ws_subscription_buffer_size,
+            lag_policy: config.ws_subscription_lag_policy,
+        }
+    }
+}
+
+impl<Adapter: APIAdapter + 'static> AxonPubSubServer for AxonPubSub<Adapter> {
+    fn subscribe(
+        &self,
+        mut sink: SubscriptionSink,
+        kind: SubscriptionKind,
+        params: Option<SubscriptionParams>,
+    ) -> RpcResult<()> {
+        match kind {
+            SubscriptionKind::NewHeads => {
+                let rx = BLOCK_IMPORT_NOTIFIER.subscribe();
+                tokio::spawn(forward_new_heads(
+                    rx,
+                    self.buffer_size,
+                    self.lag_policy,
+                    move |head| future::ready(sink.send(&head).is_ok()),
+                ));
+            }
+            SubscriptionKind::NewPendingTransactions => {
+                let full_tx = matches!(params, Some(SubscriptionParams::FullTx(true)));
+                let rx = POOL_TX_NOTIFIER.subscribe();
+                tokio::spawn(forward_pending_txs(
+                    rx,
+                    self.buffer_size,
+                    self.lag_policy,
+                    full_tx,
+                    move |tx| future::ready(sink.send(&tx).is_ok()),
+                ));
+            }
+            SubscriptionKind::Logs => {
+                let filter = logs_filter(params)?;
+                let sink = Mutex::new(sink);
+                let rx = BLOCK_IMPORT_NOTIFIER.subscribe();
+                tokio::spawn(forward_logs(
+                    rx,
+                    Arc::clone(&self.adapter),
+                    filter,
+                    self.buffer_size,
+                    self.lag_policy,
+                    move |log| future::ready(sink.lock().send(&log).is_ok()),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// The filter of a `logs` subscription, all the logs without one. An invalid
+/// filter fails the subscription before it starts. The subscription follows
+/// the imported blocks, so a block hash can not be waited for and is
+/// rejected, while `fromBlock` and `toBlock` bound the pushed blocks.
+pub fn logs_filter(params: Option<SubscriptionParams>) -> RpcResult<Filter> {
+    match params {
+        None => Ok(Filter::default()),
+        Some(SubscriptionParams::Logs(filter)) => {
+            filter.validate().map_err(Error::Custom)?;
+            let filter = filter.try_into();
+            if matches!(filter.from_block, BlockId::Hash(_))
+                || matches!(filter.to_block, BlockId::Hash(_))
+            {
+                return Err(Error::Custom(
+                    "invalid logs subscription, blocks can not be given by hash".to_string(),
+                ));
+            }
+            Ok(filter)
+        }
+        Some(SubscriptionParams::FullTx(_)) => Err(Error::Custom(
+            "invalid logs subscription, expected a filter object".to_string(),
+        )),
+    }
+}
+
+/// Push the logs of every imported block matching `filter`, see [`forward`]
+/// for `buffer_size` and `lag_policy`.
+pub async fn forward_logs<Adapter, F, Fut>(
+    rx: broadcast::Receiver<Block>,
+    adapter: Arc<Adapter>,
+    filter: Filter,
+    buffer_size: usize,
+    lag_policy: SubscriptionLagPolicy,
+    send: F,
+) where
+    Adapter: APIAdapter + 'static,
+    F: Fn(Web3Log) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = bool> + Send,
+{
+    let send = Arc::new(send);
+    forward(rx, buffer_size, lag_policy, "logs", Some, move |block| {
+        let adapter = Arc::clone(&adapter);
+        let filter = filter.clone();
+        let send = Arc::clone(&send);
+        async move {
+            for log in block_logs(&*adapter, &block, &filter).await {
+                if !send(log).await {
+                    return false;
+                }
+            }
+            true
+        }
+    })
+    .await
+}
+
+/// The logs of `block` matching the block range, addresses and topics of
+/// `filter`, in the order of the block. `latest` and `pending` leave the
+/// range open.
+pub async fn block_logs<Adapter: APIAdapter>(
+    adapter: &Adapter,
+    block: &Block,
+    filter: &Filter,
+) -> Vec<Web3Log> {
+    let number = block.header.number;
+    let after_start = match filter.from_block {
+        BlockId::Num(from) => number >= from,
+        _ => true,
+    };
+    let before_end = match filter.to_block {
+        BlockId::Num(to) => number <= to,
+        _ => true,
+    };
+    if !after_start || !before_end {
+        return Vec::new();
+    }
+
+    let receipts = match adapter
+        .get_receipts_by_hashes(Context::new(), block.header.number, &block.tx_hashes)
+        .await
+    {
+        Ok(receipts) => receipts,
+        Err(e) => {
+            log::warn!(
+                "[jsonrpc] logs subscription can not get the receipts of block {}: {}",
+                block.header.number,
+                e
+            );
+            return Vec::new();
+        }
+    };
+
+    let mut logs = Vec::new();
+    let mut index = 0;
+    for receipt in receipts.into_iter().flatten() {
+        let log_len = receipt.logs.len();
+        from_receipt_to_web3_log(index, &filter.topics, receipt, &mut logs);
+        index += log_len;
+    }
+
+    match filter.address.as_ref() {
+        Some(addresses) if !addresses.is_empty() => {
+            logs.retain(|log| addresses.contains(&log.address));
+            logs
+        }
+        _ => logs,
+    }
+}
+
+/// The `newHeads` item carries the header fields only, so the transaction
+/// hashes are left out.
+pub fn new_head(block: Block) -> Web3Block {
+    let mut head = Web3Block::from(block);
+    head.transactions = vec![];
+    head
+}
+
+/// Push a header for every imported block until the receiver is closed or
+/// the subscriber goes away, see [`forward`] for `buffer_size` and
+/// `lag_policy`.
+pub async fn forward_new_heads<F, Fut>(
+    rx: broadcast::Receiver<Block>,
+    buffer_size: usize,
+    lag_policy: SubscriptionLagPolicy,
+    send: F,
+) where
+    F: FnMut(Web3Block) -> Fut + Send + 'static,
+    Fut: Future<Output = bool> + Send,
+{
+    let to_item = |block| Some(new_head(block));
+    forward(rx, buffer_size, lag_policy, "newHeads", to_item, send).await
+}
+
+/// Push every transaction inserted into the pool, see
+/// [`Web3PendingTransaction`] for the item shape. A replacement is pushed as
+/// the new transaction, which with `full_tx` names the replaced one. The
+/// removals are not pushed.
+pub async fn forward_pending_txs<F, Fut>(
+    rx: broadcast::Receiver<PoolTxEvent>,
+    buffer_size: usize,
+    lag_policy: SubscriptionLagPolicy,
+    full_tx: bool,
+    send: F,
+) where
+    F: FnMut(Web3PendingTransaction) -> Fut + Send + 'static,
+    Fut: Future<Output = bool> + Send,
+{
+    let to_item = move |event: PoolTxEvent| match event {
+        PoolTxEvent::Inserted { tx, replaces } => {
+            Some(Web3PendingTransaction::new(tx, replaces, full_tx))
+        }
+        PoolTxEvent::Removed(_) => None,
+    };
+    forward(
+        rx,
+        buffer_size,
+        lag_policy,
+        "newPendingTransactions",
+        to_item,
+        send,
+    )
+    .await
+}
+
+/// Push the items `to_item` makes of `rx` through `send` until it fails. At
+/// most `buffer_size` items wait while an earlier one is sent, once more arrive
+/// the subscription is terminated or the overflowed items are skipped according
+/// to `lag_policy`. The buffer fills up while sending takes time, like reading
+/// the logs of a block. A slow reader on the other end is bounded by
+/// `ws_max_buffered_bytes` at the listener in front of the server instead,
+/// which closes its connection and so terminates its subscriptions.
+async fn forward<T, I, M, F, Fut>(
+    mut rx: broadcast::Receiver<T>,
+    buffer_size: usize,
+    lag_policy: SubscriptionLagPolicy,
+    name: &'static str,
+    to_item: M,
+    mut send: F,
+) where
+    T: Clone,
+    I: Send + 'static,
+    M: Fn(T) -> Option<I>,
+    F: FnMut(I) -> Fut + Send + 'static,
+    Fut: Future<Output = bool> + Send,
+{
+    let (tx, mut buffer) = mpsc::channel(buffer_size.max(1));
+    let sender = tokio::spawn(async move {
+        while let Some(item) = buffer.recv().await {
+            if !send(item).await {
+                break;
+            }
+        }
+    });
+
+    loop {
+        let lagged = match rx.recv().await {
+            Ok(value) => match to_item(value).map(|item| tx.try_send(item)) {
+                None | Some(Ok(())) => 0,
+                Some(Err(mpsc::error::TrySendError::Full(_))) => 1,
+                Some(Err(mpsc::error::TrySendError::Closed(_))) => break,
+            },
+            Err(broadcast::error::RecvError::Lagged(n)) => n,
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+
+        if lagged == 0 {
+            continue;
+        }
+
+        match lag_policy {
+            SubscriptionLagPolicy::Skip => {
+                log::warn!(
+                    "[jsonrpc] {} subscription lagging, skip {} items",
+                    name,
+                    lagged
+                );
+            }
+            SubscriptionLagPolicy::Drop => {
+                log::warn!("[jsonrpc] {} subscription lagging, terminate it", name);
+                // Dropping the sink closes the subscription.
+                sender.abort();
+                let _ = sender.await;
+                return;
+            }
+        }
+    }
+
+    drop(tx);
+    let _ = sender.await;
+}
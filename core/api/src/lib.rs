@@ -1,6 +1,8 @@
 pub mod adapter;
 pub mod graphql;
 pub mod jsonrpc;
+#[cfg(test)]
+mod tests;
 
 pub use adapter::DefaultAPIAdapter;
 
@@ -21,6 +23,9 @@ pub enum APIError {
 
     #[display(fmt = "storage error {:?}", _0)]
     Storage(String),
+
+    #[display(fmt = "keystore error {:?}", _0)]
+    KeyStore(String),
 }
 
 impl Error for APIError {}
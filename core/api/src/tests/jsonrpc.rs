@@ -0,0 +1,2861 @@
+use std::str::FromStr;
+use std::time::Instant;
+
+use common_config_parser::types::ConfigKeystoreAccount;
+use common_crypto::{
+    secp256k1_recover, BlsPrivateKey, BlsPublicKey, BlsSignature, HashValue, PrivateKey,
+    Signature as _,
+};
+use core_consensus::util::proof_vote_hash;
+use core_consensus::{
+    notify_block_import, ConsensusStatus, BLOCK_IMPORT_NOTIFIER, CONSENSUS_STATUS,
+};
+use core_mempool::PoolTxEvent;
+use protocol::codec::hex_decode;
+use protocol::tokio::{self, sync::broadcast};
+use protocol::types::{public_to_address, Hex, MetadataVersion, Public, ValidatorExtend};
+
+use crate::adapter::lowest_stored_block;
+use crate::jsonrpc::consensus_rpc::{to_web3_status, ConsensusRpcImpl};
+use crate::jsonrpc::eip712::TypedData;
+use crate::jsonrpc::keystore::{KeyStore, LocalKeyStore};
+use crate::jsonrpc::personal_rpc::PersonalRpcImpl;
+use crate::jsonrpc::request_log::{log_requests, RequestLogger};
+use crate::jsonrpc::web3_types::{
+    BlockId, ChangeWeb3Filter, FilterChanges, Index, LogOrder, SubscriptionParams, TraceOptions,
+    VariadicValue, Web3Block, Web3BlockNumber, Web3CallRequest, Web3Filter, Web3PendingTransaction,
+    Web3StorageKey, Web3TxStatus,
+};
+use crate::jsonrpc::ws_conn_limit::{replace_host, spawn_forward, IpConnLimiter};
+use crate::jsonrpc::ws_subscription::{
+    forward_logs, forward_new_heads, forward_pending_txs, logs_filter,
+};
+use crate::jsonrpc::{
+    check_method_names, rpc_module, serves_personal_rpc, AxonConsensusRpcServer, AxonJsonRpcServer,
+    AxonPersonalRpcServer,
+};
+
+use super::*;
+
+fn mock_call_request(data: &str) -> Web3CallRequest {
+    Web3CallRequest {
+        transaction_type:         None,
+        from:                     None,
+        to:                       H160::repeat_byte(0x11),
+        gas_price:                None,
+        max_fee_per_gas:          None,
+        gas:                      None,
+        value:                    None,
+        data:                     Hex::from_string(data.to_string()).unwrap(),
+        nonce:                    None,
+        access_list:              None,
+        max_priority_fee_per_gas: None,
+    }
+}
+
+#[tokio::test]
+async fn test_call_with_explicit_gas() {
+    let (_adapter, rpc) = mock_rpc(&mock_config());
+    let mut req = mock_call_request("0x1234");
+
+    // Without `gas` the call runs with the block gas limit.
+    assert_eq!(
+        rpc.call(req.clone(), Some(BlockId::Latest)).await.unwrap(),
+        Hex::from_string("0x1234".to_string()).unwrap()
+    );
+
+    // A too low `gas` is not raised, the call runs out of gas.
+    req.gas = Some(U256::from(20000u64));
+    let err = rpc.call(req, Some(BlockId::Latest)).await.unwrap_err();
+    assert!(err.to_string().contains("out of gas"));
+}
+
+#[tokio::test]
+async fn test_call_cache_disabled_by_default() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let req = mock_call_request("0x1234");
+
+    rpc.call(req.clone(), Some(BlockId::Latest)).await.unwrap();
+    rpc.call(req, Some(BlockId::Latest)).await.unwrap();
+    assert_eq!(adapter.evm_call_count(), 2);
+}
+
+#[tokio::test]
+async fn test_call_with_logs() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let log = Log {
+        address: H160::repeat_byte(0x11),
+        topics:  vec![H256::repeat_byte(0x2a)],
+        data:    vec![0xff],
+    };
+    adapter.set_call_logs(vec![log.clone()]);
+
+    let res = rpc
+        .call_with_logs(mock_call_request("0x1234"), None)
+        .await
+        .unwrap();
+    assert_eq!(
+        res.return_data,
+        Hex::from_string("0x1234".to_string()).unwrap()
+    );
+    assert_eq!(res.gas_used, 21000u64.into());
+    assert_eq!(res.logs.len(), 1);
+    assert_eq!(res.logs[0].address, log.address);
+    assert_eq!(res.logs[0].topics, log.topics);
+    assert_eq!(res.logs[0].data, Hex::encode(&log.data));
+    assert_eq!(res.logs[0].log_index, Some(0u64.into()));
+}
+
+#[tokio::test]
+async fn test_multicall_runs_each_call_alone() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let mut short = mock_call_request("0x56");
+    short.gas = Some(1000u64.into());
+    let reqs = vec![mock_call_request("0x12"), short, mock_call_request("0x34")];
+
+    let res = rpc.multicall(reqs, Some(BlockId::Latest)).await.unwrap();
+    assert_eq!(adapter.evm_call_count(), 3);
+    assert_eq!(res.len(), 3);
+    assert!(res[0].success);
+    assert_eq!(
+        res[0].return_data,
+        Hex::from_string("0x12".to_string()).unwrap()
+    );
+    assert!(!res[1].success);
+    assert!(res[1].error.is_some());
+    // A failed call leaves the calls after it alone.
+    assert!(res[2].success);
+    assert_eq!(
+        res[2].return_data,
+        Hex::from_string("0x34".to_string()).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_simulate_bundle_carries_state_forward() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let reqs = vec![mock_call_request("0x12"), mock_call_request("0x34")];
+
+    let res = rpc
+        .simulate_bundle(reqs, Some(BlockId::Latest))
+        .await
+        .unwrap();
+    assert_eq!(adapter.evm_call_count(), 0);
+    assert_eq!(res.len(), 2);
+    assert_eq!(
+        res[0].return_data,
+        Hex::from_string("0x12".to_string()).unwrap()
+    );
+    assert_eq!(
+        res[1].return_data,
+        Hex::from_string("0x1234".to_string()).unwrap()
+    );
+    assert_eq!(
+        serde_json::to_value(&res[1]).unwrap(),
+        serde_json::json!({
+            "success": true,
+            "returnData": "0x1234",
+            "gasUsed": "0x5208",
+            "error": null,
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_multicall_limits() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let reqs = vec![mock_call_request("0x12"); 5];
+    let err = rpc
+        .multicall(reqs.clone(), Some(BlockId::Latest))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("too many calls"));
+    let err = rpc
+        .simulate_bundle(reqs, Some(BlockId::Latest))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("too many calls"));
+    assert_eq!(adapter.evm_call_count(), 0);
+
+    // A call returning too much data fails alone.
+    let large = format!("0x{}", "ab".repeat(4097));
+    let reqs = vec![mock_call_request(&large), mock_call_request("0x34")];
+    let res = rpc.multicall(reqs, Some(BlockId::Latest)).await.unwrap();
+    assert!(!res[0].success);
+    assert_eq!(res[0].return_data, Hex::empty());
+    assert!(res[0]
+        .error
+        .as_ref()
+        .unwrap()
+        .contains("return data too large"));
+    assert!(res[1].success);
+}
+
+#[tokio::test]
+async fn test_multicall_takes_trace_permit() {
+    let mut config = mock_config();
+    config.max_concurrent_traces = 1;
+    let (adapter, rpc) = mock_rpc(&config);
+    let rpc = Arc::new(rpc);
+    let hash = adapter.mine_tx(mock_signed_tx(0)).tx_hashes[0];
+
+    let gate = Arc::new(Semaphore::new(0));
+    adapter.gate_traces(Arc::clone(&gate));
+    let running = {
+        let rpc = Arc::clone(&rpc);
+        tokio::spawn(async move { rpc.trace_transaction(hash, None).await })
+    };
+    while adapter.trace_count() == 0 {
+        tokio::task::yield_now().await;
+    }
+
+    let reqs = vec![mock_call_request("0x12")];
+    let err = rpc
+        .multicall(reqs.clone(), Some(BlockId::Latest))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("too many concurrent traces"));
+    let err = rpc
+        .simulate_bundle(reqs.clone(), Some(BlockId::Latest))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("too many concurrent traces"));
+
+    gate.add_permits(1);
+    running.await.unwrap().unwrap();
+    assert!(rpc
+        .multicall(reqs.clone(), Some(BlockId::Latest))
+        .await
+        .is_ok());
+    assert!(rpc
+        .simulate_bundle(reqs, Some(BlockId::Latest))
+        .await
+        .is_ok());
+}
+
+#[tokio::test]
+async fn test_call_cache_reuse_result_at_same_block() {
+    let mut config = mock_config();
+    config.call_cache_size = 16;
+    let (adapter, rpc) = mock_rpc(&config);
+    let req = mock_call_request("0x1234");
+
+    let first = rpc.call(req.clone(), Some(BlockId::Latest)).await.unwrap();
+    let second = rpc.call(req.clone(), Some(BlockId::Latest)).await.unwrap();
+    assert_eq!(first, second);
+    assert_eq!(adapter.evm_call_count(), 1);
+
+    adapter.push_block(vec![]);
+    rpc.call(req, Some(BlockId::Latest)).await.unwrap();
+    assert_eq!(adapter.evm_call_count(), 2);
+}
+
+#[tokio::test]
+async fn test_estimate_gas_covers_refund() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    // A contract clearing a slot then self-destructing, the refund of the
+    // slot is only paid back after it ran with the full gas.
+    adapter.set_call_gas(33_609, 28_809);
+
+    let req = mock_call_request("0x");
+    let estimate = rpc
+        .estimate_gas(req.clone(), Some(BlockId::Latest))
+        .await
+        .unwrap();
+    assert_eq!(estimate, U256::from(33_609u64));
+
+    let mut call_req = req.clone();
+    call_req.gas = Some(estimate);
+    assert!(rpc
+        .call(call_req.clone(), Some(BlockId::Latest))
+        .await
+        .is_ok());
+    call_req.gas = Some(estimate - 1);
+    assert!(rpc.call(call_req, Some(BlockId::Latest)).await.is_err());
+
+    // Without a refund the gas used is enough.
+    adapter.set_call_gas(21000, 21000);
+    assert_eq!(
+        rpc.estimate_gas(req, Some(BlockId::Latest)).await.unwrap(),
+        U256::from(21000u64)
+    );
+}
+
+#[tokio::test]
+async fn test_new_heads_push_headers_in_order() {
+    let adapter = MockAPIAdapter::new();
+    let (tx, rx) = broadcast::channel(16);
+    tx.send(adapter.push_block(vec![Hash::default()])).unwrap();
+    tx.send(adapter.push_block(vec![])).unwrap();
+    drop(tx);
+
+    let heads = Arc::new(Mutex::new(Vec::new()));
+    let pushed = Arc::clone(&heads);
+    forward_new_heads(rx, 16, SubscriptionLagPolicy::Drop, move |head| {
+        pushed.lock().push(head);
+        async { true }
+    })
+    .await;
+
+    let heads = heads.lock();
+    assert_eq!(heads.len(), 2);
+    assert_eq!(heads[0].number, U256::from(1u64));
+    assert_eq!(heads[1].parent_hash, heads[0].hash);
+    assert!(heads[0].transactions.is_empty());
+    assert_ne!(heads[1].state_root, Hash::default());
+    assert_eq!(heads[1].base_fee_per_gas, U256::from(1_000_000_000u64));
+}
+
+#[tokio::test]
+async fn test_stalled_new_heads_subscriber_is_dropped() {
+    let adapter = MockAPIAdapter::new();
+    let (tx, rx) = broadcast::channel(16);
+    for _ in 0..4 {
+        tx.send(adapter.push_block(vec![])).unwrap();
+    }
+
+    // The subscriber never finishes sending the first head, so the buffer
+    // of 2 heads overflows.
+    let sink = Arc::new(());
+    let stalled = Arc::clone(&sink);
+    let forward = forward_new_heads(rx, 2, SubscriptionLagPolicy::Drop, move |_| {
+        let _sink = &stalled;
+        std::future::pending::<bool>()
+    });
+
+    tokio::time::timeout(std::time::Duration::from_secs(5), forward)
+        .await
+        .expect("the subscription should be terminated");
+    assert_eq!(Arc::strong_count(&sink), 1);
+    drop(tx);
+}
+
+#[tokio::test]
+async fn test_pending_transactions_push_replacement() {
+    let replaced = mock_signed_tx(0);
+    let mut utx = replaced.transaction.clone();
+    utx.unsigned.max_fee_per_gas = U256::one();
+    let replacement = SignedTransaction {
+        transaction: utx.hash(),
+        ..replaced.clone()
+    };
+
+    let (tx, rx) = broadcast::channel(16);
+    for (stx, replaces) in [
+        (replaced.clone(), None),
+        (replacement.clone(), Some(replaced.transaction.hash)),
+    ] {
+        tx.send(PoolTxEvent::Inserted { tx: stx, replaces })
+            .unwrap();
+    }
+    // Removals are not pushed.
+    tx.send(PoolTxEvent::Removed(vec![replaced.transaction.hash]))
+        .unwrap();
+    drop(tx);
+
+    let items = Arc::new(Mutex::new(Vec::new()));
+    let pushed = Arc::clone(&items);
+    forward_pending_txs(rx, 16, SubscriptionLagPolicy::Drop, true, move |item| {
+        pushed.lock().push(serde_json::to_value(item).unwrap());
+        async { true }
+    })
+    .await;
+
+    let items = items.lock();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0]["replaces"], serde_json::Value::Null);
+    assert_eq!(
+        items[1]["hash"],
+        serde_json::to_value(replacement.transaction.hash).unwrap()
+    );
+    assert_eq!(
+        items[1]["replaces"],
+        serde_json::to_value(replaced.transaction.hash).unwrap()
+    );
+    assert_eq!(items[1]["transactionIndex"], serde_json::Value::Null);
+
+    let hash_only = Web3PendingTransaction::new(replacement.clone(), None, false);
+    assert_eq!(
+        serde_json::to_value(hash_only).unwrap(),
+        serde_json::to_value(replacement.transaction.hash).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn test_get_transaction_status() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let stx = mock_signed_tx(0);
+    let hash = stx.transaction.hash;
+
+    let status = rpc.get_transaction_status(hash).await.unwrap();
+    assert_eq!(status, Web3TxStatus::Unknown);
+
+    adapter
+        .insert_signed_txs(Context::new(), stx.clone())
+        .await
+        .unwrap();
+    let status = rpc.get_transaction_status(hash).await.unwrap();
+    assert_eq!(status, Web3TxStatus::Pending);
+
+    let block = adapter.mine_tx(stx);
+    adapter.push_block(vec![]);
+    adapter.push_block(vec![]);
+    let status = rpc.get_transaction_status(hash).await.unwrap();
+    assert_eq!(status, Web3TxStatus::Mined {
+        block_number:  block.header.number.into(),
+        confirmations: 3u64.into(),
+    });
+}
+
+#[tokio::test]
+async fn test_receipt_recovers_absent_sender() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let hash = rpc
+        .send_raw_transaction(mock_raw_tx(&Hasher::digest(b"cow"), 0))
+        .await
+        .unwrap();
+    let mut stx = adapter.pool_tx(&hash).unwrap();
+    let sender = stx.sender;
+    assert!(!sender.is_zero());
+
+    // Neither the stored transaction nor its receipt carries the sender.
+    stx.sender = H160::zero();
+    adapter.mine_tx(stx);
+
+    let receipt = rpc.get_transaction_receipt(hash).await.unwrap().unwrap();
+    assert_eq!(receipt.from, sender);
+}
+
+#[tokio::test]
+async fn test_receipt_and_transaction_agree_on_sender() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let hash = rpc
+        .send_raw_transaction(mock_raw_tx(&Hasher::digest(b"dog"), 0))
+        .await
+        .unwrap();
+    let stx = adapter.pool_tx(&hash).unwrap();
+    let sender = stx.sender;
+    adapter.mine_tx(stx);
+    adapter.set_receipt_sender(&hash, H160::repeat_byte(0xee));
+
+    let tx = rpc.get_transaction_by_hash(hash).await.unwrap().unwrap();
+    let receipt = rpc.get_transaction_receipt(hash).await.unwrap().unwrap();
+    assert_eq!(tx.from, sender);
+    assert_eq!(receipt.from, tx.from);
+
+    // Without a sender in the stored transaction both recover the same one.
+    let hash = rpc
+        .send_raw_transaction(mock_raw_tx(&Hasher::digest(b"dog"), 1))
+        .await
+        .unwrap();
+    let mut stx = adapter.pool_tx(&hash).unwrap();
+    stx.sender = H160::zero();
+    adapter.mine_tx(stx);
+    adapter.set_receipt_sender(&hash, H160::repeat_byte(0xee));
+
+    let tx = rpc.get_transaction_by_hash(hash).await.unwrap().unwrap();
+    let receipt = rpc.get_transaction_receipt(hash).await.unwrap().unwrap();
+    assert_eq!(tx.from, sender);
+    assert_eq!(receipt.from, tx.from);
+}
+
+#[tokio::test]
+async fn test_pending_contract_creation() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    adapter.push_block(vec![]);
+
+    let code = hex_decode("6001600055").unwrap();
+    let mut stx = mock_signed_tx(3);
+    stx.transaction.unsigned.action = TransactionAction::Create;
+    stx.transaction.unsigned.data = code.clone().into();
+    stx.transaction = stx.transaction.hash();
+    let contract = H160::from(code_address(&stx.sender, &U256::from(3u64)));
+    adapter.insert_pool_tx(stx);
+
+    assert_eq!(
+        rpc.get_code(contract, Some(BlockId::Pending))
+            .await
+            .unwrap(),
+        Hex::encode(&code)
+    );
+    assert_eq!(
+        rpc.get_code(contract, Some(BlockId::Latest)).await.unwrap(),
+        Hex::empty()
+    );
+
+    let slot: Web3StorageKey = serde_json::from_value(serde_json::json!("0x0")).unwrap();
+    assert_eq!(
+        rpc.get_storage_at(contract, slot, Some(BlockId::Pending))
+            .await
+            .unwrap(),
+        H256::zero()
+    );
+    assert_eq!(
+        rpc.get_storage_at(contract, slot, None).await.unwrap(),
+        H256::zero()
+    );
+    assert_eq!(
+        rpc.get_code(H160::repeat_byte(0x33), Some(BlockId::Pending))
+            .await
+            .unwrap(),
+        Hex::empty()
+    );
+    // The pool of the other tests may change in between, so the pending block
+    // is executed at least once and at most once per read.
+    assert!((1..=3).contains(&adapter.replay_count()));
+}
+
+#[tokio::test]
+async fn test_get_transaction_by_hash_uses_index() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let stxs = (0..3).map(mock_signed_tx).collect::<Vec<_>>();
+    let hash = stxs[2].transaction.hash;
+    let block = adapter.mine_txs(stxs);
+    adapter.push_block(vec![]);
+
+    let block_reads = adapter.block_reads();
+    let index_reads = adapter.index_reads();
+
+    let tx = rpc.get_transaction_by_hash(hash).await.unwrap().unwrap();
+    assert_eq!(tx.block_number, block.header.number.into());
+    assert_eq!(tx.block_hash, block.header_hash());
+    assert_eq!(tx.transaction_index, Some(2u64.into()));
+
+    let receipt = rpc.get_transaction_receipt(hash).await.unwrap().unwrap();
+    assert_eq!(receipt.block_number, block.header.number.into());
+    assert_eq!(receipt.transaction_index, Some(2u64.into()));
+
+    let unknown = H256::repeat_byte(0xab);
+    assert!(rpc
+        .get_transaction_by_hash(unknown)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(rpc
+        .get_transaction_receipt(unknown)
+        .await
+        .unwrap()
+        .is_none());
+
+    assert_eq!(adapter.index_reads(), index_reads + 4);
+    assert_eq!(adapter.block_reads(), block_reads);
+}
+
+#[tokio::test]
+async fn test_pending_block_transaction_count() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let latest = adapter.push_block(vec![]);
+    assert_eq!(
+        rpc.get_transaction_count_by_number(BlockId::Pending)
+            .await
+            .unwrap(),
+        U256::zero()
+    );
+
+    // Only three of them fit in the 30M gas limit of the block.
+    for nonce in 0..4 {
+        let mut stx = mock_signed_tx(nonce);
+        stx.transaction.unsigned.gas_limit = latest.header.gas_limit / 3;
+        stx.transaction = stx.transaction.hash();
+        adapter.insert_pool_tx(stx);
+    }
+    adapter.push_block(vec![]);
+
+    assert_eq!(
+        rpc.get_transaction_count_by_number(BlockId::Pending)
+            .await
+            .unwrap(),
+        U256::from(3u64)
+    );
+    assert_eq!(
+        rpc.get_transaction_count_by_number(BlockId::Latest)
+            .await
+            .unwrap(),
+        U256::zero()
+    );
+}
+
+#[tokio::test]
+async fn test_fee_history_gas_weighted_reward() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let gwei = U256::from(1_000_000_000u64);
+    // (tip in gwei, gas used)
+    let stxs = [(3u64, 60_000u64), (1, 21_000), (2, 30_000)]
+        .iter()
+        .enumerate()
+        .map(|(nonce, &(tip, gas))| {
+            let mut stx = mock_signed_tx(nonce as u64);
+            let unsigned = &mut stx.transaction.unsigned;
+            unsigned.max_priority_fee_per_gas = gwei * tip;
+            unsigned.max_fee_per_gas = gwei * 10u64;
+            unsigned.gas_limit = gas.into();
+            stx.transaction = stx.transaction.hash();
+            stx
+        })
+        .collect::<Vec<_>>();
+    let block = adapter.mine_txs(stxs);
+
+    let history = rpc
+        .fee_history(
+            1,
+            BlockId::Num(block.header.number),
+            Some(vec![0.0, 50.0, 100.0]),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(history.oldest_block, block.header.number.into());
+    assert_eq!(history.base_fee_per_gas, vec![
+        block.header.base_fee_per_gas;
+        2
+    ]);
+    // Sorted by tip the cumulative gas is 21000, 51000 and 111000, so half of
+    // the gas is only reached by the 3 gwei transaction, while the plain
+    // median is 2 gwei.
+    assert_eq!(
+        history.reward,
+        Some(vec![vec![gwei, gwei * 3u64, gwei * 3u64]])
+    );
+
+    let history = rpc
+        .fee_history(1, BlockId::Num(block.header.number), None)
+        .await
+        .unwrap();
+    assert_eq!(history.reward, None);
+
+    let err = rpc
+        .fee_history(1, BlockId::Latest, Some(vec![50.0, 10.0]))
+        .await;
+    assert!(err.is_err());
+}
+
+#[tokio::test]
+async fn test_fee_history_base_fee_per_block() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let gwei = U256::from(1_000_000_000u64);
+    let mine = |nonce: u64, base_fee: u64| {
+        let mut stx = mock_signed_tx(nonce);
+        let unsigned = &mut stx.transaction.unsigned;
+        unsigned.max_priority_fee_per_gas = gwei * 3u64;
+        unsigned.max_fee_per_gas = gwei * 6u64;
+        stx.transaction = stx.transaction.hash();
+        let number = adapter.mine_txs(vec![stx]).header.number;
+        adapter.set_base_fee(number, gwei * base_fee);
+        number
+    };
+    let first = mine(0, 1);
+    let second = mine(1, 5);
+    let third = mine(2, 4);
+
+    let history = rpc
+        .fee_history(2, BlockId::Num(second), Some(vec![50.0]))
+        .await
+        .unwrap();
+    assert_eq!(history.oldest_block, first.into());
+    // The last one is the base fee of the block after the newest.
+    assert_eq!(history.base_fee_per_gas, vec![
+        gwei,
+        gwei * 5u64,
+        gwei * 4u64
+    ]);
+    // The tip is capped by the max fee over the base fee of each block.
+    assert_eq!(history.reward, Some(vec![vec![gwei * 3u64], vec![gwei]]));
+
+    let history = rpc.fee_history(1, BlockId::Num(third), None).await.unwrap();
+    assert_eq!(history.base_fee_per_gas, vec![gwei * 4u64; 2]);
+}
+
+#[tokio::test]
+async fn test_fee_history_interpolation() {
+    let fee_history = |interpolation: FeeHistoryInterpolation| async move {
+        let config = ConfigApi {
+            fee_history_interpolation: interpolation,
+            ..mock_config()
+        };
+        let (adapter, rpc) = mock_rpc(&config);
+        let gwei = U256::from(1_000_000_000u64);
+        let stxs = (1..=4u64)
+            .map(|tip| {
+                let mut stx = mock_signed_tx(tip);
+                let unsigned = &mut stx.transaction.unsigned;
+                unsigned.max_priority_fee_per_gas = gwei * tip;
+                unsigned.max_fee_per_gas = gwei * 10u64;
+                stx.transaction = stx.transaction.hash();
+                stx
+            })
+            .collect::<Vec<_>>();
+        let block = adapter.mine_txs(stxs);
+
+        rpc.fee_history(
+            1,
+            BlockId::Num(block.header.number),
+            Some(vec![0.0, 25.0, 50.0, 100.0]),
+        )
+        .await
+        .unwrap()
+        .reward
+        .unwrap()
+        .remove(0)
+    };
+    let gwei = U256::from(1_000_000_000u64);
+
+    // Four transactions of the same gas with tips of 1 to 4 gwei.
+    assert_eq!(
+        fee_history(FeeHistoryInterpolation::NearestRank).await,
+        vec![gwei, gwei, gwei * 2u64, gwei * 4u64]
+    );
+    // They stand at 12.5%, 37.5%, 62.5% and 87.5% of the gas.
+    assert_eq!(fee_history(FeeHistoryInterpolation::Linear).await, vec![
+        gwei,
+        gwei * 3u64 / 2u64,
+        gwei * 5u64 / 2u64,
+        gwei * 4u64
+    ]);
+}
+
+#[tokio::test]
+async fn test_fee_suggestions_default_on_empty_history() {
+    let config = ConfigApi {
+        default_priority_fee: 7,
+        ..mock_config()
+    };
+    let (adapter, rpc) = mock_rpc(&config);
+    adapter.push_block(vec![]);
+    let latest = adapter.push_block(vec![]);
+
+    // The price is never below the base fee of the next block.
+    assert_eq!(
+        rpc.gas_price().await.unwrap(),
+        latest.header.base_fee_per_gas + 7u64
+    );
+    assert_eq!(
+        rpc.max_priority_fee_per_gas().await.unwrap(),
+        U256::from(7u64)
+    );
+
+    // Once a recent block has transactions the suggestions follow them.
+    let mut stx = mock_signed_tx(0);
+    stx.transaction.unsigned.max_priority_fee_per_gas = 3u64.into();
+    stx.transaction.unsigned.max_fee_per_gas = U256::MAX;
+    stx.transaction = stx.transaction.hash();
+    let block = adapter.mine_tx(stx);
+
+    assert_eq!(
+        rpc.max_priority_fee_per_gas().await.unwrap(),
+        U256::from(3u64)
+    );
+    assert_eq!(
+        rpc.gas_price().await.unwrap(),
+        block.header.base_fee_per_gas + 3u64
+    );
+}
+
+#[tokio::test]
+async fn test_max_concurrent_traces() {
+    let mut config = mock_config();
+    config.max_concurrent_traces = 1;
+    let (adapter, rpc) = mock_rpc(&config);
+    let rpc = Arc::new(rpc);
+    let hash = adapter.mine_tx(mock_signed_tx(0)).tx_hashes[0];
+    let options = || {
+        Some(TraceOptions {
+            tracer: Some("prestateTracer".to_string()),
+        })
+    };
+
+    let gate = Arc::new(Semaphore::new(0));
+    adapter.gate_traces(Arc::clone(&gate));
+
+    let running = {
+        let rpc = Arc::clone(&rpc);
+        let options = options();
+        tokio::spawn(async move { rpc.trace_transaction(hash, options).await })
+    };
+    while adapter.trace_count() == 0 {
+        tokio::task::yield_now().await;
+    }
+
+    let err = rpc.trace_transaction(hash, options()).await.unwrap_err();
+    assert!(err.to_string().contains("too many concurrent traces"));
+    assert_eq!(adapter.trace_count(), 1);
+
+    gate.add_permits(1);
+    running.await.unwrap().unwrap();
+
+    // The slot is released once the running trace finishes.
+    rpc.trace_transaction(hash, options()).await.unwrap();
+    assert_eq!(adapter.trace_count(), 2);
+}
+
+#[tokio::test]
+async fn test_storage_range_max_result() {
+    let mut config = mock_config();
+    config.storage_range_max_result = 16;
+    let (adapter, rpc) = mock_rpc(&config);
+    let block = adapter.mine_tx(mock_signed_tx(0));
+    let range = |max_result: usize| {
+        rpc.storage_range_at(
+            block.header_hash(),
+            0,
+            H160::repeat_byte(0x01),
+            H256::zero(),
+            max_result,
+        )
+    };
+
+    range(usize::MAX).await.unwrap();
+    assert_eq!(adapter.range_limit(), 16);
+    range(5).await.unwrap();
+    assert_eq!(adapter.range_limit(), 5);
+}
+
+#[tokio::test]
+async fn test_is_block_canonical() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let orphan = adapter.push_block(vec![]);
+    adapter.reorg(orphan.header.number, H256::repeat_byte(0x01));
+    let canonical = adapter
+        .get_block_by_number(Context::new(), None)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(canonical.header.number, orphan.header.number);
+    assert_ne!(canonical.header_hash(), orphan.header_hash());
+
+    for (block, is_canonical) in [(&canonical, true), (&orphan, false)] {
+        let hash = block.header_hash();
+        let web3_block = rpc.get_block_by_hash(hash, false).await.unwrap().unwrap();
+        assert_eq!(web3_block.hash, hash);
+        assert_eq!(rpc.is_block_canonical(hash).await.unwrap(), is_canonical);
+    }
+
+    assert!(rpc
+        .is_block_canonical(H256::repeat_byte(0xab))
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_consensus_status() {
+    let proposer = H160::repeat_byte(0x33);
+    let status = ConsensusStatus {
+        height:                11,
+        round:                 2,
+        last_committed_height: 10,
+        last_proposer:         proposer,
+        last_commit_time:      1000,
+    };
+
+    let web3_status = to_web3_status(status.clone(), 1005);
+    assert_eq!(web3_status.height, U256::from(11u64));
+    assert_eq!(web3_status.round, U256::from(2u64));
+    assert_eq!(web3_status.last_committed_height, U256::from(10u64));
+    assert_eq!(web3_status.proposer, proposer);
+    assert_eq!(web3_status.time_since_last_commit, U256::from(5u64));
+
+    *CONSENSUS_STATUS.write() = status;
+    let web3_status = ConsensusRpcImpl.consensus_status().await.unwrap();
+    assert_eq!(web3_status.height, U256::from(11u64));
+    assert_eq!(web3_status.proposer, proposer);
+}
+
+#[tokio::test]
+async fn test_get_logs_pin_head_during_reorg() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let topic = H256::repeat_byte(0x44);
+    let chain = (0..4)
+        .map(|_| adapter.push_block_with_log(topic, b"origin"))
+        .collect::<Vec<_>>();
+
+    // Blocks from height 3 onward are replaced right after the head is read.
+    adapter.reorg_after_next_head(3, topic);
+    let filter = Web3Filter {
+        from_block:    Some(BlockId::Num(1)),
+        to_block:      Some(BlockId::Latest),
+        block_hash:    None,
+        address:       None,
+        topics:        Some(vec![VariadicValue::Single(topic)]),
+        limit:         None,
+        confirmations: None,
+        order:         None,
+    };
+    let logs = rpc.get_logs(filter).await.unwrap();
+
+    assert_eq!(logs.len(), chain.len());
+    for (log, block) in logs.iter().zip(chain.iter()) {
+        assert_eq!(log.block_hash, Some(block.header_hash()));
+        assert_eq!(log.data, Hex::encode(b"origin"));
+    }
+
+    let forked = adapter
+        .get_block_by_number(Context::new(), Some(3))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_ne!(forked.header_hash(), chain[2].header_hash());
+}
+
+#[tokio::test]
+async fn test_get_logs_exclude_orphaned_blocks() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let topic = H256::repeat_byte(0x45);
+    adapter.push_block_with_log(topic, b"origin");
+    let orphan = adapter.push_block_with_log(topic, b"origin");
+    adapter.reorg(orphan.header.number, topic);
+    let canonical = adapter
+        .get_block_by_number(Context::new(), Some(orphan.header.number))
+        .await
+        .unwrap()
+        .unwrap();
+
+    let filter = |block_hash: Option<H256>| Web3Filter {
+        from_block: block_hash.is_none().then(|| BlockId::Num(1)),
+        to_block: block_hash.is_none().then(|| BlockId::Latest),
+        block_hash,
+        address: None,
+        topics: Some(vec![VariadicValue::Single(topic)]),
+        limit: None,
+        confirmations: None,
+        order: None,
+    };
+
+    let logs = rpc.get_logs(filter(None)).await.unwrap();
+    assert_eq!(logs.len(), 2);
+    assert_eq!(logs[1].block_hash, Some(canonical.header_hash()));
+    assert_eq!(logs[1].data, Hex::encode(b"fork"));
+    assert!(logs
+        .iter()
+        .all(|log| log.block_hash != Some(orphan.header_hash())));
+
+    // The orphaned block is still stored, but its logs are not returned.
+    assert!(rpc
+        .get_logs(filter(Some(orphan.header_hash())))
+        .await
+        .unwrap()
+        .is_empty());
+    let logs = rpc
+        .get_logs(filter(Some(canonical.header_hash())))
+        .await
+        .unwrap();
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0].data, Hex::encode(b"fork"));
+}
+
+#[tokio::test]
+async fn test_get_logs_reverse_scan_with_limit() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let topic = H256::repeat_byte(0x46);
+    let chain = (0..6)
+        .map(|i| adapter.push_block_with_log(topic, &[i]))
+        .collect::<Vec<_>>();
+
+    let filter = |limit: Option<usize>, order: Option<LogOrder>| Web3Filter {
+        from_block: Some(BlockId::Num(1)),
+        to_block: Some(BlockId::Latest),
+        block_hash: None,
+        address: None,
+        topics: Some(vec![VariadicValue::Single(topic)]),
+        limit,
+        confirmations: None,
+        order,
+    };
+
+    let reads = adapter.block_reads();
+    let all = rpc.get_logs(filter(None, None)).await.unwrap();
+    let full_scan_reads = adapter.block_reads() - reads;
+    assert_eq!(all.len(), chain.len());
+
+    let reads = adapter.block_reads();
+    let newest = rpc
+        .get_logs(filter(Some(2), Some(LogOrder::Desc)))
+        .await
+        .unwrap();
+    assert_eq!(newest.len(), 2);
+    assert_eq!(newest[0].block_hash, Some(chain[5].header_hash()));
+    assert_eq!(newest[1].block_hash, Some(chain[4].header_hash()));
+    assert!(adapter.block_reads() - reads < full_scan_reads);
+
+    // A limit larger than the matches returns all of them, newest first.
+    let logs = rpc
+        .get_logs(filter(Some(10), Some(LogOrder::Desc)))
+        .await
+        .unwrap();
+    assert_eq!(logs, all.into_iter().rev().collect::<Vec<_>>());
+
+    // The walk starts at the end of the range, not at the newest block.
+    let mut bounded = filter(Some(1), Some(LogOrder::Desc));
+    bounded.to_block = Some(BlockId::Num(chain[3].header.number));
+    let reads = adapter.block_reads();
+    let logs = rpc.get_logs(bounded).await.unwrap();
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0].block_hash, Some(chain[3].header_hash()));
+    assert!(adapter.block_reads() - reads < full_scan_reads);
+}
+
+#[tokio::test]
+async fn test_send_transaction_with_unlocked_account() {
+    let keystore = LocalKeyStore::new(&[ConfigKeystoreAccount {
+        privkey:  Hex::from_string(
+            "0x95500289866f83502cc1fb894ef5e2b840ca5f867cc9e84ab32fb8872b5dd36c".to_string(),
+        )
+        .unwrap(),
+        password: "axon".to_string(),
+    }])
+    .unwrap();
+    let address = keystore.accounts()[0];
+    let adapter = Arc::new(MockAPIAdapter::new());
+    let rpc = PersonalRpcImpl::new(Arc::clone(&adapter), Arc::new(keystore));
+
+    let mut req = mock_call_request("0x1234");
+    req.from = Some(address);
+    assert!(rpc.send_transaction(req.clone()).await.is_err());
+    assert!(!rpc
+        .unlock_account(address, "wrong".to_string(), None)
+        .await
+        .unwrap());
+    assert!(rpc
+        .unlock_account(address, "axon".to_string(), None)
+        .await
+        .unwrap());
+
+    let hash = rpc.send_transaction(req.clone()).await.unwrap();
+    let stx = adapter.pool_tx(&hash).unwrap();
+    assert_eq!(stx.sender, address);
+    assert_eq!(
+        stx.transaction.unsigned.max_fee_per_gas,
+        adapter
+            .get_block_header_by_number(Context::new(), None)
+            .await
+            .unwrap()
+            .unwrap()
+            .base_fee_per_gas
+    );
+    assert!(stx.transaction.check_hash());
+    assert_eq!(stx.transaction.unsigned.data, req.data.as_bytes());
+    assert_eq!(
+        SignedTransaction::try_from(stx.transaction).unwrap().sender,
+        address
+    );
+
+    // The next send takes the nonce after the one waiting in the pool.
+    let next = rpc.send_transaction(req.clone()).await.unwrap();
+    assert_eq!(
+        adapter.pool_tx(&next).unwrap().transaction.unsigned.nonce,
+        stx.transaction.unsigned.nonce + 1
+    );
+
+    assert!(rpc.lock_account(address).await.unwrap());
+    assert!(rpc.send_transaction(req).await.is_err());
+}
+
+#[test]
+fn test_personal_rpc_only_on_loopback() {
+    let mut config = mock_config();
+    let local = "127.0.0.1:8000".parse().unwrap();
+    let public = "0.0.0.0:8000".parse().unwrap();
+    assert!(!serves_personal_rpc(&config, local));
+
+    config.enable_personal_rpc = true;
+    assert!(serves_personal_rpc(&config, local));
+    assert!(serves_personal_rpc(&config, "[::1]:8000".parse().unwrap()));
+    assert!(!serves_personal_rpc(&config, public));
+}
+
+#[tokio::test]
+async fn test_sign_typed_data_recover_signer() {
+    // The `Mail` example of EIP-712, signed by keccak256("cow").
+    let data: TypedData = serde_json::from_str(
+        r#"{
+            "types": {
+                "EIP712Domain": [
+                    { "name": "name", "type": "string" },
+                    { "name": "version", "type": "string" },
+                    { "name": "chainId", "type": "uint256" },
+                    { "name": "verifyingContract", "type": "address" }
+                ],
+                "Person": [
+                    { "name": "name", "type": "string" },
+                    { "name": "wallet", "type": "address" }
+                ],
+                "Mail": [
+                    { "name": "from", "type": "Person" },
+                    { "name": "to", "type": "Person" },
+                    { "name": "contents", "type": "string" }
+                ]
+            },
+            "primaryType": "Mail",
+            "domain": {
+                "name": "Ether Mail",
+                "version": "1",
+                "chainId": 1,
+                "verifyingContract": "0xCcCCccccCCCCcCCCCCCcCcCccCcCCCcCcccccccC"
+            },
+            "message": {
+                "from": {
+                    "name": "Cow",
+                    "wallet": "0xCD2a3d9F938E13CD947Ec05AbC7FE734Df8DD826"
+                },
+                "to": {
+                    "name": "Bob",
+                    "wallet": "0xbBbBBBBbbBBBbbbBbbBbbbbBBbBbbbbBbBbbBBbB"
+                },
+                "contents": "Hello, Bob!"
+            }
+        }"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        data.encode_type("Mail").unwrap(),
+        "Mail(Person from,Person to,string contents)Person(string name,address wallet)"
+    );
+    assert_eq!(
+        data.hash_struct("EIP712Domain", &data.domain).unwrap(),
+        H256::from_str("f2cee375fa42b42143804025fc449deafd50cc031ca257e0b194a650a912090f").unwrap()
+    );
+    let hash = data.sign_hash().unwrap();
+    assert_eq!(
+        hash,
+        H256::from_str("be609aee343fb3c4b28e1df9e632fca64fcfaede20f02e86244efddf30957bd2").unwrap()
+    );
+
+    let keystore = LocalKeyStore::new(&[ConfigKeystoreAccount {
+        privkey:  Hex::encode(Hasher::digest(b"cow")),
+        password: "axon".to_string(),
+    }])
+    .unwrap();
+    let signer = keystore.accounts()[0];
+    assert_eq!(
+        signer,
+        H160::from_str("cd2a3d9f938e13cd947ec05abc7fe734df8dd826").unwrap()
+    );
+
+    let rpc = PersonalRpcImpl::new(Arc::new(MockAPIAdapter::new()), Arc::new(keystore));
+    assert!(rpc
+        .unlock_account(signer, "axon".to_string(), Some(0))
+        .await
+        .unwrap());
+    let signature = rpc.sign_typed_data(signer, data).await.unwrap().as_bytes();
+    assert_eq!(signature.len(), 65);
+    assert!(signature[64] == 27 || signature[64] == 28);
+
+    let mut rsv = signature.to_vec();
+    rsv[64] -= 27;
+    let public = Public::from_slice(
+        &secp256k1_recover(hash.as_bytes(), &rsv)
+            .unwrap()
+            .serialize_uncompressed()[1..65],
+    );
+    assert_eq!(public_to_address(&public), signer);
+}
+
+#[tokio::test]
+async fn test_get_logs_empty_topics() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let topic = H256::repeat_byte(0x66);
+    adapter.push_block_with_topics(vec![], b"anonymous");
+    adapter.push_block_with_topics(vec![topic], b"one");
+    adapter.push_block_with_topics(vec![topic, topic], b"two");
+
+    let get_logs = |topics: serde_json::Value| {
+        let filter: Web3Filter = serde_json::from_value(serde_json::json!({
+            "fromBlock": "0x1",
+            "toBlock": "latest",
+            "topics": topics,
+        }))
+        .unwrap();
+        let rpc = &rpc;
+        async move {
+            rpc.get_logs(filter)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|log| log.data)
+                .collect::<Vec<_>>()
+        }
+    };
+
+    // Any log, including the one without topics.
+    let all = vec![
+        Hex::encode(b"anonymous"),
+        Hex::encode(b"one"),
+        Hex::encode(b"two"),
+    ];
+    assert_eq!(get_logs(serde_json::json!([])).await, all);
+
+    // The logs with at least one topic, whatever it is.
+    let with_topic = vec![Hex::encode(b"one"), Hex::encode(b"two")];
+    assert_eq!(get_logs(serde_json::json!([null])).await, with_topic);
+    assert_eq!(get_logs(serde_json::json!([[]])).await, with_topic);
+}
+
+#[tokio::test]
+async fn test_anonymous_event_logs() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let topic = H256::repeat_byte(0x67);
+    let id = rpc
+        .new_filter(ChangeWeb3Filter {
+            from_block: None,
+            to_block:   None,
+            block_hash: None,
+            address:    None,
+            topics:     None,
+            limit:      None,
+        })
+        .await
+        .unwrap();
+    let topic0_id = rpc
+        .new_filter(ChangeWeb3Filter {
+            from_block: None,
+            to_block:   None,
+            block_hash: None,
+            address:    None,
+            topics:     Some(vec![VariadicValue::Single(topic)]),
+            limit:      None,
+        })
+        .await
+        .unwrap();
+
+    adapter.push_block_with_topics(vec![], b"anonymous");
+    adapter.push_block_with_topics(vec![topic], b"named");
+
+    let get_logs = |filter: serde_json::Value| {
+        let filter: Web3Filter = serde_json::from_value(filter).unwrap();
+        let rpc = &rpc;
+        async move {
+            rpc.get_logs(filter)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|log| log.data)
+                .collect::<Vec<_>>()
+        }
+    };
+    let filter_changes = |id: U256| {
+        let rpc = &rpc;
+        async move {
+            match rpc.filter_changes(filter_index(id)).await.unwrap() {
+                FilterChanges::Logs(logs) => {
+                    logs.into_iter().map(|log| log.data).collect::<Vec<_>>()
+                }
+                changes => panic!("unexpected changes {:?}", changes),
+            }
+        }
+    };
+
+    let all = vec![Hex::encode(b"anonymous"), Hex::encode(b"named")];
+    assert_eq!(
+        get_logs(serde_json::json!({"fromBlock": "0x1", "toBlock": "latest"})).await,
+        all
+    );
+    assert_eq!(filter_changes(id).await, all);
+
+    // Constraining topic0 leaves out the log without topics.
+    assert_eq!(
+        get_logs(serde_json::json!({
+            "fromBlock": "0x1",
+            "toBlock": "latest",
+            "topics": [topic],
+        }))
+        .await,
+        vec![Hex::encode(b"named")]
+    );
+    assert_eq!(filter_changes(topic0_id).await, vec![Hex::encode(b"named")]);
+}
+
+#[tokio::test]
+async fn test_get_logs_topics_and_across_positions() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let (a, b, c, other) = (
+        H256::repeat_byte(0x0a),
+        H256::repeat_byte(0x0b),
+        H256::repeat_byte(0x0c),
+        H256::repeat_byte(0xff),
+    );
+    adapter.push_block_with_topics(vec![a, other], b"only a");
+    adapter.push_block_with_topics(vec![other, b], b"only b");
+    adapter.push_block_with_topics(vec![b, a], b"swapped");
+    adapter.push_block_with_topics(vec![a, b], b"a and b");
+    adapter.push_block_with_topics(vec![c, b, other], b"c and b");
+
+    let get_logs = |topics: serde_json::Value| {
+        let filter: Web3Filter = serde_json::from_value(serde_json::json!({
+            "fromBlock": "0x1",
+            "toBlock": "latest",
+            "topics": topics,
+        }))
+        .unwrap();
+        let rpc = &rpc;
+        async move {
+            rpc.get_logs(filter)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|log| log.data)
+                .collect::<Vec<_>>()
+        }
+    };
+
+    // Every constrained position has to match within the same log.
+    assert_eq!(get_logs(serde_json::json!([[a], [b]])).await, vec![
+        Hex::encode(b"a and b")
+    ]);
+    // The topics of one position are alternatives.
+    assert_eq!(get_logs(serde_json::json!([[a, c], [b]])).await, vec![
+        Hex::encode(b"a and b"),
+        Hex::encode(b"c and b"),
+    ]);
+}
+
+#[tokio::test]
+async fn test_get_logs_with_confirmations() {
+    let mut config = mock_config();
+    config.get_logs_confirmations = 2;
+    let (adapter, rpc) = mock_rpc(&config);
+    let topic = H256::repeat_byte(0x55);
+    let chain = (0..5)
+        .map(|_| adapter.push_block_with_log(topic, b"log"))
+        .collect::<Vec<_>>();
+
+    let mut filter = Web3Filter {
+        from_block:    Some(BlockId::Num(1)),
+        to_block:      Some(BlockId::Latest),
+        block_hash:    None,
+        address:       None,
+        topics:        Some(vec![VariadicValue::Single(topic)]),
+        limit:         None,
+        confirmations: None,
+        order:         None,
+    };
+    let logs = rpc.get_logs(filter.clone()).await.unwrap();
+    assert_eq!(logs.len(), 3);
+    assert_eq!(
+        logs.last().unwrap().block_hash,
+        Some(chain[2].header_hash())
+    );
+
+    // Only the recent blocks are queried.
+    filter.from_block = Some(BlockId::Num(4));
+    assert!(rpc.get_logs(filter.clone()).await.unwrap().is_empty());
+
+    // An explicit number is not held back by the confirmations.
+    filter.to_block = Some(BlockId::Num(5));
+    assert_eq!(rpc.get_logs(filter.clone()).await.unwrap().len(), 2);
+
+    // Neither is `pending`, which is followed by the pool logs.
+    filter.to_block = Some(BlockId::Pending);
+    let logs = rpc.get_logs(filter.clone()).await.unwrap();
+    assert_eq!(logs.len(), 2);
+    assert_eq!(
+        logs.last().unwrap().block_hash,
+        Some(chain[4].header_hash())
+    );
+    filter.to_block = Some(BlockId::Latest);
+
+    // The filter overrides the config.
+    filter.confirmations = Some(0);
+    assert_eq!(rpc.get_logs(filter).await.unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn test_get_logs_pending_only_when_asked() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let topic = H256::repeat_byte(0x77);
+    adapter.push_block_with_log(topic, b"mined");
+
+    // A pool transaction which emits a log once executed.
+    let pending_tx = mock_signed_tx(0);
+    adapter.insert_pool_tx(pending_tx.clone());
+    adapter.set_call_logs(vec![Log {
+        address: H160::repeat_byte(0x22),
+        topics:  vec![topic],
+        data:    b"pending".to_vec(),
+    }]);
+
+    let mut filter = Web3Filter {
+        from_block:    Some(BlockId::Num(1)),
+        to_block:      Some(BlockId::Latest),
+        block_hash:    None,
+        address:       None,
+        topics:        Some(vec![VariadicValue::Single(topic)]),
+        limit:         None,
+        confirmations: None,
+        order:         None,
+    };
+    let logs = rpc.get_logs(filter.clone()).await.unwrap();
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0].data, Hex::encode(b"mined"));
+
+    filter.to_block = Some(BlockId::Num(1));
+    assert_eq!(rpc.get_logs(filter.clone()).await.unwrap().len(), 1);
+
+    filter.to_block = Some(BlockId::Pending);
+    let logs = rpc.get_logs(filter.clone()).await.unwrap();
+    assert_eq!(logs.len(), 2);
+    assert_eq!(logs[1].data, Hex::encode(b"pending"));
+    assert_eq!(logs[1].transaction_hash, Some(pending_tx.transaction.hash));
+    assert_eq!(logs[1].block_hash, None);
+    assert_eq!(logs[1].block_number, Some(2u64.into()));
+
+    // The pending block is replayed once per snapshot.
+    let replays = adapter.replay_count();
+    assert_eq!(rpc.get_logs(filter.clone()).await.unwrap().len(), 2);
+    assert_eq!(adapter.replay_count(), replays);
+    assert_eq!(adapter.evm_call_count(), 0);
+
+    // The address of the filter applies to the pending logs too.
+    filter.address = Some(H160::repeat_byte(0x33));
+    let logs = rpc.get_logs(filter.clone()).await.unwrap();
+    assert!(logs.iter().all(|log| log.data != Hex::encode(b"pending")));
+    filter.address = Some(H160::repeat_byte(0x22));
+    let logs = rpc.get_logs(filter).await.unwrap();
+    assert_eq!(logs.last().unwrap().data, Hex::encode(b"pending"));
+}
+
+#[tokio::test]
+async fn test_pending_logs_take_trace_permit() {
+    let mut config = mock_config();
+    config.max_concurrent_traces = 1;
+    let (adapter, rpc) = mock_rpc(&config);
+    let rpc = Arc::new(rpc);
+    let hash = adapter.mine_tx(mock_signed_tx(0)).tx_hashes[0];
+    adapter.insert_pool_tx(mock_signed_tx(1));
+
+    let gate = Arc::new(Semaphore::new(0));
+    adapter.gate_traces(Arc::clone(&gate));
+    let running = {
+        let rpc = Arc::clone(&rpc);
+        let options = Some(TraceOptions {
+            tracer: Some("prestateTracer".to_string()),
+        });
+        tokio::spawn(async move { rpc.trace_transaction(hash, options).await })
+    };
+    while adapter.trace_count() == 0 {
+        tokio::task::yield_now().await;
+    }
+
+    let filter = Web3Filter {
+        from_block:    Some(BlockId::Latest),
+        to_block:      Some(BlockId::Pending),
+        block_hash:    None,
+        address:       None,
+        topics:        None,
+        limit:         None,
+        confirmations: None,
+        order:         None,
+    };
+    let err = rpc.get_logs(filter.clone()).await.unwrap_err();
+    assert!(err.to_string().contains("too many concurrent traces"));
+
+    gate.add_permits(1);
+    running.await.unwrap().unwrap();
+    rpc.get_logs(filter).await.unwrap();
+}
+
+#[tokio::test]
+async fn test_send_raw_transaction_rate_limit_per_sender() {
+    let mut config = mock_config();
+    config.max_txs_per_sender_per_sec = 3;
+    let (_adapter, rpc) = mock_rpc(&config);
+    let spammer = Hasher::digest(b"cow");
+    let other = Hasher::digest(b"dog");
+
+    for nonce in 0..3 {
+        rpc.send_raw_transaction(mock_raw_tx(&spammer, nonce))
+            .await
+            .unwrap();
+    }
+    let err = rpc
+        .send_raw_transaction(mock_raw_tx(&spammer, 3))
+        .await
+        .unwrap_err();
+    assert!(err
+        .to_string()
+        .contains("too many transactions from sender"));
+
+    rpc.send_raw_transaction(mock_raw_tx(&other, 0))
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_send_raw_transaction_sync() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let privkey = Hasher::digest(b"cow");
+
+    // Nothing is gossiped, so the wait times out.
+    let ret = rpc
+        .send_raw_transaction_sync(mock_raw_tx(&privkey, 0), Some(50))
+        .await
+        .unwrap();
+    assert!(!ret.propagated);
+    assert_eq!(
+        adapter.get_pending_txs(Context::new()).await.unwrap().len(),
+        1
+    );
+
+    let delay = Duration::from_millis(200);
+    adapter.gossip_txs(1, delay);
+    let start = Instant::now();
+    let ret = rpc
+        .send_raw_transaction_sync(mock_raw_tx(&privkey, 1), Some(5_000))
+        .await
+        .unwrap();
+    assert!(ret.propagated);
+    assert!(start.elapsed() >= delay);
+    assert!(start.elapsed() < Duration::from_secs(5));
+
+    // The broadcast is drowned out before the wait sees it.
+    adapter.flood_gossip(4096);
+    let start = Instant::now();
+    let ret = rpc
+        .send_raw_transaction_sync(mock_raw_tx(&privkey, 2), Some(5_000))
+        .await
+        .unwrap();
+    assert!(ret.propagated);
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test]
+async fn test_pending_balance_and_nonce_share_snapshot() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let sender = H160::repeat_byte(0x11);
+    let recipient = H160::repeat_byte(0x22);
+    adapter.set_account(sender, Account {
+        nonce:        U256::from(1u64),
+        balance:      U256::from(1_000_000u64),
+        storage_root: Hash::default(),
+        code_hash:    Hasher::digest(Bytes::new()),
+    });
+
+    let pool_tx = |nonce: u64| {
+        let mut stx = mock_signed_tx(nonce);
+        stx.transaction.unsigned.value = U256::from(100u64);
+        stx.transaction.unsigned.max_fee_per_gas = U256::from(2u64);
+        stx
+    };
+    adapter.insert_pool_tx(pool_tx(1));
+
+    let cost = U256::from(100u64 + 21000 * 2);
+    let nonce = rpc
+        .get_transaction_count(sender, Some(BlockId::Pending))
+        .await
+        .unwrap();
+    let balance = rpc
+        .get_balance(sender, Some(BlockId::Pending))
+        .await
+        .unwrap();
+    assert_eq!(nonce, U256::from(2u64));
+    assert_eq!(balance, U256::from(1_000_000u64) - cost);
+    assert_eq!(
+        rpc.get_balance(recipient, Some(BlockId::Pending))
+            .await
+            .unwrap(),
+        U256::from(100u64)
+    );
+    assert_eq!(
+        rpc.get_transaction_count(sender, Some(BlockId::Latest))
+            .await
+            .unwrap(),
+        U256::from(1u64)
+    );
+
+    // Both queries read a new snapshot once the pool changes, without
+    // waiting for the next block.
+    let second = pool_tx(2);
+    adapter.insert_pool_tx(second.clone());
+    assert_eq!(
+        rpc.get_transaction_count(sender, Some(BlockId::Pending))
+            .await
+            .unwrap(),
+        U256::from(3u64)
+    );
+    assert_eq!(
+        rpc.get_balance(sender, Some(BlockId::Pending))
+            .await
+            .unwrap(),
+        U256::from(1_000_000u64) - cost - cost
+    );
+
+    adapter.remove_pool_tx(&second.transaction.hash);
+    assert_eq!(
+        rpc.get_transaction_count(sender, Some(BlockId::Pending))
+            .await
+            .unwrap(),
+        nonce
+    );
+    assert_eq!(
+        rpc.get_balance(sender, Some(BlockId::Pending))
+            .await
+            .unwrap(),
+        balance
+    );
+}
+
+#[tokio::test]
+async fn test_latest_nonce_is_cached_within_a_block() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let sender = H160::repeat_byte(0x11);
+    let account = |nonce: u64| Account {
+        nonce:        U256::from(nonce),
+        balance:      U256::from(1_000_000u64),
+        storage_root: Hash::default(),
+        code_hash:    Hasher::digest(Bytes::new()),
+    };
+    adapter.set_account(sender, account(5));
+
+    let reads = adapter.account_reads();
+    for _ in 0..2 {
+        assert_eq!(
+            rpc.get_transaction_count(sender, Some(BlockId::Latest))
+                .await
+                .unwrap(),
+            U256::from(5u64)
+        );
+    }
+    assert_eq!(adapter.account_reads(), reads + 1);
+
+    // The pending tag always goes to the state.
+    rpc.get_transaction_count(sender, Some(BlockId::Pending))
+        .await
+        .unwrap();
+    assert_eq!(adapter.account_reads(), reads + 2);
+
+    // A new block drops the cached nonces.
+    adapter.set_account(sender, account(6));
+    adapter.push_block(vec![]);
+    assert_eq!(
+        rpc.get_transaction_count(sender, Some(BlockId::Latest))
+            .await
+            .unwrap(),
+        U256::from(6u64)
+    );
+    assert_eq!(adapter.account_reads(), reads + 3);
+}
+
+#[test]
+fn test_method_names() {
+    let mut config = mock_config();
+    config.enable_consensus_rpc = true;
+    config.enable_personal_rpc = true;
+    let rpc = rpc_module(Arc::new(MockAPIAdapter::new()), &config, true).unwrap();
+    assert!(rpc.method("personal_unlockAccount").is_some());
+
+    assert!(rpc.method("eth_submitWork").is_some());
+    assert!(rpc.method("eth_submitWork ").is_none());
+    assert!(rpc.method_names().all(|name| name.trim() == name));
+
+    let mut bad = jsonrpsee::RpcModule::new(());
+    bad.register_method("eth_submitWork ", |_, _| Ok(true))
+        .unwrap();
+    assert!(check_method_names(&bad).is_err());
+}
+
+#[tokio::test]
+async fn test_omitted_block_defaults_to_tag() {
+    let sender = H160::repeat_byte(0x11);
+    let get_balance = |tag: DefaultBlockTag| async move {
+        let mut config = mock_config();
+        config.default_block_tag = tag;
+        let adapter = Arc::new(MockAPIAdapter::new());
+        adapter.set_account(sender, Account {
+            nonce:        U256::zero(),
+            balance:      U256::from(1_000_000u64),
+            storage_root: Hash::default(),
+            code_hash:    Hasher::digest(Bytes::new()),
+        });
+        let mut pool_tx = mock_signed_tx(0);
+        pool_tx.transaction.unsigned.value = U256::from(100u64);
+        adapter.insert_pool_tx(pool_tx);
+
+        let rpc = rpc_module(adapter, &config, false).unwrap();
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "eth_getBalance",
+            "params": [sender],
+        });
+        let (resp, _) = rpc.raw_json_request(&request.to_string()).await.unwrap();
+        let resp: serde_json::Value = serde_json::from_str(&resp).unwrap();
+        serde_json::from_value::<U256>(resp["result"].clone()).unwrap()
+    };
+
+    assert_eq!(
+        get_balance(DefaultBlockTag::Latest).await,
+        U256::from(1_000_000u64)
+    );
+    assert_eq!(
+        get_balance(DefaultBlockTag::Pending).await,
+        U256::from(1_000_000u64 - 100)
+    );
+}
+
+#[tokio::test]
+async fn test_mining_methods_are_not_supported() {
+    let (_adapter, rpc) = mock_rpc(&mock_config());
+    let assert_not_supported = |err: jsonrpsee::core::Error, method: &str| match err {
+        jsonrpsee::core::Error::Call(jsonrpsee::types::error::CallError::Custom {
+            code,
+            message,
+            ..
+        }) => {
+            assert_eq!(code, -32004);
+            assert_eq!(
+                message,
+                format!(
+                    "{} is not supported, blocks are produced by consensus rather than mined",
+                    method
+                )
+            );
+        }
+        e => panic!("unexpected error {:?}", e),
+    };
+
+    assert_not_supported(rpc.coinbase().await.unwrap_err(), "eth_coinbase");
+    assert_not_supported(rpc.hashrate().await.unwrap_err(), "eth_hashrate");
+    assert_not_supported(rpc.get_work().await.unwrap_err(), "eth_getWork");
+    assert_not_supported(
+        rpc.submit_work(U256::one(), H256::default(), Hex::empty())
+            .await
+            .unwrap_err(),
+        "eth_submitWork",
+    );
+    assert_not_supported(
+        rpc.submit_hashrate(Hex::empty(), Hex::empty())
+            .await
+            .unwrap_err(),
+        "eth_submitHashrate",
+    );
+
+    // In compatibility mode they answer like a node that is not mining.
+    let config = ConfigApi {
+        mining_rpc_compat: true,
+        ..mock_config()
+    };
+    let (_adapter, rpc) = mock_rpc(&config);
+
+    assert_eq!(rpc.coinbase().await.unwrap(), H160::default());
+    assert_eq!(rpc.hashrate().await.unwrap(), U256::zero());
+    assert_not_supported(rpc.get_work().await.unwrap_err(), "eth_getWork");
+    assert!(!rpc
+        .submit_work(U256::one(), H256::default(), Hex::empty())
+        .await
+        .unwrap());
+    assert!(!rpc
+        .submit_hashrate(Hex::empty(), Hex::empty())
+        .await
+        .unwrap());
+}
+
+fn filter_index(id: U256) -> Index {
+    serde_json::from_value(serde_json::json!(format!("{:#x}", id))).unwrap()
+}
+
+#[tokio::test]
+async fn test_filter_changes_by_kind() {
+    let (_adapter, rpc) = mock_rpc(&mock_config());
+
+    let block_filter = rpc.new_block_filter().await.unwrap();
+    let pending_filter = rpc.new_pending_transaction_filter().await.unwrap();
+    let log_filter = rpc
+        .new_filter(ChangeWeb3Filter {
+            from_block: None,
+            to_block:   None,
+            block_hash: None,
+            address:    None,
+            topics:     None,
+            limit:      None,
+        })
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        rpc.filter_changes(filter_index(block_filter))
+            .await
+            .unwrap(),
+        FilterChanges::Hashes(_)
+    ));
+    assert!(matches!(
+        rpc.filter_changes(filter_index(pending_filter))
+            .await
+            .unwrap(),
+        FilterChanges::Hashes(_)
+    ));
+    assert!(matches!(
+        rpc.filter_changes(filter_index(log_filter)).await.unwrap(),
+        FilterChanges::Logs(_)
+    ));
+}
+
+#[tokio::test]
+async fn test_log_filter_buffer_overflow() {
+    let mut config = mock_config();
+    config.filter_max_buffered_logs = 2;
+    let (adapter, rpc) = mock_rpc(&config);
+    let topic = H256::repeat_byte(0x47);
+    let id = rpc
+        .new_filter(ChangeWeb3Filter {
+            from_block: None,
+            to_block:   None,
+            block_hash: None,
+            address:    None,
+            topics:     None,
+            limit:      None,
+        })
+        .await
+        .unwrap();
+
+    for i in 0..3 {
+        adapter.push_block_with_log(topic, &[i]);
+    }
+    let err = rpc.filter_changes(filter_index(id)).await.unwrap_err();
+    assert!(err.to_string().contains("filter buffer overflow"));
+    assert!(err.to_string().contains("3 logs in blocks 0 to 3 skipped"));
+
+    // The failed poll moves the filter to the head, so it recovers.
+    let poll = || async { rpc.filter_changes(filter_index(id)).await.unwrap() };
+    assert!(matches!(poll().await, FilterChanges::Logs(logs) if logs.is_empty()));
+    for i in 3..5 {
+        adapter.push_block_with_log(topic, &[i]);
+    }
+    match poll().await {
+        FilterChanges::Logs(logs) => assert_eq!(logs.len(), 2),
+        changes => panic!("unexpected changes {:?}", changes),
+    }
+}
+
+#[tokio::test]
+async fn test_log_filter_block_range_exceeded() {
+    let mut config = mock_config();
+    config.get_logs_max_block_range = 2;
+    let (adapter, rpc) = mock_rpc(&config);
+    let topic = H256::repeat_byte(0x48);
+    let id = rpc
+        .new_filter(ChangeWeb3Filter {
+            from_block: None,
+            to_block:   None,
+            block_hash: None,
+            address:    None,
+            topics:     None,
+            limit:      None,
+        })
+        .await
+        .unwrap();
+
+    for i in 0..3 {
+        adapter.push_block_with_log(topic, &[i]);
+    }
+    let err = rpc.filter_changes(filter_index(id)).await.unwrap_err();
+    assert!(err.to_string().contains("max block range 2"));
+
+    adapter.push_block_with_log(topic, &[3]);
+    match rpc.filter_changes(filter_index(id)).await.unwrap() {
+        FilterChanges::Logs(logs) => assert_eq!(logs.len(), 1),
+        changes => panic!("unexpected changes {:?}", changes),
+    }
+}
+
+#[tokio::test]
+async fn test_log_filter_idle_polls_are_empty() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let topic = H256::repeat_byte(0x49);
+    let id = rpc
+        .new_filter(ChangeWeb3Filter {
+            from_block: None,
+            to_block:   None,
+            block_hash: None,
+            address:    None,
+            topics:     None,
+            limit:      None,
+        })
+        .await
+        .unwrap();
+
+    adapter.push_block_with_log(topic, &[0]);
+    match rpc.filter_changes(filter_index(id)).await.unwrap() {
+        FilterChanges::Logs(logs) => assert_eq!(logs.len(), 1),
+        changes => panic!("unexpected changes {:?}", changes),
+    }
+    for _ in 0..2 {
+        match rpc.filter_changes(filter_index(id)).await.unwrap() {
+            FilterChanges::Logs(logs) => assert!(logs.is_empty()),
+            changes => panic!("unexpected changes {:?}", changes),
+        }
+    }
+}
+
+/// Keep the block filters of `rpc` away from the blocks imported by the other
+/// tests, the blocks are pushed with `BlockFilterHook::block_imported`.
+fn detach_block_filters(rpc: &JsonRpcImpl<MockAPIAdapter>) {
+    let (_, rx) = broadcast::channel(1);
+    rpc.block_filter_hook().start(|| rx);
+}
+
+#[tokio::test]
+async fn test_block_filter_delivers_each_block_once() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    detach_block_filters(&rpc);
+    let import = |block: &Block| rpc.block_filter_hook().block_imported(block);
+    let id = rpc.new_block_filter().await.unwrap();
+    let poll = || async {
+        match rpc.filter_changes(filter_index(id)).await.unwrap() {
+            FilterChanges::Hashes(hashes) => hashes,
+            changes => panic!("unexpected changes {:?}", changes),
+        }
+    };
+
+    let first = adapter.push_block(vec![]);
+    let second = adapter.push_block(vec![]);
+    import(&first);
+    import(&second);
+    assert_eq!(poll().await, vec![
+        first.header_hash(),
+        second.header_hash()
+    ]);
+
+    let third = adapter.push_block(vec![]);
+    import(&third);
+    assert_eq!(poll().await, vec![third.header_hash()]);
+    assert!(poll().await.is_empty());
+
+    // The block replacing a delivered one is delivered as a new block.
+    adapter.reorg(3, H256::repeat_byte(0x01));
+    let fork = adapter
+        .get_block_by_number(Context::new(), Some(3))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_ne!(fork.header_hash(), third.header_hash());
+    import(&fork);
+    assert_eq!(poll().await, vec![fork.header_hash()]);
+    assert!(poll().await.is_empty());
+
+    // A block the hook missed is read from storage once the next block does
+    // not extend the last reported one.
+    let missed = adapter.push_block(vec![]);
+    let fifth = adapter.push_block(vec![]);
+    import(&fifth);
+    assert_eq!(poll().await, vec![
+        missed.header_hash(),
+        fifth.header_hash()
+    ]);
+    assert!(poll().await.is_empty());
+}
+
+#[tokio::test]
+async fn test_block_import_pushes_to_filters_and_new_heads() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let id = rpc.new_block_filter().await.unwrap();
+    let heads = Arc::new(Mutex::new(Vec::new()));
+    let pushed = Arc::clone(&heads);
+    let forward = tokio::spawn(forward_new_heads(
+        BLOCK_IMPORT_NOTIFIER.subscribe(),
+        16,
+        SubscriptionLagPolicy::Drop,
+        move |head| {
+            pushed.lock().push(head);
+            async { true }
+        },
+    ));
+
+    let block = adapter.push_block_with_extra(vec![], Bytes::from_static(b"import"));
+    let reads = adapter.block_reads();
+    notify_block_import(block.clone());
+
+    // Neither the import nor the polls read the block, an empty poll just
+    // finds nothing pushed yet.
+    let hashes = loop {
+        match rpc.filter_changes(filter_index(id)).await.unwrap() {
+            FilterChanges::Hashes(hashes) if hashes.is_empty() => tokio::task::yield_now().await,
+            FilterChanges::Hashes(hashes) => break hashes,
+            changes => panic!("unexpected changes {:?}", changes),
+        }
+    };
+    assert_eq!(hashes, vec![block.header_hash()]);
+    assert_eq!(adapter.block_reads(), reads);
+
+    while !heads
+        .lock()
+        .iter()
+        .any(|head| head.hash == block.header_hash())
+    {
+        tokio::task::yield_now().await;
+    }
+    forward.abort();
+}
+
+#[tokio::test]
+async fn test_pending_transaction_filter_delivers_each_hash_once() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let privkey = Hasher::digest(b"cow");
+    let stale = rpc
+        .send_raw_transaction(mock_raw_tx(&privkey, 0))
+        .await
+        .unwrap();
+
+    let id = rpc.new_pending_transaction_filter().await.unwrap();
+    let poll = || async {
+        match rpc.filter_changes(filter_index(id)).await.unwrap() {
+            FilterChanges::Hashes(hashes) => hashes,
+            changes => panic!("unexpected changes {:?}", changes),
+        }
+    };
+
+    let first = rpc
+        .send_raw_transaction(mock_raw_tx(&privkey, 1))
+        .await
+        .unwrap();
+    assert_eq!(poll().await, vec![first]);
+
+    // A transaction mined before the next poll is still delivered.
+    let second = rpc
+        .send_raw_transaction(mock_raw_tx(&privkey, 2))
+        .await
+        .unwrap();
+    adapter.mine_tx(adapter.pool_tx(&second).unwrap());
+    let hashes = poll().await;
+    assert_eq!(hashes, vec![second]);
+    assert!(!hashes.contains(&stale));
+    assert!(poll().await.is_empty());
+}
+
+#[tokio::test]
+async fn test_oldest_available_block() {
+    let (_adapter, rpc) = mock_rpc(&mock_config());
+    assert_eq!(rpc.oldest_available_block().await.unwrap(), U256::zero());
+
+    let stored_from = |first: BlockNumber| {
+        move |number: BlockNumber| async move { ProtocolResult::Ok(number >= first) }
+    };
+    assert_eq!(lowest_stored_block(9, stored_from(0)).await.unwrap(), 0);
+    for first in 1..=9 {
+        assert_eq!(
+            lowest_stored_block(9, stored_from(first)).await.unwrap(),
+            first
+        );
+    }
+}
+
+#[test]
+fn test_ws_conn_limit_per_ip() {
+    let limiter = Arc::new(IpConnLimiter::new(3));
+    let greedy: std::net::IpAddr = "10.0.0.1".parse().unwrap();
+    let other: std::net::IpAddr = "10.0.0.2".parse().unwrap();
+
+    let mut conns = (0..3)
+        .map(|_| limiter.try_acquire(greedy).unwrap())
+        .collect::<Vec<_>>();
+    assert!(limiter.try_acquire(greedy).is_none());
+    assert!(limiter.try_acquire(other).is_some());
+
+    // A closed connection frees its slot.
+    conns.pop();
+    assert!(limiter.try_acquire(greedy).is_some());
+}
+
+#[tokio::test]
+async fn test_ws_conn_limit_forward() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    // An upstream echoing whatever it receives.
+    let upstream = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let upstream_addr = upstream.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut conn, _)) = upstream.accept().await {
+            tokio::spawn(async move {
+                let (mut r, mut w) = conn.split();
+                let _ = tokio::io::copy(&mut r, &mut w).await;
+            });
+        }
+    });
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let forward = spawn_forward(
+        listener,
+        upstream_addr,
+        "upstream".to_string(),
+        Arc::new(IpConnLimiter::new(1)),
+        0,
+    );
+
+    let head = "GET / HTTP/1.1\r\nhost: 127.0.0.1\r\nUpgrade: websocket\r\n\r\n";
+    let expect = replace_host(head, "upstream") + "ping";
+    assert!(expect.starts_with("GET / HTTP/1.1\r\nHost: upstream\r\nUpgrade"));
+    let exchange = |mut conn: TcpStream| {
+        let expect = expect.clone();
+        async move {
+            conn.write_all(head.as_bytes()).await.ok()?;
+            conn.write_all(b"ping").await.ok()?;
+            let mut buf = vec![0u8; expect.len()];
+            conn.read_exact(&mut buf).await.ok()?;
+            assert_eq!(String::from_utf8(buf).unwrap(), expect);
+            Some(conn)
+        }
+    };
+
+    // The loopback is limited like any other IP, a second connection is
+    // closed.
+    let first = exchange(TcpStream::connect(addr).await.unwrap())
+        .await
+        .unwrap();
+    assert!(exchange(TcpStream::connect(addr).await.unwrap())
+        .await
+        .is_none());
+
+    // The slot is freed once the first connection is closed.
+    drop(first);
+    let mut reconnected = false;
+    for _ in 0..50 {
+        if exchange(TcpStream::connect(addr).await.unwrap())
+            .await
+            .is_some()
+        {
+            reconnected = true;
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert!(reconnected);
+
+    // Dropping the task stops accepting connections.
+    drop(forward);
+    let mut stopped = false;
+    for _ in 0..50 {
+        if TcpStream::connect(addr).await.is_err() {
+            stopped = true;
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+    }
+    assert!(stopped);
+}
+
+#[tokio::test]
+async fn test_ws_forward_closes_client_not_reading() {
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::{TcpListener, TcpStream};
+
+    // An upstream pushing subscription items as fast as they are taken, it
+    // reports how much it wrote once its connection is closed.
+    let upstream = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let upstream_addr = upstream.local_addr().unwrap();
+    let (closed_tx, closed_rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (mut conn, _) = upstream.accept().await.unwrap();
+        let item = [0u8; 16 * 1024];
+        let mut written = 0;
+        while conn.write_all(&item).await.is_ok() {
+            written += item.len();
+        }
+        let _ = closed_tx.send(written);
+    });
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let _forward = spawn_forward(
+        listener,
+        upstream_addr,
+        "upstream".to_string(),
+        Arc::new(IpConnLimiter::new(0)),
+        64 * 1024,
+    );
+
+    // The client subscribes and then never reads.
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client
+        .write_all(b"GET / HTTP/1.1\r\nhost: 127.0.0.1\r\nUpgrade: websocket\r\n\r\n")
+        .await
+        .unwrap();
+
+    let written = tokio::time::timeout(std::time::Duration::from_secs(30), closed_rx)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(written > 64 * 1024);
+    drop(client);
+}
+
+#[test]
+fn test_replace_ws_handshake_host() {
+    let head = "GET / HTTP/1.1\r\nHOST: evil\r\nHost: evil\r\nUpgrade: websocket\r\n\r\n";
+    assert_eq!(
+        replace_host(head, "upstream"),
+        "GET / HTTP/1.1\r\nHost: upstream\r\nUpgrade: websocket\r\n\r\n"
+    );
+    assert_eq!(
+        replace_host("GET / HTTP/1.1\r\n\r\n", "upstream"),
+        "GET / HTTP/1.1\r\nHost: upstream\r\n\r\n"
+    );
+}
+
+#[tokio::test]
+async fn test_get_logs_over_block_range_suggests_split() {
+    let mut config = mock_config();
+    config.get_logs_max_block_range = 2;
+    let (adapter, rpc) = mock_rpc(&config);
+    for _ in 0..5 {
+        adapter.push_block(vec![]);
+    }
+
+    let filter = Web3Filter {
+        from_block:    Some(BlockId::Num(1)),
+        to_block:      Some(BlockId::Num(5)),
+        block_hash:    None,
+        address:       None,
+        topics:        Some(vec![]),
+        limit:         None,
+        confirmations: None,
+        order:         None,
+    };
+    let data = match rpc.get_logs(filter.clone()).await.unwrap_err() {
+        jsonrpsee::core::Error::Call(jsonrpsee::types::error::CallError::Custom {
+            code,
+            data,
+            ..
+        }) => {
+            assert_eq!(code, -32005);
+            serde_json::from_str::<serde_json::Value>(data.unwrap().get()).unwrap()
+        }
+        e => panic!("unexpected error {:?}", e),
+    };
+    assert_eq!(
+        data,
+        serde_json::json!([["0x1", "0x2"], ["0x3", "0x4"], ["0x5", "0x5"]])
+    );
+
+    // A limited reverse scan is held to the same range.
+    let mut desc = filter.clone();
+    desc.limit = Some(1);
+    desc.order = Some(LogOrder::Desc);
+    assert!(rpc.get_logs(desc).await.is_err());
+
+    let mut filter = filter;
+    filter.to_block = Some(BlockId::Num(2));
+    assert!(rpc.get_logs(filter).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_chain_id_before_genesis() {
+    // No genesis block is stored yet.
+    let adapter = Arc::new(MockAPIAdapter::default());
+    let rpc = JsonRpcImpl::new(Arc::clone(&adapter), &mock_config());
+    assert!(rpc.chain_id().await.is_err());
+
+    let config = ConfigApi {
+        chain_id: Some(5),
+        ..mock_config()
+    };
+    let rpc = JsonRpcImpl::new(adapter, &config);
+    assert_eq!(rpc.chain_id().await.unwrap(), U256::from(5u64));
+    assert_eq!(rpc.net_version().await.unwrap(), U256::from(5u64));
+}
+
+#[tokio::test]
+async fn test_block_extra_data_hex() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let short = adapter.push_block_with_extra(vec![], Bytes::from(vec![0u8, 0, 0xab]));
+    let long = adapter.push_block_with_extra(vec![], Bytes::from(vec![0u8; 40]));
+
+    let block = rpc
+        .get_block_by_number(BlockId::Num(short.header.number), false)
+        .await
+        .unwrap()
+        .unwrap();
+    let json = serde_json::to_value(&block).unwrap();
+    assert_eq!(json["extraData"], "0x0000ab");
+    assert_eq!(
+        serde_json::from_value::<Web3Block>(json)
+            .unwrap()
+            .extra_data,
+        block.extra_data
+    );
+
+    // Extra data longer than Ethereum's 32 bytes is not cut.
+    let block = rpc
+        .get_block_by_number(BlockId::Num(long.header.number), false)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(block.extra_data.as_bytes(), long.header.extra_data);
+}
+
+#[tokio::test]
+async fn test_total_difficulty_modes() {
+    let total_difficulty = |mode: TotalDifficultyMode| async move {
+        let config = ConfigApi {
+            total_difficulty: mode,
+            ..mock_config()
+        };
+        let (adapter, rpc) = mock_rpc(&config);
+        adapter.push_block(vec![]);
+        adapter.push_block(vec![]);
+        adapter.set_difficulty(1, U256::from(5u64));
+
+        let block = rpc
+            .get_block_by_number(BlockId::Num(2), false)
+            .await
+            .unwrap()
+            .unwrap();
+        serde_json::to_value(&block).unwrap()["totalDifficulty"].clone()
+    };
+
+    assert_eq!(
+        total_difficulty(TotalDifficultyMode::None).await,
+        serde_json::Value::Null
+    );
+    assert_eq!(
+        total_difficulty(TotalDifficultyMode::Constant(U256::from(0x2au64))).await,
+        serde_json::json!("0x2a")
+    );
+    assert_eq!(
+        total_difficulty(TotalDifficultyMode::Sum).await,
+        serde_json::json!("0x6")
+    );
+}
+
+#[test]
+fn test_request_log_redacts_raw_transaction() {
+    let elapsed = Duration::from_micros(1500);
+    let logger = RequestLogger::new(RequestLogLevel::All);
+    let raw_tx = serde_json::json!(["0xf86c0185e8d4a5100082520894"]);
+
+    // The size of the raw transaction is logged, never its bytes.
+    assert_eq!(
+        logger.entry("eth_sendRawTransaction", Some(&raw_tx), true, elapsed),
+        Some(
+            "[jsonrpc] method=eth_sendRawTransaction params_size=32 params=<redacted> status=ok \
+             duration=1500us"
+                .to_string()
+        )
+    );
+    let password = serde_json::json!(["0x1", "secret", 10]);
+    let entry = logger
+        .entry("personal_unlockAccount", Some(&password), true, elapsed)
+        .unwrap();
+    assert!(!entry.contains("secret"));
+
+    // The others are logged, cut to a bounded length.
+    let block = serde_json::json!(["0x1", false]);
+    assert_eq!(
+        logger.entry("eth_getBlockByNumber", Some(&block), true, elapsed),
+        Some(
+            "[jsonrpc] method=eth_getBlockByNumber params_size=13 params=[\"0x1\",false] \
+             status=ok duration=1500us"
+                .to_string()
+        )
+    );
+    let long = serde_json::json!(["0".repeat(1000)]);
+    let entry = logger
+        .entry("eth_getCode", Some(&long), true, elapsed)
+        .unwrap();
+    assert!(entry.contains("params_size=1004"));
+    assert!(entry.len() < 400);
+
+    let logger = RequestLogger::new(RequestLogLevel::Failed);
+    assert_eq!(
+        logger.entry("eth_sendRawTransaction", Some(&raw_tx), true, elapsed),
+        None
+    );
+    assert!(logger
+        .entry("eth_sendRawTransaction", Some(&raw_tx), false, elapsed)
+        .unwrap()
+        .contains("status=error"));
+
+    let logger = RequestLogger::new(RequestLogLevel::Off);
+    assert_eq!(
+        logger.entry("eth_sendRawTransaction", Some(&raw_tx), false, elapsed),
+        None
+    );
+}
+
+#[tokio::test]
+async fn test_logged_methods_forward_calls() {
+    let rpc = rpc_module(Arc::new(MockAPIAdapter::new()), &mock_config(), false).unwrap();
+    let rpc = log_requests(rpc, RequestLogLevel::All).unwrap();
+    let call = |method: &str, params: serde_json::Value| {
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": method,
+            "params": params,
+        });
+        let rpc = &rpc;
+        async move {
+            let (resp, _) = rpc.raw_json_request(&request.to_string()).await.unwrap();
+            serde_json::from_str::<serde_json::Value>(&resp).unwrap()
+        }
+    };
+
+    let resp = call("eth_blockNumber", serde_json::json!([])).await;
+    assert_eq!(resp["result"], serde_json::json!("0x0"));
+
+    // The errors keep the code of the original method.
+    let resp = call("eth_sendRawTransaction", serde_json::json!(["0x01"])).await;
+    assert!(resp["result"].is_null());
+    let direct = rpc_module(Arc::new(MockAPIAdapter::new()), &mock_config(), false)
+        .unwrap()
+        .raw_json_request(
+            &serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "eth_sendRawTransaction",
+                "params": ["0x01"],
+            })
+            .to_string(),
+        )
+        .await
+        .unwrap()
+        .0;
+    let direct = serde_json::from_str::<serde_json::Value>(&direct).unwrap();
+    assert_eq!(resp["error"], direct["error"]);
+}
+
+#[tokio::test]
+async fn test_get_proof_normalizes_storage_keys() {
+    let (_adapter, rpc) = mock_rpc(&mock_config());
+    let address = H160::from_low_u64_be(1);
+    let keys = serde_json::from_value::<Vec<Web3StorageKey>>(serde_json::json!([
+        "0x1",
+        "0x0001",
+        "0x0000000000000000000000000000000000000000000000000000000000000001"
+    ]))
+    .unwrap();
+
+    let proof = rpc
+        .get_proof(address, keys, Some(BlockId::Latest))
+        .await
+        .unwrap();
+    assert_eq!(proof.storage_proof.len(), 3);
+    for slot in proof.storage_proof.iter() {
+        assert_eq!(slot.key, H256::from_low_u64_be(1));
+        assert_eq!(slot.proof, proof.storage_proof[0].proof);
+    }
+
+    // A key longer than a word is not a slot.
+    assert!(
+        serde_json::from_value::<Web3StorageKey>(serde_json::json!(format!(
+            "0x01{}",
+            "00".repeat(32)
+        )))
+        .is_err()
+    );
+    assert!(serde_json::from_value::<Web3StorageKey>(serde_json::json!("0xzz")).is_err());
+}
+
+#[tokio::test]
+async fn test_verify_block_proof() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let block = adapter.push_block(vec![]);
+    let next = adapter.push_block(vec![]);
+
+    let keys = [
+        (
+            "37aa0f893d05914a4def0460c0a984d3611546cfb26924d7a7ca6e0db9950a2d",
+            "ac85bbb40347b6e06ac2dc2da1f75eece029cdc0ed2d456c457d27e288bfbfbcd4c5c19716e9b250134a0e76ce50fa22",
+        ),
+        (
+            "383fcff8683b8115e31613949be24254b4204ffbe43c227408a76334a2e3fb32",
+            "91ed9f3c51c580e56948b1bda9d00c2159665f8a6e284191ab816ee64ef2487d78453a547a0f14efbf842bba5b5a3b4f",
+        ),
+        (
+            "51ce21643b911347c5d5c85c323d9d5421810dc89f46b688720b2715f5e8e936",
+            "92e5d0856fb20ea9cb5ab5da2d3331c38d32cc96507f6ad902fa3da9400096a485fb4e09834bc93de55db224f26c229c",
+        ),
+        (
+            "69ff51f4c22f30615f68b88efa740f8f1b9169e88842b83d189748d06f1a948e",
+            "a694f4e48a5a173b61731998f8f1204342dc5c8eb1e32cdae37415c20d11ae035ddac4a39f105e9c2d4d3691024d385d",
+        ),
+    ];
+    let metadata = |start, end, validators: &[(&str, &str)]| Metadata {
+        version: MetadataVersion::new(start, end),
+        common_ref: Hex::encode("muta"),
+        verifier_list: validators
+            .iter()
+            .enumerate()
+            .map(|(i, (_, bls_pub_key))| ValidatorExtend {
+                bls_pub_key:    Hex::from_string(format!("0x{}", bls_pub_key)).unwrap(),
+                pub_key:        Hex::encode([i as u8 + 1; 33]),
+                address:        H160::from_low_u64_be(i as u64 + 1),
+                propose_weight: 1,
+                vote_weight:    1,
+            })
+            .collect(),
+        ..Default::default()
+    };
+    // The four validators are replaced by the first one from the next block.
+    adapter.set_metadata(metadata(0, next.header.number, &keys));
+    adapter.set_metadata(metadata(next.header.number, 100, &keys[..1]));
+
+    let sign = |block: &Block| {
+        let mut proof = Proof {
+            number:     block.header.number,
+            round:      0,
+            block_hash: Hasher::digest(Proposal::from(block.clone()).encode().unwrap()),
+            signature:  Bytes::new(),
+            // All the four validators.
+            bitmap:     Bytes::from(vec![0b1111_0000]),
+        };
+        let vote_hash = HashValue::try_from(proof_vote_hash(&proof).as_bytes()).unwrap();
+        let signatures = keys
+            .iter()
+            .map(|(priv_key, bls_pub_key)| {
+                let priv_key =
+                    BlsPrivateKey::try_from(hex_decode(priv_key).unwrap().as_ref()).unwrap();
+                let bls_pub_key =
+                    BlsPublicKey::try_from(hex_decode(bls_pub_key).unwrap().as_ref()).unwrap();
+                (priv_key.sign_message(&vote_hash), bls_pub_key)
+            })
+            .collect::<Vec<_>>();
+        proof.signature = BlsSignature::combine(signatures).unwrap().to_bytes();
+        proof
+    };
+
+    // The block is verified with the validators at its height, not the
+    // current ones.
+    let proof = sign(&block);
+    adapter.set_block_proof(block.header.number, proof.clone());
+    assert!(rpc.verify_block_proof(block.header_hash()).await.unwrap());
+
+    // The old validators do not prove a block after they are replaced.
+    adapter.set_block_proof(next.header.number, sign(&next));
+    assert!(!rpc.verify_block_proof(next.header_hash()).await.unwrap());
+
+    let mut tampered = proof.clone();
+    let mut signature = tampered.signature.to_vec();
+    signature[10] ^= 1;
+    tampered.signature = Bytes::from(signature);
+    adapter.set_block_proof(block.header.number, tampered);
+    assert!(!rpc.verify_block_proof(block.header_hash()).await.unwrap());
+
+    // One of the four validators is not a quorum.
+    let mut minority = proof;
+    minority.bitmap = Bytes::from(vec![0b1000_0000]);
+    adapter.set_block_proof(block.header.number, minority);
+    assert!(!rpc.verify_block_proof(block.header_hash()).await.unwrap());
+
+    // The latest block is not proven yet.
+    let latest = adapter.push_block(vec![]);
+    assert!(!rpc.verify_block_proof(latest.header_hash()).await.unwrap());
+}
+
+#[tokio::test]
+async fn test_get_logs_by_block_hash_only() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let token = H160::repeat_byte(0xaa);
+    let exchange = H160::repeat_byte(0xbb);
+    let log = |address: H160, topics: Vec<H256>, data: &[u8]| Log {
+        address,
+        topics,
+        data: data.to_vec(),
+    };
+    let block = adapter.push_block_with_tx_logs(vec![
+        vec![
+            log(token, vec![H256::repeat_byte(1)], b"transfer"),
+            log(exchange, vec![H256::repeat_byte(2)], b"swap"),
+        ],
+        // A log without topics matches a filter without topics.
+        vec![log(token, vec![], b"anonymous")],
+    ]);
+    adapter.push_block_with_log(H256::repeat_byte(1), b"other block");
+
+    let logs = rpc
+        .get_logs(Web3Filter {
+            from_block:    None,
+            to_block:      None,
+            block_hash:    Some(block.header_hash()),
+            address:       None,
+            topics:        None,
+            limit:         None,
+            confirmations: None,
+            order:         None,
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(logs.iter().map(|l| l.address).collect::<Vec<_>>(), vec![
+        token, exchange, token
+    ]);
+    assert_eq!(
+        logs.iter().map(|l| l.data.clone()).collect::<Vec<_>>(),
+        vec![
+            Hex::encode(b"transfer"),
+            Hex::encode(b"swap"),
+            Hex::encode(b"anonymous")
+        ]
+    );
+    assert_eq!(
+        logs.iter()
+            .map(|l| (l.transaction_index.unwrap(), l.log_index.unwrap()))
+            .collect::<Vec<_>>(),
+        vec![
+            (U256::zero(), U256::zero()),
+            (U256::zero(), U256::one()),
+            (U256::one(), U256::from(2u64))
+        ]
+    );
+    assert!(logs
+        .iter()
+        .all(|l| l.block_hash == Some(block.header_hash())));
+}
+
+#[tokio::test]
+async fn test_simulate_access_set_takes_trace_permit() {
+    let mut config = mock_config();
+    config.max_concurrent_traces = 1;
+    let (adapter, rpc) = mock_rpc(&config);
+    let rpc = Arc::new(rpc);
+    let hash = adapter.mine_tx(mock_signed_tx(0)).tx_hashes[0];
+    let mut req = mock_call_request("0x");
+    req.from = Some(H160::repeat_byte(0x22));
+
+    let gate = Arc::new(Semaphore::new(0));
+    adapter.gate_traces(Arc::clone(&gate));
+    let running = {
+        let rpc = Arc::clone(&rpc);
+        let options = Some(TraceOptions {
+            tracer: Some("prestateTracer".to_string()),
+        });
+        tokio::spawn(async move { rpc.trace_transaction(hash, options).await })
+    };
+    while adapter.trace_count() == 0 {
+        tokio::task::yield_now().await;
+    }
+
+    let err = rpc
+        .simulate_access_set(req.clone(), Some(BlockId::Latest))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("too many concurrent traces"));
+
+    gate.add_permits(1);
+    running.await.unwrap().unwrap();
+    let set = rpc
+        .simulate_access_set(req, Some(BlockId::Latest))
+        .await
+        .unwrap();
+    assert_eq!(
+        serde_json::to_value(&set).unwrap(),
+        serde_json::json!({
+            "readsAccounts": [H160::repeat_byte(0x22)],
+            "writesAccounts": [H160::repeat_byte(0x11)],
+            "readsSlots": {},
+            "writesSlots": {},
+        })
+    );
+}
+
+#[tokio::test]
+async fn test_call_return_data_too_large() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    // The mock call returns its input.
+    let oversized = mock_call_request(&Hex::encode(vec![0xab; 4097]).as_string());
+    let err = rpc
+        .call(oversized.clone(), Some(BlockId::Latest))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("return data too large"));
+    assert!(rpc
+        .call_with_logs(oversized.clone(), Some(BlockId::Latest))
+        .await
+        .is_err());
+
+    let fits = mock_call_request(&Hex::encode(vec![0xab; 4096]).as_string());
+    assert!(rpc.call(fits, Some(BlockId::Latest)).await.is_ok());
+
+    // The revert data is limited before it is decoded into the message.
+    adapter.set_revert_calls(true);
+    let err = rpc
+        .call(oversized, Some(BlockId::Latest))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("return data too large"));
+    adapter.set_revert_calls(false);
+
+    let hash = adapter.mine_tx(mock_signed_tx(0)).tx_hashes[0];
+    let options = || {
+        Some(TraceOptions {
+            tracer: Some("prestateTracer".to_string()),
+        })
+    };
+    let mut prestate = BTreeMap::new();
+    prestate.insert(H160::repeat_byte(0x11), PrestateAccount {
+        balance: U256::zero(),
+        nonce:   U256::zero(),
+        code:    vec![0x60; 4096],
+        storage: BTreeMap::new(),
+    });
+    adapter.set_prestate(prestate.clone());
+    assert!(rpc.trace_transaction(hash, options()).await.is_ok());
+
+    prestate
+        .get_mut(&H160::repeat_byte(0x11))
+        .unwrap()
+        .storage
+        .insert(H256::zero(), H256::zero());
+    adapter.set_prestate(prestate);
+    let err = rpc.trace_transaction(hash, options()).await.unwrap_err();
+    assert!(err.to_string().contains("trace too large"));
+
+    let config = ConfigApi {
+        max_call_return_size: 0,
+        ..mock_config()
+    };
+    let (_adapter, rpc) = mock_rpc(&config);
+    let unlimited = mock_call_request(&Hex::encode(vec![0xab; 4097]).as_string());
+    assert!(rpc.call(unlimited, Some(BlockId::Latest)).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_logs_subscription_rejects_invalid_filter() {
+    let (_adapter, rpc) = mock_rpc(&mock_config());
+    let change_filter =
+        |value: serde_json::Value| -> ChangeWeb3Filter { serde_json::from_value(value).unwrap() };
+    // Deserializing rejects these already, built by hand to reach the checks.
+    let subscribe_err = |filter: ChangeWeb3Filter| {
+        logs_filter(Some(SubscriptionParams::Logs(filter)))
+            .unwrap_err()
+            .to_string()
+    };
+
+    let reversed = ChangeWeb3Filter {
+        from_block: Some(Web3BlockNumber::Num(16)),
+        to_block: Some(Web3BlockNumber::Num(2)),
+        ..Default::default()
+    };
+    assert!(subscribe_err(reversed.clone()).contains("fromBlock 16 is after toBlock 2"));
+    let err = rpc.new_filter(reversed).await.unwrap_err().to_string();
+    assert!(err.contains("invalid block range"));
+
+    let hash_and_range = ChangeWeb3Filter {
+        block_hash: Some(H256::repeat_byte(0x01)),
+        from_block: Some(Web3BlockNumber::Num(1)),
+        ..Default::default()
+    };
+    assert!(subscribe_err(hash_and_range).contains("blockHash can not be combined"));
+
+    let by_hash = change_filter(serde_json::json!({"blockHash": H256::repeat_byte(0x01)}));
+    assert!(subscribe_err(by_hash).contains("blocks can not be given by hash"));
+
+    let topic = H256::repeat_byte(0x02);
+    let too_many_topics = ChangeWeb3Filter {
+        topics: Some(vec![VariadicValue::Single(topic); 5]),
+        ..Default::default()
+    };
+    assert!(subscribe_err(too_many_topics).contains("too many topics, 5 positions"));
+
+    let filter = logs_filter(Some(SubscriptionParams::Logs(change_filter(
+        serde_json::json!({"fromBlock": "0x1", "toBlock": "0x2", "topics": [topic]}),
+    ))))
+    .unwrap();
+    assert_eq!(filter.from_block, BlockId::Num(1));
+    assert_eq!(filter.topics, vec![Some(vec![topic])]);
+    assert_eq!(logs_filter(None).unwrap(), Default::default());
+}
+
+#[tokio::test]
+async fn test_logs_subscription_pushes_matching_logs() {
+    let adapter = Arc::new(MockAPIAdapter::new());
+    let wanted = H256::repeat_byte(0x0a);
+    let other = H256::repeat_byte(0x0b);
+    let (tx, rx) = broadcast::channel(16);
+    let first = adapter.push_block_with_log(wanted, &[1]);
+    tx.send(first.clone()).unwrap();
+    tx.send(adapter.push_block_with_log(other, &[2])).unwrap();
+    tx.send(adapter.push_block_with_log(wanted, &[3])).unwrap();
+    tx.send(adapter.push_block_with_log(wanted, &[4])).unwrap();
+    drop(tx);
+
+    // The blocks after `toBlock` are left out.
+    let filter = logs_filter(Some(SubscriptionParams::Logs(ChangeWeb3Filter {
+        from_block: Some(Web3BlockNumber::Num(first.header.number)),
+        to_block: Some(Web3BlockNumber::Num(first.header.number + 2)),
+        topics: Some(vec![VariadicValue::Single(wanted)]),
+        ..Default::default()
+    })))
+    .unwrap();
+    let logs = Arc::new(Mutex::new(Vec::new()));
+    let pushed = Arc::clone(&logs);
+    forward_logs(
+        rx,
+        Arc::clone(&adapter),
+        filter,
+        16,
+        SubscriptionLagPolicy::Drop,
+        move |log| {
+            pushed.lock().push(log);
+            async { true }
+        },
+    )
+    .await;
+
+    let logs = logs.lock();
+    assert_eq!(logs.len(), 2);
+    assert_eq!(logs[0].block_hash, Some(first.header_hash()));
+    assert_eq!(logs[0].data, Hex::encode([1]));
+    assert_eq!(logs[1].data, Hex::encode([3]));
+    assert!(logs.iter().all(|log| log.topics == vec![wanted]));
+}
+
+#[tokio::test]
+async fn test_block_seal_fields_use_own_proof() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let block = adapter.push_block(vec![]);
+    let head = adapter.push_block(vec![]);
+
+    adapter.set_block_proof(block.header.number, Proof {
+        number:     block.header.number,
+        round:      3,
+        block_hash: block.header_hash(),
+        signature:  Bytes::from(vec![1u8; 96]),
+        bitmap:     Bytes::from(vec![0b1111_0000]),
+    });
+    let ret = rpc
+        .get_block_by_number(BlockId::Num(block.header.number), false)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        ret.seal_fields[0],
+        Bytes::copy_from_slice(&3u64.to_be_bytes())
+    );
+    assert_eq!(ret.seal_fields[2], Bytes::from(vec![0b1111_0000]));
+
+    // The head has no proof yet, the one in its header is of its parent.
+    let ret = rpc
+        .get_block_by_hash(head.header_hash(), false)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(ret.seal_fields.is_empty());
+}
+
+#[tokio::test]
+async fn test_get_logs_walks_only_the_range() {
+    let (adapter, rpc) = mock_rpc(&mock_config());
+    let topic = H256::repeat_byte(0x47);
+    let chain = (0..20)
+        .map(|i| adapter.push_block_with_log(topic, &[i]))
+        .collect::<Vec<_>>();
+
+    let reads = adapter.block_reads();
+    let logs = rpc
+        .get_logs(Web3Filter {
+            from_block:    Some(BlockId::Num(2)),
+            to_block:      Some(BlockId::Num(3)),
+            block_hash:    None,
+            address:       None,
+            topics:        Some(vec![VariadicValue::Single(topic)]),
+            limit:         None,
+            confirmations: None,
+            order:         None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(logs.len(), 2);
+    assert_eq!(logs[0].block_hash, Some(chain[1].header_hash()));
+    assert_eq!(logs[1].block_hash, Some(chain[2].header_hash()));
+    // The head, block 3 by number and block 2 by its hash.
+    assert_eq!(adapter.block_reads() - reads, 3);
+}
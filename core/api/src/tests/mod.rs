@@ -0,0 +1,825 @@
+mod jsonrpc;
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::{Mutex, RwLock};
+
+use common_config_parser::types::{
+    ConfigApi, DefaultBlockTag, FeeHistoryInterpolation, RequestLogLevel, SubscriptionLagPolicy,
+    TotalDifficultyMode,
+};
+use common_crypto::{Crypto, Secp256k1Recoverable, Signature};
+use core_executor::code_address;
+use core_mempool::{notify_tx_broadcast, PoolTxEvent, POOL_TX_NOTIFIER};
+use protocol::tokio::{self, sync::Semaphore};
+use protocol::traits::{APIAdapter, Context};
+use protocol::types::{
+    AccessList, AccessSet, Account, AccountOverlay, AccountProof, Block, BlockNumber, BundleCall,
+    Bytes, ExitError, ExitReason, ExitRevert, ExitSucceed, Hash, Hasher, Header, Hex, Log,
+    Metadata, OverlayReplay, PrestateAccount, Proof, Proposal, Receipt, SignatureComponents,
+    SignedTransaction, StorageProof, StorageRange, Transaction, TransactionAction, TxPoolStatus,
+    TxPosition, TxResp, UnverifiedTransaction, H160, H256, U256,
+};
+use protocol::{async_trait, codec::ProtocolCodec, ProtocolResult};
+
+use crate::jsonrpc::r#impl::JsonRpcImpl;
+
+#[derive(Default)]
+pub struct MockAPIAdapter {
+    blocks:         RwLock<Vec<Block>>,
+    orphans:        RwLock<Vec<Block>>,
+    pending_reorg:  Mutex<Option<(BlockNumber, H256)>>,
+    pool:           RwLock<HashMap<Hash, SignedTransaction>>,
+    pool_log:       RwLock<Vec<Hash>>,
+    accounts:       RwLock<HashMap<H160, Account>>,
+    txs:            RwLock<HashMap<Hash, SignedTransaction>>,
+    receipts:       RwLock<HashMap<Hash, Receipt>>,
+    positions:      RwLock<HashMap<Hash, TxPosition>>,
+    evm_call_count: AtomicUsize,
+    account_reads:  AtomicUsize,
+    block_reads:    AtomicUsize,
+    index_reads:    AtomicUsize,
+    trace_count:    AtomicUsize,
+    range_limit:    AtomicUsize,
+    replay_count:   AtomicUsize,
+    trace_gate:     RwLock<Option<Arc<Semaphore>>>,
+    call_logs:      RwLock<Vec<Log>>,
+    call_gas:       RwLock<Option<(u64, u64)>>,
+    revert_calls:   AtomicBool,
+    prestate:       RwLock<BTreeMap<H160, PrestateAccount>>,
+    peers:          AtomicUsize,
+    gossip_delay:   RwLock<Option<Duration>>,
+    gossip_flood:   AtomicUsize,
+    metadata:       RwLock<Vec<Metadata>>,
+}
+
+impl MockAPIAdapter {
+    pub fn new() -> Self {
+        let adapter = MockAPIAdapter::default();
+        adapter.push_block(vec![]);
+        adapter
+    }
+
+    /// Append a block on top of the latest one and return it.
+    pub fn push_block(&self, tx_hashes: Vec<Hash>) -> Block {
+        self.push_block_with_extra(tx_hashes, Bytes::new())
+    }
+
+    fn push_block_with_extra(&self, tx_hashes: Vec<Hash>, extra_data: Bytes) -> Block {
+        let mut blocks = self.blocks.write();
+        let (number, prev_hash) = match blocks.last() {
+            Some(b) => (b.header.number + 1, b.header_hash()),
+            None => (0, Hash::default()),
+        };
+
+        let mut header = Header {
+            number,
+            prev_hash,
+            state_root: Hasher::digest(number.to_be_bytes()),
+            timestamp: number,
+            gas_limit: U256::from(30_000_000u64),
+            extra_data,
+            base_fee_per_gas: U256::from(1_000_000_000u64),
+            // Like the blocks built by consensus on top of a zero difficulty
+            // genesis.
+            difficulty: U256::from((number > 0) as u64),
+            ..Default::default()
+        };
+        header.proof.number = number.saturating_sub(1);
+        header.proof.block_hash = prev_hash;
+
+        let block = Block { header, tx_hashes };
+        blocks.push(block.clone());
+        block
+    }
+
+    /// Make every call need `needed` gas to run and use `used` gas once the
+    /// refund is paid back, instead of 21000 for both.
+    pub fn set_call_gas(&self, needed: u64, used: u64) {
+        *self.call_gas.write() = Some((needed, used));
+    }
+
+    /// Make every call revert with its input as the return data.
+    pub fn set_revert_calls(&self, revert: bool) {
+        self.revert_calls.store(revert, Ordering::SeqCst);
+    }
+
+    /// Replace the prestate returned by the traces, empty by default.
+    pub fn set_prestate(&self, prestate: BTreeMap<H160, PrestateAccount>) {
+        *self.prestate.write() = prestate;
+    }
+
+    /// Replace the proof of block `number`, which is carried by the next
+    /// block.
+    pub fn set_block_proof(&self, number: BlockNumber, proof: Proof) {
+        self.blocks.write()[number as usize + 1].header.proof = proof;
+    }
+
+    /// The `max_result` of the last storage range.
+    pub fn range_limit(&self) -> usize {
+        self.range_limit.load(Ordering::SeqCst)
+    }
+
+    /// How many times the transactions of a pending block are replayed.
+    pub fn replay_count(&self) -> usize {
+        self.replay_count.load(Ordering::SeqCst)
+    }
+
+    pub fn set_difficulty(&self, number: BlockNumber, difficulty: U256) {
+        self.blocks.write()[number as usize].header.difficulty = difficulty;
+    }
+
+    pub fn set_base_fee(&self, number: BlockNumber, base_fee: U256) {
+        self.blocks.write()[number as usize].header.base_fee_per_gas = base_fee;
+    }
+
+    /// Add a metadata version, the versions are looked up by height.
+    pub fn set_metadata(&self, metadata: Metadata) {
+        self.metadata.write().push(metadata);
+    }
+
+    /// Append a block with one transaction emitting a log of `topic` and
+    /// `data`.
+    pub fn push_block_with_log(&self, topic: H256, data: &[u8]) -> Block {
+        self.push_block_with_topics(vec![topic], data)
+    }
+
+    /// Append a block with one transaction emitting a log of `topics` and
+    /// `data`.
+    pub fn push_block_with_topics(&self, topics: Vec<H256>, data: &[u8]) -> Block {
+        self.push_block_with_log_and_extra(topics, data, Bytes::new())
+    }
+
+    fn push_block_with_log_and_extra(
+        &self,
+        topics: Vec<H256>,
+        data: &[u8],
+        extra_data: Bytes,
+    ) -> Block {
+        let stx = mock_signed_tx(self.receipts.read().len() as u64);
+        let hash = stx.transaction.hash;
+        let block = self.push_block_with_extra(vec![hash], extra_data);
+        let receipt = Receipt {
+            tx_hash: hash,
+            block_number: block.header.number,
+            block_hash: block.header_hash(),
+            sender: stx.sender,
+            logs: vec![Log {
+                address: stx.sender,
+                topics,
+                data: data.to_vec(),
+            }],
+            ..Default::default()
+        };
+        self.commit_tx(stx, receipt);
+        block
+    }
+
+    /// Append a block with one transaction per entry of `logs`, each emitting
+    /// the logs of its entry.
+    pub fn push_block_with_tx_logs(&self, logs: Vec<Vec<Log>>) -> Block {
+        let nonce = self.receipts.read().len() as u64;
+        let stxs = (0..logs.len() as u64)
+            .map(|i| mock_signed_tx(nonce + i))
+            .collect::<Vec<_>>();
+        let block = self.push_block(stxs.iter().map(|stx| stx.transaction.hash).collect());
+        for (idx, (stx, logs)) in stxs.into_iter().zip(logs.into_iter()).enumerate() {
+            let receipt = Receipt {
+                tx_hash: stx.transaction.hash,
+                block_number: block.header.number,
+                block_hash: block.header_hash(),
+                tx_index: idx as u32,
+                sender: stx.sender,
+                logs,
+                ..Default::default()
+            };
+            self.commit_tx(stx, receipt);
+        }
+        block
+    }
+
+    fn commit_tx(&self, stx: SignedTransaction, receipt: Receipt) {
+        let hash = stx.transaction.hash;
+        self.positions.write().insert(hash, TxPosition {
+            block_number: receipt.block_number,
+            tx_index:     receipt.tx_index,
+        });
+        self.txs.write().insert(hash, stx);
+        self.receipts.write().insert(hash, receipt);
+    }
+
+    /// Replace the blocks from `number` onward with a fork of the same
+    /// length whose blocks log `topic` with the data `fork`. The replaced
+    /// blocks can still be found by hash.
+    pub fn reorg(&self, number: BlockNumber, topic: H256) {
+        let replaced = self.blocks.write().split_off(number as usize);
+        let len = replaced.len();
+        self.orphans.write().extend(replaced);
+
+        for _ in 0..len {
+            self.push_block_with_log_and_extra(vec![topic], b"fork", Bytes::from_static(b"fork"));
+        }
+    }
+
+    /// Trigger a [`reorg`](Self::reorg) right after the next time the latest
+    /// block is read.
+    pub fn reorg_after_next_head(&self, number: BlockNumber, topic: H256) {
+        *self.pending_reorg.lock() = Some((number, topic));
+    }
+
+    fn find_block(&self, hash: Hash) -> Option<Block> {
+        let blocks = self.blocks.read();
+        let orphans = self.orphans.read();
+        blocks
+            .iter()
+            .chain(orphans.iter())
+            .find(|b| b.header_hash() == hash)
+            .cloned()
+    }
+
+    /// Mine the transaction into a new block.
+    pub fn mine_tx(&self, stx: SignedTransaction) -> Block {
+        self.mine_txs(vec![stx])
+    }
+
+    /// Mine the transactions, in order, into a new block. Each one uses all
+    /// of its gas limit.
+    pub fn mine_txs(&self, stxs: Vec<SignedTransaction>) -> Block {
+        let hashes = stxs
+            .iter()
+            .map(|stx| stx.transaction.hash)
+            .collect::<Vec<_>>();
+        {
+            let mut pool = self.pool.write();
+            hashes.iter().for_each(|hash| {
+                pool.remove(hash);
+            });
+        }
+
+        let block = self.push_block(hashes);
+        for (idx, stx) in stxs.into_iter().enumerate() {
+            let receipt = Receipt {
+                tx_hash: stx.transaction.hash,
+                block_number: block.header.number,
+                block_hash: block.header_hash(),
+                tx_index: idx as u32,
+                used_gas: stx.transaction.unsigned.gas_limit,
+                sender: stx.sender,
+                ..Default::default()
+            };
+            self.commit_tx(stx, receipt);
+        }
+        block
+    }
+
+    pub fn pool_tx(&self, hash: &Hash) -> Option<SignedTransaction> {
+        self.pool.read().get(hash).cloned()
+    }
+
+    /// Insert into the pool and notify it like the mempool does.
+    pub fn insert_pool_tx(&self, stx: SignedTransaction) {
+        self.pool_log.write().push(stx.transaction.hash);
+        self.pool.write().insert(stx.transaction.hash, stx.clone());
+        let _ = POOL_TX_NOTIFIER.send(PoolTxEvent::Inserted {
+            tx:       stx,
+            replaces: None,
+        });
+    }
+
+    /// Remove from the pool and notify it like the mempool does.
+    pub fn remove_pool_tx(&self, hash: &Hash) {
+        self.pool.write().remove(hash);
+        let _ = POOL_TX_NOTIFIER.send(PoolTxEvent::Removed(vec![*hash]));
+    }
+
+    /// Overwrite the `sender` of the receipt of a mined transaction.
+    pub fn set_receipt_sender(&self, hash: &Hash, sender: H160) {
+        if let Some(receipt) = self.receipts.write().get_mut(hash) {
+            receipt.sender = sender;
+        }
+    }
+
+    pub fn set_account(&self, address: H160, account: Account) {
+        self.accounts.write().insert(address, account);
+    }
+
+    pub fn evm_call_count(&self) -> usize {
+        self.evm_call_count.load(Ordering::SeqCst)
+    }
+
+    /// How many times an account was read from the state.
+    pub fn account_reads(&self) -> usize {
+        self.account_reads.load(Ordering::SeqCst)
+    }
+
+    /// How many times a block was read, by number or by hash.
+    pub fn block_reads(&self) -> usize {
+        self.block_reads.load(Ordering::SeqCst)
+    }
+
+    /// How many times the transaction index was consulted.
+    pub fn index_reads(&self) -> usize {
+        self.index_reads.load(Ordering::SeqCst)
+    }
+
+    /// How many traces have started.
+    pub fn trace_count(&self) -> usize {
+        self.trace_count.load(Ordering::SeqCst)
+    }
+
+    /// Make every `evm_call` emit `logs`.
+    pub fn set_call_logs(&self, logs: Vec<Log>) {
+        *self.call_logs.write() = logs;
+    }
+
+    /// Connect `peers` peers and gossip every inserted transaction to them
+    /// after `delay`.
+    pub fn gossip_txs(&self, peers: usize, delay: Duration) {
+        self.peers.store(peers, Ordering::SeqCst);
+        *self.gossip_delay.write() = Some(delay);
+    }
+
+    /// Follow every gossip with `count` other ones, more than a subscriber
+    /// buffers.
+    pub fn flood_gossip(&self, count: usize) {
+        self.gossip_flood.store(count, Ordering::SeqCst);
+    }
+
+    /// Hold the traces until a permit is added to `gate`.
+    pub fn gate_traces(&self, gate: Arc<Semaphore>) {
+        *self.trace_gate.write() = Some(gate);
+    }
+}
+
+#[async_trait]
+impl APIAdapter for MockAPIAdapter {
+    async fn insert_signed_txs(
+        &self,
+        _ctx: Context,
+        signed_tx: SignedTransaction,
+    ) -> ProtocolResult<()> {
+        let hash = signed_tx.transaction.hash;
+        self.insert_pool_tx(signed_tx);
+
+        if let Some(delay) = *self.gossip_delay.read() {
+            let flood = self.gossip_flood.load(Ordering::SeqCst);
+            tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+                notify_tx_broadcast(vec![hash]);
+                for i in 0..flood {
+                    notify_tx_broadcast(vec![Hasher::digest(i.to_be_bytes())]);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    async fn get_block_by_number(
+        &self,
+        _ctx: Context,
+        height: Option<u64>,
+    ) -> ProtocolResult<Option<Block>> {
+        self.block_reads.fetch_add(1, Ordering::SeqCst);
+        let block = match height {
+            Some(n) => self.blocks.read().get(n as usize).cloned(),
+            None => self.blocks.read().last().cloned(),
+        };
+
+        if height.is_none() {
+            if let Some((number, topic)) = self.pending_reorg.lock().take() {
+                self.reorg(number, topic);
+            }
+        }
+
+        Ok(block)
+    }
+
+    async fn get_block_by_hash(&self, _ctx: Context, hash: Hash) -> ProtocolResult<Option<Block>> {
+        self.block_reads.fetch_add(1, Ordering::SeqCst);
+        Ok(self.find_block(hash))
+    }
+
+    async fn get_block_header_by_number(
+        &self,
+        ctx: Context,
+        height: Option<u64>,
+    ) -> ProtocolResult<Option<Header>> {
+        Ok(self
+            .get_block_by_number(ctx, height)
+            .await?
+            .map(|b| b.header))
+    }
+
+    async fn get_receipt_by_tx_hash(
+        &self,
+        _ctx: Context,
+        tx_hash: Hash,
+    ) -> ProtocolResult<Option<Receipt>> {
+        Ok(self.receipts.read().get(&tx_hash).cloned())
+    }
+
+    async fn get_receipts_by_hashes(
+        &self,
+        _ctx: Context,
+        _block_number: u64,
+        tx_hashes: &[Hash],
+    ) -> ProtocolResult<Vec<Option<Receipt>>> {
+        let receipts = self.receipts.read();
+        Ok(tx_hashes.iter().map(|h| receipts.get(h).cloned()).collect())
+    }
+
+    async fn get_transaction_by_hash(
+        &self,
+        _ctx: Context,
+        tx_hash: Hash,
+    ) -> ProtocolResult<Option<SignedTransaction>> {
+        Ok(self.txs.read().get(&tx_hash).cloned())
+    }
+
+    async fn get_transaction_position(
+        &self,
+        _ctx: Context,
+        tx_hash: Hash,
+    ) -> ProtocolResult<Option<TxPosition>> {
+        self.index_reads.fetch_add(1, Ordering::SeqCst);
+        Ok(self.positions.read().get(&tx_hash).copied())
+    }
+
+    async fn get_transactions_by_hashes(
+        &self,
+        _ctx: Context,
+        _block_number: u64,
+        tx_hashes: &[Hash],
+    ) -> ProtocolResult<Vec<Option<SignedTransaction>>> {
+        let txs = self.txs.read();
+        Ok(tx_hashes.iter().map(|h| txs.get(h).cloned()).collect())
+    }
+
+    async fn get_account(
+        &self,
+        _ctx: Context,
+        address: H160,
+        _number: Option<BlockNumber>,
+    ) -> ProtocolResult<Account> {
+        self.account_reads.fetch_add(1, Ordering::SeqCst);
+        Ok(self
+            .accounts
+            .read()
+            .get(&address)
+            .cloned()
+            .unwrap_or(Account {
+                nonce:        U256::zero(),
+                balance:      U256::zero(),
+                storage_root: Hash::default(),
+                code_hash:    Hasher::digest(Bytes::new()),
+            }))
+    }
+
+    // Echoes each storage key as its own proof, so tests can see which keys
+    // reached the adapter.
+    async fn get_proof(
+        &self,
+        ctx: Context,
+        address: H160,
+        storage_keys: Vec<H256>,
+        number: Option<BlockNumber>,
+    ) -> ProtocolResult<AccountProof> {
+        let account = self.get_account(ctx, address, number).await?;
+        let storage_proof = storage_keys
+            .into_iter()
+            .map(|key| StorageProof {
+                key,
+                value: H256::zero(),
+                proof: vec![Bytes::copy_from_slice(key.as_bytes())],
+            })
+            .collect();
+
+        Ok(AccountProof {
+            account,
+            account_proof: vec![Bytes::copy_from_slice(address.as_bytes())],
+            storage_proof,
+        })
+    }
+
+    async fn evm_call(
+        &self,
+        _ctx: Context,
+        _from: Option<H160>,
+        _address: H160,
+        _value: U256,
+        data: Vec<u8>,
+        _access_list: AccessList,
+        _state_root: Hash,
+        proposal: Proposal,
+    ) -> ProtocolResult<TxResp> {
+        self.evm_call_count.fetch_add(1, Ordering::SeqCst);
+        // Every call costs at least the intrinsic gas of a transaction.
+        let (needed, used) = self.call_gas.read().unwrap_or((21000, 21000));
+        if proposal.gas_limit < U256::from(needed) {
+            return Ok(TxResp {
+                exit_reason:  ExitReason::Error(ExitError::OutOfGas),
+                ret:          Vec::new(),
+                gas_used:     proposal.gas_limit.as_u64(),
+                remain_gas:   0,
+                logs:         Vec::new(),
+                code_address: None,
+            });
+        }
+
+        let exit_reason = if self.revert_calls.load(Ordering::SeqCst) {
+            ExitReason::Revert(ExitRevert::Reverted)
+        } else {
+            ExitReason::Succeed(ExitSucceed::Returned)
+        };
+        Ok(TxResp {
+            exit_reason,
+            ret: data,
+            gas_used: used,
+            remain_gas: 0,
+            logs: self.call_logs.read().clone(),
+            code_address: None,
+        })
+    }
+
+    // The call reads the account of the caller and writes the called one.
+    async fn simulate_access_set(
+        &self,
+        _ctx: Context,
+        from: Option<H160>,
+        address: H160,
+        _value: U256,
+        _data: Vec<u8>,
+        _access_list: AccessList,
+        _state_root: Hash,
+        _proposal: Proposal,
+    ) -> ProtocolResult<AccessSet> {
+        let mut set = AccessSet::default();
+        set.reads_accounts.insert(from.unwrap_or_default());
+        set.writes_accounts.insert(address);
+        Ok(set)
+    }
+
+    // Each call returns the data of the calls so far, as if every call
+    // appended its data to the state the next one reads.
+    async fn simulate_bundle(
+        &self,
+        _ctx: Context,
+        calls: Vec<BundleCall>,
+        _state_root: Hash,
+        _proposal: Proposal,
+    ) -> ProtocolResult<Vec<TxResp>> {
+        let mut state = Vec::new();
+        Ok(calls
+            .into_iter()
+            .map(|call| {
+                state.extend(call.data);
+                TxResp {
+                    exit_reason:  ExitReason::Succeed(ExitSucceed::Returned),
+                    ret:          state.clone(),
+                    gas_used:     21000,
+                    remain_gas:   0,
+                    logs:         Vec::new(),
+                    code_address: None,
+                }
+            })
+            .collect())
+    }
+
+    async fn trace_prestate(
+        &self,
+        _ctx: Context,
+        _preceding: Vec<SignedTransaction>,
+        _tx: SignedTransaction,
+        _state_root: Hash,
+        _proposal: Proposal,
+    ) -> ProtocolResult<BTreeMap<H160, PrestateAccount>> {
+        self.trace_count.fetch_add(1, Ordering::SeqCst);
+        let gate = self.trace_gate.read().clone();
+        if let Some(gate) = gate {
+            let _ = gate.acquire().await;
+        }
+        Ok(self.prestate.read().clone())
+    }
+
+    async fn storage_range(
+        &self,
+        _ctx: Context,
+        _preceding: Vec<SignedTransaction>,
+        _address: H160,
+        _start: H256,
+        max_result: usize,
+        _state_root: Hash,
+        _proposal: Proposal,
+    ) -> ProtocolResult<StorageRange> {
+        self.range_limit.store(max_result, Ordering::SeqCst);
+        Ok(StorageRange::default())
+    }
+
+    // A creation deploys its data as it is and the storage is always empty.
+    async fn replay_account(
+        &self,
+        _ctx: Context,
+        preceding: Vec<SignedTransaction>,
+        address: H160,
+        slots: Vec<H256>,
+        _state_root: Hash,
+        _proposal: Proposal,
+    ) -> ProtocolResult<(Bytes, Vec<H256>)> {
+        let code = preceding
+            .into_iter()
+            .filter(|stx| stx.transaction.unsigned.action == TransactionAction::Create)
+            .filter(|stx| {
+                H160::from(code_address(&stx.sender, &stx.transaction.unsigned.nonce)) == address
+            })
+            .last()
+            .map(|stx| stx.transaction.unsigned.data)
+            .unwrap_or_default();
+        Ok((code, vec![H256::zero(); slots.len()]))
+    }
+
+    // A creation deploys its data as it is on an empty storage, the other
+    // transactions change nothing and emit the logs set for the calls.
+    async fn replay_overlay(
+        &self,
+        _ctx: Context,
+        txs: Vec<SignedTransaction>,
+        _state_root: Hash,
+        _proposal: Proposal,
+    ) -> ProtocolResult<OverlayReplay> {
+        self.replay_count.fetch_add(1, Ordering::SeqCst);
+        let mut replay = OverlayReplay::default();
+        for stx in txs.into_iter() {
+            if stx.transaction.unsigned.action != TransactionAction::Create {
+                replay.logs.push(self.call_logs.read().clone());
+                continue;
+            }
+
+            let address = code_address(&stx.sender, &stx.transaction.unsigned.nonce);
+            replay.overlay.insert(H160::from(address), AccountOverlay {
+                code:          Some(stx.transaction.unsigned.data.to_vec()),
+                storage:       BTreeMap::new(),
+                reset_storage: true,
+            });
+            replay.logs.push(Vec::new());
+        }
+        Ok(replay)
+    }
+
+    async fn get_block_proof(
+        &self,
+        _ctx: Context,
+        number: BlockNumber,
+    ) -> ProtocolResult<Option<Proof>> {
+        Ok(self
+            .blocks
+            .read()
+            .get(number as usize + 1)
+            .map(|b| b.header.proof.clone()))
+    }
+
+    async fn get_metadata(
+        &self,
+        _ctx: Context,
+        number: BlockNumber,
+    ) -> ProtocolResult<Option<Metadata>> {
+        Ok(self
+            .metadata
+            .read()
+            .iter()
+            .find(|metadata| metadata.version.contains(number))
+            .cloned())
+    }
+
+    async fn get_code_by_hash(&self, _ctx: Context, _hash: &Hash) -> ProtocolResult<Option<Bytes>> {
+        Ok(None)
+    }
+
+    async fn peer_count(&self, _ctx: Context) -> ProtocolResult<U256> {
+        Ok(self.peers.load(Ordering::SeqCst).into())
+    }
+
+    async fn get_number_by_hash(&self, _ctx: Context, hash: Hash) -> ProtocolResult<Option<u64>> {
+        Ok(self.find_block(hash).map(|b| b.header.number))
+    }
+
+    // Every block is kept.
+    async fn get_oldest_available_block(&self, _ctx: Context) -> ProtocolResult<BlockNumber> {
+        Ok(0)
+    }
+
+    async fn get_pool_tx_status(
+        &self,
+        _ctx: Context,
+        tx_hash: Hash,
+    ) -> ProtocolResult<Option<TxPoolStatus>> {
+        Ok(self
+            .pool
+            .read()
+            .get(&tx_hash)
+            .map(|_| TxPoolStatus::Pending))
+    }
+
+    async fn get_pending_txs(&self, _ctx: Context) -> ProtocolResult<Vec<SignedTransaction>> {
+        Ok(self.pool.read().values().cloned().collect())
+    }
+
+    async fn get_pool_txs_inserted_since(
+        &self,
+        _ctx: Context,
+        cursor: Option<u64>,
+    ) -> ProtocolResult<(u64, Vec<Hash>)> {
+        let log = self.pool_log.read();
+        let hashes = match cursor {
+            Some(cursor) => log.iter().skip(cursor as usize).cloned().collect(),
+            None => Vec::new(),
+        };
+        Ok((log.len() as u64, hashes))
+    }
+}
+
+fn mock_signed_tx(nonce: u64) -> SignedTransaction {
+    let utx = UnverifiedTransaction {
+        unsigned:  Transaction {
+            nonce:                    nonce.into(),
+            max_priority_fee_per_gas: Default::default(),
+            max_fee_per_gas:          Default::default(),
+            gas_limit:                U256::from(21000u64),
+            action:                   TransactionAction::Call(H160::repeat_byte(0x22)),
+            value:                    Default::default(),
+            data:                     Bytes::new(),
+            access_list:              vec![],
+        },
+        signature: Some(SignatureComponents {
+            standard_v: 0,
+            r:          H256::repeat_byte(0x01),
+            s:          H256::repeat_byte(0x02),
+        }),
+        chain_id:  5,
+        hash:      Default::default(),
+    };
+
+    SignedTransaction {
+        transaction: utx.hash(),
+        sender:      H160::repeat_byte(0x11),
+        public:      None,
+    }
+}
+
+/// A raw transaction signed by `privkey`, as sent to `eth_sendRawTransaction`.
+fn mock_raw_tx(privkey: &Hash, nonce: u64) -> Hex {
+    let mut utx = mock_signed_tx(nonce).transaction;
+    utx.signature = None;
+    let signature =
+        Secp256k1Recoverable::sign_message(utx.signature_hash().as_bytes(), privkey.as_bytes())
+            .unwrap()
+            .to_bytes();
+    utx.signature = Some(signature.into());
+
+    Hex::encode(utx.hash().encode().unwrap())
+}
+
+fn mock_config() -> ConfigApi {
+    ConfigApi {
+        http_listening_address:      None,
+        ws_listening_address:        None,
+        maxconn:                     100,
+        max_payload_size:            1024 * 1024,
+        enable_dump_profile:         None,
+        client_version:              "0.1.0".to_string(),
+        life_time:                   60_000,
+        call_cache_size:             0,
+        call_cache_ttl:              1000,
+        nonce_cache_size:            16,
+        chain_id:                    None,
+        enable_consensus_rpc:        false,
+        enable_personal_rpc:         false,
+        keystore:                    vec![],
+        ws_subscription_buffer_size: 64,
+        ws_subscription_lag_policy:  SubscriptionLagPolicy::Skip,
+        get_logs_confirmations:      0,
+        max_txs_per_sender_per_sec:  0,
+        ws_max_conn_per_ip:          0,
+        ws_max_buffered_bytes:       0,
+        get_logs_max_block_range:    0,
+        filter_max_buffered_logs:    1024,
+        default_block_tag:           DefaultBlockTag::Latest,
+        max_concurrent_traces:       0,
+        storage_range_max_result:    1024,
+        total_difficulty:            TotalDifficultyMode::Sum,
+        default_priority_fee:        1,
+        request_log:                 RequestLogLevel::Off,
+        max_call_return_size:        4096,
+        fee_history_interpolation:   FeeHistoryInterpolation::NearestRank,
+        mining_rpc_compat:           false,
+        max_bundle_calls:            4,
+    }
+}
+
+fn mock_rpc(config: &ConfigApi) -> (Arc<MockAPIAdapter>, JsonRpcImpl<MockAPIAdapter>) {
+    let adapter = Arc::new(MockAPIAdapter::new());
+    let rpc = JsonRpcImpl::new(Arc::clone(&adapter), config);
+    (adapter, rpc)
+}
@@ -1,8 +1,8 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use overlord::types::{Node, OverlordMsg, Vote, VoteType};
-use overlord::{extract_voters, Crypto, OverlordHandler};
+use overlord::types::OverlordMsg;
+use overlord::OverlordHandler;
 use parking_lot::RwLock;
 
 use common_apm::muta_apm;
@@ -14,20 +14,23 @@ use protocol::traits::{
     MessageTarget, PeerTrust, Priority, Rpc, Storage, SynchronizationAdapter,
 };
 use protocol::types::{
-    BatchSignedTxs, Block, BlockNumber, Bytes, ExecResp, Hash, Hasher, Header, Hex, Log,
-    MerkleRoot, Proof, Proposal, Receipt, SignedTransaction, Validator, U256,
+    BatchSignedTxs, Block, BlockNumber, Bytes, ExecResp, Hash, Header, Hex, Log, MerkleRoot,
+    Metadata, Proof, Proposal, Receipt, SignedTransaction, Validator, U256,
 };
-use protocol::{async_trait, codec::ProtocolCodec, tokio::task, ProtocolResult};
+use protocol::{async_trait, tokio::task, ProtocolResult};
 
 use crate::consensus::gen_overlord_status;
 use crate::message::{
-    BROADCAST_HEIGHT, RPC_SYNC_PULL_BLOCK, RPC_SYNC_PULL_PROOF, RPC_SYNC_PULL_TXS,
+    BROADCAST_HEIGHT, END_GOSSIP_AGGREGATED_VOTE, RPC_ACKED_AGGREGATED_VOTE, RPC_SYNC_PULL_BLOCK,
+    RPC_SYNC_PULL_PROOF, RPC_SYNC_PULL_TXS,
 };
 use crate::types::PullTxsRequest;
-use crate::util::{convert_hex_to_bls_pubkeys, OverlordCrypto};
+use crate::util::{
+    convert_hex_to_bls_pubkeys, verify_block_proof, verify_proof_weight, OverlordCrypto,
+};
 use crate::BlockHeaderField::PreviousBlockHash;
-use crate::BlockProofField::{BitMap, HashMismatch, HeightMismatch, Signature, WeightNotFound};
-use crate::{BlockProofField, ConsensusError, METADATA_CONTROLER};
+use crate::BlockProofField::Signature;
+use crate::{ConsensusError, METADATA_CONTROLER};
 
 pub struct OverlordConsensusAdapter<
     M: MemPool,
@@ -43,6 +46,7 @@ pub struct OverlordConsensusAdapter<
     cross_client:     Arc<CS>,
     overlord_handler: RwLock<Option<OverlordHandler<Proposal>>>,
     crypto:           Arc<OverlordCrypto>,
+    executor:         EvmExecutor,
 }
 
 #[async_trait]
@@ -90,6 +94,19 @@ where
     ) -> ProtocolResult<()> {
         match target {
             MessageTarget::Broadcast => {
+                // The aggregated vote is also sent to the consensus peers with
+                // acknowledgement, off the critical path, to tell how many of
+                // them got it.
+                if end == END_GOSSIP_AGGREGATED_VOTE {
+                    let network = Arc::clone(&self.network);
+                    let (ctx, msg) = (ctx.clone(), msg.clone());
+                    task::spawn(async move {
+                        if let Err(e) = broadcast_qc(network.as_ref(), ctx, msg).await {
+                            log::warn!("consensus: acked qc broadcast {}", e);
+                        }
+                    });
+                }
+
                 self.network
                     .broadcast(ctx.clone(), end, msg, Priority::High)
                     .await
@@ -262,6 +279,10 @@ where
         self.storage.update_latest_proof(ctx, proof).await
     }
 
+    async fn save_metadata(&self, ctx: Context, metadata: Metadata) -> ProtocolResult<()> {
+        self.storage.insert_metadata(ctx, metadata).await
+    }
+
     /// Save some signed transactions to the database.
     // #[muta_apm::derive::tracing_span(
     //     kind = "consensus.adapter",
@@ -362,7 +383,7 @@ where
         )?;
 
         Ok(task::block_in_place(|| {
-            EvmExecutor::default().exec(&mut backend, signed_txs)
+            self.executor.exec(&mut backend, signed_txs)
         }))
     }
 
@@ -426,36 +447,12 @@ where
     }
 
     // #[muta_apm::derive::tracing_span(kind = "consensus.adapter")]
-    async fn verify_proof(&self, ctx: Context, block: Block, proof: Proof) -> ProtocolResult<()> {
+    async fn verify_proof(&self, _ctx: Context, block: Block, proof: Proof) -> ProtocolResult<()> {
         // the block 0 has no proof, which is consensus-ed by community, not by chain
         if block.header.number == 0 {
             return Ok(());
         };
 
-        if block.header.number != proof.number {
-            log::error!(
-                "[consensus] verify_proof, block_header.number: {}, proof.number: {}",
-                block.header.number,
-                proof.number
-            );
-            return Err(ConsensusError::VerifyProof(
-                block.header.number,
-                HeightMismatch(block.header.number, proof.number),
-            )
-            .into());
-        }
-
-        let proposal_hash = Hasher::digest(Proposal::from(block.clone()).encode()?);
-
-        if proposal_hash != proof.block_hash {
-            log::error!(
-                "[consensus] verify_proof, blockhash: {:?}, proof.block_hash: {:?}",
-                proposal_hash,
-                proof.block_hash
-            );
-            return Err(ConsensusError::VerifyProof(block.header.number, HashMismatch).into());
-        }
-
         // the auth_list for the target should comes from previous number
         let metadata = METADATA_CONTROLER.load().current();
 
@@ -467,70 +464,15 @@ where
             .into());
         }
 
-        let mut authority_list = metadata
-            .verifier_list
-            .iter()
-            .map(|v| Node {
-                address:        v.pub_key.as_bytes(),
-                propose_weight: v.propose_weight,
-                vote_weight:    v.vote_weight,
-            })
-            .collect::<Vec<Node>>();
-
-        let signed_voters = extract_voters(&mut authority_list, &proof.bitmap).map_err(|_| {
-            log::error!("[consensus] extract_voters fails, bitmap error");
-            ConsensusError::VerifyProof(block.header.number, BitMap)
-        })?;
-
-        let vote = Vote {
-            height:     proof.number,
-            round:      proof.round,
-            vote_type:  VoteType::Precommit,
-            block_hash: Bytes::from(proof.block_hash.as_bytes().to_vec()),
-        };
-
-        let weight_map = authority_list
-            .iter()
-            .map(|node| (node.address.clone(), node.vote_weight))
-            .collect::<HashMap<overlord::types::Address, u32>>();
-        self.verify_proof_weight(
-            ctx.clone(),
-            block.header.number,
-            weight_map,
-            signed_voters.clone(),
-        )?;
-
-        let vote_hash = self.crypto.hash(Bytes::from(rlp::encode(&vote)));
-        let hex_pubkeys = metadata
-            .verifier_list
-            .iter()
-            .filter_map(|v| {
-                if signed_voters.contains(&v.pub_key.as_bytes()) {
-                    Some(v.bls_pub_key.clone())
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<_>>();
-
-        self.verify_proof_signature(
-            ctx,
-            block.header.number,
-            vote_hash.clone(),
-            proof.signature.clone(),
-            hex_pubkeys,
-        ).map_err(|e| {
-            log::error!("[consensus] verify_proof_signature error, number {}, vote: {:?}, vote_hash:{:?}, sig:{:?}, signed_voter:{:?}",
-            block.header.number,
-            vote,
-            vote_hash,
-            proof.signature,
-            signed_voters,
+        verify_block_proof(&block, &proof, &metadata).map_err(|e| {
+            log::error!(
+                "[consensus] verify_proof error, number {}, proof: {:?}, error: {}",
+                block.header.number,
+                proof,
+                e
             );
             e
-        })?;
-
-        Ok(())
+        })
     }
 
     async fn notify_block_logs(
@@ -579,43 +521,7 @@ where
         weight_map: HashMap<Bytes, u32>,
         signed_voters: Vec<Bytes>,
     ) -> ProtocolResult<()> {
-        let total_validator_weight: u64 = weight_map.iter().map(|pair| u64::from(*pair.1)).sum();
-
-        let mut accumulator = 0u64;
-        for signed_voter_address in signed_voters {
-            if weight_map.contains_key(signed_voter_address.as_ref()) {
-                let weight = weight_map
-                    .get(signed_voter_address.as_ref())
-                    .ok_or(ConsensusError::VerifyProof(block_number, WeightNotFound))
-                    .map_err(|e| {
-                        log::error!(
-                            "[consensus] verify_proof_weight,signed_voter_address: {:?}",
-                            signed_voter_address
-                        );
-                        e
-                    })?;
-                accumulator += u64::from(*(weight));
-            } else {
-                log::error!(
-                    "[consensus] verify_proof_weight, weight not found, signed_voter_address: {:?}",
-                    signed_voter_address
-                );
-                return Err(
-                    ConsensusError::VerifyProof(block_number, BlockProofField::Validator).into(),
-                );
-            }
-        }
-
-        if 3 * accumulator <= 2 * total_validator_weight {
-            log::error!(
-                "[consensus] verify_proof_weight, accumulator: {}, total: {}",
-                accumulator,
-                total_validator_weight
-            );
-
-            return Err(ConsensusError::VerifyProof(block_number, BlockProofField::Weight).into());
-        }
-        Ok(())
+        verify_proof_weight(block_number, &weight_map, &signed_voters)
     }
 }
 
@@ -634,6 +540,7 @@ where
         trie_db: Arc<DB>,
         cross_client: Arc<CS>,
         crypto: Arc<OverlordCrypto>,
+        executor: EvmExecutor,
     ) -> ProtocolResult<Self> {
         Ok(OverlordConsensusAdapter {
             network,
@@ -643,6 +550,7 @@ where
             cross_client,
             overlord_handler: RwLock::new(None),
             crypto,
+            executor,
         })
     }
 
@@ -650,3 +558,104 @@ where
         *self.overlord_handler.write() = Some(handler)
     }
 }
+
+/// Send the aggregated vote to the connected consensus peers and return how
+/// many of them acknowledged it. A vote which some of them did not
+/// acknowledge is logged and counted, since they may fail to commit the block
+/// in time.
+async fn broadcast_qc<N: Gossip>(network: &N, ctx: Context, msg: Vec<u8>) -> ProtocolResult<usize> {
+    let (sent, acked) = network
+        .broadcast_to_consensus_acked(ctx, RPC_ACKED_AGGREGATED_VOTE, msg)
+        .await?;
+    common_apm::metrics::consensus::ENGINE_QC_ACKED_COUNTER.inc_by(acked as u64);
+    if acked < sent {
+        common_apm::metrics::consensus::ENGINE_QC_UNACKED_COUNTER.inc_by((sent - acked) as u64);
+        log::warn!(
+            "consensus: qc acknowledged by {} of {} consensus peers",
+            acked,
+            sent
+        );
+    }
+
+    Ok(acked)
+}
+
+#[cfg(test)]
+mod tests {
+    use parking_lot::Mutex;
+
+    use protocol::tokio;
+    use protocol::traits::MessageCodec;
+
+    use super::*;
+
+    /// A network of consensus peers, each of which either acknowledges the
+    /// calls it receives or never answers them.
+    struct MockConsensusPeers {
+        acks:     Vec<bool>,
+        received: Mutex<Vec<(String, Bytes)>>,
+    }
+
+    #[async_trait]
+    impl Gossip for MockConsensusPeers {
+        async fn broadcast<M>(&self, _: Context, _: &str, _: M, _: Priority) -> ProtocolResult<()>
+        where
+            M: MessageCodec,
+        {
+            unreachable!()
+        }
+
+        async fn broadcast_to_consensus_acked<M>(
+            &self,
+            _: Context,
+            end: &str,
+            mut msg: M,
+        ) -> ProtocolResult<(usize, usize)>
+        where
+            M: MessageCodec + Clone,
+        {
+            let bytes = msg.encode_msg()?;
+            let mut received = self.received.lock();
+            for _ in self.acks.iter() {
+                received.push((end.to_owned(), bytes.clone()));
+            }
+
+            let acked = self.acks.iter().filter(|acked| **acked).count();
+            Ok((self.acks.len(), acked))
+        }
+
+        async fn multicast<'a, M, P>(
+            &self,
+            _: Context,
+            _: &str,
+            _: P,
+            _: M,
+            _: Priority,
+        ) -> ProtocolResult<()>
+        where
+            M: MessageCodec,
+            P: AsRef<[Bytes]> + Send + 'a,
+        {
+            unreachable!()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_qc_counts_acks() {
+        let network = MockConsensusPeers {
+            acks:     vec![true, false, true],
+            received: Mutex::new(Vec::new()),
+        };
+        let mut qc = vec![1u8, 2, 3];
+        let encoded = qc.encode_msg().unwrap();
+
+        let acked = broadcast_qc(&network, Context::new(), qc).await.unwrap();
+        assert_eq!(acked, 2);
+
+        let received = network.received.lock();
+        assert_eq!(received.len(), 3);
+        assert!(received
+            .iter()
+            .all(|(end, bytes)| end == RPC_ACKED_AGGREGATED_VOTE && *bytes == encoded));
+    }
+}
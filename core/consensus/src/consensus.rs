@@ -108,6 +108,7 @@ impl<Adapter: ConsensusAdapter + 'static> OverlordConsensus<Adapter> {
         lock: Arc<AsyncMutex<()>>,
         consensus_wal: Arc<ConsensusWal>,
         cross_period_interval: u64,
+        max_timestamp_drift: u64,
     ) -> Self {
         let engine = Arc::new(ConsensusEngine::new(
             status,
@@ -118,6 +119,7 @@ impl<Adapter: ConsensusAdapter + 'static> OverlordConsensus<Adapter> {
             lock,
             consensus_wal,
             cross_period_interval,
+            max_timestamp_drift,
         ));
         let status = engine.status();
         let metadata = METADATA_CONTROLER.load().current();
@@ -20,8 +20,8 @@ use common_merkle::Merkle;
 use protocol::codec::ProtocolCodec;
 use protocol::traits::{ConsensusAdapter, Context, MessageTarget, NodeInfo};
 use protocol::types::{
-    Block, BlockNumber, Bloom, BloomInput, Bytes, ExecResp, Hash, Hasher, Log, MerkleRoot,
-    Metadata, Proof, Proposal, Receipt, SignedTransaction, ValidatorExtend, U256,
+    logs_bloom, Block, BlockNumber, Bytes, ExecResp, Hash, Hasher, Log, MerkleRoot, Metadata,
+    Proof, Proposal, Receipt, SignedTransaction, ValidatorExtend, U256,
 };
 use protocol::{
     async_trait, lazy::CURRENT_STATE_ROOT, tokio::sync::Mutex as AsyncMutex, ProtocolError,
@@ -32,7 +32,7 @@ use crate::message::{
     END_GOSSIP_AGGREGATED_VOTE, END_GOSSIP_SIGNED_CHOKE, END_GOSSIP_SIGNED_PROPOSAL,
     END_GOSSIP_SIGNED_VOTE,
 };
-use crate::status::{CurrentStatus, StatusAgent};
+use crate::status::{notify_block_import, CurrentStatus, StatusAgent, CONSENSUS_STATUS};
 use crate::util::{digest_signed_transactions, time_now, OverlordCrypto};
 use crate::wal::{ConsensusWal, SignedTxsWAL};
 use crate::{ConsensusError, METADATA_CONTROLER};
@@ -50,6 +50,7 @@ pub struct ConsensusEngine<Adapter> {
     lock:    Arc<AsyncMutex<()>>,
 
     cross_period_interval:        u64,
+    max_timestamp_drift:          u64,
     last_commit_time:             RwLock<u64>,
     consensus_wal:                Arc<ConsensusWal>,
     last_check_block_fail_reason: RwLock<String>,
@@ -80,13 +81,17 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<Proposal> for ConsensusEngine<A
         let order_root = Merkle::from_hashes(txs.clone())
             .get_root_hash()
             .unwrap_or_default();
+        let parent = self
+            .adapter
+            .get_block_by_number(ctx.clone(), next_number - 1)
+            .await?;
 
         let proposal = Proposal {
             prev_hash:                  status.prev_hash,
             proposer:                   self.node_info.self_address.0,
             transactions_root:          order_root,
             signed_txs_hash:            digest_signed_transactions(&signed_txs),
-            timestamp:                  time_now(),
+            timestamp:                  proposal_timestamp(parent.header.timestamp, time_now()),
             number:                     next_number,
             gas_limit:                  100_000_000_000u64.into(),
             extra_data:                 Default::default(),
@@ -278,8 +283,14 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<Proposal> for ConsensusEngine<A
             self.exemption_hash.write().clear();
         }
 
+        let previous_version = METADATA_CONTROLER.load().current().version;
         self.update_metadata(current_number + 1);
         let metadata = METADATA_CONTROLER.load().current();
+        if metadata.version != previous_version {
+            self.adapter
+                .save_metadata(ctx.clone(), metadata.clone())
+                .await?;
+        }
 
         let status = Status {
             height:         current_number + 1,
@@ -293,6 +304,9 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<Proposal> for ConsensusEngine<A
             .await?;
 
         self.metric_commit(current_number, txs_len);
+        CONSENSUS_STATUS
+            .write()
+            .commit(current_number, proposal.proposer, time_now());
 
         Ok(status)
     }
@@ -413,6 +427,7 @@ impl<Adapter: ConsensusAdapter + 'static> Engine<Proposal> for ConsensusEngine<A
             }
             _ => reason.to_string(),
         };
+        CONSENSUS_STATUS.write().view_change(number, round);
 
         log(
             log::Level::Warn,
@@ -449,6 +464,7 @@ impl<Adapter: ConsensusAdapter + 'static> ConsensusEngine<Adapter> {
         lock: Arc<AsyncMutex<()>>,
         consensus_wal: Arc<ConsensusWal>,
         cross_period_interval: u64,
+        max_timestamp_drift: u64,
     ) -> Self {
         Self {
             status,
@@ -459,6 +475,7 @@ impl<Adapter: ConsensusAdapter + 'static> ConsensusEngine<Adapter> {
             crypto,
             lock,
             cross_period_interval,
+            max_timestamp_drift,
             last_commit_time: RwLock::new(time_now()),
             consensus_wal,
             last_check_block_fail_reason: RwLock::new(String::new()),
@@ -496,10 +513,11 @@ impl<Adapter: ConsensusAdapter + 'static> ConsensusEngine<Adapter> {
             .await?;
 
         // verify block timestamp.
-        if !validate_timestamp(
-            current_timestamp,
-            proposal.timestamp,
+        if !verify_timestamp(
             previous_block.header.timestamp,
+            proposal.timestamp,
+            current_timestamp,
+            self.max_timestamp_drift,
         ) {
             return Err(ProtocolError::from(ConsensusError::InvalidTimestamp));
         }
@@ -633,6 +651,7 @@ impl<Adapter: ConsensusAdapter + 'static> ConsensusEngine<Adapter> {
 
         CURRENT_STATE_ROOT.swap(Arc::new(resp.state_root));
         self.status.swap(new_status);
+        notify_block_import(block.clone());
 
         // update timeout_gap of mempool
         self.adapter.set_args(
@@ -704,12 +723,22 @@ fn convert_to_overlord_authority(validators: &[ValidatorExtend]) -> Vec<Node> {
     authority
 }
 
-fn validate_timestamp(
-    current_timestamp: u64,
-    proposal_timestamp: u64,
+/// The local time, or just after the parent if the local clock is behind it
+/// so that the proposal passes `verify_timestamp`.
+fn proposal_timestamp(previous_timestamp: u64, current_timestamp: u64) -> u64 {
+    current_timestamp.max(previous_timestamp + 1)
+}
+
+/// A proposal must be later than its parent, and not later than the local
+/// time by more than `max_drift` seconds to tolerate the clock differences
+/// between the nodes.
+fn verify_timestamp(
     previous_timestamp: u64,
+    proposal_timestamp: u64,
+    current_timestamp: u64,
+    max_drift: u64,
 ) -> bool {
-    if proposal_timestamp < previous_timestamp {
+    if proposal_timestamp <= previous_timestamp {
         log::error!(
             "[consensus] invalid timestamp previous {:?}, proposal {:?}",
             previous_timestamp,
@@ -718,11 +747,12 @@ fn validate_timestamp(
         return false;
     }
 
-    if proposal_timestamp > current_timestamp {
+    if proposal_timestamp > current_timestamp.saturating_add(max_drift) {
         log::error!(
-            "[consensus] invalid timestamp proposal {:?}, current {:?}",
+            "[consensus] invalid timestamp proposal {:?}, current {:?}, max drift {:?}",
             proposal_timestamp,
-            current_timestamp
+            current_timestamp,
+            max_drift
         );
         return false;
     }
@@ -748,7 +778,7 @@ pub fn generate_receipts_and_logs(
             tx_index: idx as u32,
             state_root,
             used_gas: U256::from(res.gas_used),
-            logs_bloom: Bloom::from(BloomInput::Raw(rlp::encode_list(&res.logs).as_ref())),
+            logs_bloom: logs_bloom(&res.logs),
             logs: res.logs.clone(),
             code_address: res.code_address,
             sender: tx.sender,
@@ -766,17 +796,35 @@ fn gauge_txs_len(proposal: &Proposal) {
 
 #[cfg(test)]
 mod tests {
-    use super::validate_timestamp;
+    use super::{proposal_timestamp, verify_timestamp};
 
     #[test]
-    fn test_validate_timestamp() {
-        // current 10, proposal 9, previous 8. true
-        assert!(validate_timestamp(10, 9, 8));
+    fn test_verify_timestamp() {
+        // previous 8, proposal 9, current 10. true
+        assert!(verify_timestamp(8, 9, 10, 0));
+
+        // previous 8, proposal 11, current 10. false
+        assert!(!verify_timestamp(8, 11, 10, 0));
 
-        // current 10, proposal 11, previous 8. true
-        assert!(!validate_timestamp(10, 11, 8));
+        // previous 11, proposal 9, current 10. false
+        assert!(!verify_timestamp(11, 9, 10, 0));
 
-        // current 10, proposal 9, previous 11. true
-        assert!(!validate_timestamp(10, 9, 11));
+        // previous 9, proposal 9, current 10. false
+        assert!(!verify_timestamp(9, 9, 10, 3));
+
+        // previous 9, proposal 13, current 10, drift 3. true
+        assert!(verify_timestamp(9, 13, 10, 3));
+
+        // previous 9, proposal 14, current 10, drift 3. false
+        assert!(!verify_timestamp(9, 14, 10, 3));
+    }
+
+    #[test]
+    fn test_proposal_timestamp() {
+        assert_eq!(proposal_timestamp(8, 10), 10);
+        // The local clock is behind the parent.
+        assert_eq!(proposal_timestamp(10, 10), 11);
+        assert_eq!(proposal_timestamp(12, 10), 13);
+        assert!(verify_timestamp(12, proposal_timestamp(12, 10), 10, 3));
     }
 }
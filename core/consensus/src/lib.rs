@@ -24,13 +24,17 @@ use protocol::{Display, ProtocolError, ProtocolErrorKind};
 
 pub use crate::adapter::OverlordConsensusAdapter;
 pub use crate::consensus::OverlordConsensus;
-pub use crate::status::METADATA_CONTROLER;
+pub use crate::status::{
+    notify_block_import, ConsensusStatus, BLOCK_IMPORT_NOTIFIER, CONSENSUS_STATUS,
+    METADATA_CONTROLER,
+};
 pub use crate::synchronization::{OverlordSynchronization, RichBlock, SyncStatus, SYNC_STATUS};
 pub use crate::wal::{ConsensusWal, SignedTxsWAL};
 pub use overlord::{types::Node, DurationConfig};
 
 pub const DEFAULT_OVERLORD_GAP: usize = 5;
 pub const DEFAULT_SYNC_TXS_CHUNK_SIZE: usize = 5000;
+pub const DEFAULT_MAX_TIMESTAMP_DRIFT: u64 = 3;
 
 #[derive(Clone, Debug, Display, PartialEq, Eq)]
 pub enum ConsensusType {
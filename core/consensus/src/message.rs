@@ -8,7 +8,8 @@ use rlp::Encodable;
 
 use common_apm::muta_apm;
 use protocol::traits::{
-    Consensus, Context, MessageHandler, Priority, Rpc, Storage, Synchronization, TrustFeedback,
+    Consensus, Context, MessageHandler, Priority, RejectCode, Rpc, Storage, Synchronization,
+    TrustFeedback,
 };
 use protocol::types::BatchSignedTxs;
 use protocol::{async_trait, types::BlockNumber, ProtocolError};
@@ -28,6 +29,8 @@ pub const RPC_RESP_SYNC_PULL_TXS: &str = "/rpc_resp/consensus/sync_pull_txs";
 pub const BROADCAST_HEIGHT: &str = "/gossip/consensus/broadcast_height";
 pub const RPC_SYNC_PULL_PROOF: &str = "/rpc_call/consensus/sync_pull_proof";
 pub const RPC_RESP_SYNC_PULL_PROOF: &str = "/rpc_resp/consensus/sync_pull_proof";
+pub const RPC_ACKED_AGGREGATED_VOTE: &str = "/rpc_call/consensus/acked_qc";
+pub const RPC_RESP_ACKED_AGGREGATED_VOTE: &str = "/rpc_resp/consensus/acked_qc";
 
 macro_rules! overlord_message {
     ($msg_name: ident, $overlord_type_name: ident) => {
@@ -113,7 +116,7 @@ impl<C: Consensus + 'static> MessageHandler for ProposalMessageHandler<C> {
     async fn process(&self, ctx: Context, msg: Self::Message) -> TrustFeedback {
         if let Err(e) = self.consensus.set_proposal(ctx, msg.to_vec()).await {
             warn!("set proposal {}", e);
-            return TrustFeedback::Worse(e.to_string());
+            return TrustFeedback::Worse(e.to_string(), None);
         }
 
         TrustFeedback::Good
@@ -138,7 +141,7 @@ impl<C: Consensus + 'static> MessageHandler for VoteMessageHandler<C> {
     async fn process(&self, ctx: Context, msg: Self::Message) -> TrustFeedback {
         if let Err(e) = self.consensus.set_vote(ctx, msg.to_vec()).await {
             warn!("set vote {}", e);
-            return TrustFeedback::Worse(e.to_string());
+            return TrustFeedback::Worse(e.to_string(), None);
         }
 
         TrustFeedback::Good
@@ -164,13 +167,54 @@ impl<C: Consensus + 'static> MessageHandler for QCMessageHandler<C> {
     async fn process(&self, ctx: Context, msg: Self::Message) -> TrustFeedback {
         if let Err(e) = self.consensus.set_qc(ctx, msg.to_vec()).await {
             warn!("set qc {}", e);
-            return TrustFeedback::Worse(e.to_string());
+            return TrustFeedback::Worse(e.to_string(), None);
         }
 
         TrustFeedback::Good
     }
 }
 
+/// Handle an aggregated vote sent by `broadcast_to_consensus_acked`, and
+/// acknowledge it once consensus accepted it.
+pub struct AckedQCRpcHandler<R, C> {
+    rpc:       Arc<R>,
+    consensus: Arc<C>,
+}
+
+impl<R: Rpc + 'static, C: Consensus + 'static> AckedQCRpcHandler<R, C> {
+    pub fn new(rpc: Arc<R>, consensus: Arc<C>) -> Self {
+        Self { rpc, consensus }
+    }
+}
+
+#[async_trait]
+impl<R: Rpc + 'static, C: Consensus + 'static> MessageHandler for AckedQCRpcHandler<R, C> {
+    type Message = QC;
+
+    async fn process(&self, ctx: Context, msg: Self::Message) -> TrustFeedback {
+        let ret = self.consensus.set_qc(ctx.clone(), msg.to_vec()).await;
+        let feedback = match ret.as_ref() {
+            Ok(_) => TrustFeedback::Good,
+            Err(e) => {
+                warn!("set acked qc {}", e);
+                TrustFeedback::Worse(e.to_string(), None)
+            }
+        };
+
+        self.rpc
+            .response(
+                ctx,
+                RPC_RESP_ACKED_AGGREGATED_VOTE,
+                ret.map(|_| Vec::<u8>::new()),
+                Priority::High,
+            )
+            .unwrap_or_else(move |e: ProtocolError| warn!("[core_consensus] ack qc {}", e))
+            .await;
+
+        feedback
+    }
+}
+
 pub struct ChokeMessageHandler<C> {
     consensus: Arc<C>,
 }
@@ -190,7 +234,7 @@ impl<C: Consensus + 'static> MessageHandler for ChokeMessageHandler<C> {
     async fn process(&self, ctx: Context, msg: Self::Message) -> TrustFeedback {
         if let Err(e) = self.consensus.set_choke(ctx, msg.to_vec()).await {
             warn!("set choke {}", e);
-            return TrustFeedback::Worse(e.to_string());
+            return TrustFeedback::Worse(e.to_string(), None);
         }
 
         TrustFeedback::Good
@@ -220,10 +264,13 @@ impl<Sy: Synchronization + 'static> MessageHandler for RemoteHeightMessageHandle
         {
             warn!("sync: receive remote block {}", e);
             if e.to_string().contains("timeout") {
-                return TrustFeedback::Bad("sync block timeout".to_owned());
+                return TrustFeedback::Bad(
+                    "sync block timeout".to_owned(),
+                    Some(RejectCode::Timeout),
+                );
             } else {
                 // Just in case, don't use worse here
-                return TrustFeedback::Bad(e.to_string());
+                return TrustFeedback::Bad(e.to_string(), None);
             }
         }
 
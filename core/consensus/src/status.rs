@@ -1,12 +1,23 @@
 use std::sync::Arc;
 
 use arc_swap::ArcSwap;
-use parking_lot::Mutex;
+use parking_lot::{Mutex, RwLock};
 
-use protocol::types::{BlockNumber, Hash, Metadata, Proof, H256, U256};
+use protocol::tokio::sync::broadcast;
+use protocol::types::{Block, BlockNumber, Hash, Metadata, Proof, H160, H256, U256};
+
+const BLOCK_IMPORT_CHANNEL_SIZE: usize = 128;
 
 lazy_static::lazy_static! {
     pub static ref METADATA_CONTROLER: ArcSwap<MetadataController> = ArcSwap::from_pointee(MetadataController::default());
+    pub static ref BLOCK_IMPORT_NOTIFIER: broadcast::Sender<Block> = broadcast::channel(BLOCK_IMPORT_CHANNEL_SIZE).0;
+    pub static ref CONSENSUS_STATUS: RwLock<ConsensusStatus> = RwLock::new(ConsensusStatus::default());
+}
+
+/// Notify the subscribers of `BLOCK_IMPORT_NOTIFIER` that a block has been
+/// committed and saved. It is fine that nobody is listening.
+pub fn notify_block_import(block: Block) {
+    let _ = BLOCK_IMPORT_NOTIFIER.send(block);
 }
 
 #[derive(Default, Debug)]
@@ -37,6 +48,14 @@ impl MetadataController {
         self.previous.lock().clone()
     }
 
+    /// The metadata in effect at `number`, if it is the current or the
+    /// previous one.
+    pub fn get(&self, number: BlockNumber) -> Option<Metadata> {
+        [self.current(), self.previous()]
+            .into_iter()
+            .find(|metadata| metadata.version.contains(number))
+    }
+
     pub fn set_next(&self, next: Metadata) {
         *self.next.lock() = next;
     }
@@ -80,3 +99,32 @@ pub struct CurrentStatus {
     pub base_fee_per_gas: U256,
     pub proof:            Proof,
 }
+
+/// The overlord view of this node, updated on every commit and view change.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct ConsensusStatus {
+    /// The height which is under consensus.
+    pub height:                BlockNumber,
+    /// The round of `height`.
+    pub round:                 u64,
+    pub last_committed_height: BlockNumber,
+    /// The proposer of the last committed block.
+    pub last_proposer:         H160,
+    /// Unix timestamp of the last commit in seconds.
+    pub last_commit_time:      u64,
+}
+
+impl ConsensusStatus {
+    pub fn commit(&mut self, number: BlockNumber, proposer: H160, time: u64) {
+        self.height = number + 1;
+        self.round = 0;
+        self.last_committed_height = number;
+        self.last_proposer = proposer;
+        self.last_commit_time = time;
+    }
+
+    pub fn view_change(&mut self, number: BlockNumber, round: u64) {
+        self.height = number;
+        self.round = round + 1;
+    }
+}
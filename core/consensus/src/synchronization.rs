@@ -4,13 +4,15 @@ use std::time::{Duration, Instant};
 use parking_lot::RwLock;
 
 // use common_apm::muta_apm;
-use protocol::tokio::{sync::Mutex, time::sleep};
+use protocol::tokio::{self, sync::Mutex, time::sleep};
 use protocol::traits::{Context, Synchronization, SynchronizationAdapter};
 use protocol::types::{Block, Proof, Proposal, Receipt, SignedTransaction, U256};
 use protocol::{async_trait, ProtocolResult};
 
-use crate::status::{CurrentStatus, StatusAgent, METADATA_CONTROLER};
-use crate::util::digest_signed_transactions;
+use crate::status::{
+    notify_block_import, CurrentStatus, StatusAgent, CONSENSUS_STATUS, METADATA_CONTROLER,
+};
+use crate::util::{digest_signed_transactions, recover_senders, time_now};
 use crate::{engine::generate_receipts_and_logs, ConsensusError};
 
 const POLLING_BROADCAST: u64 = 2000;
@@ -360,6 +362,10 @@ impl<Adapter: SynchronizationAdapter> OverlordSynchronization<Adapter> {
             rich_block.block.clone(),
         )
         .await?;
+        notify_block_import(rich_block.block.clone());
+        CONSENSUS_STATUS
+            .write()
+            .commit(block.header.number, block.header.proposer, time_now());
 
         // If there are transactions in the trasnaction pool that have been on chain
         // after this execution, make sure they are cleaned up.
@@ -390,6 +396,12 @@ impl<Adapter: SynchronizationAdapter> OverlordSynchronization<Adapter> {
             txs.extend(remote_txs);
         }
 
+        // The senders from the remote are not trusted, the recovered ones have
+        // to match the signed transactions hash of the block.
+        let txs = tokio::task::spawn_blocking(move || recover_senders(txs))
+            .await
+            .map_err(|e| ConsensusError::Other(e.to_string()))??;
+
         Ok(RichBlock { block, txs })
     }
 
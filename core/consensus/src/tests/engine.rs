@@ -112,6 +112,10 @@ impl CommonConsensusAdapter for MockConsensusAdapter {
         Ok(())
     }
 
+    async fn save_metadata(&self, _ctx: Context, _metadata: Metadata) -> ProtocolResult<()> {
+        Ok(())
+    }
+
     async fn save_proof(&self, _ctx: Context, _proof: Proof) -> ProtocolResult<()> {
         Ok(())
     }
@@ -3,21 +3,45 @@ use std::convert::TryFrom;
 use std::error::Error;
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use overlord::Crypto;
+use overlord::types::{Node, Vote, VoteType};
+use overlord::{extract_voters, Crypto};
 use parking_lot::RwLock;
+use rayon::prelude::*;
 
-use crate::ConsensusError;
+use crate::{BlockProofField, ConsensusError};
 use common_crypto::{
     BlsPrivateKey, BlsPublicKey, BlsSignature, BlsSignatureVerify, HashValue, PrivateKey, Signature,
 };
+use protocol::codec::{hex_decode, ProtocolCodec};
 use protocol::traits::Context;
-use protocol::types::{Address, Bytes, Hash, Hasher, Hex, MerkleRoot, SignedTransaction};
+use protocol::types::{
+    Address, Block, Bytes, Hash, Hasher, Hex, MerkleRoot, Metadata, Proof, Proposal,
+    SignedTransaction,
+};
 use protocol::{ProtocolError, ProtocolResult};
 
+lazy_static::lazy_static! {
+    static ref SENDER_RECOVERY_POOL: rayon::ThreadPool = rayon::ThreadPoolBuilder::new()
+        .thread_name(|i| format!("sender-recovery-{}", i))
+        .build()
+        .expect("build sender recovery pool");
+}
+
 pub fn digest_signed_transactions(stxs: &[SignedTransaction]) -> Hash {
     Hasher::digest(rlp::encode_list(stxs))
 }
 
+/// Recover the sender and the public key of every transaction from its
+/// signature, in parallel on a pool of one worker per CPU. The transactions
+/// keep their order.
+pub fn recover_senders(stxs: Vec<SignedTransaction>) -> ProtocolResult<Vec<SignedTransaction>> {
+    SENDER_RECOVERY_POOL.install(|| {
+        stxs.into_par_iter()
+            .map(|stx| SignedTransaction::try_from(stx.transaction).map_err(Into::into))
+            .collect()
+    })
+}
+
 pub fn time_now() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -146,20 +170,142 @@ impl OverlordCrypto {
         pub_keys: Vec<BlsPublicKey>,
         signature: Bytes,
     ) -> ProtocolResult<()> {
-        let aggregate_key = BlsPublicKey::aggregate(pub_keys)
-            .map_err(|e| ConsensusError::CryptoErr(Box::new(e.into())))?;
-        let aggregated_signature = BlsSignature::try_from(signature.as_ref())
-            .map_err(|e| ConsensusError::CryptoErr(Box::new(e)))?;
-        let hash = HashValue::try_from(hash.as_ref())
-            .map_err(|_| ConsensusError::Other("failed to convert hash value".to_string()))?;
-
-        aggregated_signature
-            .verify(&hash, &aggregate_key, &self.common_ref)
-            .map_err(|e| ConsensusError::CryptoErr(Box::new(e)))?;
-        Ok(())
+        verify_aggregated_bls_signature(&hash, pub_keys, &signature, &self.common_ref)
     }
 }
 
+fn verify_aggregated_bls_signature(
+    hash: &[u8],
+    pub_keys: Vec<BlsPublicKey>,
+    signature: &[u8],
+    common_ref: &str,
+) -> ProtocolResult<()> {
+    let aggregate_key = BlsPublicKey::aggregate(pub_keys)
+        .map_err(|e| ConsensusError::CryptoErr(Box::new(e.into())))?;
+    let aggregated_signature =
+        BlsSignature::try_from(signature).map_err(|e| ConsensusError::CryptoErr(Box::new(e)))?;
+    let hash = HashValue::try_from(hash)
+        .map_err(|_| ConsensusError::Other("failed to convert hash value".to_string()))?;
+
+    aggregated_signature
+        .verify(&hash, &aggregate_key, &common_ref.into())
+        .map_err(|e| ConsensusError::CryptoErr(Box::new(e)))?;
+    Ok(())
+}
+
+/// The hash of the precommit vote aggregated into `proof`, which is what the
+/// validators sign.
+pub fn proof_vote_hash(proof: &Proof) -> Hash {
+    let vote = Vote {
+        height:     proof.number,
+        round:      proof.round,
+        vote_type:  VoteType::Precommit,
+        block_hash: Bytes::from(proof.block_hash.as_bytes().to_vec()),
+    };
+    Hasher::digest(Bytes::from(rlp::encode(&vote)))
+}
+
+/// Check that `proof` finalizes `block` without a consensus node, only with
+/// the `metadata` in effect at its height: the proof is for the proposal of
+/// the block and is signed by validators holding more than two thirds of the
+/// vote weight.
+pub fn verify_block_proof(block: &Block, proof: &Proof, metadata: &Metadata) -> ProtocolResult<()> {
+    let number = block.header.number;
+    if number != proof.number {
+        return Err(ConsensusError::VerifyProof(
+            number,
+            BlockProofField::HeightMismatch(number, proof.number),
+        )
+        .into());
+    }
+
+    if Hasher::digest(Proposal::from(block.clone()).encode()?) != proof.block_hash {
+        return Err(ConsensusError::VerifyProof(number, BlockProofField::HashMismatch).into());
+    }
+
+    let mut authority_list = metadata
+        .verifier_list
+        .iter()
+        .map(|v| Node {
+            address:        v.pub_key.as_bytes(),
+            propose_weight: v.propose_weight,
+            vote_weight:    v.vote_weight,
+        })
+        .collect::<Vec<_>>();
+    let signed_voters = extract_voters(&mut authority_list, &proof.bitmap).map_err(|_| {
+        log::error!("[consensus] extract_voters fails, bitmap error");
+        ConsensusError::VerifyProof(number, BlockProofField::BitMap)
+    })?;
+
+    let weight_map = authority_list
+        .iter()
+        .map(|node| (node.address.clone(), node.vote_weight))
+        .collect::<HashMap<Bytes, u32>>();
+    verify_proof_weight(number, &weight_map, &signed_voters)?;
+
+    let pub_keys = metadata
+        .verifier_list
+        .iter()
+        .filter(|v| signed_voters.contains(&v.pub_key.as_bytes()))
+        .map(|v| convert_hex_to_bls_pubkeys(v.bls_pub_key.clone()))
+        .collect::<ProtocolResult<Vec<_>>>()?;
+    let common_ref = String::from_utf8(hex_decode(&metadata.common_ref.as_string_trim0x())?)
+        .map_err(|e| ConsensusError::Other(e.to_string()))?;
+
+    verify_aggregated_bls_signature(
+        proof_vote_hash(proof).as_bytes(),
+        pub_keys,
+        &proof.signature,
+        &common_ref,
+    )
+    .map_err(|e| {
+        log::error!(
+            "[consensus] verify_proof_signature error, number {}, signed_voter: {:?}, error: {}",
+            number,
+            signed_voters,
+            e
+        );
+        ConsensusError::VerifyProof(number, BlockProofField::Signature).into()
+    })
+}
+
+/// Check that the signed voters are all validators and hold more than two
+/// thirds of the vote weight.
+pub fn verify_proof_weight(
+    block_number: u64,
+    weight_map: &HashMap<Bytes, u32>,
+    signed_voters: &[Bytes],
+) -> ProtocolResult<()> {
+    let total_validator_weight: u64 = weight_map.values().map(|w| u64::from(*w)).sum();
+
+    let mut accumulator = 0u64;
+    for signed_voter_address in signed_voters {
+        match weight_map.get(signed_voter_address) {
+            Some(weight) => accumulator += u64::from(*weight),
+            None => {
+                log::error!(
+                    "[consensus] verify_proof_weight, weight not found, signed_voter_address: {:?}",
+                    signed_voter_address
+                );
+                return Err(
+                    ConsensusError::VerifyProof(block_number, BlockProofField::Validator).into(),
+                );
+            }
+        }
+    }
+
+    if 3 * accumulator <= 2 * total_validator_weight {
+        log::error!(
+            "[consensus] verify_proof_weight, accumulator: {}, total: {}",
+            accumulator,
+            total_validator_weight
+        );
+
+        return Err(ConsensusError::VerifyProof(block_number, BlockProofField::Weight).into());
+    }
+    Ok(())
+}
+
 #[derive(Clone, Debug)]
 pub struct ExecuteInfo {
     pub ctx:          Context,
@@ -184,7 +330,77 @@ pub fn convert_hex_to_bls_pubkeys(hex: Hex) -> ProtocolResult<BlsPublicKey> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use common_crypto::{
+        Crypto as _, Secp256k1Recoverable, Secp256k1RecoverablePrivateKey, UncompressedPublicKey,
+    };
     use protocol::codec::hex_decode;
+    use protocol::types::{
+        public_to_address, Public, Transaction, TransactionAction, UnverifiedTransaction, H160,
+        U256,
+    };
+    use rand::rngs::OsRng;
+
+    fn mock_signed_tx(priv_key: &Secp256k1RecoverablePrivateKey, nonce: u64) -> SignedTransaction {
+        let mut utx = UnverifiedTransaction {
+            unsigned:  Transaction {
+                nonce:                    nonce.into(),
+                max_priority_fee_per_gas: U256::one(),
+                max_fee_per_gas:          U256::one(),
+                gas_limit:                U256::from(21000u64),
+                action:                   TransactionAction::Call(H160::repeat_byte(0x11)),
+                value:                    U256::one(),
+                data:                     Bytes::new(),
+                access_list:              vec![],
+            },
+            signature: None,
+            chain_id:  5,
+            hash:      Default::default(),
+        };
+        let signature = Secp256k1Recoverable::sign_message(
+            utx.signature_hash().as_bytes(),
+            &priv_key.to_bytes(),
+        )
+        .unwrap()
+        .to_bytes();
+        utx.signature = Some(signature.into());
+
+        let public = Public::from_slice(&priv_key.pub_key().to_uncompressed_bytes()[1..65]);
+        SignedTransaction {
+            transaction: utx.hash(),
+            sender:      public_to_address(&public),
+            public:      Some(public),
+        }
+    }
+
+    #[test]
+    fn test_recover_senders() {
+        let keys = (0..10)
+            .map(|_| Secp256k1RecoverablePrivateKey::generate(&mut OsRng))
+            .collect::<Vec<_>>();
+        let stxs = (0..100u64)
+            .map(|i| mock_signed_tx(&keys[i as usize % keys.len()], i))
+            .collect::<Vec<_>>();
+
+        let serial = stxs
+            .iter()
+            .map(|stx| SignedTransaction::try_from(stx.transaction.clone()).unwrap())
+            .collect::<Vec<_>>();
+
+        // The senders of a remote block are not trusted, start from blank ones.
+        let blank = stxs
+            .iter()
+            .cloned()
+            .map(|mut stx| {
+                stx.sender = H160::zero();
+                stx.public = None;
+                stx
+            })
+            .collect::<Vec<_>>();
+        let parallel = recover_senders(blank).unwrap();
+
+        assert_eq!(parallel, serial);
+        assert_eq!(parallel, stxs);
+    }
 
     #[test]
     fn test_blst() {
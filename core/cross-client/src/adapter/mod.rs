@@ -289,7 +289,7 @@ where
         let tx = Transaction {
             nonce:                    self.get_nonce(&addr),
             max_priority_fee_per_gas: TWO_THOUSAND.into(),
-            gas_price:                TWO_THOUSAND.into(),
+            max_fee_per_gas:          TWO_THOUSAND.into(),
             gas_limit:                100000u64.into(),
             action:                   TransactionAction::Call(**ASSET_CONTRACT_ADDRESS.load()),
             data:                     Bytes::from(input),
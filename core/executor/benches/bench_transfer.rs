@@ -97,7 +97,7 @@ fn mock_transaction(nonce: u64) -> SignedTransaction {
     let tx = Transaction {
         nonce:                    nonce.into(),
         max_priority_fee_per_gas: 1u64.into(),
-        gas_price:                85u64.into(),
+        max_fee_per_gas:          85u64.into(),
         gas_limit:                1000000u64.into(),
         value:                    10u64.into(),
         data:                     Default::default(),
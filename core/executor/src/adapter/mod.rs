@@ -4,13 +4,15 @@ mod trie_db;
 pub use trie::MPTTrie;
 pub use trie_db::RocksTrieDB;
 
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use evm::backend::{Apply, Basic};
 
 use protocol::traits::{ApplyBackend, Backend, Context, ExecutorAdapter, Storage};
 use protocol::types::{
-    Account, Bytes, ExecutorContext, Hasher, Log, MerkleRoot, H160, H256, NIL_DATA, RLP_NULL, U256,
+    Account, AccountProof, Bytes, ExecutorContext, Hasher, Log, MerkleRoot, StorageProof, H160,
+    H256, NIL_DATA, RLP_NULL, U256,
 };
 use protocol::{codec::ProtocolCodec, ProtocolResult};
 
@@ -64,6 +66,29 @@ where
     fn get(&self, key: &[u8]) -> Option<Bytes> {
         self.trie.get(key).ok().flatten()
     }
+
+    fn storage_slots(&self, address: H160, start: H256, limit: usize) -> Vec<(H256, H256)> {
+        let storage_root = match self.get(address.as_bytes()).map(Account::decode) {
+            Some(Ok(account)) => account.storage_root,
+            _ => return Vec::new(),
+        };
+        if storage_root == RLP_NULL {
+            return Vec::new();
+        }
+
+        let trie = match MPTTrie::from_root(storage_root, Arc::clone(&self.db)) {
+            Ok(trie) => trie,
+            Err(_) => return Vec::new(),
+        };
+
+        // The storage trie is keyed by the slot itself rather than its hash, so
+        // it is walked in slot order.
+        trie.iter_from(start.as_bytes())
+            .map(|(k, v)| (H256::from_slice(&k), H256::from_slice(&v)))
+            .filter(|(_, v)| !v.is_zero())
+            .take(limit)
+            .collect()
+    }
 }
 
 impl<S, DB> Backend for EVMExecutorAdapter<S, DB>
@@ -254,6 +279,63 @@ where
         self.trie.root
     }
 
+    /// The proofs of the account and of its `keys` storage slots. The storage
+    /// trie is opened once for all the keys, and a repeated key is proved
+    /// once.
+    pub fn account_proof(&self, address: H160, keys: &[H256]) -> ProtocolResult<AccountProof> {
+        let account_proof = self.trie.get_proof(address.as_bytes())?;
+        let account = match self.trie.get(address.as_bytes())? {
+            Some(raw) => Account::decode(raw)?,
+            None => Account {
+                nonce:        U256::zero(),
+                balance:      U256::zero(),
+                storage_root: RLP_NULL,
+                code_hash:    NIL_DATA,
+            },
+        };
+
+        let storage_trie = if account.storage_root == RLP_NULL {
+            None
+        } else {
+            Some(MPTTrie::from_root(
+                account.storage_root,
+                Arc::clone(&self.db),
+            )?)
+        };
+
+        let mut proved: HashMap<H256, StorageProof> = HashMap::new();
+        let mut storage_proof = Vec::with_capacity(keys.len());
+        for key in keys.iter() {
+            if let Some(proof) = proved.get(key) {
+                storage_proof.push(proof.clone());
+                continue;
+            }
+
+            let proof = match storage_trie.as_ref() {
+                Some(trie) => StorageProof {
+                    key:   *key,
+                    value: trie
+                        .get(key.as_bytes())?
+                        .map(|value| H256::from_slice(&value))
+                        .unwrap_or_default(),
+                    proof: trie.get_proof(key.as_bytes())?,
+                },
+                None => StorageProof {
+                    key: *key,
+                    ..Default::default()
+                },
+            };
+            proved.insert(*key, proof.clone());
+            storage_proof.push(proof);
+        }
+
+        Ok(AccountProof {
+            account,
+            account_proof,
+            storage_proof,
+        })
+    }
+
     fn apply<I: IntoIterator<Item = (H256, H256)>>(
         &mut self,
         address: H160,
@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use cita_trie::{PatriciaTrie, Trie, TrieError, DB as TrieDB};
 use hasher::HasherKeccak;
+use rlp::{DecoderError, Rlp};
 
 use protocol::types::{Bytes, Hash, MerkleRoot};
 use protocol::{Display, From, ProtocolError, ProtocolErrorKind, ProtocolResult};
@@ -13,23 +14,25 @@ lazy_static::lazy_static! {
 pub struct MPTTrie<DB: TrieDB> {
     pub root: MerkleRoot,
     trie:     PatriciaTrie<DB, HasherKeccak>,
+    db:       Arc<DB>,
 }
 
 impl<DB: TrieDB> MPTTrie<DB> {
     pub fn new(db: Arc<DB>) -> Self {
-        let trie = PatriciaTrie::new(db, Arc::clone(&HASHER_INST));
+        let trie = PatriciaTrie::new(Arc::clone(&db), Arc::clone(&HASHER_INST));
 
         Self {
             root: Hash::default(),
             trie,
+            db,
         }
     }
 
     pub fn from_root(root: MerkleRoot, db: Arc<DB>) -> ProtocolResult<Self> {
-        let trie = PatriciaTrie::from(db, Arc::clone(&HASHER_INST), root.as_bytes())
+        let trie = PatriciaTrie::from(Arc::clone(&db), Arc::clone(&HASHER_INST), root.as_bytes())
             .map_err(MPTTrieError::from)?;
 
-        Ok(Self { root, trie })
+        Ok(Self { root, trie, db })
     }
 
     pub fn get(&self, key: &[u8]) -> ProtocolResult<Option<Bytes>> {
@@ -40,10 +43,38 @@ impl<DB: TrieDB> MPTTrie<DB> {
             .map(Bytes::from))
     }
 
+    /// The nodes on the path from the root to `key`, which also prove the
+    /// absence of a missing key.
+    pub fn get_proof(&self, key: &[u8]) -> ProtocolResult<Vec<Bytes>> {
+        Ok(self
+            .trie
+            .get_proof(key)
+            .map_err(MPTTrieError::from)?
+            .into_iter()
+            .map(Bytes::from)
+            .collect())
+    }
+
     pub fn contains(&self, key: &[u8]) -> ProtocolResult<bool> {
         Ok(self.trie.contains(key).map_err(MPTTrieError::from)?)
     }
 
+    /// Iterate over the entries in key order.
+    pub fn iter(&self) -> impl Iterator<Item = (Vec<u8>, Vec<u8>)> + '_ {
+        self.trie.iter()
+    }
+
+    /// Iterate over the entries from `start` on in key order. Unlike `iter`
+    /// the nodes of the keys before `start` are never read, but only the
+    /// committed `root` is walked.
+    pub fn iter_from(&self, start: &[u8]) -> SeekIter<DB> {
+        SeekIter {
+            db:    Arc::clone(&self.db),
+            start: to_nibbles(start),
+            stack: vec![(NodeRef::Hash(self.root.as_bytes().to_vec()), Vec::new())],
+        }
+    }
+
     pub fn insert(&mut self, key: &[u8], value: &[u8]) -> ProtocolResult<()> {
         self.trie
             .insert(key.to_vec(), value.to_vec())
@@ -67,6 +98,115 @@ impl<DB: TrieDB> MPTTrie<DB> {
     }
 }
 
+enum NodeRef {
+    Hash(Vec<u8>),
+    Inline(Vec<u8>),
+}
+
+/// The iterator of `MPTTrie::iter_from`, a depth first walk skipping the
+/// subtrees whose keys are all before the start key.
+pub struct SeekIter<DB: TrieDB> {
+    db:    Arc<DB>,
+    start: Vec<u8>,
+    stack: Vec<(NodeRef, Vec<u8>)>,
+}
+
+impl<DB: TrieDB> SeekIter<DB> {
+    /// Whether the subtree under the nibble `path` has a key not before the
+    /// start key.
+    fn reaches_start(&self, path: &[u8]) -> bool {
+        let len = path.len().min(self.start.len());
+        path >= &self.start[..len]
+    }
+
+    fn push(&mut self, item: Rlp, path: Vec<u8>) -> Result<(), DecoderError> {
+        if item.is_empty() || !self.reaches_start(&path) {
+            return Ok(());
+        }
+
+        let node = if item.is_list() {
+            NodeRef::Inline(item.as_raw().to_vec())
+        } else {
+            NodeRef::Hash(item.data()?.to_vec())
+        };
+        self.stack.push((node, path));
+        Ok(())
+    }
+
+    /// Expand the node on the nibble `path`, returning the nibble key and
+    /// the value it holds if any.
+    fn expand(
+        &mut self,
+        node: &[u8],
+        path: Vec<u8>,
+    ) -> Result<Option<(Vec<u8>, Vec<u8>)>, DecoderError> {
+        let rlp = Rlp::new(node);
+        match rlp.item_count()? {
+            // A branch, its value comes before the keys of its children.
+            17 => {
+                for nibble in (0..16u8).rev() {
+                    let mut child = path.clone();
+                    child.push(nibble);
+                    self.push(rlp.at(nibble as usize)?, child)?;
+                }
+                let value = rlp.at(16)?.data()?;
+                Ok((!value.is_empty() && path >= self.start).then(|| (path, value.to_vec())))
+            }
+            2 => {
+                let (nibbles, is_leaf) = from_hex_prefix(rlp.at(0)?.data()?)?;
+                let mut child = path;
+                child.extend_from_slice(&nibbles);
+                if !is_leaf {
+                    self.push(rlp.at(1)?, child)?;
+                    return Ok(None);
+                }
+
+                let value = rlp.at(1)?.data()?;
+                Ok((child >= self.start).then(|| (child, value.to_vec())))
+            }
+            _ => Err(DecoderError::RlpIncorrectListLen),
+        }
+    }
+}
+
+impl<DB: TrieDB> Iterator for SeekIter<DB> {
+    type Item = (Vec<u8>, Vec<u8>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, path)) = self.stack.pop() {
+            let node = match node {
+                NodeRef::Inline(node) => node,
+                NodeRef::Hash(hash) => self.db.get(&hash).ok().flatten()?,
+            };
+
+            if let Some((key, value)) = self.expand(&node, path).ok()? {
+                return Some((from_nibbles(&key), value));
+            }
+        }
+        None
+    }
+}
+
+fn to_nibbles(key: &[u8]) -> Vec<u8> {
+    key.iter().flat_map(|b| [b >> 4, b & 0x0f]).collect()
+}
+
+fn from_nibbles(nibbles: &[u8]) -> Vec<u8> {
+    nibbles
+        .chunks(2)
+        .map(|c| (c[0] << 4) | c.get(1).copied().unwrap_or(0))
+        .collect()
+}
+
+/// Decode the hex prefix encoded path of a leaf or extension node.
+fn from_hex_prefix(encoded: &[u8]) -> Result<(Vec<u8>, bool), DecoderError> {
+    let flag = encoded.first().ok_or(DecoderError::RlpIsTooShort)? >> 4;
+    let mut nibbles = to_nibbles(encoded);
+    // An even path is padded with a zero nibble after the flag.
+    nibbles.drain(..if flag & 1 == 1 { 1 } else { 2 });
+    Ok((nibbles, flag >= 2))
+}
+
 #[derive(Debug, Display, From)]
 pub enum MPTTrieError {
     #[display(fmt = "{:?}", _0)]
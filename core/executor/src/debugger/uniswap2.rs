@@ -37,7 +37,7 @@ fn construct_tx(action: TransactionAction, value: U256, data: Vec<u8>) -> Transa
     Transaction {
         nonce: U256::default(),
         max_priority_fee_per_gas: U256::default(),
-        gas_price: U256::default(),
+        max_fee_per_gas: U256::default(),
         gas_limit: 10000000000u64.into(),
         action,
         value,
@@ -5,57 +5,281 @@ pub mod adapter;
 mod debugger;
 #[cfg(test)]
 mod tests;
+mod tracer;
 
 use std::collections::BTreeMap;
+use std::sync::Arc;
 
+use cita_trie::MemoryDB;
+use evm::backend::Apply;
 use evm::executor::stack::{MemoryStackState, StackExecutor, StackSubstateMetadata};
+use rlp::Encodable;
 
 use common_merkle::Merkle;
 use protocol::codec::ProtocolCodec;
 use protocol::traits::{ApplyBackend, Backend, Executor, ExecutorAdapter as Adapter};
 use protocol::types::{
-    Account, Config, ExecResp, Hasher, SignedTransaction, TransactionAction, TxResp, H160, H256,
+    exit_status, logs_bloom, AccessList, AccessSet, Account, BlockNumber, BundleCall, Config,
+    ConsensusReceipt, ExecResp, ExitError, ExitReason, Hasher, MerkleRoot, OverlayReplay,
+    PrestateAccount, SignedTransaction, StorageRange, TransactionAction, TxResp, H160, H256,
     NIL_DATA, RLP_NULL, U256,
 };
 
 pub use crate::adapter::{EVMExecutorAdapter, MPTTrie, RocksTrieDB};
+pub use crate::tracer::PrestateTracer;
 
-#[derive(Default)]
-pub struct EvmExecutor;
+/// The root of the Ethereum receipts trie, keyed by the RLP encoded index of
+/// the receipt in the block.
+pub fn receipts_root(receipts: &[ConsensusReceipt]) -> MerkleRoot {
+    let mut trie = MPTTrie::new(Arc::new(MemoryDB::new(false)));
+    for (idx, receipt) in receipts.iter().enumerate() {
+        trie.insert(&rlp::encode(&idx), &receipt.rlp_bytes())
+            .expect("insert into memory trie");
+    }
+    trie.commit().expect("commit memory trie")
+}
+
+/// The receipts root of the blocks before the receipts trie fork, the merkle
+/// root of the hashes of the return data.
+pub fn legacy_receipts_root(tx_resp: &[TxResp]) -> MerkleRoot {
+    let hashes = tx_resp.iter().map(|r| Hasher::digest(&r.ret)).collect();
+    Merkle::from_hashes(hashes)
+        .get_root_hash()
+        .unwrap_or_default()
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct EvmExecutor {
+    stack_limit:        usize,
+    memory_limit:       usize,
+    receipts_trie_fork: Option<BlockNumber>,
+}
+
+impl Default for EvmExecutor {
+    fn default() -> Self {
+        let config = Config::london();
+        EvmExecutor {
+            stack_limit:        config.stack_limit,
+            memory_limit:       config.memory_limit,
+            receipts_trie_fork: None,
+        }
+    }
+}
 
 impl EvmExecutor {
     pub fn new() -> Self {
         EvmExecutor::default()
     }
+
+    /// An executor whose EVM stack holds at most `stack_limit` items and
+    /// whose memory grows to at most `memory_limit` bytes.
+    pub fn with_limits(stack_limit: usize, memory_limit: usize) -> Self {
+        EvmExecutor {
+            stack_limit,
+            memory_limit,
+            ..Default::default()
+        }
+    }
+
+    /// Commit the receipts of the blocks from `fork` on to an Ethereum
+    /// receipts trie, and the ones before to [`legacy_receipts_root`]. All
+    /// the blocks use the legacy root when it is `None`.
+    pub fn with_receipts_trie_fork(mut self, fork: Option<BlockNumber>) -> Self {
+        self.receipts_trie_fork = fork;
+        self
+    }
+
+    fn config(&self) -> Config {
+        let mut config = Config::london();
+        config.stack_limit = self.stack_limit;
+        config.memory_limit = self.memory_limit;
+        config
+    }
 }
 
 impl Executor for EvmExecutor {
     // Used for query data API, this function will not modify the world state.
-    fn call<B: Backend>(&self, backend: &mut B, addr: H160, data: Vec<u8>) -> TxResp {
-        let config = Config::london();
+    fn call<B: Backend>(
+        &self,
+        backend: &mut B,
+        from: Option<H160>,
+        addr: H160,
+        value: U256,
+        gas_limit: u64,
+        data: Vec<u8>,
+        access_list: AccessList,
+    ) -> TxResp {
+        let config = self.config();
         let metadata = StackSubstateMetadata::new(u64::MAX, &config);
         let state = MemoryStackState::new(metadata, backend);
         let precompiles = BTreeMap::new();
         let mut executor = StackExecutor::new_with_precompiles(state, &config, &precompiles);
+        // The value is transferred like in a transaction, so a call with value
+        // to a non-payable function reverts as it would on chain.
         let (exit_reason, ret) = executor.transact_call(
-            Default::default(),
+            from.unwrap_or_default(),
             addr,
-            U256::default(),
+            value,
             data,
-            u64::MAX,
-            Vec::new(),
+            gas_limit,
+            access_list
+                .into_iter()
+                .map(|x| (x.address, x.slots))
+                .collect(),
         );
+        // The refund is deducted from the gas used like in a receipt, capped
+        // at a fifth of it per EIP-3529.
+        let remain_gas = executor.gas();
+        let gas_used = executor.used_gas();
+        // The logs are only collected, the state changes are dropped.
+        let (_, logs) = executor.into_state().deconstruct();
 
         TxResp {
             exit_reason,
             ret,
-            remain_gas: 0,
-            gas_used: 0,
-            logs: vec![],
+            remain_gas,
+            gas_used,
+            logs: logs.into_iter().collect(),
             code_address: None,
         }
     }
 
+    fn access_set<B: Backend>(
+        &self,
+        backend: &B,
+        from: Option<H160>,
+        addr: H160,
+        value: U256,
+        gas_limit: u64,
+        data: Vec<u8>,
+        access_list: AccessList,
+    ) -> AccessSet {
+        let mut tracer = PrestateTracer::new(backend);
+        tracer.start_recording();
+
+        let caller = from.unwrap_or_default();
+        let config = self.config();
+        let metadata = StackSubstateMetadata::new(u64::MAX, &config);
+        let state = MemoryStackState::new(metadata, &tracer);
+        let precompiles = BTreeMap::new();
+        let mut executor = StackExecutor::new_with_precompiles(state, &config, &precompiles);
+        let _ = executor.transact_call(
+            caller,
+            addr,
+            value,
+            data,
+            gas_limit,
+            access_list
+                .into_iter()
+                .map(|x| (x.address, x.slots))
+                .collect(),
+        );
+        let applies = collect_applies(executor.into_state().deconstruct().0);
+
+        // Every account and slot the call loads is in the prestate with the
+        // value it had before the call.
+        let prestate = tracer.into_prestate();
+        let mut set = AccessSet::default();
+        for apply in applies.into_iter() {
+            match apply {
+                Apply::Modify {
+                    address,
+                    basic,
+                    code,
+                    storage,
+                    reset_storage,
+                } => {
+                    // The nonce of the caller is bumped by every call, it is
+                    // only written if its balance changes.
+                    let before = prestate.get(&address);
+                    let mut written = code.is_some()
+                        || reset_storage
+                        || before.map_or(true, |account| {
+                            account.balance != basic.balance
+                                || (address != caller && account.nonce != basic.nonce)
+                        });
+
+                    for (index, value) in storage.into_iter() {
+                        let old = before
+                            .and_then(|account| account.storage.get(&index))
+                            .copied()
+                            .unwrap_or_default();
+                        if value != old {
+                            set.writes_slots.entry(address).or_default().insert(index);
+                            written = true;
+                        }
+                    }
+
+                    if written {
+                        set.writes_accounts.insert(address);
+                    }
+                }
+                Apply::Delete { address } => {
+                    set.writes_accounts.insert(address);
+                }
+            }
+        }
+
+        for (address, account) in prestate.into_iter() {
+            if !set.writes_accounts.contains(&address) {
+                set.reads_accounts.insert(address);
+            }
+
+            let written = set.writes_slots.get(&address);
+            for index in account.storage.into_keys() {
+                if !written.map_or(false, |slots| slots.contains(&index)) {
+                    set.reads_slots.entry(address).or_default().insert(index);
+                }
+            }
+        }
+        set
+    }
+
+    fn call_bundle<B: Backend>(&self, backend: &B, calls: Vec<BundleCall>) -> Vec<TxResp> {
+        let mut tracer = PrestateTracer::new(backend);
+        let config = self.config();
+        let mut resps = Vec::with_capacity(calls.len());
+
+        for call in calls.into_iter() {
+            let metadata = StackSubstateMetadata::new(u64::MAX, &config);
+            let state = MemoryStackState::new(metadata, &tracer);
+            let precompiles = BTreeMap::new();
+            let mut executor = StackExecutor::new_with_precompiles(state, &config, &precompiles);
+            let (exit_reason, ret) = executor.transact_call(
+                call.from.unwrap_or_default(),
+                call.to,
+                call.value,
+                call.data,
+                call.gas_limit,
+                call.access_list
+                    .into_iter()
+                    .map(|x| (x.address, x.slots))
+                    .collect(),
+            );
+            let remain_gas = executor.gas();
+            let gas_used = executor.used_gas();
+            let (values, logs) = executor.into_state().deconstruct();
+            let logs = logs.into_iter().collect::<Vec<_>>();
+            let applies = collect_applies(values);
+
+            // The next calls see the state left by a successful one.
+            if exit_reason.is_succeed() {
+                tracer.apply(applies, Vec::new(), true);
+            }
+
+            resps.push(TxResp {
+                exit_reason,
+                ret,
+                remain_gas,
+                gas_used,
+                logs,
+                code_address: None,
+            });
+        }
+
+        resps
+    }
+
     // Function execute returns exit_reason, ret_data and remain_gas.
     fn exec<B: Backend + ApplyBackend + Adapter>(
         &self,
@@ -64,26 +288,39 @@ impl Executor for EvmExecutor {
     ) -> ExecResp {
         let txs_len = txs.len();
         let mut res = Vec::with_capacity(txs_len);
-        let mut hashes = Vec::with_capacity(txs_len);
+        let mut receipts = Vec::with_capacity(txs_len);
         let mut gas_use = 0u64;
+        let number = backend.block_number().low_u64();
+        let receipts_trie = self.receipts_trie_fork.map_or(false, |fork| number >= fork);
 
         txs.into_iter().for_each(|tx| {
-            backend.set_gas_price(tx.transaction.unsigned.gas_price);
+            backend.set_gas_price(tx.transaction.unsigned.max_fee_per_gas);
             let mut r = self.inner_exec(backend, tx);
             r.logs = backend.get_logs();
             gas_use += r.gas_used;
 
-            hashes.push(Hasher::digest(&r.ret));
+            if receipts_trie {
+                receipts.push(ConsensusReceipt {
+                    status:              exit_status(&r.exit_reason),
+                    cumulative_gas_used: U256::from(gas_use),
+                    logs_bloom:          logs_bloom(&r.logs),
+                    logs:                r.logs.clone(),
+                });
+            }
             res.push(r);
         });
 
+        let receipt_root = if receipts_trie {
+            receipts_root(&receipts)
+        } else {
+            legacy_receipts_root(&res)
+        };
+
         ExecResp {
-            state_root:   backend.state_root(),
-            receipt_root: Merkle::from_hashes(hashes)
-                .get_root_hash()
-                .unwrap_or_default(),
-            gas_used:     gas_use,
-            tx_resp:      res,
+            state_root: backend.state_root(),
+            receipt_root,
+            gas_used: gas_use,
+            tx_resp: res,
         }
     }
 
@@ -98,6 +335,102 @@ impl Executor for EvmExecutor {
             },
         }
     }
+
+    fn trace_prestate<B: Backend>(
+        &self,
+        backend: &B,
+        preceding: Vec<SignedTransaction>,
+        tx: SignedTransaction,
+    ) -> BTreeMap<H160, PrestateAccount> {
+        // Replayed with the stack and memory limits of the executor like
+        // `call`, see `with_limits`.
+        let mut tracer = PrestateTracer::new(backend);
+        for stx in preceding.into_iter() {
+            self.inner_exec(&mut tracer, stx);
+        }
+
+        tracer.start_recording();
+        self.inner_exec(&mut tracer, tx);
+        tracer.into_prestate()
+    }
+
+    fn storage_range<B: Backend + Adapter>(
+        &self,
+        backend: &B,
+        preceding: Vec<SignedTransaction>,
+        address: H160,
+        start: H256,
+        max_result: usize,
+    ) -> StorageRange {
+        let mut tracer = PrestateTracer::new(backend);
+        for stx in preceding.into_iter() {
+            self.inner_exec(&mut tracer, stx);
+        }
+
+        // A slot written by the preceding transactions hides at most one slot
+        // of the backend, fetching that many more slots from the backend keeps
+        // the first `max_result + 1` merged slots complete.
+        let limit = max_result.saturating_add(1);
+        let slots: BTreeMap<H256, H256> = match tracer.changed_storage(address) {
+            Some((changed, true)) => changed.range(start..).map(|(k, v)| (*k, *v)).collect(),
+            Some((changed, false)) => {
+                let mut slots = backend
+                    .storage_slots(address, start, limit.saturating_add(changed.len()))
+                    .into_iter()
+                    .collect::<BTreeMap<_, _>>();
+                slots.extend(changed.range(start..).map(|(k, v)| (*k, *v)));
+                slots
+            }
+            None => backend
+                .storage_slots(address, start, limit)
+                .into_iter()
+                .collect(),
+        };
+
+        let mut slots = slots.into_iter().filter(|(_, v)| !v.is_zero());
+        let storage = slots.by_ref().take(max_result).collect();
+        StorageRange {
+            storage,
+            next_key: slots.next().map(|(k, _)| k),
+        }
+    }
+
+    fn replay_account<B: Backend>(
+        &self,
+        backend: &B,
+        preceding: Vec<SignedTransaction>,
+        address: H160,
+        slots: &[H256],
+    ) -> (Vec<u8>, Vec<H256>) {
+        let mut tracer = PrestateTracer::new(backend);
+        for stx in preceding.into_iter() {
+            self.inner_exec(&mut tracer, stx);
+        }
+
+        let storage = slots
+            .iter()
+            .map(|slot| tracer.storage(address, *slot))
+            .collect();
+        (tracer.code(address), storage)
+    }
+
+    fn replay_overlay<B: Backend>(
+        &self,
+        backend: &B,
+        txs: Vec<SignedTransaction>,
+    ) -> OverlayReplay {
+        let mut tracer = PrestateTracer::new(backend);
+        let mut logs = Vec::with_capacity(txs.len());
+        for stx in txs.into_iter() {
+            self.inner_exec(&mut tracer, stx);
+            logs.push(tracer.take_logs());
+        }
+
+        OverlayReplay {
+            overlay: tracer.into_overlay(),
+            logs,
+        }
+    }
 }
 
 impl EvmExecutor {
@@ -107,7 +440,7 @@ impl EvmExecutor {
         tx: SignedTransaction,
     ) -> TxResp {
         let old_nonce = backend.basic(tx.sender).nonce;
-        let config = Config::london();
+        let config = self.config();
         let metadata = StackSubstateMetadata::new(u64::MAX, &config);
         let state = MemoryStackState::new(metadata, backend);
         let precompiles = BTreeMap::new();
@@ -169,6 +502,91 @@ impl EvmExecutor {
     }
 }
 
+/// Collect the changes of an execution so that they can be applied to
+/// another backend, with the storage of every account as a list of slots.
+fn collect_applies<A, I>(values: A) -> Vec<Apply<Vec<(H256, H256)>>>
+where
+    A: IntoIterator<Item = Apply<I>>,
+    I: IntoIterator<Item = (H256, H256)>,
+{
+    values
+        .into_iter()
+        .map(|apply| match apply {
+            Apply::Modify {
+                address,
+                basic,
+                code,
+                storage,
+                reset_storage,
+            } => Apply::Modify {
+                address,
+                basic,
+                code,
+                storage: storage.into_iter().collect(),
+                reset_storage,
+            },
+            Apply::Delete { address } => Apply::Delete { address },
+        })
+        .collect()
+}
+
+/// Describe why an execution failed, or None if it succeeded. The messages
+/// follow the ones of geth so that clients can tell the failures apart.
+pub fn exit_error_message(reason: &ExitReason) -> Option<String> {
+    let message = match reason {
+        ExitReason::Succeed(_) => return None,
+        ExitReason::Revert(_) => "execution reverted".to_string(),
+        ExitReason::Error(e) => match e {
+            ExitError::StackOverflow => "stack limit reached".to_string(),
+            ExitError::StackUnderflow => "stack underflow".to_string(),
+            ExitError::OutOfGas => "out of gas".to_string(),
+            ExitError::DesignatedInvalid => "invalid opcode: INVALID".to_string(),
+            ExitError::InvalidJump => "invalid jump destination".to_string(),
+            ExitError::CallTooDeep => "max call depth exceeded".to_string(),
+            ExitError::OutOfFund => "insufficient balance for transfer".to_string(),
+            ExitError::Other(msg) => msg.to_string(),
+            e => format!("execution error: {:?}", e),
+        },
+        ExitReason::Fatal(e) => format!("fatal error: {:?}", e),
+    };
+
+    Some(message)
+}
+
+/// Like [`exit_error_message`], but a revert carrying an `Error(string)`
+/// reason is reported as `execution reverted: <reason>`.
+pub fn call_error_message(reason: &ExitReason, ret: &[u8]) -> Option<String> {
+    let message = exit_error_message(reason)?;
+    match (reason, decode_revert_reason(ret)) {
+        (ExitReason::Revert(_), Some(revert_reason)) => {
+            Some(format!("{}: {}", message, revert_reason))
+        }
+        _ => Some(message),
+    }
+}
+
+/// Decode the ABI encoded `Error(string)` returned by a revert.
+pub fn decode_revert_reason(ret: &[u8]) -> Option<String> {
+    const ERROR_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+    if ret.len() < 4 + 64 || ret[..4] != ERROR_SELECTOR {
+        return None;
+    }
+    let body = &ret[4..];
+    let offset = U256::from_big_endian(&body[..32]);
+    if offset > U256::from(body.len()) {
+        return None;
+    }
+    let offset = offset.as_usize();
+    let len_end = offset.checked_add(32).filter(|end| *end <= body.len())?;
+    let len = U256::from_big_endian(&body[offset..len_end]);
+    if len > U256::from(body.len() - len_end) {
+        return None;
+    }
+
+    String::from_utf8(body[len_end..len_end + len.as_usize()].to_vec()).ok()
+}
+
 pub fn code_address(sender: &H160, nonce: &U256) -> H256 {
     let mut stream = rlp::RlpStream::new_list(2);
     stream.append(sender);
@@ -1,15 +1,25 @@
 use std::collections::BTreeMap;
 use std::str::FromStr;
+use std::sync::Arc;
 
-use evm::backend::{MemoryAccount, MemoryBackend, MemoryVicinity};
+use cita_trie::MemoryDB;
+use evm::backend::{
+    Apply, ApplyBackend, Backend, Basic, MemoryAccount, MemoryBackend, MemoryVicinity,
+};
 
+use common_merkle::Merkle;
+use core_storage::{adapter::memory::MemoryAdapter, ImplStorage};
 use protocol::types::{
-    ExitReason, ExitSucceed, Public, SignatureComponents, SignedTransaction, Transaction,
-    TransactionAction, UnverifiedTransaction, H160, H256, U256,
+    logs_bloom, AccessListItem, BundleCall, ConsensusReceipt, ExecutorContext, ExitError,
+    ExitReason, ExitSucceed, Hasher, Log, Public, SignatureComponents, SignedTransaction,
+    Transaction, TransactionAction, UnverifiedTransaction, H160, H256, RLP_NULL, U256,
 };
 use protocol::{codec::hex_decode, traits::Executor};
 
-use crate::EvmExecutor;
+use crate::{
+    call_error_message, exit_error_message, legacy_receipts_root, receipts_root,
+    EVMExecutorAdapter, EvmExecutor, MPTTrie,
+};
 
 fn gen_vicinity() -> MemoryVicinity {
     MemoryVicinity {
@@ -32,7 +42,7 @@ fn gen_tx(sender: H160, addr: H160, data: Vec<u8>) -> SignedTransaction {
             unsigned:  Transaction {
                 nonce:                    U256::default(),
                 max_priority_fee_per_gas: U256::default(),
-                gas_price:                U256::default(),
+                max_fee_per_gas:          U256::default(),
                 gas_limit:                U256::from_str("0x1000000000").unwrap(),
                 action:                   TransactionAction::Call(addr),
                 value:                    U256::default(),
@@ -168,8 +178,12 @@ fn test_simplestorage() {
     // let's call SimpleStorage.get() by call
     let r = executor.call(
         &mut backend,
+        None,
         H160::from_str("0xc15d2ba57d126e6603240e89437efd419ce329d2").unwrap(),
+        U256::zero(),
+        u64::MAX,
         hex_decode("6d4ce63c").unwrap(),
+        Vec::new(),
     );
     assert_eq!(r.exit_reason, ExitReason::Succeed(ExitSucceed::Returned));
     assert_eq!(r.ret, vec![
@@ -177,3 +191,871 @@ fn test_simplestorage() {
         0, 42
     ]);
 }
+
+#[test]
+fn test_call_gas_used_with_capped_refund() {
+    let contract = H160::from_str("0x1000000000000000000000000000000000000000").unwrap();
+    let mut storage = BTreeMap::new();
+    for slot in 0..3u64 {
+        storage.insert(H256::from_low_u64_be(slot), H256::from_low_u64_be(1));
+    }
+
+    let mut state = BTreeMap::new();
+    state.insert(contract, MemoryAccount {
+        nonce: U256::one(),
+        balance: U256::zero(),
+        storage,
+        // sstore(0, 0) sstore(1, 0) sstore(2, 0)
+        code: hex_decode("600060005560006001556000600255").unwrap(),
+    });
+
+    let vicinity = gen_vicinity();
+    let mut backend = MemoryBackend::new(&vicinity, state);
+    let r = EvmExecutor::new().call(
+        &mut backend,
+        None,
+        contract,
+        U256::zero(),
+        u64::MAX,
+        Vec::new(),
+        Vec::new(),
+    );
+    assert_eq!(r.exit_reason, ExitReason::Succeed(ExitSucceed::Stopped));
+
+    // 21000 intrinsic + 6 PUSH1 + 3 cold SSTORE resets, clearing the slots
+    // refunds 3 * 4800 which is capped at a fifth of the gas used.
+    let total = 21000 + 6 * 3 + 3 * (2100 + 2900);
+    assert_eq!(r.gas_used, total - total / 5);
+    assert!(r.gas_used > total - 3 * 4800);
+}
+
+#[test]
+fn test_call_access_list_warms_slot() {
+    let contract = H160::from_str("0x1000000000000000000000000000000000000000").unwrap();
+    let mut state = BTreeMap::new();
+    state.insert(contract, MemoryAccount {
+        nonce:   U256::one(),
+        balance: U256::zero(),
+        storage: BTreeMap::new(),
+        // sload(0)
+        code:    hex_decode("600054").unwrap(),
+    });
+
+    let vicinity = gen_vicinity();
+    let mut backend = MemoryBackend::new(&vicinity, state);
+    let cold = EvmExecutor::new().call(
+        &mut backend,
+        None,
+        contract,
+        U256::zero(),
+        u64::MAX,
+        Vec::new(),
+        Vec::new(),
+    );
+    let warm = EvmExecutor::new().call(
+        &mut backend,
+        None,
+        contract,
+        U256::zero(),
+        u64::MAX,
+        Vec::new(),
+        vec![AccessListItem {
+            address: contract,
+            slots:   vec![H256::zero()],
+        }],
+    );
+    assert_eq!(cold.exit_reason, ExitReason::Succeed(ExitSucceed::Stopped));
+    assert_eq!(warm.exit_reason, ExitReason::Succeed(ExitSucceed::Stopped));
+
+    // 21000 intrinsic + PUSH1 + cold SLOAD.
+    assert_eq!(cold.gas_used, 21000 + 3 + 2100);
+
+    // The access list is charged 2400 for the address and 1900 for the slot
+    // up front, after which the SLOAD is warm.
+    let access_list_cost = 2400 + 1900;
+    assert_eq!(warm.gas_used - access_list_cost, 21000 + 3 + 100);
+    assert!(warm.gas_used - access_list_cost < cold.gas_used);
+}
+
+#[test]
+fn test_call_respects_gas_limit() {
+    let contract = H160::from_str("0x1000000000000000000000000000000000000000").unwrap();
+    let mut state = BTreeMap::new();
+    state.insert(contract, MemoryAccount {
+        nonce:   U256::one(),
+        balance: U256::zero(),
+        storage: BTreeMap::new(),
+        // sload(0)
+        code:    hex_decode("600054").unwrap(),
+    });
+
+    let vicinity = gen_vicinity();
+    let mut backend = MemoryBackend::new(&vicinity, state);
+    let mut call = |gas_limit: u64| {
+        EvmExecutor::new().call(
+            &mut backend,
+            None,
+            contract,
+            U256::zero(),
+            gas_limit,
+            Vec::new(),
+            Vec::new(),
+        )
+    };
+
+    // 21000 intrinsic + PUSH1 + cold SLOAD.
+    let needed = 21000 + 3 + 2100;
+    assert_eq!(
+        call(needed).exit_reason,
+        ExitReason::Succeed(ExitSucceed::Stopped)
+    );
+    assert_eq!(
+        call(needed - 1).exit_reason,
+        ExitReason::Error(ExitError::OutOfGas)
+    );
+    assert_eq!(
+        call(20000).exit_reason,
+        ExitReason::Error(ExitError::OutOfGas)
+    );
+}
+
+#[test]
+fn test_selfdestruct_refund_is_paid_after_execution() {
+    let contract = H160::from_str("0x1000000000000000000000000000000000000000").unwrap();
+    let mut storage = BTreeMap::new();
+    storage.insert(H256::zero(), H256::from_low_u64_be(1));
+    let mut state = BTreeMap::new();
+    state.insert(contract, MemoryAccount {
+        nonce: U256::one(),
+        balance: U256::zero(),
+        storage,
+        // sstore(0, 0) selfdestruct(0x2000..00)
+        code: hex_decode("6000600055732000000000000000000000000000000000000000ff").unwrap(),
+    });
+
+    let vicinity = gen_vicinity();
+    let mut backend = MemoryBackend::new(&vicinity, state);
+    let mut call = |gas_limit: u64| {
+        EvmExecutor::new().call(
+            &mut backend,
+            None,
+            contract,
+            U256::zero(),
+            gas_limit,
+            Vec::new(),
+            Vec::new(),
+        )
+    };
+
+    let resp = call(u64::MAX);
+    assert_eq!(resp.exit_reason, ExitReason::Succeed(ExitSucceed::Suicided));
+
+    // The cleared slot refunds 4800 gas, SELFDESTRUCT refunds nothing since
+    // EIP-3529. The refund is deducted from the gas used but the execution
+    // needs the gas before it.
+    assert_eq!(
+        call(resp.gas_used).exit_reason,
+        ExitReason::Error(ExitError::OutOfGas)
+    );
+    assert!(call(resp.gas_used + 4800).exit_reason.is_succeed());
+}
+
+#[test]
+fn test_access_set_classifies_slots() {
+    let contract = H160::from_str("0x1000000000000000000000000000000000000000").unwrap();
+    let mut storage = BTreeMap::new();
+    storage.insert(H256::zero(), H256::from_low_u64_be(42));
+    let mut state = BTreeMap::new();
+    state.insert(contract, MemoryAccount {
+        nonce: U256::one(),
+        balance: U256::zero(),
+        storage,
+        // sstore(1, sload(0))
+        code: hex_decode("600054600155").unwrap(),
+    });
+
+    let vicinity = gen_vicinity();
+    let backend = MemoryBackend::new(&vicinity, state);
+    let set = EvmExecutor::new().access_set(
+        &backend,
+        None,
+        contract,
+        U256::zero(),
+        u64::MAX,
+        Vec::new(),
+        Vec::new(),
+    );
+
+    assert_eq!(
+        set.reads_slots.get(&contract),
+        Some(&vec![H256::zero()].into_iter().collect())
+    );
+    assert_eq!(
+        set.writes_slots.get(&contract),
+        Some(&vec![H256::from_low_u64_be(1)].into_iter().collect())
+    );
+    assert!(set.writes_accounts.contains(&contract));
+    assert!(!set.reads_accounts.contains(&contract));
+    // The simulation leaves the state as it was.
+    assert_eq!(
+        backend.storage(contract, H256::from_low_u64_be(1)),
+        H256::zero()
+    );
+}
+
+#[test]
+fn test_access_set_leaves_out_caller_nonce() {
+    let caller = H160::from_str("0xf000000000000000000000000000000000000000").unwrap();
+    let receiver = H160::from_str("0x2000000000000000000000000000000000000000").unwrap();
+    let mut state = BTreeMap::new();
+    state.insert(caller, MemoryAccount {
+        nonce:   U256::from(7),
+        balance: U256::from(10),
+        storage: BTreeMap::new(),
+        code:    Vec::new(),
+    });
+
+    let vicinity = gen_vicinity();
+    let backend = MemoryBackend::new(&vicinity, state);
+    let access_set = |value| {
+        EvmExecutor::new().access_set(
+            &backend,
+            Some(caller),
+            receiver,
+            value,
+            u64::MAX,
+            Vec::new(),
+            Vec::new(),
+        )
+    };
+
+    let set = access_set(U256::zero());
+    assert!(set.reads_accounts.contains(&caller));
+    assert!(!set.writes_accounts.contains(&caller));
+
+    // A transfer of value changes the balance of the caller.
+    let set = access_set(U256::one());
+    assert!(set.writes_accounts.contains(&caller));
+    assert!(set.writes_accounts.contains(&receiver));
+}
+
+fn call_code(executor: EvmExecutor, code: &str) -> ExitReason {
+    let contract = H160::from_str("0x1000000000000000000000000000000000000000").unwrap();
+    let mut state = BTreeMap::new();
+    state.insert(contract, MemoryAccount {
+        nonce:   U256::one(),
+        balance: U256::zero(),
+        storage: BTreeMap::new(),
+        code:    hex_decode(code).unwrap(),
+    });
+
+    let vicinity = gen_vicinity();
+    let mut backend = MemoryBackend::new(&vicinity, state);
+    executor
+        .call(
+            &mut backend,
+            None,
+            contract,
+            U256::zero(),
+            u64::MAX,
+            Vec::new(),
+            Vec::new(),
+        )
+        .exit_reason
+}
+
+#[test]
+fn test_call_returns_logs() {
+    let contract = H160::from_str("0x1000000000000000000000000000000000000000").unwrap();
+    let mut state = BTreeMap::new();
+    state.insert(contract, MemoryAccount {
+        nonce:   U256::one(),
+        balance: U256::zero(),
+        storage: BTreeMap::new(),
+        // mstore8(0, 0xff) log1(0, 1, 0x2a)
+        code:    hex_decode("60ff600053602a60016000a1").unwrap(),
+    });
+
+    let vicinity = gen_vicinity();
+    let mut backend = MemoryBackend::new(&vicinity, state);
+    let resp = EvmExecutor::new().call(
+        &mut backend,
+        None,
+        contract,
+        U256::zero(),
+        u64::MAX,
+        Vec::new(),
+        Vec::new(),
+    );
+    assert_eq!(resp.exit_reason, ExitReason::Succeed(ExitSucceed::Stopped));
+
+    assert_eq!(resp.logs, vec![Log {
+        address: contract,
+        topics:  vec![H256::from_low_u64_be(0x2a)],
+        data:    vec![0xff],
+    }]);
+}
+
+#[test]
+fn test_call_error_messages() {
+    // push1 0 invalid
+    let r = call_code(EvmExecutor::new(), "6000fe");
+    assert_eq!(r, ExitReason::Error(ExitError::DesignatedInvalid));
+    assert_eq!(
+        exit_error_message(&r).as_deref(),
+        Some("invalid opcode: INVALID")
+    );
+
+    // jumpdest push1 0 push1 0 jump, which grows the stack by one item a loop
+    let overflow = "5b6000600056";
+    let r = call_code(EvmExecutor::new(), overflow);
+    assert_eq!(r, ExitReason::Error(ExitError::StackOverflow));
+    assert_eq!(
+        exit_error_message(&r).as_deref(),
+        Some("stack limit reached")
+    );
+
+    let r = call_code(EvmExecutor::with_limits(16, usize::MAX), overflow);
+    assert_eq!(r, ExitReason::Error(ExitError::StackOverflow));
+
+    assert_eq!(
+        exit_error_message(&ExitReason::Error(ExitError::OutOfGas)).as_deref(),
+        Some("out of gas")
+    );
+    assert_eq!(
+        exit_error_message(&ExitReason::Succeed(ExitSucceed::Stopped)),
+        None
+    );
+}
+
+#[test]
+fn test_call_with_value() {
+    let caller = H160::from_str("0xf000000000000000000000000000000000000000").unwrap();
+    let contract = H160::from_str("0x1000000000000000000000000000000000000000").unwrap();
+    // callvalue iszero push1 0x11 jumpi, otherwise revert with the
+    // Error("not payable") copied from the tail of the code
+    let non_payable = concat!(
+        "3415601157606460136000396064600060fd5b00",
+        "08c379a0",
+        "0000000000000000000000000000000000000000000000000000000000000020",
+        "000000000000000000000000000000000000000000000000000000000000000b",
+        "6e6f742070617961626c65000000000000000000000000000000000000000000",
+    );
+    let call = |code: &str, value: u64| {
+        let mut state = BTreeMap::new();
+        state.insert(caller, MemoryAccount {
+            nonce:   U256::zero(),
+            balance: U256::from(100u64),
+            storage: BTreeMap::new(),
+            code:    Vec::new(),
+        });
+        state.insert(contract, MemoryAccount {
+            nonce:   U256::one(),
+            balance: U256::zero(),
+            storage: BTreeMap::new(),
+            code:    hex_decode(code).unwrap(),
+        });
+        let vicinity = gen_vicinity();
+        let mut backend = MemoryBackend::new(&vicinity, state);
+        EvmExecutor::new().call(
+            &mut backend,
+            Some(caller),
+            contract,
+            U256::from(value),
+            u64::MAX,
+            Vec::new(),
+            Vec::new(),
+        )
+    };
+
+    let r = call(non_payable, 0);
+    assert_eq!(r.exit_reason, ExitReason::Succeed(ExitSucceed::Stopped));
+
+    let r = call(non_payable, 1);
+    assert!(matches!(r.exit_reason, ExitReason::Revert(_)));
+    assert_eq!(
+        call_error_message(&r.exit_reason, &r.ret).as_deref(),
+        Some("execution reverted: not payable")
+    );
+
+    // A payable function, just stop.
+    let r = call("00", 1);
+    assert_eq!(r.exit_reason, ExitReason::Succeed(ExitSucceed::Stopped));
+
+    // The caller can not afford the value.
+    let r = call("00", 101);
+    assert_eq!(
+        call_error_message(&r.exit_reason, &r.ret).as_deref(),
+        Some("insufficient balance for transfer")
+    );
+}
+
+#[test]
+fn test_trace_prestate() {
+    let sender = H160::from_str("0xf000000000000000000000000000000000000000").unwrap();
+    let contract = H160::from_str("0xc15d2ba57d126e6603240e89437efd419ce329d2").unwrap();
+    let mut state = BTreeMap::new();
+    state.insert(sender, MemoryAccount {
+        nonce:   U256::one(),
+        balance: U256::max_value(),
+        storage: BTreeMap::new(),
+        code:    Vec::new(),
+    });
+    let vicinity = gen_vicinity();
+    let mut backend = MemoryBackend::new(&vicinity, state);
+    let executor = EvmExecutor::new();
+
+    // The SimpleStorage contract of `test_simplestorage`.
+    let simplestorage_create_code = "608060405234801561001057600080fd5b5060df8061001f6000396000f3006080604052600436106049576000357c0100000000000000000000000000000000000000000000000000000000900463ffffffff16806360fe47b114604e5780636d4ce63c146078575b600080fd5b348015605957600080fd5b5060766004803603810190808035906020019092919050505060a0565b005b348015608357600080fd5b50608a60aa565b6040518082815260200191505060405180910390f35b8060008190555050565b600080549050905600a165627a7a7230582099c66a25d59f0aa78f7ebc40748fa1d1fbc335d8d780f284841b30e0365acd960029";
+    let mut tx = gen_tx(
+        sender,
+        contract,
+        hex_decode(simplestorage_create_code).unwrap(),
+    );
+    tx.transaction.unsigned.action = TransactionAction::Create;
+    let r = executor.inner_exec(&mut backend, tx);
+    assert_eq!(r.exit_reason, ExitReason::Succeed(ExitSucceed::Returned));
+
+    // SimpleStorage.set(7) is replayed before tracing SimpleStorage.set(42).
+    let preceding = gen_tx(
+        sender,
+        contract,
+        hex_decode("60fe47b10000000000000000000000000000000000000000000000000000000000000007")
+            .unwrap(),
+    );
+    let tx = gen_tx(
+        sender,
+        contract,
+        hex_decode("60fe47b1000000000000000000000000000000000000000000000000000000000000002a")
+            .unwrap(),
+    );
+    let prestate = executor.trace_prestate(&backend, vec![preceding], tx);
+
+    let sender_state = prestate.get(&sender).unwrap();
+    assert_eq!(sender_state.balance, U256::max_value());
+    assert_eq!(sender_state.nonce, U256::from(3u64));
+
+    let contract_state = prestate.get(&contract).unwrap();
+    assert!(!contract_state.code.is_empty());
+    assert_eq!(
+        contract_state.storage.get(&H256::zero()),
+        Some(&H256::from_low_u64_be(7))
+    );
+
+    // Nothing is committed to the backend.
+    assert_eq!(backend.storage(contract, H256::zero()), H256::zero());
+}
+
+#[test]
+fn test_trace_prestate_with_limits() {
+    let sender = H160::from_str("0xf000000000000000000000000000000000000000").unwrap();
+    let contract = H160::from_str("0x1000000000000000000000000000000000000000").unwrap();
+    // mstore(0x100, 1), push 20 items, then sload(1)
+    let code = format!("600161010052{}60015400", "6001".repeat(20));
+    let mut state = BTreeMap::new();
+    state.insert(contract, MemoryAccount {
+        nonce:   U256::one(),
+        balance: U256::zero(),
+        storage: BTreeMap::new(),
+        code:    hex_decode(&code).unwrap(),
+    });
+    let vicinity = gen_vicinity();
+    let backend = MemoryBackend::new(&vicinity, state);
+    let slot = H256::from_low_u64_be(1);
+    let loads_slot = |executor: EvmExecutor| {
+        executor
+            .trace_prestate(&backend, vec![], gen_tx(sender, contract, vec![]))
+            .get(&contract)
+            .unwrap()
+            .storage
+            .contains_key(&slot)
+    };
+
+    assert!(loads_slot(EvmExecutor::new()));
+    // The traced transaction stops before the sload on either limit.
+    assert!(!loads_slot(EvmExecutor::with_limits(16, usize::MAX)));
+    assert!(!loads_slot(EvmExecutor::with_limits(1024, 64)));
+}
+
+#[test]
+fn test_trie_iter_from() {
+    let check = |entries: BTreeMap<Vec<u8>, Vec<u8>>, starts: Vec<Vec<u8>>| {
+        let mut trie = MPTTrie::new(Arc::new(MemoryDB::new(false)));
+        for (k, v) in entries.iter() {
+            trie.insert(k, v).unwrap();
+        }
+        trie.commit().unwrap();
+
+        for start in starts.into_iter() {
+            let expect = entries
+                .range(start.clone()..)
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect::<Vec<_>>();
+            assert_eq!(trie.iter_from(&start).collect::<Vec<_>>(), expect);
+        }
+    };
+
+    // Storage like keys, whose nodes are all stored by hash.
+    let slots = (0..200u64)
+        .map(|i| {
+            let key = Hasher::digest(i.to_be_bytes()).as_bytes().to_vec();
+            (key, vec![i as u8 + 1; 32])
+        })
+        .collect::<BTreeMap<_, _>>();
+    let mut starts = slots.keys().step_by(7).cloned().collect::<Vec<_>>();
+    starts.extend([vec![0u8; 32], vec![0x80; 32], vec![0xff; 32]]);
+    check(slots, starts);
+
+    // Short keys, some prefixes of others, so that there are inline nodes and
+    // branch values.
+    let short = [0x12u8, 0x13, 0x20, 0xf0]
+        .iter()
+        .flat_map(|&a| {
+            [
+                (vec![a], vec![a]),
+                (vec![a, 0x34], vec![a, 1]),
+                (vec![a, 0x35], vec![a, 2]),
+            ]
+        })
+        .collect::<BTreeMap<_, _>>();
+    let mut starts = short.keys().cloned().collect::<Vec<_>>();
+    starts.extend([vec![], vec![0x12, 0x00], vec![0x14], vec![0xff]]);
+    check(short, starts);
+}
+
+#[test]
+fn test_storage_range_paging() {
+    let contract = H160::from_str("0xc15d2ba57d126e6603240e89437efd419ce329d2").unwrap();
+    let slots = (1..=5u64)
+        .map(|i| (H256::from_low_u64_be(i), H256::from_low_u64_be(i * 10)))
+        .collect::<BTreeMap<_, _>>();
+
+    let mut backend = EVMExecutorAdapter::new(
+        Arc::new(MemoryDB::new(false)),
+        Arc::new(ImplStorage::new(Arc::new(MemoryAdapter::new()))),
+        ExecutorContext::default(),
+    )
+    .unwrap();
+    ApplyBackend::apply(
+        &mut backend,
+        vec![Apply::Modify {
+            address:       contract,
+            basic:         Basic {
+                balance: U256::one(),
+                nonce:   U256::one(),
+            },
+            code:          None,
+            storage:       slots.clone().into_iter().collect::<Vec<_>>(),
+            reset_storage: false,
+        }],
+        Vec::<Log>::new(),
+        false,
+    );
+    let executor = EvmExecutor::new();
+
+    let first = executor.storage_range(&backend, vec![], contract, H256::zero(), 3);
+    assert_eq!(first.storage.len(), 3);
+    assert_eq!(first.next_key, Some(H256::from_low_u64_be(4)));
+
+    let second = executor.storage_range(&backend, vec![], contract, first.next_key.unwrap(), 3);
+    assert_eq!(second.storage.len(), 2);
+    assert_eq!(second.next_key, None);
+
+    let mut all = first.storage;
+    all.extend(second.storage);
+    assert_eq!(all, slots);
+}
+
+#[test]
+fn test_account_proof_dedups_keys() {
+    let contract = H160::from_str("0xc15d2ba57d126e6603240e89437efd419ce329d2").unwrap();
+    let slot = H256::from_low_u64_be(1);
+    let value = H256::from_low_u64_be(10);
+
+    let mut backend = EVMExecutorAdapter::new(
+        Arc::new(MemoryDB::new(false)),
+        Arc::new(ImplStorage::new(Arc::new(MemoryAdapter::new()))),
+        ExecutorContext::default(),
+    )
+    .unwrap();
+    ApplyBackend::apply(
+        &mut backend,
+        vec![Apply::Modify {
+            address:       contract,
+            basic:         Basic {
+                balance: U256::one(),
+                nonce:   U256::one(),
+            },
+            code:          None,
+            storage:       vec![(slot, value)],
+            reset_storage: false,
+        }],
+        Vec::<Log>::new(),
+        false,
+    );
+
+    let missing = H256::from_low_u64_be(2);
+    let proof = backend
+        .account_proof(contract, &[slot, missing, slot])
+        .unwrap();
+    assert_eq!(proof.account.balance, U256::one());
+    assert!(!proof.account_proof.is_empty());
+    assert_eq!(proof.storage_proof.len(), 3);
+    assert_eq!(proof.storage_proof[0].value, value);
+    assert!(!proof.storage_proof[0].proof.is_empty());
+    assert_eq!(proof.storage_proof[1].key, missing);
+    assert_eq!(proof.storage_proof[1].value, H256::zero());
+    assert_eq!(proof.storage_proof[2], proof.storage_proof[0]);
+
+    // An absent account is proved empty.
+    let proof = backend
+        .account_proof(H160::from_low_u64_be(1), &[slot])
+        .unwrap();
+    assert_eq!(proof.account.storage_root, RLP_NULL);
+    assert_eq!(proof.storage_proof[0].value, H256::zero());
+}
+
+#[test]
+fn test_receipts_root() {
+    let sender = H160::from_str("0xf000000000000000000000000000000000000000").unwrap();
+    let contract = H160::from_str("0x1000000000000000000000000000000000000000").unwrap();
+
+    let new_backend = |number: u64| {
+        let mut backend = EVMExecutorAdapter::new(
+            Arc::new(MemoryDB::new(false)),
+            Arc::new(ImplStorage::new(Arc::new(MemoryAdapter::new()))),
+            ExecutorContext {
+                block_number: number.into(),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        ApplyBackend::apply(
+            &mut backend,
+            vec![
+                Apply::Modify {
+                    address:       sender,
+                    basic:         Basic {
+                        balance: U256::max_value(),
+                        nonce:   U256::zero(),
+                    },
+                    code:          None,
+                    storage:       Vec::new(),
+                    reset_storage: false,
+                },
+                // PUSH1 0x00 PUSH1 0x00 LOG0
+                Apply::Modify {
+                    address:       contract,
+                    basic:         Basic {
+                        balance: U256::zero(),
+                        nonce:   U256::one(),
+                    },
+                    code:          Some(hex_decode("60006000a0").unwrap()),
+                    storage:       Vec::new(),
+                    reset_storage: false,
+                },
+            ],
+            Vec::<Log>::new(),
+            false,
+        );
+        backend
+    };
+    let txs = || {
+        vec![
+            gen_tx(sender, contract, vec![]),
+            gen_tx(sender, H160::repeat_byte(0x22), vec![]),
+        ]
+    };
+    let executor = EvmExecutor::new().with_receipts_trie_fork(Some(5));
+
+    // The blocks before the fork commit to the return data.
+    let legacy = executor.exec(&mut new_backend(4), txs());
+    let hashes = legacy
+        .tx_resp
+        .iter()
+        .map(|r| Hasher::digest(&r.ret))
+        .collect();
+    let expect = Merkle::from_hashes(hashes)
+        .get_root_hash()
+        .unwrap_or_default();
+    assert_eq!(legacy.receipt_root, expect);
+    assert_eq!(legacy_receipts_root(&legacy.tx_resp), expect);
+    let unforked = EvmExecutor::new().exec(&mut new_backend(5), txs());
+    assert_eq!(unforked.receipt_root, expect);
+
+    let empty = executor.exec(&mut new_backend(5), vec![]);
+    assert_eq!(empty.receipt_root, RLP_NULL);
+
+    let resp = executor.exec(&mut new_backend(5), txs());
+    assert_eq!(resp.tx_resp[0].logs.len(), 1);
+    assert_ne!(resp.receipt_root, expect);
+
+    // Rebuild the receipts trie from the encoded receipts of the block.
+    let mut trie = MPTTrie::new(Arc::new(MemoryDB::new(false)));
+    let mut receipts = Vec::new();
+    let mut cumulative_gas_used = 0u64;
+    for (idx, r) in resp.tx_resp.iter().enumerate() {
+        cumulative_gas_used += r.gas_used;
+        let receipt = ConsensusReceipt {
+            status:              U256::one(),
+            cumulative_gas_used: U256::from(cumulative_gas_used),
+            logs_bloom:          logs_bloom(&r.logs),
+            logs:                r.logs.clone(),
+        };
+        let encoded = rlp::Encodable::rlp_bytes(&receipt);
+        assert_eq!(encoded[0], 0x02);
+        trie.insert(&rlp::encode(&idx), &encoded).unwrap();
+        receipts.push(receipt);
+    }
+
+    assert_eq!(trie.commit().unwrap(), resp.receipt_root);
+    assert_eq!(receipts_root(&receipts), resp.receipt_root);
+    assert_eq!(cumulative_gas_used, resp.gas_used);
+}
+
+#[test]
+fn test_replay_account_sees_created_contract() {
+    let sender = H160::from_str("0xf000000000000000000000000000000000000000").unwrap();
+    let contract = H160::from_str("0xc15d2ba57d126e6603240e89437efd419ce329d2").unwrap();
+    let mut state = BTreeMap::new();
+    state.insert(sender, MemoryAccount {
+        nonce:   U256::one(),
+        balance: U256::max_value(),
+        storage: BTreeMap::new(),
+        code:    Vec::new(),
+    });
+    let vicinity = gen_vicinity();
+    let backend = MemoryBackend::new(&vicinity, state);
+    let executor = EvmExecutor::new();
+
+    // The SimpleStorage contract of `test_simplestorage` is created, then
+    // SimpleStorage.set(7) is called.
+    let simplestorage_create_code = "608060405234801561001057600080fd5b5060df8061001f6000396000f3006080604052600436106049576000357c0100000000000000000000000000000000000000000000000000000000900463ffffffff16806360fe47b114604e5780636d4ce63c146078575b600080fd5b348015605957600080fd5b5060766004803603810190808035906020019092919050505060a0565b005b348015608357600080fd5b50608a60aa565b6040518082815260200191505060405180910390f35b8060008190555050565b600080549050905600a165627a7a7230582099c66a25d59f0aa78f7ebc40748fa1d1fbc335d8d780f284841b30e0365acd960029";
+    let mut create = gen_tx(
+        sender,
+        contract,
+        hex_decode(simplestorage_create_code).unwrap(),
+    );
+    create.transaction.unsigned.action = TransactionAction::Create;
+    let set = gen_tx(
+        sender,
+        contract,
+        hex_decode("60fe47b10000000000000000000000000000000000000000000000000000000000000007")
+            .unwrap(),
+    );
+
+    let (code, storage) =
+        executor.replay_account(&backend, vec![create, set], contract, &[H256::zero()]);
+    assert!(!code.is_empty());
+    assert_eq!(storage, vec![H256::from_low_u64_be(7)]);
+
+    // Nothing is committed to the backend.
+    assert!(backend.code(contract).is_empty());
+    assert_eq!(backend.storage(contract, H256::zero()), H256::zero());
+}
+
+#[test]
+fn test_replay_overlay_logs_follow_preceding_txs() {
+    let sender = H160::from_str("0xf000000000000000000000000000000000000000").unwrap();
+    let contract = H160::from_str("0xc15d2ba57d126e6603240e89437efd419ce329d2").unwrap();
+    let mut state = BTreeMap::new();
+    state.insert(sender, MemoryAccount {
+        nonce:   U256::one(),
+        balance: U256::max_value(),
+        storage: BTreeMap::new(),
+        code:    Vec::new(),
+    });
+    let vicinity = gen_vicinity();
+    let backend = MemoryBackend::new(&vicinity, state);
+    let executor = EvmExecutor::new();
+
+    // The created contract runs PUSH1 0x00 PUSH1 0x00 LOG0, so the call only
+    // emits a log once the creation before it is replayed.
+    let mut create = gen_tx(
+        sender,
+        contract,
+        hex_decode("6460006000a06000526005601bf3").unwrap(),
+    );
+    create.transaction.unsigned.action = TransactionAction::Create;
+    let call = gen_tx(sender, contract, vec![]);
+    let transfer = gen_tx(sender, H160::repeat_byte(0x22), vec![]);
+
+    let replay = executor.replay_overlay(&backend, vec![create, call, transfer]);
+    assert_eq!(
+        replay.overlay[&contract].code,
+        Some(hex_decode("60006000a0").unwrap())
+    );
+    assert_eq!(replay.logs.len(), 3);
+    assert!(replay.logs[0].is_empty());
+    assert_eq!(replay.logs[1].len(), 1);
+    assert_eq!(replay.logs[1][0].address, contract);
+    assert!(replay.logs[2].is_empty());
+}
+
+#[test]
+fn test_call_bundle_carries_state_forward() {
+    let sender = H160::from_str("0xf000000000000000000000000000000000000000").unwrap();
+    let contract = H160::from_str("0xc15d2ba57d126e6603240e89437efd419ce329d2").unwrap();
+    // The runtime code of the SimpleStorage contract of `test_simplestorage`.
+    let simplestorage_create_code = hex_decode("608060405234801561001057600080fd5b5060df8061001f6000396000f3006080604052600436106049576000357c0100000000000000000000000000000000000000000000000000000000900463ffffffff16806360fe47b114604e5780636d4ce63c146078575b600080fd5b348015605957600080fd5b5060766004803603810190808035906020019092919050505060a0565b005b348015608357600080fd5b50608a60aa565b6040518082815260200191505060405180910390f35b8060008190555050565b600080549050905600a165627a7a7230582099c66a25d59f0aa78f7ebc40748fa1d1fbc335d8d780f284841b30e0365acd960029").unwrap();
+    let mut state = BTreeMap::new();
+    state.insert(sender, MemoryAccount {
+        nonce:   U256::one(),
+        balance: U256::max_value(),
+        storage: BTreeMap::new(),
+        code:    Vec::new(),
+    });
+    state.insert(contract, MemoryAccount {
+        nonce:   U256::one(),
+        balance: U256::zero(),
+        storage: BTreeMap::new(),
+        code:    simplestorage_create_code[0x1f..0x1f + 0xdf].to_vec(),
+    });
+    let vicinity = gen_vicinity();
+    let mut backend = MemoryBackend::new(&vicinity, state);
+    let executor = EvmExecutor::new();
+
+    // SimpleStorage.set(7) and SimpleStorage.get()
+    let set =
+        hex_decode("60fe47b10000000000000000000000000000000000000000000000000000000000000007")
+            .unwrap();
+    let get = hex_decode("6d4ce63c").unwrap();
+    let call = |data: Vec<u8>| BundleCall {
+        from: Some(sender),
+        to: contract,
+        value: U256::zero(),
+        gas_limit: 1_000_000,
+        data,
+        access_list: Vec::new(),
+    };
+
+    // Separate calls do not see each other.
+    let resp = executor.call(
+        &mut backend,
+        Some(sender),
+        contract,
+        U256::zero(),
+        1_000_000,
+        set.clone(),
+        Vec::new(),
+    );
+    assert!(resp.exit_reason.is_succeed());
+    let resp = executor.call(
+        &mut backend,
+        Some(sender),
+        contract,
+        U256::zero(),
+        1_000_000,
+        get.clone(),
+        Vec::new(),
+    );
+    assert_eq!(resp.ret, H256::zero().as_bytes());
+
+    let resps = executor.call_bundle(&backend, vec![call(set), call(get)]);
+    assert_eq!(resps.len(), 2);
+    assert!(resps[0].exit_reason.is_succeed());
+    assert_eq!(resps[1].ret, H256::from_low_u64_be(7).as_bytes());
+
+    // Nothing is committed to the backend.
+    assert_eq!(backend.storage(contract, H256::zero()), H256::zero());
+}
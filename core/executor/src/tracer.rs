@@ -0,0 +1,268 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use evm::backend::{Apply, Basic};
+
+use protocol::traits::{ApplyBackend, Backend};
+use protocol::types::{AccountOverlay, Log, PrestateAccount, H160, H256, U256};
+
+#[derive(Default)]
+struct OverlayAccount {
+    basic:         Basic,
+    code:          Option<Vec<u8>>,
+    storage:       BTreeMap<H256, H256>,
+    reset_storage: bool,
+}
+
+/// A backend for the `prestateTracer`. The state changes applied to it are
+/// kept in memory on top of the inner backend, and once recording starts the
+/// first seen value of every account and storage slot read through it is
+/// recorded. The logs of the applied changes are kept until taken.
+pub struct PrestateTracer<'a, B> {
+    backend:   &'a B,
+    // `None` means the account is deleted.
+    overlay:   BTreeMap<H160, Option<OverlayAccount>>,
+    logs:      Vec<Log>,
+    recording: bool,
+    prestate:  RefCell<BTreeMap<H160, PrestateAccount>>,
+}
+
+impl<'a, B: Backend> PrestateTracer<'a, B> {
+    pub fn new(backend: &'a B) -> Self {
+        PrestateTracer {
+            backend,
+            overlay: BTreeMap::new(),
+            logs: Vec::new(),
+            recording: false,
+            prestate: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn start_recording(&mut self) {
+        self.recording = true;
+    }
+
+    /// The logs of the changes applied since the last call.
+    pub fn take_logs(&mut self) -> Vec<Log> {
+        std::mem::take(&mut self.logs)
+    }
+
+    pub fn into_prestate(self) -> BTreeMap<H160, PrestateAccount> {
+        self.prestate.into_inner()
+    }
+
+    /// The storage slots of `address` written by the applied changes and
+    /// whether its storage was cleared before them, `None` if untouched.
+    pub fn changed_storage(&self, address: H160) -> Option<(BTreeMap<H256, H256>, bool)> {
+        self.overlay.get(&address).map(|account| match account {
+            Some(account) => (account.storage.clone(), account.reset_storage),
+            None => (BTreeMap::new(), true),
+        })
+    }
+
+    /// The code and storage the applied changes leave the touched accounts
+    /// with.
+    pub fn into_overlay(self) -> BTreeMap<H160, AccountOverlay> {
+        self.overlay
+            .into_iter()
+            .map(|(address, account)| {
+                let overlay = match account {
+                    Some(account) => AccountOverlay {
+                        code:          account.code,
+                        storage:       account.storage,
+                        reset_storage: account.reset_storage,
+                    },
+                    None => AccountOverlay {
+                        code:          Some(Vec::new()),
+                        storage:       BTreeMap::new(),
+                        reset_storage: true,
+                    },
+                };
+                (address, overlay)
+            })
+            .collect()
+    }
+
+    fn current_basic(&self, address: H160) -> Basic {
+        match self.overlay.get(&address) {
+            Some(Some(account)) => account.basic.clone(),
+            Some(None) => Basic::default(),
+            None => self.backend.basic(address),
+        }
+    }
+
+    fn current_code(&self, address: H160) -> Vec<u8> {
+        match self.overlay.get(&address) {
+            Some(Some(OverlayAccount {
+                code: Some(code), ..
+            })) => code.clone(),
+            Some(None) => Vec::new(),
+            _ => self.backend.code(address),
+        }
+    }
+
+    fn current_storage(&self, address: H160, index: H256) -> H256 {
+        match self.overlay.get(&address) {
+            Some(Some(account)) => match account.storage.get(&index) {
+                Some(value) => *value,
+                None if account.reset_storage => H256::default(),
+                None => self.backend.storage(address, index),
+            },
+            Some(None) => H256::default(),
+            None => self.backend.storage(address, index),
+        }
+    }
+
+    fn record_account(&self, address: H160) {
+        if !self.recording || self.prestate.borrow().contains_key(&address) {
+            return;
+        }
+
+        let basic = self.current_basic(address);
+        self.prestate.borrow_mut().insert(address, PrestateAccount {
+            balance: basic.balance,
+            nonce:   basic.nonce,
+            code:    self.current_code(address),
+            storage: BTreeMap::new(),
+        });
+    }
+}
+
+impl<'a, B: Backend> Backend for PrestateTracer<'a, B> {
+    fn gas_price(&self) -> U256 {
+        self.backend.gas_price()
+    }
+
+    fn origin(&self) -> H160 {
+        self.backend.origin()
+    }
+
+    fn block_hash(&self, number: U256) -> H256 {
+        self.backend.block_hash(number)
+    }
+
+    fn block_number(&self) -> U256 {
+        self.backend.block_number()
+    }
+
+    fn block_coinbase(&self) -> H160 {
+        self.backend.block_coinbase()
+    }
+
+    fn block_timestamp(&self) -> U256 {
+        self.backend.block_timestamp()
+    }
+
+    fn block_difficulty(&self) -> U256 {
+        self.backend.block_difficulty()
+    }
+
+    fn block_gas_limit(&self) -> U256 {
+        self.backend.block_gas_limit()
+    }
+
+    fn block_base_fee_per_gas(&self) -> U256 {
+        self.backend.block_base_fee_per_gas()
+    }
+
+    fn chain_id(&self) -> U256 {
+        self.backend.chain_id()
+    }
+
+    fn exists(&self, address: H160) -> bool {
+        let exists = match self.overlay.get(&address) {
+            Some(account) => account.is_some(),
+            None => self.backend.exists(address),
+        };
+
+        if exists {
+            self.record_account(address);
+        }
+        exists
+    }
+
+    fn basic(&self, address: H160) -> Basic {
+        self.record_account(address);
+        self.current_basic(address)
+    }
+
+    fn code(&self, address: H160) -> Vec<u8> {
+        self.record_account(address);
+        self.current_code(address)
+    }
+
+    fn storage(&self, address: H160, index: H256) -> H256 {
+        self.record_account(address);
+        let value = self.current_storage(address, index);
+
+        if self.recording {
+            if let Some(account) = self.prestate.borrow_mut().get_mut(&address) {
+                account.storage.entry(index).or_insert(value);
+            }
+        }
+        value
+    }
+
+    fn original_storage(&self, address: H160, index: H256) -> Option<H256> {
+        Some(self.storage(address, index))
+    }
+}
+
+impl<'a, B: Backend> ApplyBackend for PrestateTracer<'a, B> {
+    fn apply<A, I, L>(&mut self, values: A, logs: L, delete_empty: bool)
+    where
+        A: IntoIterator<Item = Apply<I>>,
+        I: IntoIterator<Item = (H256, H256)>,
+        L: IntoIterator<Item = Log>,
+    {
+        for apply in values.into_iter() {
+            match apply {
+                Apply::Modify {
+                    address,
+                    basic,
+                    code,
+                    storage,
+                    reset_storage,
+                } => {
+                    let mut account = match self.overlay.remove(&address) {
+                        Some(Some(account)) => account,
+                        Some(None) => OverlayAccount {
+                            code: Some(Vec::new()),
+                            reset_storage: true,
+                            ..Default::default()
+                        },
+                        None => OverlayAccount::default(),
+                    };
+
+                    account.basic = basic;
+                    if code.is_some() {
+                        account.code = code;
+                    }
+                    if reset_storage {
+                        account.storage.clear();
+                        account.reset_storage = true;
+                    }
+                    account.storage.extend(storage);
+
+                    let is_empty = account.basic.balance.is_zero()
+                        && account.basic.nonce.is_zero()
+                        && account
+                            .code
+                            .as_ref()
+                            .map(|c| c.is_empty())
+                            .unwrap_or_else(|| self.backend.code(address).is_empty());
+                    if is_empty && delete_empty {
+                        self.overlay.insert(address, None);
+                    } else {
+                        self.overlay.insert(address, Some(account));
+                    }
+                }
+                Apply::Delete { address } => {
+                    self.overlay.insert(address, None);
+                }
+            }
+        }
+
+        self.logs.extend(logs);
+    }
+}
@@ -2,8 +2,10 @@ use super::TxContext;
 
 pub mod message;
 
+use std::collections::{HashMap, VecDeque};
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
-use std::{error::Error, marker::PhantomData, sync::Arc, time::Duration};
+use std::time::{Duration, Instant};
+use std::{error::Error, marker::PhantomData, sync::Arc};
 
 use dashmap::DashMap;
 use futures::{
@@ -19,7 +21,8 @@ use parking_lot::Mutex;
 use common_crypto::{Crypto, Secp256k1Recoverable};
 use core_executor::{EVMExecutorAdapter, EvmExecutor};
 use protocol::traits::{
-    Context, Executor, Gossip, MemPoolAdapter, PeerTrust, Priority, Rpc, Storage, TrustFeedback,
+    Context, Executor, Gossip, MemPoolAdapter, PeerTrust, Priority, RejectCode, Rpc, Storage,
+    TrustFeedback,
 };
 use protocol::types::{recover_intact_pub_key, Hash, MerkleRoot, SignedTransaction, H160, U256};
 use protocol::{
@@ -32,18 +35,88 @@ use protocol::tokio::{self, time::sleep};
 use crate::adapter::message::{
     MsgNewTxs, MsgPullTxs, MsgPushTxs, END_GOSSIP_NEW_TXS, RPC_PULL_TXS,
 };
-use crate::MemPoolError;
+use crate::{notify_tx_broadcast, MemPoolError};
 
 pub const DEFAULT_BROADCAST_TXS_SIZE: usize = 200;
 pub const DEFAULT_BROADCAST_TXS_INTERVAL: u64 = 200; // milliseconds
+pub const DEFAULT_BROADCAST_DEDUP_TTL: u64 = 60_000; // milliseconds
+const MAX_RECENT_BROADCASTS: usize = 100_000;
+
+/// The hashes of the recently broadcast transactions, a transaction admitted
+/// again within the TTL is not gossiped again. A hash is only recorded once
+/// its gossip succeeds, so a failed one is retried on the next admission.
+struct RecentBroadcasts {
+    ttl:      Duration,
+    capacity: usize,
+    sent_at:  HashMap<Hash, Instant>,
+    order:    VecDeque<(Hash, Instant)>,
+}
+
+impl RecentBroadcasts {
+    fn new(ttl: Duration, capacity: usize) -> Self {
+        RecentBroadcasts {
+            ttl,
+            capacity,
+            sent_at: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+
+    /// Return true if the hash was broadcast within the TTL.
+    fn contains(&mut self, hash: &Hash, now: Instant) -> bool {
+        self.expire(now);
+        self.sent_at.contains_key(hash)
+    }
+
+    /// Record the hash as broadcast now, return false if it was already
+    /// broadcast within the TTL.
+    fn insert(&mut self, hash: Hash, now: Instant) -> bool {
+        if self.contains(&hash, now) {
+            return false;
+        }
+
+        self.sent_at.insert(hash, now);
+        self.order.push_back((hash, now));
+        true
+    }
 
+    fn expire(&mut self, now: Instant) {
+        while let Some((old, at)) = self.order.front().cloned() {
+            if self.order.len() < self.capacity && now.duration_since(at) < self.ttl {
+                break;
+            }
+
+            self.order.pop_front();
+            // The entry is only removed if it is not refreshed since.
+            if self.sent_at.get(&old) == Some(&at) {
+                self.sent_at.remove(&old);
+            }
+        }
+    }
+}
+
+/// The tip a transaction pays the proposer over `base_fee`, its priority fee
+/// capped by what its fee cap leaves above the base fee.
+fn effective_tip(stx: &SignedTransaction, base_fee: U256) -> U256 {
+    let unsigned = &stx.transaction.unsigned;
+    unsigned
+        .max_priority_fee_per_gas
+        .min(unsigned.max_fee_per_gas.saturating_sub(base_fee))
+}
+
+/// Gossips the admitted transactions in batches, sent once `tx_size` of them
+/// are collected or the interval is reached. Each transaction comes with the
+/// base fee at its admission, one whose effective tip over it reaches
+/// `priority_tip` skips the batch and is sent on its own.
 struct IntervalTxsBroadcaster;
 
 impl IntervalTxsBroadcaster {
     pub async fn broadcast<G>(
-        stx_rx: UnboundedReceiver<SignedTransaction>,
+        stx_rx: UnboundedReceiver<(SignedTransaction, U256)>,
         interval_reached: Receiver<()>,
         tx_size: usize,
+        dedup_ttl: u64,
+        priority_tip: U256,
         gossip: G,
         err_tx: UnboundedSender<ProtocolError>,
     ) where
@@ -53,15 +126,53 @@ impl IntervalTxsBroadcaster {
         let mut interval_rx = interval_reached.fuse();
 
         let mut txs_cache = Vec::with_capacity(tx_size);
+        let mut recent =
+            RecentBroadcasts::new(Duration::from_millis(dedup_ttl), MAX_RECENT_BROADCASTS);
 
         loop {
             select! {
                 opt_stx = stx_rx.next() => {
-                    if let Some(stx) = opt_stx {
+                    if let Some((stx, base_fee)) = opt_stx {
+                        let hash = stx.transaction.hash;
+                        if recent.contains(&hash, Instant::now()) {
+                            debug!("mempool: skip rebroadcast of {:?}", hash);
+                            // It is gossiped already, tell the waiters rather
+                            // than leave them waiting for a broadcast.
+                            notify_tx_broadcast(vec![hash]);
+                            continue;
+                        }
+
+                        // It is sent with the pending batch.
+                        if txs_cache.iter().any(|cached| cached.transaction.hash == hash) {
+                            continue;
+                        }
+
+                        if !priority_tip.is_zero()
+                            && effective_tip(&stx, base_fee) >= priority_tip
+                        {
+                            let mut priority_txs = vec![stx];
+                            Self::do_broadcast(
+                                &mut priority_txs,
+                                &gossip,
+                                &mut recent,
+                                Priority::High,
+                                err_tx.clone(),
+                            )
+                            .await;
+                            continue;
+                        }
+
                         txs_cache.push(stx);
 
                         if txs_cache.len() == tx_size {
-                            Self::do_broadcast(&mut txs_cache, &gossip, err_tx.clone()).await
+                            Self::do_broadcast(
+                                &mut txs_cache,
+                                &gossip,
+                                &mut recent,
+                                Priority::Normal,
+                                err_tx.clone(),
+                            )
+                            .await
                         }
                     } else {
                         debug!("mempool: default mempool adapter dropped")
@@ -69,7 +180,14 @@ impl IntervalTxsBroadcaster {
                 },
                 signal = interval_rx.next() => {
                     if signal.is_some() {
-                        Self::do_broadcast(&mut txs_cache, &gossip, err_tx.clone()).await
+                        Self::do_broadcast(
+                            &mut txs_cache,
+                            &gossip,
+                            &mut recent,
+                            Priority::Normal,
+                            err_tx.clone(),
+                        )
+                        .await
                     }
                 },
                 complete => break,
@@ -100,6 +218,8 @@ impl IntervalTxsBroadcaster {
     async fn do_broadcast<G>(
         txs_cache: &mut Vec<SignedTransaction>,
         gossip: &G,
+        recent: &mut RecentBroadcasts,
+        priority: Priority,
         err_tx: UnboundedSender<ProtocolError>,
     ) where
         G: Gossip + Unpin,
@@ -109,6 +229,10 @@ impl IntervalTxsBroadcaster {
         }
 
         let batch_stxs = txs_cache.drain(..).collect::<Vec<_>>();
+        let hashes = batch_stxs
+            .iter()
+            .map(|stx| stx.transaction.hash)
+            .collect::<Vec<_>>();
         let gossip_msg = MsgNewTxs { batch_stxs };
 
         let ctx = Context::new();
@@ -122,11 +246,16 @@ impl IntervalTxsBroadcaster {
             }
         };
 
-        report_if_err(
-            gossip
-                .broadcast(ctx, end, gossip_msg, Priority::Normal)
-                .await,
-        )
+        let ret = gossip.broadcast(ctx, end, gossip_msg, priority).await;
+        if ret.is_ok() {
+            let now = Instant::now();
+            for hash in hashes.iter() {
+                recent.insert(*hash, now);
+            }
+            notify_tx_broadcast(hashes);
+        }
+
+        report_if_err(ret)
     }
 }
 
@@ -141,7 +270,7 @@ pub struct DefaultMemPoolAdapter<C, N, S, DB> {
     max_tx_size: AtomicUsize,
     chain_id:    u64,
 
-    stx_tx: UnboundedSender<SignedTransaction>,
+    stx_tx: UnboundedSender<(SignedTransaction, U256)>,
     err_rx: Mutex<UnboundedReceiver<ProtocolError>>,
 
     pin_c: PhantomData<C>,
@@ -164,6 +293,8 @@ where
         max_tx_size: usize,
         broadcast_txs_size: usize,
         broadcast_txs_interval: u64,
+        broadcast_dedup_ttl: u64,
+        broadcast_priority_tip: u64,
     ) -> Self {
         let (stx_tx, stx_rx) = unbounded();
         let (err_tx, err_rx) = unbounded();
@@ -178,6 +309,8 @@ where
             stx_rx,
             interval_reached,
             broadcast_txs_size,
+            broadcast_dedup_ttl,
+            broadcast_priority_tip.into(),
             network.clone(),
             err_tx,
         ));
@@ -232,9 +365,14 @@ where
         Ok(resp_msg.sig_txs)
     }
 
-    async fn broadcast_tx(&self, _ctx: Context, stx: SignedTransaction) -> ProtocolResult<()> {
+    async fn broadcast_tx(&self, ctx: Context, stx: SignedTransaction) -> ProtocolResult<()> {
+        let base_fee = self
+            .storage
+            .get_latest_block_header(ctx)
+            .await?
+            .base_fee_per_gas;
         self.stx_tx
-            .unbounded_send(stx)
+            .unbounded_send((stx, base_fee))
             .map_err(AdapterError::from)?;
 
         if let Some(mut err_rx) = self.err_rx.try_lock() {
@@ -304,7 +442,10 @@ where
             if ctx.is_network_origin_txs() {
                 self.network.report(
                     ctx,
-                    TrustFeedback::Bad(format!("Mempool exceed size limit of tx {:?}", tx_hash)),
+                    TrustFeedback::Bad(
+                        format!("Mempool exceed size limit of tx {:?}", tx_hash),
+                        Some(RejectCode::Oversized),
+                    ),
                 );
             }
             return Err(MemPoolError::ExceedSizeLimit {
@@ -321,7 +462,10 @@ where
             if ctx.is_network_origin_txs() {
                 self.network.report(
                     ctx,
-                    TrustFeedback::Bad(format!("Mempool exceed cycle limit of tx {:?}", tx_hash)),
+                    TrustFeedback::Bad(
+                        format!("Mempool exceed cycle limit of tx {:?}", tx_hash),
+                        Some(RejectCode::Oversized),
+                    ),
                 );
             }
             return Err(MemPoolError::ExceedGasLimit {
@@ -337,7 +481,10 @@ where
             if ctx.is_network_origin_txs() {
                 self.network.report(
                     ctx,
-                    TrustFeedback::Worse(format!("Mempool wrong chain of tx {:?}", tx_hash)),
+                    TrustFeedback::Worse(
+                        format!("Mempool wrong chain of tx {:?}", tx_hash),
+                        Some(RejectCode::WrongChain),
+                    ),
                 );
             }
             let wrong_chain_id = MemPoolError::WrongChain(tx_hash);
@@ -346,7 +493,7 @@ where
         }
 
         // Verify signature
-        Secp256k1Recoverable::verify_signature(
+        if let Err(err) = Secp256k1Recoverable::verify_signature(
             stx.transaction.signature_hash().as_bytes(),
             stx.transaction
                 .signature
@@ -355,15 +502,60 @@ where
                 .as_bytes()
                 .as_ref(),
             recover_intact_pub_key(&stx.public.unwrap()).as_bytes(),
-        )
-        .map_err(|err| AdapterError::VerifySignature(err.to_string()))?;
+        ) {
+            if ctx.is_network_origin_txs() {
+                self.network.report(
+                    ctx,
+                    TrustFeedback::Worse(
+                        format!("Mempool invalid signature of tx {:?}", tx_hash),
+                        Some(RejectCode::InvalidSignature),
+                    ),
+                );
+            }
+            return Err(AdapterError::VerifySignature(err.to_string()).into());
+        }
+
+        Ok(())
+    }
+
+    async fn check_balance(&self, _ctx: Context, tx: &SignedTransaction) -> ProtocolResult<()> {
+        let backend = EVMExecutorAdapter::from_root(
+            **CURRENT_STATE_ROOT.load(),
+            Arc::clone(&self.trie_db),
+            Arc::clone(&self.storage),
+            Default::default(),
+        )?;
+        let balance = EvmExecutor::default()
+            .get_account(&backend, &tx.sender)
+            .balance;
+
+        let unsigned = &tx.transaction.unsigned;
+        let cost = unsigned
+            .gas_limit
+            .saturating_mul(unsigned.max_fee_per_gas)
+            .saturating_add(unsigned.value);
+        if balance < cost {
+            return Err(MemPoolError::InsufficientBalance {
+                tx_hash: tx.transaction.hash,
+                balance,
+                cost,
+            }
+            .into());
+        }
 
         Ok(())
     }
 
     async fn check_storage_exist(&self, ctx: Context, tx_hash: &Hash) -> ProtocolResult<()> {
-        match self.storage.get_transaction_by_hash(ctx, tx_hash).await {
-            Ok(Some(_)) => Err(MemPoolError::CommittedTx(*tx_hash).into()),
+        match self
+            .storage
+            .get_transaction_by_hash(ctx.clone(), tx_hash)
+            .await
+        {
+            Ok(Some(_)) => {
+                self.report_duplicate(ctx, tx_hash);
+                Err(MemPoolError::CommittedTx(*tx_hash).into())
+            }
             Ok(None) => Ok(()),
             Err(err) => Err(err),
         }
@@ -411,6 +603,18 @@ where
             self.network.report(ctx, TrustFeedback::Good);
         }
     }
+
+    fn report_duplicate(&self, ctx: Context, tx_hash: &Hash) {
+        if ctx.is_network_origin_txs() {
+            self.network.report(
+                ctx,
+                TrustFeedback::Bad(
+                    format!("Mempool duplicate tx {:?}", tx_hash),
+                    Some(RejectCode::Duplicate),
+                ),
+            );
+        }
+    }
 }
 
 #[derive(Debug, Display)]
@@ -443,7 +647,7 @@ impl From<AdapterError> for ProtocolError {
 mod tests {
     use super::*;
 
-    use crate::{adapter::message::MsgNewTxs, tests::default_mock_txs};
+    use crate::{adapter::message::MsgNewTxs, tests::default_mock_txs, TX_BROADCAST_NOTIFIER};
     use protocol::{traits::MessageCodec, types::Bytes};
 
     use futures::{
@@ -454,6 +658,7 @@ mod tests {
 
     use std::{
         ops::Sub,
+        sync::atomic::AtomicBool,
         sync::Arc,
         time::{Duration, Instant},
     };
@@ -462,6 +667,7 @@ mod tests {
     struct MockGossip {
         msgs:      Arc<Mutex<Vec<Bytes>>>,
         signal_tx: UnboundedSender<()>,
+        fail_next: Arc<AtomicBool>,
     }
 
     impl MockGossip {
@@ -469,6 +675,7 @@ mod tests {
             MockGossip {
                 msgs: Default::default(),
                 signal_tx,
+                fail_next: Default::default(),
             }
         }
     }
@@ -492,9 +699,25 @@ mod tests {
                 .unbounded_send(())
                 .expect("send broadcast signal fail");
 
+            if self.fail_next.swap(false, Ordering::SeqCst) {
+                return Err(MemPoolError::Insert(Hash::default()).into());
+            }
+
             Ok(())
         }
 
+        async fn broadcast_to_consensus_acked<M>(
+            &self,
+            _: Context,
+            _: &str,
+            _: M,
+        ) -> ProtocolResult<(usize, usize)>
+        where
+            M: MessageCodec + Clone,
+        {
+            unreachable!()
+        }
+
         async fn multicast<'a, M, P>(
             &self,
             _: Context,
@@ -543,12 +766,16 @@ mod tests {
             stx_rx,
             interval_reached,
             tx_size,
+            DEFAULT_BROADCAST_DEDUP_TTL,
+            U256::zero(),
             gossip.clone(),
             err_tx,
         ));
 
         for stx in default_mock_txs(11).into_iter() {
-            stx_tx.unbounded_send(stx).expect("send stx fail");
+            stx_tx
+                .unbounded_send((stx, U256::zero()))
+                .expect("send stx fail");
         }
 
         broadcast_signal_rx.next().await;
@@ -573,12 +800,16 @@ mod tests {
             stx_rx,
             interval_reached,
             tx_size,
+            DEFAULT_BROADCAST_DEDUP_TTL,
+            U256::zero(),
             gossip.clone(),
             err_tx,
         ));
 
         for stx in default_mock_txs(9).into_iter() {
-            stx_tx.unbounded_send(stx).expect("send stx fail");
+            stx_tx
+                .unbounded_send((stx, U256::zero()))
+                .expect("send stx fail");
         }
 
         broadcast_signal_rx.next().await;
@@ -603,12 +834,16 @@ mod tests {
             stx_rx,
             interval_reached,
             tx_size,
+            DEFAULT_BROADCAST_DEDUP_TTL,
+            U256::zero(),
             gossip.clone(),
             err_tx,
         ));
 
         for stx in default_mock_txs(19).into_iter() {
-            stx_tx.unbounded_send(stx).expect("send stx fail");
+            stx_tx
+                .unbounded_send((stx, U256::zero()))
+                .expect("send stx fail");
         }
 
         // Should got two broadcast
@@ -632,4 +867,188 @@ mod tests {
             "first message should only have 10 stx"
         );
     }
+
+    #[tokio::test]
+    async fn test_broadcast_is_notified() {
+        let (stx_tx, stx_rx) = unbounded();
+        let (err_tx, _err_rx) = unbounded();
+        let (_signal_tx, interval_reached) = channel(1);
+        let (broadcast_signal_tx, mut broadcast_signal_rx) = unbounded();
+        let gossip = MockGossip::new(broadcast_signal_tx);
+        let mut notify_rx = TX_BROADCAST_NOTIFIER.subscribe();
+
+        tokio::spawn(IntervalTxsBroadcaster::broadcast(
+            stx_rx,
+            interval_reached,
+            2,
+            DEFAULT_BROADCAST_DEDUP_TTL,
+            U256::zero(),
+            gossip.clone(),
+            err_tx,
+        ));
+
+        let stxs = default_mock_txs(2);
+        let hashes = stxs
+            .iter()
+            .map(|stx| stx.transaction.hash)
+            .collect::<Vec<_>>();
+        for stx in stxs.into_iter() {
+            stx_tx
+                .unbounded_send((stx, U256::zero()))
+                .expect("send stx fail");
+        }
+
+        broadcast_signal_rx.next().await;
+        assert_eq!(gossip.msgs.lock().len(), 1);
+
+        // Other tests broadcast concurrently, so skip their batches.
+        loop {
+            match notify_rx.recv().await {
+                Ok(event) if event.hashes == hashes => break,
+                _ => continue,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_broadcast_dedup_within_ttl() {
+        let (stx_tx, stx_rx) = unbounded();
+        let (err_tx, _err_rx) = unbounded();
+        let (_signal_tx, interval_reached) = channel(1);
+        let (broadcast_signal_tx, mut broadcast_signal_rx) = unbounded();
+        let gossip = MockGossip::new(broadcast_signal_tx);
+
+        tokio::spawn(IntervalTxsBroadcaster::broadcast(
+            stx_rx,
+            interval_reached,
+            1,
+            DEFAULT_BROADCAST_DEDUP_TTL,
+            U256::zero(),
+            gossip.clone(),
+            err_tx,
+        ));
+
+        let stxs = default_mock_txs(2);
+        stx_tx
+            .unbounded_send((stxs[0].clone(), U256::zero()))
+            .expect("send stx fail");
+        stx_tx
+            .unbounded_send((stxs[0].clone(), U256::zero()))
+            .expect("send stx fail");
+        stx_tx
+            .unbounded_send((stxs[1].clone(), U256::zero()))
+            .expect("send stx fail");
+
+        broadcast_signal_rx.next().await;
+        broadcast_signal_rx.next().await;
+
+        // The second admission of the first tx is skipped.
+        let mut msgs = gossip.msgs.lock().drain(..).collect::<Vec<_>>();
+        assert_eq!(msgs.len(), 2, "should only have two messages");
+        assert_eq!(pop_msg!(msgs).batch_stxs, vec![stxs[1].clone()]);
+        assert_eq!(pop_msg!(msgs).batch_stxs, vec![stxs[0].clone()]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_broadcast_is_retried() {
+        let (stx_tx, stx_rx) = unbounded();
+        let (err_tx, mut err_rx) = unbounded();
+        let (_signal_tx, interval_reached) = channel(1);
+        let (broadcast_signal_tx, mut broadcast_signal_rx) = unbounded();
+        let gossip = MockGossip::new(broadcast_signal_tx);
+        gossip.fail_next.store(true, Ordering::SeqCst);
+
+        tokio::spawn(IntervalTxsBroadcaster::broadcast(
+            stx_rx,
+            interval_reached,
+            1,
+            DEFAULT_BROADCAST_DEDUP_TTL,
+            U256::zero(),
+            gossip.clone(),
+            err_tx,
+        ));
+
+        let stx = default_mock_txs(1).remove(0);
+        stx_tx
+            .unbounded_send((stx.clone(), U256::zero()))
+            .expect("send stx fail");
+        broadcast_signal_rx.next().await;
+        assert!(err_rx.next().await.is_some());
+
+        // The failed gossip is not recorded, so the tx is sent again.
+        stx_tx
+            .unbounded_send((stx.clone(), U256::zero()))
+            .expect("send stx fail");
+        broadcast_signal_rx.next().await;
+
+        let mut msgs = gossip.msgs.lock().drain(..).collect::<Vec<_>>();
+        assert_eq!(msgs.len(), 2, "should send the tx twice");
+        assert_eq!(pop_msg!(msgs).batch_stxs, vec![stx.clone()]);
+        assert_eq!(pop_msg!(msgs).batch_stxs, vec![stx]);
+    }
+
+    #[tokio::test]
+    async fn test_priority_tx_skips_batch() {
+        let (stx_tx, stx_rx) = unbounded();
+        let (err_tx, _err_rx) = unbounded();
+        let (mut signal_tx, interval_reached) = channel(1);
+        let (broadcast_signal_tx, mut broadcast_signal_rx) = unbounded();
+        let gossip = MockGossip::new(broadcast_signal_tx);
+
+        tokio::spawn(IntervalTxsBroadcaster::broadcast(
+            stx_rx,
+            interval_reached,
+            10,
+            DEFAULT_BROADCAST_DEDUP_TTL,
+            U256::from(2u64),
+            gossip.clone(),
+            err_tx,
+        ));
+
+        // The mock transactions pay a priority fee of 1. Over a base fee of 1
+        // the third one is capped at a tip of 1 by its fee cap of 2, the last
+        // one pays a tip of 2.
+        let mut stxs = default_mock_txs(4);
+        stxs[2].transaction.unsigned.max_priority_fee_per_gas = U256::from(5u64);
+        stxs[2].transaction.unsigned.max_fee_per_gas = U256::from(2u64);
+        stxs[3].transaction.unsigned.max_priority_fee_per_gas = U256::from(2u64);
+        stxs[3].transaction.unsigned.max_fee_per_gas = U256::from(3u64);
+        for stx in stxs.iter().cloned() {
+            stx_tx
+                .unbounded_send((stx, U256::one()))
+                .expect("send stx fail");
+        }
+
+        broadcast_signal_rx.next().await;
+        let mut msgs = gossip.msgs.lock().drain(..).collect::<Vec<_>>();
+        assert_eq!(msgs.len(), 1, "only the priority tx is sent at once");
+        assert_eq!(pop_msg!(msgs).batch_stxs, vec![stxs[3].clone()]);
+
+        // The others are admitted within the interval and sent in one batch.
+        signal_tx.try_send(()).expect("send interval signal fail");
+        broadcast_signal_rx.next().await;
+        let mut msgs = gossip.msgs.lock().drain(..).collect::<Vec<_>>();
+        assert_eq!(msgs.len(), 1, "should only have one message");
+        assert_eq!(pop_msg!(msgs).batch_stxs, stxs[..3].to_vec());
+    }
+
+    #[test]
+    fn test_recent_broadcasts_expire() {
+        let ttl = Duration::from_millis(100);
+        let mut recent = RecentBroadcasts::new(ttl, 2);
+        let now = Instant::now();
+        let hashes = default_mock_txs(3)
+            .into_iter()
+            .map(|stx| stx.transaction.hash)
+            .collect::<Vec<_>>();
+
+        assert!(recent.insert(hashes[0], now));
+        assert!(!recent.insert(hashes[0], now + ttl / 2));
+        assert!(recent.insert(hashes[0], now + ttl));
+
+        // The oldest hash is evicted once the capacity is reached.
+        assert!(recent.insert(hashes[1], now + ttl));
+        assert!(recent.insert(hashes[2], now + ttl));
+        assert!(recent.insert(hashes[0], now + ttl));
+    }
 }
@@ -0,0 +1,179 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use parking_lot::Mutex;
+
+use protocol::codec::ProtocolCodec;
+use protocol::types::SignedTransaction;
+use protocol::ProtocolResult;
+
+use crate::MemPoolError;
+
+const JOURNAL_FILE: &str = "pool.journal";
+const RECORD_LEN_BYTES: usize = 4;
+
+/// An append only file of the transactions admitted into the pool, which is
+/// replayed on startup. Each record is the length of the encoded transaction
+/// in 4 big endian bytes followed by the transaction.
+///
+/// The appended records are queued and written by `write_pending` off the
+/// admission path, one `sync_data` for all the records queued meanwhile.
+pub struct PoolJournal {
+    path:    PathBuf,
+    file:    Mutex<File>,
+    pending: Mutex<PendingRecords>,
+    records: AtomicUsize,
+}
+
+#[derive(Default)]
+struct PendingRecords {
+    bytes:   Vec<u8>,
+    count:   usize,
+    writing: bool,
+}
+
+impl PoolJournal {
+    pub fn open<P: AsRef<Path>>(dir: P) -> ProtocolResult<Self> {
+        fs::create_dir_all(&dir).map_err(MemPoolError::Journal)?;
+        let path = dir.as_ref().join(JOURNAL_FILE);
+        let file = open_append(&path)?;
+
+        Ok(PoolJournal {
+            path,
+            file: Mutex::new(file),
+            pending: Mutex::new(PendingRecords::default()),
+            records: AtomicUsize::new(0),
+        })
+    }
+
+    /// Queue the record of `tx` without touching the file. Return true if no
+    /// writer is running, then the caller has to run `write_pending`.
+    pub fn append(&self, tx: &SignedTransaction) -> ProtocolResult<bool> {
+        let record = encode_record(tx)?;
+        let mut pending = self.pending.lock();
+        pending.bytes.extend(record);
+        pending.count += 1;
+        Ok(!std::mem::replace(&mut pending.writing, true))
+    }
+
+    /// Write the queued records until none is left. This blocks on the file
+    /// so it is meant for a blocking thread.
+    pub fn write_pending(&self) -> ProtocolResult<()> {
+        loop {
+            let mut file = self.file.lock();
+            let (bytes, count) = {
+                let mut pending = self.pending.lock();
+                if pending.count == 0 {
+                    pending.writing = false;
+                    return Ok(());
+                }
+                (
+                    std::mem::take(&mut pending.bytes),
+                    std::mem::take(&mut pending.count),
+                )
+            };
+
+            if let Err(e) = file.write_all(&bytes).and_then(|_| file.sync_data()) {
+                self.pending.lock().writing = false;
+                return Err(MemPoolError::Journal(e).into());
+            }
+            self.records.fetch_add(count, Ordering::AcqRel);
+        }
+    }
+
+    /// Whether the records of the transactions which left the pool are more
+    /// than the ones of the `pooled` transactions, so a rewrite is worth it.
+    pub fn needs_compaction(&self, pooled: usize) -> bool {
+        self.records.load(Ordering::Acquire) > pooled.saturating_mul(2)
+    }
+
+    /// Read back the journaled transactions. A torn record at the end, left
+    /// by a crash in the middle of a write, is ignored.
+    pub fn load(&self) -> ProtocolResult<Vec<SignedTransaction>> {
+        let mut bytes = Vec::new();
+        File::open(&self.path)
+            .and_then(|mut file| file.read_to_end(&mut bytes))
+            .map_err(MemPoolError::Journal)?;
+
+        let mut txs = Vec::new();
+        let mut rest = bytes.as_slice();
+        while rest.len() >= RECORD_LEN_BYTES {
+            let mut len = [0u8; RECORD_LEN_BYTES];
+            len.copy_from_slice(&rest[..RECORD_LEN_BYTES]);
+            let end = RECORD_LEN_BYTES + u32::from_be_bytes(len) as usize;
+            if rest.len() < end {
+                break;
+            }
+
+            match SignedTransaction::decode(&rest[RECORD_LEN_BYTES..end]) {
+                Ok(tx) => txs.push(tx),
+                Err(e) => {
+                    log::warn!("[mempool]: stop loading the pool journal at {:?}", e);
+                    break;
+                }
+            }
+            rest = &rest[end..];
+        }
+
+        self.records.store(txs.len(), Ordering::Release);
+        Ok(txs)
+    }
+
+    /// Replace the records with the transactions `snapshot` returns,
+    /// dropping the ones which left the pool. The snapshot is taken with the
+    /// appends held and replaces the queued records, so a transaction
+    /// appended meanwhile is either in it or appended to the new file.
+    pub fn rewrite<F>(&self, snapshot: F) -> ProtocolResult<()>
+    where
+        F: FnOnce() -> Vec<SignedTransaction>,
+    {
+        let mut file = self.file.lock();
+        let txs = {
+            let mut pending = self.pending.lock();
+            pending.bytes.clear();
+            pending.count = 0;
+            snapshot()
+        };
+
+        let mut bytes = Vec::new();
+        for tx in txs.iter() {
+            bytes.extend(encode_record(tx)?);
+        }
+        let tmp_path = self.path.with_extension("tmp");
+        File::create(&tmp_path)
+            .and_then(|mut tmp| {
+                tmp.write_all(&bytes)?;
+                tmp.sync_all()
+            })
+            .map_err(MemPoolError::Journal)?;
+        fs::rename(&tmp_path, &self.path).map_err(MemPoolError::Journal)?;
+        if let Some(dir) = self.path.parent() {
+            File::open(dir)
+                .and_then(|dir| dir.sync_all())
+                .map_err(MemPoolError::Journal)?;
+        }
+
+        *file = open_append(&self.path)?;
+        self.records.store(txs.len(), Ordering::Release);
+        Ok(())
+    }
+}
+
+fn open_append(path: &Path) -> ProtocolResult<File> {
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(MemPoolError::Journal)?;
+    Ok(file)
+}
+
+fn encode_record(tx: &SignedTransaction) -> ProtocolResult<Vec<u8>> {
+    let bytes = tx.encode()?;
+    let mut record = Vec::with_capacity(RECORD_LEN_BYTES + bytes.len());
+    record.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    record.extend_from_slice(&bytes);
+    Ok(record)
+}
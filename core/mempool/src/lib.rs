@@ -3,6 +3,7 @@
 
 mod adapter;
 mod context;
+mod journal;
 mod pool;
 #[cfg(test)]
 mod tests;
@@ -13,25 +14,115 @@ pub use adapter::message::{
     RPC_PULL_TXS, RPC_RESP_PULL_TXS, RPC_RESP_PULL_TXS_SYNC,
 };
 pub use adapter::DefaultMemPoolAdapter;
-pub use adapter::{DEFAULT_BROADCAST_TXS_INTERVAL, DEFAULT_BROADCAST_TXS_SIZE};
+pub use adapter::{
+    DEFAULT_BROADCAST_DEDUP_TTL, DEFAULT_BROADCAST_TXS_INTERVAL, DEFAULT_BROADCAST_TXS_SIZE,
+};
+pub use journal::PoolJournal;
 
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
 use std::error::Error;
 use std::sync::Arc;
 use std::time::Instant;
 
 use futures::future::try_join_all;
+use parking_lot::Mutex;
 
+use protocol::tokio::sync::broadcast;
 use protocol::traits::{Context, MemPool, MemPoolAdapter};
-use protocol::types::{Hash, SignedTransaction, H256, U256};
+use protocol::types::{Hash, SignedTransaction, TxPoolStatus, H256, U256};
 use protocol::{async_trait, tokio, Display, ProtocolError, ProtocolErrorKind, ProtocolResult};
 
 use crate::context::TxContext;
 use crate::pool::PirorityPool;
 
+const POOL_TX_CHANNEL_SIZE: usize = 1024;
+const MAX_BROADCAST_RECORDS: usize = 100_000;
+
+lazy_static::lazy_static! {
+    pub static ref POOL_TX_NOTIFIER: broadcast::Sender<PoolTxEvent> = broadcast::channel(POOL_TX_CHANNEL_SIZE).0;
+    pub static ref TX_BROADCAST_NOTIFIER: broadcast::Sender<TxBroadcastEvent> = broadcast::channel(POOL_TX_CHANNEL_SIZE).0;
+    static ref BROADCAST_RECORDS: Mutex<BroadcastRecords> = Mutex::new(BroadcastRecords::default());
+}
+
+#[derive(Clone, Debug)]
+pub enum PoolTxEvent {
+    /// A transaction inserted into the pool. `replaces` is the hash of the
+    /// transaction with the same sender and nonce that the new one
+    /// supersedes. The transactions a flush leaves in the pool are not
+    /// inserted again.
+    Inserted {
+        tx:       SignedTransaction,
+        replaces: Option<Hash>,
+    },
+    /// The transactions removed from the pool once a block is committed,
+    /// the committed ones and the dropped ones.
+    Removed(Vec<Hash>),
+}
+
+/// Notify the subscribers of `POOL_TX_NOTIFIER` that a transaction has been
+/// inserted. The transaction is only cloned when somebody is listening.
+pub(crate) fn notify_pool_insert(tx: &SignedTransaction, replaces: Option<Hash>) {
+    if POOL_TX_NOTIFIER.receiver_count() > 0 {
+        let _ = POOL_TX_NOTIFIER.send(PoolTxEvent::Inserted {
+            tx: tx.clone(),
+            replaces,
+        });
+    }
+}
+
+/// Notify the subscribers of `POOL_TX_NOTIFIER` that transactions have been
+/// removed.
+pub(crate) fn notify_pool_remove(hashes: Vec<Hash>) {
+    if !hashes.is_empty() && POOL_TX_NOTIFIER.receiver_count() > 0 {
+        let _ = POOL_TX_NOTIFIER.send(PoolTxEvent::Removed(hashes));
+    }
+}
+
+/// A batch of transactions handed to the gossip layer successfully.
+#[derive(Clone, Debug)]
+pub struct TxBroadcastEvent {
+    pub hashes: Vec<Hash>,
+}
+
+/// The hashes of the last `MAX_BROADCAST_RECORDS` gossiped transactions, for
+/// the subscribers of `TX_BROADCAST_NOTIFIER` lagging behind.
+#[derive(Default)]
+struct BroadcastRecords {
+    hashes: HashSet<Hash>,
+    order:  VecDeque<Hash>,
+}
+
+/// Notify the subscribers of `TX_BROADCAST_NOTIFIER` that a batch of
+/// transactions has been gossiped to the peers.
+pub fn notify_tx_broadcast(hashes: Vec<Hash>) {
+    {
+        let mut records = BROADCAST_RECORDS.lock();
+        for hash in hashes.iter() {
+            if records.hashes.insert(*hash) {
+                records.order.push_back(*hash);
+            }
+        }
+        while records.order.len() > MAX_BROADCAST_RECORDS {
+            let old = records.order.pop_front().unwrap();
+            records.hashes.remove(&old);
+        }
+    }
+
+    if TX_BROADCAST_NOTIFIER.receiver_count() > 0 {
+        let _ = TX_BROADCAST_NOTIFIER.send(TxBroadcastEvent { hashes });
+    }
+}
+
+/// Whether the transaction is among the recently gossiped ones, for a
+/// subscriber that missed the events of `TX_BROADCAST_NOTIFIER`.
+pub fn is_tx_broadcast(hash: &Hash) -> bool {
+    BROADCAST_RECORDS.lock().hashes.contains(hash)
+}
+
 pub struct MemPoolImpl<Adapter> {
-    pool:    PirorityPool,
+    pool:    Arc<PirorityPool>,
     adapter: Arc<Adapter>,
+    journal: Option<Arc<PoolJournal>>,
 }
 
 impl<Adapter> MemPoolImpl<Adapter>
@@ -44,8 +135,9 @@ where
         initial_txs: Vec<SignedTransaction>,
     ) -> Self {
         let mempool = MemPoolImpl {
-            pool:    PirorityPool::new(pool_size).await,
+            pool:    Arc::new(PirorityPool::new(pool_size).await),
             adapter: Arc::new(adapter),
+            journal: None,
         };
 
         for tx in initial_txs.into_iter() {
@@ -57,6 +149,36 @@ where
         mempool
     }
 
+    /// Write the admitted transactions ahead into `journal`. Call
+    /// `restore_journal` once the state is ready to replay the ones written
+    /// before the restart.
+    pub fn with_journal(mut self, journal: PoolJournal) -> Self {
+        self.journal = Some(Arc::new(journal));
+        self
+    }
+
+    /// Replay the journaled transactions into the pool and return how many
+    /// are restored. The ones no longer valid, like a nonce now too low or a
+    /// sender who cannot pay any more, are dropped from the journal.
+    pub async fn restore_journal(&self) -> ProtocolResult<usize> {
+        let journal = match self.journal.as_ref() {
+            Some(journal) => journal,
+            None => return Ok(0),
+        };
+
+        let mut restored = 0;
+        for tx in journal.load()?.into_iter() {
+            let tx_hash = tx.transaction.hash;
+            match self.replay_tx(Context::new(), tx).await {
+                Ok(()) => restored += 1,
+                Err(e) => log::info!("[mempool]: drop journaled tx {:?}, {}", tx_hash, e),
+            }
+        }
+        journal.rewrite(|| self.pool.all_txs())?;
+
+        Ok(restored)
+    }
+
     pub fn len(&self) -> usize {
         self.pool.len()
     }
@@ -89,8 +211,28 @@ where
         self.pool.insert(stx)
     }
 
+    async fn replay_tx(&self, ctx: Context, tx: SignedTransaction) -> ProtocolResult<()> {
+        if self.pool.contains(&tx.transaction.hash) {
+            return Err(MemPoolError::Dup(tx.transaction.hash).into());
+        }
+
+        self.adapter.check_authorization(ctx.clone(), &tx).await?;
+        self.adapter.check_transaction(ctx.clone(), &tx).await?;
+        self.adapter.check_balance(ctx.clone(), &tx).await?;
+        self.adapter
+            .check_storage_exist(ctx.clone(), &tx.transaction.hash)
+            .await?;
+
+        self.pool.insert(tx.clone())?;
+        self.adapter.broadcast_tx(ctx, tx).await
+    }
+
     async fn insert_tx(&self, ctx: Context, tx: SignedTransaction) -> ProtocolResult<()> {
         let tx_hash = &tx.transaction.hash;
+        if self.pool.contains(tx_hash) {
+            self.adapter.report_duplicate(ctx, tx_hash);
+            return Err(MemPoolError::Dup(*tx_hash).into());
+        }
         if self.pool.reach_limit() {
             return Err(MemPoolError::ReachLimit(self.pool.pool_size()).into());
         }
@@ -102,6 +244,20 @@ where
             .await?;
 
         self.pool.insert(tx.clone())?;
+        if let Some(journal) = self.journal.as_ref() {
+            match journal.append(&tx) {
+                Ok(true) => {
+                    let journal = Arc::clone(journal);
+                    tokio::task::spawn_blocking(move || {
+                        if let Err(e) = journal.write_pending() {
+                            log::warn!("[mempool]: write pool journal failed {:?}", e);
+                        }
+                    });
+                }
+                Ok(false) => (),
+                Err(e) => log::warn!("[mempool]: journal tx {:?} failed {:?}", tx_hash, e),
+            }
+        }
 
         if !ctx.is_network_origin_txs() {
             self.adapter.broadcast_tx(ctx, tx).await?;
@@ -191,7 +347,22 @@ where
             "[core_mempool]: flush mempool with {:?} tx_hashes",
             tx_hashes.len(),
         );
-        self.pool.flush(tx_hashes)
+        self.pool.flush(tx_hashes)?;
+
+        // Compact the journal off the async runtime once most of its records
+        // are of the transactions gone.
+        if let Some(journal) = self.journal.as_ref() {
+            if journal.needs_compaction(self.pool.len()) {
+                let journal = Arc::clone(journal);
+                let pool = Arc::clone(&self.pool);
+                tokio::task::spawn_blocking(move || {
+                    if let Err(e) = journal.rewrite(|| pool.all_txs()) {
+                        log::warn!("[mempool]: compact pool journal failed {:?}", e);
+                    }
+                });
+            }
+        }
+        Ok(())
     }
 
     // This method is used to handle fetch signed transactions rpc request from
@@ -293,6 +464,18 @@ where
         self.adapter
             .set_args(context, state_root, timeout_gap, gas_limit, max_tx_size);
     }
+
+    fn get_tx_status(&self, _ctx: Context, tx_hash: &Hash) -> Option<TxPoolStatus> {
+        self.pool.tx_status(tx_hash)
+    }
+
+    fn get_pending_txs(&self, _ctx: Context) -> Vec<SignedTransaction> {
+        self.pool.pending_txs()
+    }
+
+    fn get_txs_inserted_since(&self, _ctx: Context, cursor: Option<u64>) -> (u64, Vec<Hash>) {
+        self.pool.inserted_since(cursor)
+    }
 }
 
 fn check_dup_order_hashes(order_tx_hashes: &[Hash]) -> ProtocolResult<()> {
@@ -344,6 +527,18 @@ pub enum MemPoolError {
     #[display(fmt = "Tx nonce {} is invalid current nonce {}", tx_nonce, current)]
     InvalidNonce { current: u64, tx_nonce: u64 },
 
+    #[display(
+        fmt = "Tx: {:?} costs {} but the sender has {}",
+        tx_hash,
+        cost,
+        balance
+    )]
+    InsufficientBalance {
+        tx_hash: Hash,
+        balance: U256,
+        cost:    U256,
+    },
+
     #[display(fmt = "Tx: {:?} inserts failed", _0)]
     Insert(Hash),
 
@@ -391,6 +586,9 @@ pub enum MemPoolError {
 
     #[display(fmt = "Encode transaction to JSON failed")]
     EncodeJson,
+
+    #[display(fmt = "Pool journal error {:?}", _0)]
+    Journal(std::io::Error),
 }
 
 impl Error for MemPoolError {}
@@ -1,4 +1,4 @@
-use std::collections::{BTreeMap, BinaryHeap, HashSet};
+use std::collections::{BTreeMap, BinaryHeap, HashSet, VecDeque};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -7,17 +7,26 @@ use dashmap::DashMap;
 use parking_lot::Mutex;
 
 use protocol::tokio::{self, time::sleep};
-use protocol::types::{Hash, SignedTransaction, H160, U256};
+use protocol::types::{Hash, SignedTransaction, TxPoolStatus, H160, U256};
 use protocol::ProtocolResult;
 
 use crate::tx_wrapper::{TxPtr, TxWrapper};
-use crate::MemPoolError;
+use crate::{notify_pool_insert, notify_pool_remove, MemPoolError};
+
+/// The hashes of the recently inserted transactions, numbered in insertion
+/// order starting from 1.
+#[derive(Default)]
+struct InsertionLog {
+    last_seq: u64,
+    entries:  VecDeque<(u64, Hash)>,
+}
 
 pub struct PirorityPool {
     occupied_nonce: DashMap<H160, BTreeMap<U256, TxPtr>>,
     co_queue:       Arc<ArrayQueue<TxPtr>>,
     real_queue:     Arc<Mutex<BinaryHeap<TxPtr>>>,
     tx_map:         DashMap<Hash, SignedTransaction>,
+    insertion_log:  Mutex<InsertionLog>,
 
     flush_lock: Arc<Mutex<()>>,
 }
@@ -29,6 +38,7 @@ impl PirorityPool {
             co_queue:       Arc::new(ArrayQueue::new(size * 2)),
             real_queue:     Arc::new(Mutex::new(BinaryHeap::with_capacity(size))),
             tx_map:         DashMap::new(),
+            insertion_log:  Mutex::new(InsertionLog::default()),
             flush_lock:     Arc::new(Mutex::new(())),
         };
 
@@ -53,16 +63,70 @@ impl PirorityPool {
     }
 
     pub fn insert(&self, stx: SignedTransaction) -> ProtocolResult<()> {
+        let (hash, stx, replaces) = self.queue_tx(stx)?;
+        self.log_insertion(hash);
+        notify_pool_insert(&stx, replaces);
+        self.tx_map.insert(hash, stx);
+        Ok(())
+    }
+
+    /// Put the transaction back after a flush. It was logged and notified
+    /// when it was first inserted, so it is neither again.
+    fn reinsert(&self, stx: SignedTransaction) -> ProtocolResult<()> {
+        let (hash, stx, _) = self.queue_tx(stx)?;
+        self.tx_map.insert(hash, stx);
+        Ok(())
+    }
+
+    /// Queue the transaction for packaging and occupy its nonce, returning
+    /// its hash, the transaction and the hash of the one it replaces, if any.
+    fn queue_tx(
+        &self,
+        stx: SignedTransaction,
+    ) -> ProtocolResult<(Hash, SignedTransaction, Option<Hash>)> {
         if self.co_queue.is_full() {
             return Err(MemPoolError::ReachLimit(self.co_queue.len()).into());
         }
 
         let tx_wrapper = TxWrapper::from(stx);
         let _ = self.co_queue.push(tx_wrapper.ptr());
-        self.occupy_nonce(tx_wrapper.ptr());
-        self.tx_map
-            .insert(tx_wrapper.hash(), tx_wrapper.into_signed_transaction());
-        Ok(())
+        let replaces = self.occupy_nonce(tx_wrapper.ptr());
+        Ok((
+            tx_wrapper.hash(),
+            tx_wrapper.into_signed_transaction(),
+            replaces,
+        ))
+    }
+
+    fn log_insertion(&self, hash: Hash) {
+        let mut log = self.insertion_log.lock();
+        if log.entries.len() >= self.pool_size() {
+            log.entries.pop_front();
+        }
+
+        log.last_seq += 1;
+        let seq = log.last_seq;
+        log.entries.push_back((seq, hash));
+    }
+
+    /// Return the sequence number of the last inserted transaction along with
+    /// the hashes of the transactions inserted after `cursor`, including the
+    /// ones already packaged or dropped since. Only the last `pool_size`
+    /// insertions are kept. Without a cursor only the sequence number is
+    /// returned.
+    pub fn inserted_since(&self, cursor: Option<u64>) -> (u64, Vec<Hash>) {
+        let log = self.insertion_log.lock();
+        let hashes = match cursor {
+            Some(cursor) => log
+                .entries
+                .iter()
+                .filter(|(seq, _)| *seq > cursor)
+                .map(|(_, hash)| *hash)
+                .collect(),
+            None => Vec::new(),
+        };
+
+        (log.last_seq, hashes)
     }
 
     pub fn package(&self, _gas_limit: U256, limit: usize) -> Vec<Hash> {
@@ -95,32 +159,80 @@ impl PirorityPool {
         self.tx_map.get(hash).map(|r| r.clone())
     }
 
+    pub fn tx_status(&self, hash: &Hash) -> Option<TxPoolStatus> {
+        let (sender, nonce) = self
+            .tx_map
+            .get(hash)
+            .map(|stx| (stx.sender, stx.transaction.unsigned.nonce))?;
+        let nonces = self.occupied_nonce.get(&sender)?;
+
+        match nonces.get(&nonce) {
+            Some(ptr) if ptr.hash == *hash && !ptr.is_dropped() => {}
+            _ => return Some(TxPoolStatus::Dropped),
+        }
+
+        // The transaction is queued if there is a nonce gap before it.
+        let lowest = *nonces.keys().next()?;
+        let continuous = nonces.range(lowest..=nonce).count();
+        if U256::from(continuous) == nonce - lowest + 1 {
+            Some(TxPoolStatus::Pending)
+        } else {
+            Some(TxPoolStatus::Queued)
+        }
+    }
+
+    /// Return the transactions which are ready to be packaged, excluding the
+    /// queued and dropped ones.
+    pub fn pending_txs(&self) -> Vec<SignedTransaction> {
+        let txs = self
+            .tx_map
+            .iter()
+            .map(|r| r.value().clone())
+            .collect::<Vec<_>>();
+
+        txs.into_iter()
+            .filter(|stx| self.tx_status(&stx.transaction.hash) == Some(TxPoolStatus::Pending))
+            .collect()
+    }
+
+    /// Return every transaction in the pool, including the queued ones.
+    pub fn all_txs(&self) -> Vec<SignedTransaction> {
+        self.tx_map.iter().map(|r| r.value().clone()).collect()
+    }
+
     pub fn flush(&self, hashes: &[Hash]) -> ProtocolResult<()> {
         let _flushing = self.flush_lock.lock();
 
         self.topple_queue();
-        let residual = self.get_residual(hashes);
+        let (removed, residual) = self.get_residual(hashes);
 
         self.clear_all();
 
         for tx in residual.into_iter() {
-            self.insert(tx)?;
+            self.reinsert(tx)?;
         }
 
+        notify_pool_remove(removed);
         Ok(())
     }
 
-    fn get_residual(&self, hashes: &[Hash]) -> Vec<SignedTransaction> {
+    /// Remove the committed and dropped transactions, and return their hashes
+    /// along with the transactions left.
+    fn get_residual(&self, hashes: &[Hash]) -> (Vec<Hash>, Vec<SignedTransaction>) {
         let hashes = hashes.iter().collect::<HashSet<_>>();
         let q = self.real_queue.lock();
 
+        let mut removed = Vec::new();
         for tx_ptr in q.iter() {
-            if hashes.contains(&tx_ptr.hash()) || tx_ptr.is_dropped() {
-                self.tx_map.remove(tx_ptr.hash());
+            if (hashes.contains(&tx_ptr.hash()) || tx_ptr.is_dropped())
+                && self.tx_map.remove(tx_ptr.hash()).is_some()
+            {
+                removed.push(*tx_ptr.hash());
             }
         }
 
-        self.tx_map.iter().map(|kv| kv.value().clone()).collect()
+        let residual = self.tx_map.iter().map(|kv| kv.value().clone()).collect();
+        (removed, residual)
     }
 
     fn topple_queue(&self) {
@@ -135,15 +247,15 @@ impl PirorityPool {
         self.real_queue.lock().clear();
     }
 
-    fn occupy_nonce(&self, tx_ptr: TxPtr) {
-        if let Some(old_ptr) = self
+    /// Return the hash of the transaction replaced by `tx_ptr`, if any.
+    fn occupy_nonce(&self, tx_ptr: TxPtr) -> Option<Hash> {
+        let old_ptr = self
             .occupied_nonce
             .entry(tx_ptr.sender)
             .or_insert_with(BTreeMap::new)
-            .insert(tx_ptr.nonce, tx_ptr)
-        {
-            old_ptr.set_dropped();
-        }
+            .insert(tx_ptr.nonce, tx_ptr)?;
+        old_ptr.set_dropped();
+        Some(old_ptr.hash)
     }
 
     #[cfg(test)]
@@ -2,9 +2,11 @@ use std::sync::Arc;
 
 use test::Bencher;
 
+use protocol::tokio::sync::broadcast::error::TryRecvError;
 use protocol::types::Hasher;
 
 use super::*;
+use crate::context::TxContext;
 
 macro_rules! insert {
     (normal($pool_size: expr, $input: expr, $output: expr)) => {
@@ -119,6 +121,188 @@ async fn test_flush() {
     assert_eq!(mempool.get_tx_cache().len(), 432);
 }
 
+#[tokio::test]
+async fn test_flush_keeps_insertion_log() {
+    let mempool = Arc::new(default_mempool().await);
+    let (start, _) = mempool.get_txs_inserted_since(Context::new(), None);
+    let txs = default_mock_txs(3);
+    concurrent_insert(txs.clone(), Arc::clone(&mempool)).await;
+
+    let (cursor, hashes) = mempool.get_txs_inserted_since(Context::new(), Some(start));
+    assert_eq!(hashes.len(), 3);
+
+    // The transactions left in the pool are not inserted again.
+    exec_flush(vec![], Arc::clone(&mempool)).await;
+    assert_eq!(mempool.len(), 3);
+    let (last, hashes) = mempool.get_txs_inserted_since(Context::new(), Some(cursor));
+    assert_eq!(last, cursor);
+    assert!(hashes.is_empty());
+}
+
+#[tokio::test]
+async fn test_tx_status() {
+    let mempool = Arc::new(default_mempool().await);
+    let priv_key = Secp256k1RecoverablePrivateKey::generate(&mut OsRng);
+    let pub_key = priv_key.pub_key();
+
+    let replaced = mock_signed_tx(&priv_key, &pub_key, TIMEOUT, 0, true);
+    let pending = mock_signed_tx(&priv_key, &pub_key, TIMEOUT, 0, true);
+    let queued = mock_signed_tx(&priv_key, &pub_key, TIMEOUT, 2, true);
+    for tx in [replaced.clone(), pending.clone(), queued.clone()] {
+        exec_insert(tx, Arc::clone(&mempool)).await;
+    }
+
+    let status =
+        |tx: &SignedTransaction| mempool.get_tx_status(Context::new(), &tx.transaction.hash);
+    assert_eq!(status(&replaced), Some(TxPoolStatus::Dropped));
+    assert_eq!(status(&pending), Some(TxPoolStatus::Pending));
+    assert_eq!(status(&queued), Some(TxPoolStatus::Queued));
+    assert_eq!(status(&default_mock_txs(1)[0]), None);
+}
+
+#[tokio::test]
+async fn test_replacement_is_notified() {
+    let mempool = Arc::new(default_mempool().await);
+    let mut rx = POOL_TX_NOTIFIER.subscribe();
+    let priv_key = Secp256k1RecoverablePrivateKey::generate(&mut OsRng);
+    let pub_key = priv_key.pub_key();
+
+    let replaced = mock_signed_tx(&priv_key, &pub_key, TIMEOUT, 0, true);
+    let replacement = mock_signed_tx(&priv_key, &pub_key, TIMEOUT, 0, true);
+    let mut events = Vec::new();
+    for tx in [replaced.clone(), replacement.clone()] {
+        exec_insert(tx, Arc::clone(&mempool)).await;
+
+        // Other tests insert into their own pools concurrently, so drain
+        // right away and keep the events of this sender only.
+        loop {
+            match rx.try_recv() {
+                Ok(PoolTxEvent::Inserted { tx, replaces }) if tx.sender == replaced.sender => {
+                    events.push((tx, replaces))
+                }
+                Ok(_) | Err(TryRecvError::Lagged(_)) => continue,
+                Err(_) => break,
+            }
+        }
+    }
+
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0], (replaced.clone(), None));
+    assert_eq!(events[1], (replacement, Some(replaced.transaction.hash)));
+}
+
+#[tokio::test]
+async fn test_flush_notifies_removal() {
+    let mempool = Arc::new(default_mempool().await);
+    let txs = default_mock_txs(3);
+    concurrent_insert(txs.clone(), Arc::clone(&mempool)).await;
+
+    let mut rx = POOL_TX_NOTIFIER.subscribe();
+    let committed = txs[0].transaction.hash;
+    exec_flush(vec![committed], Arc::clone(&mempool)).await;
+
+    // Other tests insert into and flush their own pools concurrently.
+    let mut removed = Vec::new();
+    let mut inserted = Vec::new();
+    loop {
+        match rx.try_recv() {
+            Ok(PoolTxEvent::Removed(hashes)) => removed.extend(hashes),
+            Ok(PoolTxEvent::Inserted { tx, .. }) => inserted.push(tx.transaction.hash),
+            Err(TryRecvError::Lagged(_)) => continue,
+            Err(_) => break,
+        }
+    }
+
+    assert!(removed.contains(&committed));
+    assert!(!removed.contains(&txs[1].transaction.hash));
+    // The transactions left in the pool are not announced again.
+    assert!(txs
+        .iter()
+        .all(|tx| !inserted.contains(&tx.transaction.hash)));
+}
+
+// The journal is written in the background.
+async fn wait_journaled(journal: &PoolJournal, len: usize) -> Vec<SignedTransaction> {
+    for _ in 0..100 {
+        let txs = journal.load().unwrap();
+        if txs.len() == len {
+            return txs;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    journal.load().unwrap()
+}
+
+#[tokio::test]
+async fn test_pool_journal_restore() {
+    let dir = tempfile::tempdir().unwrap();
+    let txs = default_mock_txs(3);
+
+    let mempool = default_mempool()
+        .await
+        .with_journal(PoolJournal::open(dir.path()).unwrap());
+    for tx in txs.iter() {
+        mempool.insert(Context::new(), tx.clone()).await.unwrap();
+    }
+    let journal = PoolJournal::open(dir.path()).unwrap();
+    assert_eq!(wait_journaled(&journal, 3).await.len(), 3);
+    drop(mempool);
+
+    // The sender of the last transaction cannot pay for it after the restart.
+    let adapter = HashMemPoolAdapter::new();
+    adapter.balances.insert(txs[2].sender, U256::zero());
+    let mempool = MemPoolImpl::new(POOL_SIZE, adapter, vec![])
+        .await
+        .with_journal(PoolJournal::open(dir.path()).unwrap());
+    assert_eq!(mempool.restore_journal().await.unwrap(), 2);
+    assert!(mempool.get_tx_cache().contains(&txs[0].transaction.hash));
+    assert!(mempool.get_tx_cache().contains(&txs[1].transaction.hash));
+    assert!(!mempool.get_tx_cache().contains(&txs[2].transaction.hash));
+
+    let journaled = PoolJournal::open(dir.path()).unwrap().load().unwrap();
+    assert_eq!(journaled.len(), 2);
+}
+
+#[tokio::test]
+async fn test_reject_duplicate_tx() {
+    let mempool = default_mempool().await;
+    let tx = default_mock_txs(1).remove(0);
+    let hash = tx.transaction.hash;
+
+    mempool.insert(Context::new(), tx.clone()).await.unwrap();
+    assert!(mempool.get_adapter().duplicates.get(&hash).is_none());
+
+    let ctx = Context::new().mark_network_origin_new_txs();
+    assert!(mempool.insert(ctx, tx).await.is_err());
+    assert_eq!(*mempool.get_adapter().duplicates.get(&hash).unwrap(), 1);
+    assert_eq!(mempool.len(), 1);
+}
+
+#[tokio::test]
+async fn test_pool_journal_compact_on_flush() {
+    let dir = tempfile::tempdir().unwrap();
+    let txs = default_mock_txs(3);
+
+    let mempool = default_mempool()
+        .await
+        .with_journal(PoolJournal::open(dir.path()).unwrap());
+    for tx in txs.iter() {
+        mempool.insert(Context::new(), tx.clone()).await.unwrap();
+    }
+    let journal = PoolJournal::open(dir.path()).unwrap();
+    assert_eq!(wait_journaled(&journal, 3).await.len(), 3);
+
+    mempool
+        .flush(Context::new(), &[
+            txs[0].transaction.hash,
+            txs[1].transaction.hash,
+        ])
+        .await
+        .unwrap();
+
+    assert_eq!(wait_journaled(&journal, 1).await, vec![txs[2].clone()]);
+}
+
 macro_rules! ensure_order_txs {
     ($in_pool: expr, $out_pool: expr) => {
         let mempool = &Arc::new(default_mempool().await);
@@ -16,11 +16,14 @@ use protocol::codec::ProtocolCodec;
 use protocol::traits::{Context, MemPool, MemPoolAdapter};
 use protocol::types::{
     public_to_address, recover_intact_pub_key, Bytes, Hash, Hasher, Public, SignedTransaction,
-    Transaction, TransactionAction, UnverifiedTransaction, H256, U256,
+    Transaction, TransactionAction, TxPoolStatus, UnverifiedTransaction, H160, H256, U256,
 };
 use protocol::{async_trait, tokio, ProtocolResult};
 
-use crate::{adapter::AdapterError, check_dup_order_hashes, MemPoolError, MemPoolImpl};
+use crate::{
+    adapter::AdapterError, check_dup_order_hashes, MemPoolError, MemPoolImpl, PoolJournal,
+    PoolTxEvent, POOL_TX_NOTIFIER,
+};
 
 const CYCLE_LIMIT: u64 = 1_000_000;
 const TX_NUM_LIMIT: u64 = 10_000;
@@ -32,12 +35,16 @@ const TIMEOUT_GAP: u64 = 100;
 
 pub struct HashMemPoolAdapter {
     network_txs: DashMap<Hash, SignedTransaction>,
+    balances:    DashMap<H160, U256>,
+    duplicates:  DashMap<Hash, usize>,
 }
 
 impl HashMemPoolAdapter {
     fn new() -> HashMemPoolAdapter {
         HashMemPoolAdapter {
             network_txs: DashMap::new(),
+            balances:    DashMap::new(),
+            duplicates:  DashMap::new(),
         }
     }
 }
@@ -81,6 +88,20 @@ impl MemPoolAdapter for HashMemPoolAdapter {
         Ok(())
     }
 
+    async fn check_balance(&self, _ctx: Context, tx: &SignedTransaction) -> ProtocolResult<()> {
+        let unsigned = &tx.transaction.unsigned;
+        let cost = unsigned.gas_limit * unsigned.max_fee_per_gas + unsigned.value;
+        match self.balances.get(&tx.sender) {
+            Some(balance) if *balance < cost => Err(MemPoolError::InsufficientBalance {
+                tx_hash: tx.transaction.hash,
+                balance: *balance,
+                cost,
+            }
+            .into()),
+            _ => Ok(()),
+        }
+    }
+
     async fn check_storage_exist(&self, _ctx: Context, _tx_hash: &Hash) -> ProtocolResult<()> {
         Ok(())
     }
@@ -109,6 +130,10 @@ impl MemPoolAdapter for HashMemPoolAdapter {
     }
 
     fn report_good(&self, _ctx: Context) {}
+
+    fn report_duplicate(&self, _ctx: Context, tx_hash: &Hash) {
+        *self.duplicates.entry(*tx_hash).or_default() += 1;
+    }
 }
 
 pub fn default_mock_txs(size: usize) -> Vec<SignedTransaction> {
@@ -272,7 +297,7 @@ fn mock_transaction(nonce: u64) -> Transaction {
         nonce:                    nonce.into(),
         gas_limit:                U256::one(),
         max_priority_fee_per_gas: U256::one(),
-        gas_price:                U256::one(),
+        max_fee_per_gas:          U256::one(),
         action:                   TransactionAction::Create,
         value:                    U256::one(),
         data:                     random_bytes(32).to_vec().into(),
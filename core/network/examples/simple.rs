@@ -39,7 +39,7 @@ impl MessageHandler for TakeMyMoney {
             Ok(_) => TrustFeedback::Good,
             Err(e) => {
                 log::error!("sell {}", e);
-                TrustFeedback::Bad("sell failed".to_owned())
+                TrustFeedback::Bad("sell failed".to_owned(), None)
             }
         }
     }
@@ -60,7 +60,7 @@ impl MessageHandler for Checkout {
             .await
         {
             Ok(_) => TrustFeedback::Good,
-            Err(e) => TrustFeedback::Bad(format!("send copy {}", e)),
+            Err(e) => TrustFeedback::Bad(format!("send copy {}", e), None),
         }
     }
 }
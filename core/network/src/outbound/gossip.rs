@@ -1,4 +1,6 @@
+use std::future::Future;
 use std::sync::Arc;
+use std::time::Duration;
 
 use tentacle::secio::PeerId;
 use tentacle::service::{ServiceAsyncControl, TargetProtocol, TargetSession};
@@ -9,20 +11,30 @@ use protocol::{async_trait, tokio, types::Bytes, ProtocolResult};
 use crate::endpoint::Endpoint;
 use crate::error::NetworkError;
 use crate::message::{Headers, NetworkMessage};
+use crate::outbound::NetworkRpc;
 use crate::peer_manager::PeerManager;
 use crate::traits::NetworkContext;
 
+/// How long a consensus peer has to acknowledge an acked broadcast.
+const CONSENSUS_ACK_TIMEOUT: Duration = Duration::from_secs(3);
+
 #[derive(Clone)]
 pub struct NetworkGossip {
     pub(crate) transmitter:  ServiceAsyncControl,
     pub(crate) peer_manager: Arc<PeerManager>,
+    rpc:                     NetworkRpc,
 }
 
 impl NetworkGossip {
-    pub fn new(transmitter: ServiceAsyncControl, peer_manager: Arc<PeerManager>) -> Self {
+    pub fn new(
+        transmitter: ServiceAsyncControl,
+        peer_manager: Arc<PeerManager>,
+        rpc: NetworkRpc,
+    ) -> Self {
         NetworkGossip {
             transmitter,
             peer_manager,
+            rpc,
         }
     }
 
@@ -143,6 +155,30 @@ impl Gossip for NetworkGossip {
         Ok(())
     }
 
+    async fn broadcast_to_consensus_acked<M>(
+        &self,
+        cx: Context,
+        endpoint: &str,
+        msg: M,
+    ) -> ProtocolResult<(usize, usize)>
+    where
+        M: MessageCodec + Clone,
+    {
+        let calls = self
+            .peer_manager
+            .connected_consensus_sessions()
+            .into_iter()
+            .map(|sid| {
+                let ctx = cx.clone().set_session_id(sid);
+                self.rpc
+                    .call_raw(ctx, endpoint, msg.clone(), Priority::High)
+            })
+            .collect::<Vec<_>>();
+
+        let sent = calls.len();
+        Ok((sent, count_acks(calls, CONSENSUS_ACK_TIMEOUT).await))
+    }
+
     async fn multicast<'a, M, P>(
         &self,
         mut cx: Context,
@@ -167,3 +203,35 @@ impl Gossip for NetworkGossip {
         Ok(())
     }
 }
+
+/// Count the calls which succeed within `deadline`.
+async fn count_acks<F>(calls: Vec<F>, deadline: Duration) -> usize
+where
+    F: Future<Output = ProtocolResult<Bytes>>,
+{
+    let acks = calls
+        .into_iter()
+        .map(|call| tokio::time::timeout(deadline, call));
+
+    futures::future::join_all(acks)
+        .await
+        .into_iter()
+        .filter(|ret| matches!(ret, Ok(Ok(_))))
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::future::{self, BoxFuture, FutureExt};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_count_acks_of_consensus_peers() {
+        let acked = || future::ready(Ok(Bytes::new())).boxed();
+        let calls: Vec<BoxFuture<'static, ProtocolResult<Bytes>>> =
+            vec![acked(), future::pending().boxed(), acked()];
+
+        assert_eq!(count_acks(calls, Duration::from_millis(50)).await, 2);
+    }
+}
@@ -57,20 +57,17 @@ impl NetworkRpc {
         }
         Ok(())
     }
-}
 
-#[async_trait]
-impl Rpc for NetworkRpc {
-    async fn call<M, R>(
+    /// Send the call and wait for the raw response.
+    pub(crate) async fn call_raw<M>(
         &self,
         mut cx: Context,
         endpoint: &str,
         mut msg: M,
         priority: Priority,
-    ) -> ProtocolResult<R>
+    ) -> ProtocolResult<Bytes>
     where
         M: MessageCodec,
-        R: MessageCodec,
     {
         let endpoint = endpoint.parse::<Endpoint>()?;
         let sid = cx.session_id()?;
@@ -125,7 +122,7 @@ impl Rpc for NetworkRpc {
                     //     .rpc
                     //     .observe(common_apm::metrics::duration_to_sec(inst.elapsed()));
 
-                    Ok(R::decode_msg(v)?)
+                    Ok(v)
                 }
                 RpcResponse::Error(e) => Err(NetworkError::RemoteResponse(e).into()),
             },
@@ -136,6 +133,24 @@ impl Rpc for NetworkRpc {
             }
         }
     }
+}
+
+#[async_trait]
+impl Rpc for NetworkRpc {
+    async fn call<M, R>(
+        &self,
+        cx: Context,
+        endpoint: &str,
+        msg: M,
+        priority: Priority,
+    ) -> ProtocolResult<R>
+    where
+        M: MessageCodec,
+        R: MessageCodec,
+    {
+        let ret = self.call_raw(cx, endpoint, msg, priority).await?;
+        R::decode_msg(ret)
+    }
 
     async fn response<M>(
         &self,
@@ -103,6 +103,15 @@ impl PeerManager {
         unconnected
     }
 
+    pub fn connected_consensus_sessions(&self) -> Vec<SessionId> {
+        let online = self.online.read();
+        self.consensus_list
+            .read()
+            .iter()
+            .filter_map(|id| online.peers.get(id).map(|info| info.session_id))
+            .collect()
+    }
+
     pub fn connected_consensus_peer(&self) -> Vec<Multiaddr> {
         let online = self.online.read();
         let mut list = Vec::new();
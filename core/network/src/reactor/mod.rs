@@ -4,7 +4,7 @@ mod rpc_map;
 use std::convert::TryFrom;
 use std::marker::PhantomData;
 
-use protocol::traits::{Context, MessageCodec, MessageHandler, TrustFeedback};
+use protocol::traits::{Context, MessageCodec, MessageHandler, RejectCode, TrustFeedback};
 use protocol::{async_trait, types::Bytes, ProtocolResult};
 
 use crate::endpoint::{Endpoint, EndpointScheme, RpcEndpoint};
@@ -60,7 +60,7 @@ impl<M: MessageCodec, H: MessageHandler<Message = M>> Reactor for MessageReactor
         // };
 
         let session_id = context.remote_peer.session_id;
-        let _feedback = match endpoint.scheme() {
+        let feedback = match endpoint.scheme() {
             EndpointScheme::Gossip => {
                 let raw_context = Bytes::from(network_message.content);
                 let content = M::decode_msg(raw_context)?;
@@ -113,11 +113,31 @@ impl<M: MessageCodec, H: MessageHandler<Message = M>> Reactor for MessageReactor
             }
         };
 
+        // A bad peer may send many rejected messages, so they are not logged
+        // above debug.
+        if is_misbehavior(&feedback) {
+            log::debug!(
+                "network: reactor: {} rejected from {}, code {:?}",
+                feedback,
+                context.remote_peer,
+                feedback.code()
+            );
+        }
+
         // context.report_feedback(feedback);
         Ok(())
     }
 }
 
+/// Whether the rejection blames the peer, a duplicate is only a race of the
+/// gossip.
+pub(crate) fn is_misbehavior(feedback: &TrustFeedback) -> bool {
+    match feedback {
+        TrustFeedback::Good | TrustFeedback::Neutral => false,
+        _ => feedback.code() != Some(RejectCode::Duplicate),
+    }
+}
+
 #[derive(Debug)]
 pub struct NoopHandler<M> {
     pin_m: PhantomData<fn() -> M>,
@@ -143,3 +163,22 @@ where
         TrustFeedback::Neutral
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duplicate_is_not_misbehavior() {
+        let duplicate = TrustFeedback::Bad("seen".to_owned(), Some(RejectCode::Duplicate));
+        assert_eq!(duplicate.to_string(), "bad seen");
+        assert!(!is_misbehavior(&duplicate));
+
+        let invalid = TrustFeedback::Worse("sig".to_owned(), Some(RejectCode::InvalidSignature));
+        assert!(is_misbehavior(&invalid));
+        assert!(is_misbehavior(&TrustFeedback::Bad("?".to_owned(), None)));
+        assert!(is_misbehavior(&TrustFeedback::Fatal("?".to_owned(), None)));
+        assert!(!is_misbehavior(&TrustFeedback::Neutral));
+        assert!(!is_misbehavior(&TrustFeedback::Good));
+    }
+}
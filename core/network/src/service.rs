@@ -59,6 +59,18 @@ impl Gossip for NetworkServiceHandle {
         self.gossip.broadcast(cx, end, msg, p).await
     }
 
+    async fn broadcast_to_consensus_acked<M>(
+        &self,
+        cx: Context,
+        end: &str,
+        msg: M,
+    ) -> ProtocolResult<(usize, usize)>
+    where
+        M: MessageCodec + Clone,
+    {
+        self.gossip.broadcast_to_consensus_acked(cx, end, msg).await
+    }
+
     async fn multicast<'a, M, P>(
         &self,
         cx: Context,
@@ -284,8 +296,8 @@ impl NetworkService {
 
         let control: ServiceAsyncControl = service.control().clone();
 
-        let gossip = NetworkGossip::new(control.clone(), Arc::clone(&peer_manager));
-        let rpc = NetworkRpc::new(control, message_router);
+        let rpc = NetworkRpc::new(control.clone(), message_router);
+        let gossip = NetworkGossip::new(control, Arc::clone(&peer_manager), rpc.clone());
 
         NetworkService {
             config,
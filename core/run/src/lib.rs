@@ -18,12 +18,12 @@ use common_crypto::{
 };
 use core_api::{jsonrpc::run_jsonrpc_server, DefaultAPIAdapter};
 use core_consensus::message::{
-    ChokeMessageHandler, ProposalMessageHandler, PullBlockRpcHandler, PullProofRpcHandler,
-    PullTxsRpcHandler, QCMessageHandler, RemoteHeightMessageHandler, VoteMessageHandler,
-    BROADCAST_HEIGHT, END_GOSSIP_AGGREGATED_VOTE, END_GOSSIP_SIGNED_CHOKE,
-    END_GOSSIP_SIGNED_PROPOSAL, END_GOSSIP_SIGNED_VOTE, RPC_RESP_SYNC_PULL_BLOCK,
-    RPC_RESP_SYNC_PULL_PROOF, RPC_RESP_SYNC_PULL_TXS, RPC_SYNC_PULL_BLOCK, RPC_SYNC_PULL_PROOF,
-    RPC_SYNC_PULL_TXS,
+    AckedQCRpcHandler, ChokeMessageHandler, ProposalMessageHandler, PullBlockRpcHandler,
+    PullProofRpcHandler, PullTxsRpcHandler, QCMessageHandler, RemoteHeightMessageHandler,
+    VoteMessageHandler, BROADCAST_HEIGHT, END_GOSSIP_AGGREGATED_VOTE, END_GOSSIP_SIGNED_CHOKE,
+    END_GOSSIP_SIGNED_PROPOSAL, END_GOSSIP_SIGNED_VOTE, RPC_ACKED_AGGREGATED_VOTE,
+    RPC_RESP_ACKED_AGGREGATED_VOTE, RPC_RESP_SYNC_PULL_BLOCK, RPC_RESP_SYNC_PULL_PROOF,
+    RPC_RESP_SYNC_PULL_TXS, RPC_SYNC_PULL_BLOCK, RPC_SYNC_PULL_PROOF, RPC_SYNC_PULL_TXS,
 };
 use core_consensus::status::{CurrentStatus, MetadataController, StatusAgent};
 use core_consensus::{
@@ -33,8 +33,8 @@ use core_consensus::{
 use core_cross_client::DefaultCrossAdapter;
 use core_executor::{EVMExecutorAdapter, EvmExecutor, MPTTrie, RocksTrieDB};
 use core_mempool::{
-    DefaultMemPoolAdapter, MemPoolImpl, NewTxsHandler, PullTxsHandler, END_GOSSIP_NEW_TXS,
-    RPC_PULL_TXS, RPC_RESP_PULL_TXS, RPC_RESP_PULL_TXS_SYNC,
+    DefaultMemPoolAdapter, MemPoolImpl, NewTxsHandler, PoolJournal, PullTxsHandler,
+    END_GOSSIP_NEW_TXS, RPC_PULL_TXS, RPC_RESP_PULL_TXS, RPC_RESP_PULL_TXS_SYNC,
 };
 use core_network::{
     observe_listen_port_occupancy, NetworkConfig, NetworkService, PeerId, PeerIdExt,
@@ -136,7 +136,8 @@ impl Axon {
         )?;
 
         let proposal = Proposal::from(self.genesis.block.clone());
-        let executor = EvmExecutor::default();
+        let executor =
+            EvmExecutor::default().with_receipts_trie_fork(self.config.executor.receipts_trie_fork);
         let mut backend = EVMExecutorAdapter::from_root(
             mpt.commit()?,
             trie_db,
@@ -174,6 +175,18 @@ impl Axon {
 
     pub async fn start(self) -> ProtocolResult<()> {
         log::info!("node starts");
+        // A wallet signs for the chain id the RPC reports, so it must be the
+        // one of the chain.
+        let genesis_chain_id = self.genesis.block.header.chain_id;
+        if let Some(id) = self.config.rpc.chain_id {
+            if id != genesis_chain_id {
+                return Err(MainError::ChainIdMismatch {
+                    configured: id,
+                    genesis:    genesis_chain_id,
+                }
+                .into());
+            }
+        }
         observe_listen_port_occupancy(&[self.config.network.listening_address.clone()]).await?;
         let config = self.config.clone();
         // Init Block db
@@ -260,15 +273,19 @@ impl Axon {
             config.mempool.pool_size as usize,
             config.mempool.broadcast_txs_size,
             config.mempool.broadcast_txs_interval,
+            config.mempool.broadcast_dedup_ttl,
+            config.mempool.broadcast_priority_tip,
         );
-        let mempool = Arc::new(
-            MemPoolImpl::new(
-                config.mempool.pool_size as usize,
-                mempool_adapter,
-                current_stxs.clone(),
-            )
-            .await,
-        );
+        let mut mempool = MemPoolImpl::new(
+            config.mempool.pool_size as usize,
+            mempool_adapter,
+            current_stxs.clone(),
+        )
+        .await;
+        if config.mempool.persist_pool {
+            mempool = mempool.with_journal(PoolJournal::open(config.data_path_for_pool_journal())?);
+        }
+        let mempool = Arc::new(mempool);
 
         let monitor_mempool = Arc::clone(&mempool);
         tokio::spawn(async move {
@@ -293,6 +310,9 @@ impl Axon {
         )));
 
         let metadata = METADATA_CONTROLER.load().current();
+        storage
+            .insert_metadata(Context::new(), metadata.clone())
+            .await?;
 
         // register broadcast new transaction
         network_service.register_endpoint_handler(
@@ -341,7 +361,8 @@ impl Axon {
         } else {
             // Init executor
             let proposal = Proposal::from(current_header.clone());
-            let executor = EvmExecutor::default();
+            let executor =
+                EvmExecutor::default().with_receipts_trie_fork(config.executor.receipts_trie_fork);
             let mut backend = EVMExecutorAdapter::from_root(
                 current_header.state_root,
                 Arc::clone(&trie_db),
@@ -374,6 +395,12 @@ impl Axon {
             metadata.max_tx_size,
         );
 
+        // Replay the pool transactions journaled before the restart
+        let restored = mempool.restore_journal().await?;
+        if restored > 0 {
+            log::info!("Restore {} tx from pool journal", restored);
+        }
+
         // start cross chain client
         let cross_client = DefaultCrossAdapter::new(
             self.config.clone(),
@@ -414,6 +441,7 @@ impl Axon {
             Arc::clone(&trie_db),
             Arc::new(cross_handle),
             Arc::clone(&crypto),
+            EvmExecutor::default().with_receipts_trie_fork(config.executor.receipts_trie_fork),
         )?;
 
         let consensus_adapter = Arc::new(consensus_adapter);
@@ -429,6 +457,7 @@ impl Axon {
             Arc::clone(&lock),
             Arc::clone(&consensus_wal),
             self.config.cross_client.checkpoint_interval,
+            self.config.consensus.max_timestamp_drift,
         ));
 
         consensus_adapter.set_overlord_handler(overlord_consensus.get_overlord_handler());
@@ -459,6 +488,13 @@ impl Axon {
             END_GOSSIP_AGGREGATED_VOTE,
             QCMessageHandler::new(Arc::clone(&overlord_consensus)),
         )?;
+        network_service.register_endpoint_handler(
+            RPC_ACKED_AGGREGATED_VOTE,
+            AckedQCRpcHandler::new(
+                Arc::new(network_service.handle()),
+                Arc::clone(&overlord_consensus),
+            ),
+        )?;
         network_service.register_endpoint_handler(
             END_GOSSIP_SIGNED_VOTE,
             VoteMessageHandler::new(Arc::clone(&overlord_consensus)),
@@ -488,6 +524,7 @@ impl Axon {
         network_service.register_rpc_response(RPC_RESP_SYNC_PULL_BLOCK)?;
         network_service.register_rpc_response(RPC_RESP_SYNC_PULL_PROOF)?;
         network_service.register_rpc_response(RPC_RESP_SYNC_PULL_TXS)?;
+        network_service.register_rpc_response(RPC_RESP_ACKED_AGGREGATED_VOTE)?;
 
         let network_handle = network_service.handle();
 
@@ -500,8 +537,14 @@ impl Axon {
             Arc::clone(&storage),
             Arc::clone(&trie_db),
             Arc::new(network_handle),
+            EvmExecutor::with_limits(
+                self.config.executor.call_stack_limit,
+                self.config.executor.call_memory_limit.unwrap_or(usize::MAX),
+            ),
         ));
-        let _handles = run_jsonrpc_server(self.config.rpc.clone(), api_adapter).await?;
+        let mut rpc_config = self.config.rpc.clone();
+        rpc_config.chain_id = Some(genesis_chain_id);
+        let _handles = run_jsonrpc_server(rpc_config, api_adapter).await?;
 
         // Start prometheus http server
         Self::run_prometheus_server(config);
@@ -628,6 +671,13 @@ pub enum MainError {
     #[display(fmt = "{:?}", _0)]
     JSONParse(serde_json::error::Error),
 
+    #[display(
+        fmt = "The configured chain id {} differs from the genesis one {}",
+        configured,
+        genesis
+    )]
+    ChainIdMismatch { configured: u64, genesis: u64 },
+
     #[display(fmt = "other error {:?}", _0)]
     Other(String),
 }
@@ -42,6 +42,7 @@ impl RocksAdapter {
             map_category(StorageCategory::Wal),
             map_category(StorageCategory::HashHeight),
             map_category(StorageCategory::Code),
+            map_category(StorageCategory::Metadata),
         ];
 
         let db = DB::open_cf(&opts, path, categories.iter()).map_err(RocksAdapterError::from)?;
@@ -262,6 +263,7 @@ const C_RECEIPTS: &str = "c4";
 const C_WALS: &str = "c5";
 const C_HASH_HEIGHT_MAP: &str = "c6";
 const C_EVM_CODE_MAP: &str = "c7";
+const C_METADATA: &str = "c8";
 
 fn map_category(c: StorageCategory) -> &'static str {
     match c {
@@ -272,6 +274,7 @@ fn map_category(c: StorageCategory) -> &'static str {
         StorageCategory::Wal => C_WALS,
         StorageCategory::HashHeight => C_HASH_HEIGHT_MAP,
         StorageCategory::Code => C_EVM_CODE_MAP,
+        StorageCategory::Metadata => C_METADATA,
     }
 }
 
@@ -24,8 +24,8 @@ use protocol::traits::{
     StorageSchema,
 };
 use protocol::types::{
-    Block, BlockNumber, Bytes, DBBytes, Hash, Hasher, Header, Proof, Receipt, SignedTransaction,
-    H256,
+    Block, BlockNumber, Bytes, DBBytes, Hash, Hasher, Header, Metadata, Proof, Receipt,
+    SignedTransaction, TxPosition, H256,
 };
 use protocol::{
     async_trait, tokio, Display, From, ProtocolError, ProtocolErrorKind, ProtocolResult,
@@ -37,6 +37,7 @@ lazy_static::lazy_static! {
     pub static ref LATEST_BLOCK_KEY: Hash = Hasher::digest(Bytes::from("latest_hash"));
     pub static ref LATEST_PROOF_KEY: Hash = Hasher::digest(Bytes::from("latest_proof"));
     pub static ref OVERLORD_WAL_KEY: Hash = Hasher::digest(Bytes::from("overlord_wal"));
+    pub static ref LATEST_METADATA_KEY: Hash = Hasher::digest(Bytes::from("latest_metadata"));
 }
 
 macro_rules! get {
@@ -81,17 +82,39 @@ impl<Adapter: StorageAdapter> ImplStorage<Adapter> {
         }
     }
 
+    /// The number of the latest block, `None` before the first one is
+    /// inserted.
+    async fn latest_number(&self) -> ProtocolResult<Option<BlockNumber>> {
+        if let Some(block) = self.latest_block.load().as_ref() {
+            return Ok(Some(block.header.number));
+        }
+
+        Ok(get!(self, *LATEST_BLOCK_KEY, LatestBlockSchema)?.map(|b| b.header.number))
+    }
+
+    /// Whether the block at `height` can be read. `insert_block` stores a
+    /// block before it becomes the latest one, so a block above the latest is
+    /// hidden until then, and the latest number and the blocks readable by
+    /// number always agree.
+    async fn is_visible(&self, height: BlockNumber) -> ProtocolResult<bool> {
+        Ok(self
+            .latest_number()
+            .await?
+            .map_or(true, |latest| height <= latest))
+    }
+
     async fn batch_insert_stxs(
         &self,
         stxs: Vec<SignedTransaction>,
         block_number: BlockNumber,
     ) -> ProtocolResult<()> {
-        let (hashes, heights) = stxs
+        let (hashes, positions) = stxs
             .iter()
-            .map(|item| {
+            .enumerate()
+            .map(|(idx, item)| {
                 (
                     item.transaction.hash,
-                    StorageBatchModify::Insert(block_number),
+                    StorageBatchModify::Insert(TxHashIndex::new(block_number, idx as u32)),
                 )
             })
             .unzip();
@@ -111,7 +134,7 @@ impl<Adapter: StorageAdapter> ImplStorage<Adapter> {
             .await?;
 
         self.adapter
-            .batch_modify::<TxHashNumberSchema>(hashes, heights)
+            .batch_modify::<TxHashNumberSchema>(hashes, positions)
             .await?;
 
         Ok(())
@@ -122,9 +145,14 @@ impl<Adapter: StorageAdapter> ImplStorage<Adapter> {
         receipts: Vec<Receipt>,
         block_number: BlockNumber,
     ) -> ProtocolResult<()> {
-        let (hashes, heights) = receipts
+        let (hashes, positions) = receipts
             .iter()
-            .map(|item| (item.tx_hash, StorageBatchModify::Insert(block_number)))
+            .map(|item| {
+                (
+                    item.tx_hash,
+                    StorageBatchModify::Insert(TxHashIndex::new(block_number, item.tx_index)),
+                )
+            })
             .unzip();
 
         let (keys, batch_stxs): (Vec<_>, Vec<_>) = receipts
@@ -142,13 +170,81 @@ impl<Adapter: StorageAdapter> ImplStorage<Adapter> {
             .await?;
 
         self.adapter
-            .batch_modify::<TxHashNumberSchema>(hashes, heights)
+            .batch_modify::<TxHashNumberSchema>(hashes, positions)
             .await?;
 
         Ok(())
     }
 }
 
+/// The value of the transaction hash index, the block number and the index of
+/// the transaction in it. The entries written before the index kept the
+/// position only hold the block number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TxHashIndex {
+    block_number: BlockNumber,
+    tx_index:     Option<u32>,
+}
+
+impl TxHashIndex {
+    pub fn new(block_number: BlockNumber, tx_index: u32) -> Self {
+        TxHashIndex {
+            block_number,
+            tx_index: Some(tx_index),
+        }
+    }
+
+    pub fn legacy(block_number: BlockNumber) -> Self {
+        TxHashIndex {
+            block_number,
+            tx_index: None,
+        }
+    }
+
+    pub fn block_number(&self) -> BlockNumber {
+        self.block_number
+    }
+
+    pub fn position(&self) -> Option<TxPosition> {
+        self.tx_index.map(|tx_index| TxPosition {
+            block_number: self.block_number,
+            tx_index,
+        })
+    }
+}
+
+impl ProtocolCodec for TxHashIndex {
+    fn encode(&self) -> ProtocolResult<Bytes> {
+        match self.tx_index {
+            Some(tx_index) => {
+                let mut bytes = Vec::with_capacity(12);
+                bytes.extend_from_slice(&self.block_number.to_be_bytes());
+                bytes.extend_from_slice(&tx_index.to_be_bytes());
+                Ok(Bytes::from(bytes))
+            }
+            None => self.block_number.encode(),
+        }
+    }
+
+    // A legacy entry is the rlp of the block number, which is never 12 bytes
+    // long.
+    fn decode<B: AsRef<[u8]>>(bytes: B) -> ProtocolResult<Self> {
+        let bytes = bytes.as_ref();
+        if bytes.len() != 12 {
+            return Ok(TxHashIndex::legacy(BlockNumber::decode(bytes)?));
+        }
+
+        let mut block_number = [0u8; 8];
+        block_number.copy_from_slice(&bytes[..8]);
+        let mut tx_index = [0u8; 4];
+        tx_index.copy_from_slice(&bytes[8..]);
+        Ok(TxHashIndex::new(
+            u64::from_be_bytes(block_number),
+            u32::from_be_bytes(tx_index),
+        ))
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct CommonPrefix {
     block_height: [u8; 8], // BigEndian
@@ -288,14 +384,19 @@ impl_storage_schema_for!(
 impl_storage_schema_for!(BlockSchema, BlockKey, Block, Block);
 impl_storage_schema_for!(BlockHeaderSchema, BlockKey, Header, BlockHeader);
 impl_storage_schema_for!(BlockHashNumberSchema, Hash, u64, HashHeight);
+impl_storage_schema_for!(OrphanBlockSchema, Hash, Block, Block);
 impl_storage_schema_for!(ReceiptSchema, CommonHashKey, Receipt, Receipt);
 impl_storage_schema_for!(ReceiptBytesSchema, CommonHashKey, DBBytes, Receipt);
-impl_storage_schema_for!(TxHashNumberSchema, Hash, u64, HashHeight);
+impl_storage_schema_for!(TxHashNumberSchema, Hash, TxHashIndex, HashHeight);
 impl_storage_schema_for!(LatestBlockSchema, Hash, Block, Block);
 impl_storage_schema_for!(LatestProofSchema, Hash, Proof, Block);
 impl_storage_schema_for!(OverlordWalSchema, Hash, Bytes, Wal);
 impl_storage_schema_for!(EvmCodeSchema, Hash, Bytes, Code);
 impl_storage_schema_for!(EvmCodeAddressSchema, Hash, Hash, Code);
+// The metadata versions are keyed by their end, which is the start of the
+// next one.
+impl_storage_schema_for!(MetadataSchema, BlockKey, Metadata, Metadata);
+impl_storage_schema_for!(LatestMetadataSchema, Hash, Metadata, Metadata);
 
 #[async_trait]
 impl<Adapter: StorageAdapter> CommonStorage for ImplStorage<Adapter> {
@@ -309,10 +410,18 @@ impl<Adapter: StorageAdapter> CommonStorage for ImplStorage<Adapter> {
     }
 
     async fn get_block(&self, _ctx: Context, height: u64) -> ProtocolResult<Option<Block>> {
+        if !self.is_visible(height).await? {
+            return Ok(None);
+        }
+
         self.adapter.get::<BlockSchema>(BlockKey::new(height)).await
     }
 
     async fn get_block_header(&self, ctx: Context, height: u64) -> ProtocolResult<Option<Header>> {
+        if !self.is_visible(height).await? {
+            return Ok(None);
+        }
+
         let opt_header = self
             .adapter
             .get::<BlockHeaderSchema>(BlockKey::new(height))
@@ -325,6 +434,16 @@ impl<Adapter: StorageAdapter> CommonStorage for ImplStorage<Adapter> {
     }
 
     async fn set_block(&self, _ctx: Context, block: Block) -> ProtocolResult<()> {
+        // A block replaced at its height stays retrievable by hash as an orphan.
+        if let Some(replaced) = get!(self, BlockKey::new(block.header.number), BlockSchema)? {
+            let replaced_hash = replaced.header_hash();
+            if replaced_hash != block.header_hash() {
+                self.adapter
+                    .insert::<OrphanBlockSchema>(replaced_hash, replaced)
+                    .await?;
+            }
+        }
+
         self.adapter
             .insert::<BlockSchema>(BlockKey::new(block.header.number), block.clone())
             .await?;
@@ -403,7 +522,13 @@ impl<Adapter: StorageAdapter> Storage for ImplStorage<Adapter> {
             .await?;
 
         if let Some(num) = block_number {
-            return self.get_block(ctx, num).await;
+            if let Some(block) = self.get_block(ctx, num).await? {
+                if block.header_hash() == *block_hash {
+                    return Ok(Some(block));
+                }
+            }
+
+            return get!(self, *block_hash, OrphanBlockSchema);
         }
 
         Ok(None)
@@ -541,10 +666,10 @@ impl<Adapter: StorageAdapter> Storage for ImplStorage<Adapter> {
         _ctx: Context,
         hash: &Hash,
     ) -> ProtocolResult<Option<SignedTransaction>> {
-        if let Some(block_height) = get!(self, *hash, TxHashNumberSchema)? {
+        if let Some(index) = get!(self, *hash, TxHashNumberSchema)? {
             get!(
                 self,
-                CommonHashKey::new(block_height, *hash),
+                CommonHashKey::new(index.block_number(), *hash),
                 TransactionSchema
             )
         } else {
@@ -552,6 +677,29 @@ impl<Adapter: StorageAdapter> Storage for ImplStorage<Adapter> {
         }
     }
 
+    async fn get_transaction_position(
+        &self,
+        _ctx: Context,
+        hash: &Hash,
+    ) -> ProtocolResult<Option<TxPosition>> {
+        let index = match get!(self, *hash, TxHashNumberSchema)? {
+            Some(index) => index,
+            None => return Ok(None),
+        };
+        if let Some(position) = index.position() {
+            return Ok(Some(position));
+        }
+
+        // The entries written before the index kept the position only hold
+        // the block number, the position is recovered from the receipt.
+        let block_height = index.block_number();
+        let receipt = get!(self, CommonHashKey::new(block_height, *hash), ReceiptSchema)?;
+        Ok(receipt.map(|r| TxPosition {
+            block_number: block_height,
+            tx_index:     r.tx_index,
+        }))
+    }
+
     // #[muta_apm::derive::tracing_span(kind = "storage")]
     async fn insert_receipts(
         &self,
@@ -569,8 +717,12 @@ impl<Adapter: StorageAdapter> Storage for ImplStorage<Adapter> {
         _ctx: Context,
         hash: Hash,
     ) -> ProtocolResult<Option<Receipt>> {
-        if let Some(block_height) = get!(self, hash, TxHashNumberSchema)? {
-            get!(self, CommonHashKey::new(block_height, hash), ReceiptSchema)
+        if let Some(index) = get!(self, hash, TxHashNumberSchema)? {
+            get!(
+                self,
+                CommonHashKey::new(index.block_number(), hash),
+                ReceiptSchema
+            )
         } else {
             Ok(None)
         }
@@ -664,6 +816,49 @@ impl<Adapter: StorageAdapter> Storage for ImplStorage<Adapter> {
         let proof = ensure_get!(self, *LATEST_PROOF_KEY, LatestProofSchema);
         Ok(proof)
     }
+
+    async fn insert_metadata(&self, _ctx: Context, metadata: Metadata) -> ProtocolResult<()> {
+        let is_latest = match get!(self, *LATEST_METADATA_KEY, LatestMetadataSchema)? {
+            Some(latest) => latest.version.end <= metadata.version.end,
+            None => true,
+        };
+
+        self.adapter
+            .insert::<MetadataSchema>(BlockKey::new(metadata.version.end), metadata.clone())
+            .await?;
+        if is_latest {
+            self.adapter
+                .insert::<LatestMetadataSchema>(*LATEST_METADATA_KEY, metadata)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn get_metadata(
+        &self,
+        _ctx: Context,
+        number: BlockNumber,
+    ) -> ProtocolResult<Option<Metadata>> {
+        let mut metadata = match get!(self, *LATEST_METADATA_KEY, LatestMetadataSchema)? {
+            Some(latest) => latest,
+            None => return Ok(None),
+        };
+
+        // Walk back through the versions, each one ends where the next starts.
+        while !metadata.version.contains(number) {
+            if number >= metadata.version.end || metadata.version.start == 0 {
+                return Ok(None);
+            }
+
+            metadata = match get!(self, BlockKey::new(metadata.version.start), MetadataSchema)? {
+                Some(previous) => previous,
+                None => return Ok(None),
+            };
+        }
+
+        Ok(Some(metadata))
+    }
 }
 
 #[derive(Debug, Display, From)]
@@ -25,7 +25,7 @@ fn mock_signed_tx() -> SignedTransaction {
         unsigned:  Transaction {
             nonce:                    Default::default(),
             max_priority_fee_per_gas: Default::default(),
-            gas_price:                Default::default(),
+            max_fee_per_gas:          Default::default(),
             gas_limit:                Default::default(),
             action:                   TransactionAction::Create,
             value:                    Default::default(),
@@ -4,12 +4,13 @@ use std::sync::Arc;
 
 use test::Bencher;
 
-use protocol::traits::{CommonStorage, Context, Storage};
-use protocol::types::Hasher;
+use protocol::codec::ProtocolCodec;
+use protocol::traits::{CommonStorage, Context, Storage, StorageAdapter};
+use protocol::types::{Hasher, Metadata, MetadataVersion};
 
 use crate::adapter::memory::MemoryAdapter;
 use crate::tests::{get_random_bytes, mock_block, mock_proof, mock_receipt, mock_signed_tx};
-use crate::ImplStorage;
+use crate::{ImplStorage, TxHashIndex, TxHashNumberSchema};
 
 #[test]
 fn test_storage_block_insert() {
@@ -31,6 +32,64 @@ fn test_storage_block_insert() {
     assert_eq!(height, block.unwrap().header.number);
 }
 
+#[test]
+fn test_storage_orphan_block_by_hash() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+
+    let height = 100;
+    let orphan = mock_block(height, Hasher::digest(get_random_bytes(10)));
+    let canonical = mock_block(height, Hasher::digest(get_random_bytes(10)));
+    assert_ne!(orphan.header_hash(), canonical.header_hash());
+
+    exec!(storage.insert_block(Context::new(), orphan.clone()));
+    exec!(storage.insert_block(Context::new(), canonical.clone()));
+
+    let block = exec!(storage.get_block(Context::new(), height));
+    assert_eq!(block.as_ref(), Some(&canonical));
+
+    let block = exec!(storage.get_block_by_hash(Context::new(), &canonical.header_hash()));
+    assert_eq!(block.as_ref(), Some(&canonical));
+    let block = exec!(storage.get_block_by_hash(Context::new(), &orphan.header_hash()));
+    assert_eq!(block.as_ref(), Some(&orphan));
+}
+
+#[test]
+fn test_storage_block_number_agrees_with_blocks() {
+    let storage = Arc::new(ImplStorage::new(Arc::new(MemoryAdapter::new())));
+    let last = 500;
+    exec!(storage.insert_block(
+        Context::new(),
+        mock_block(0, Hasher::digest(get_random_bytes(10)))
+    ));
+
+    let writer = {
+        let storage = Arc::clone(&storage);
+        std::thread::spawn(move || {
+            for height in 1..=last {
+                let block = mock_block(height, Hasher::digest(get_random_bytes(10)));
+                exec!(storage.insert_block(Context::new(), block));
+            }
+        })
+    };
+
+    loop {
+        let latest = exec!(storage.get_latest_block_header(Context::new())).number;
+        assert!(exec!(storage.get_block(Context::new(), latest)).is_some());
+        assert!(exec!(storage.get_block_header(Context::new(), latest)).is_some());
+
+        // A block is only readable once the latest number has reached it.
+        if exec!(storage.get_block(Context::new(), latest + 1)).is_some() {
+            let now = exec!(storage.get_latest_block_header(Context::new())).number;
+            assert!(now > latest);
+        }
+
+        if latest == last {
+            break;
+        }
+    }
+    writer.join().unwrap();
+}
+
 #[test]
 fn test_storage_receipts_insert() {
     let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
@@ -82,6 +141,61 @@ fn test_storage_transactions_insert() {
     }
 }
 
+#[test]
+fn test_storage_transaction_position() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+    let height = 2021;
+
+    let transactions = (0..10).map(|_| mock_signed_tx()).collect::<Vec<_>>();
+    exec!(storage.insert_transactions(Context::new(), height, transactions.clone()));
+
+    // No block is stored at this height, so the lookups can only be served by
+    // the hash index.
+    assert!(exec!(storage.get_block(Context::new(), height)).is_none());
+
+    for (idx, tx) in transactions.iter().enumerate() {
+        let hash = tx.transaction.hash;
+        let position = exec!(storage.get_transaction_position(Context::new(), &hash)).unwrap();
+        assert_eq!(position.block_number, height);
+        assert_eq!(position.tx_index, idx as u32);
+
+        let stx = exec!(storage.get_transaction_by_hash(Context::new(), &hash));
+        assert_eq!(stx.as_ref(), Some(tx));
+    }
+
+    let unknown = Hasher::digest(get_random_bytes(10));
+    assert!(exec!(storage.get_transaction_position(Context::new(), &unknown)).is_none());
+}
+
+#[test]
+fn test_storage_legacy_transaction_index() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+    let height = 2021;
+
+    let tx = mock_signed_tx();
+    let hash = tx.transaction.hash;
+    let mut receipt = mock_receipt(hash);
+    receipt.tx_index = 3;
+    exec!(storage.insert_transactions(Context::new(), height, vec![tx.clone()]));
+    exec!(storage.insert_receipts(Context::new(), height, vec![receipt]));
+
+    // An entry written before the index kept the position.
+    exec!(storage
+        .adapter
+        .insert::<TxHashNumberSchema>(hash, TxHashIndex::legacy(height)));
+    assert_eq!(
+        exec!(storage.get_transaction_by_hash(Context::new(), &hash)),
+        Some(tx)
+    );
+    let position = exec!(storage.get_transaction_position(Context::new(), &hash)).unwrap();
+    assert_eq!(position.block_number, height);
+    assert_eq!(position.tx_index, 3);
+
+    for index in [TxHashIndex::legacy(height), TxHashIndex::new(height, 3)] {
+        assert_eq!(TxHashIndex::decode(index.encode().unwrap()).unwrap(), index);
+    }
+}
+
 #[test]
 fn test_storage_latest_proof_insert() {
     let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
@@ -95,6 +209,35 @@ fn test_storage_latest_proof_insert() {
     assert_eq!(proof.block_hash, proof_2.block_hash);
 }
 
+#[test]
+fn test_storage_metadata_by_height() {
+    let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
+    assert_eq!(exec!(storage.get_metadata(Context::new(), 0)), None);
+
+    let versions = [(0, 100), (100, 200), (200, 300)]
+        .iter()
+        .map(|(start, end)| Metadata {
+            version: MetadataVersion::new(*start, *end),
+            gas_limit: *start,
+            ..Default::default()
+        })
+        .collect::<Vec<_>>();
+    for metadata in versions.iter() {
+        exec!(storage.insert_metadata(Context::new(), metadata.clone()));
+    }
+
+    for (number, version) in [(0, 0), (99, 0), (100, 1), (150, 1), (299, 2)] {
+        let metadata = exec!(storage.get_metadata(Context::new(), number));
+        assert_eq!(metadata.as_ref(), Some(&versions[version]));
+    }
+    assert_eq!(exec!(storage.get_metadata(Context::new(), 300)), None);
+
+    // Saving an older version again does not take the place of the latest.
+    exec!(storage.insert_metadata(Context::new(), versions[0].clone()));
+    let metadata = exec!(storage.get_metadata(Context::new(), 250));
+    assert_eq!(metadata.as_ref(), Some(&versions[2]));
+}
+
 #[test]
 fn test_storage_evm_code_insert() {
     let storage = ImplStorage::new(Arc::new(MemoryAdapter::new()));
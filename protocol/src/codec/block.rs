@@ -3,7 +3,10 @@ use std::error::Error;
 use overlord::Codec;
 use rlp::{Decodable, DecoderError, Encodable, Prototype, Rlp, RlpStream};
 
-use crate::types::{Block, Bytes, Header, Proof, Proposal, Validator};
+use crate::types::{
+    Block, Bytes, Header, Hex, Metadata, MetadataVersion, Proof, Proposal, Validator,
+    ValidatorExtend,
+};
 use crate::{codec::error::CodecError, ProtocolError};
 
 impl Encodable for Proposal {
@@ -184,6 +187,94 @@ impl Decodable for Validator {
     }
 }
 
+impl Encodable for MetadataVersion {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(2).append(&self.start).append(&self.end);
+    }
+}
+
+impl Decodable for MetadataVersion {
+    fn decode(r: &Rlp) -> Result<Self, DecoderError> {
+        match r.prototype()? {
+            Prototype::List(2) => Ok(MetadataVersion {
+                start: r.val_at(0)?,
+                end:   r.val_at(1)?,
+            }),
+            _ => Err(DecoderError::RlpExpectedToBeList),
+        }
+    }
+}
+
+impl Encodable for ValidatorExtend {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(5)
+            .append(&self.bls_pub_key.as_bytes().to_vec())
+            .append(&self.pub_key.as_bytes().to_vec())
+            .append(&self.address)
+            .append(&self.propose_weight)
+            .append(&self.vote_weight);
+    }
+}
+
+impl Decodable for ValidatorExtend {
+    fn decode(r: &Rlp) -> Result<Self, DecoderError> {
+        match r.prototype()? {
+            Prototype::List(5) => Ok(ValidatorExtend {
+                bls_pub_key:    Hex::encode(r.val_at::<Vec<u8>>(0)?),
+                pub_key:        Hex::encode(r.val_at::<Vec<u8>>(1)?),
+                address:        r.val_at(2)?,
+                propose_weight: r.val_at(3)?,
+                vote_weight:    r.val_at(4)?,
+            }),
+            _ => Err(DecoderError::RlpExpectedToBeList),
+        }
+    }
+}
+
+impl Encodable for Metadata {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(14)
+            .append(&self.version)
+            .append(&self.common_ref.as_bytes().to_vec())
+            .append(&self.timeout_gap)
+            .append(&self.gas_limit)
+            .append(&self.gas_price)
+            .append(&self.interval)
+            .append_list(&self.verifier_list)
+            .append(&self.propose_ratio)
+            .append(&self.prevote_ratio)
+            .append(&self.precommit_ratio)
+            .append(&self.brake_ratio)
+            .append(&self.tx_num_limit)
+            .append(&self.max_tx_size)
+            .append(&self.last_checkpoint_block_hash);
+    }
+}
+
+impl Decodable for Metadata {
+    fn decode(r: &Rlp) -> Result<Self, DecoderError> {
+        match r.prototype()? {
+            Prototype::List(14) => Ok(Metadata {
+                version:                    r.val_at(0)?,
+                common_ref:                 Hex::encode(r.val_at::<Vec<u8>>(1)?),
+                timeout_gap:                r.val_at(2)?,
+                gas_limit:                  r.val_at(3)?,
+                gas_price:                  r.val_at(4)?,
+                interval:                   r.val_at(5)?,
+                verifier_list:              r.list_at(6)?,
+                propose_ratio:              r.val_at(7)?,
+                prevote_ratio:              r.val_at(8)?,
+                precommit_ratio:            r.val_at(9)?,
+                brake_ratio:                r.val_at(10)?,
+                tx_num_limit:               r.val_at(11)?,
+                max_tx_size:                r.val_at(12)?,
+                last_checkpoint_block_hash: r.val_at(13)?,
+            }),
+            _ => Err(DecoderError::RlpExpectedToBeList),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::traits::MessageCodec;
@@ -213,4 +304,23 @@ mod tests {
         let decode: Proof = Proof::decode_msg(bytes).unwrap();
         assert_eq!(proof, decode);
     }
+
+    #[test]
+    fn test_metadata_codec() {
+        let metadata = Metadata {
+            version: MetadataVersion::new(10, 20),
+            common_ref: Hex::encode("axon"),
+            verifier_list: vec![ValidatorExtend {
+                bls_pub_key:    Hex::encode([1u8; 48]),
+                pub_key:        Hex::encode([2u8; 33]),
+                address:        Default::default(),
+                propose_weight: 1,
+                vote_weight:    2,
+            }],
+            ..Default::default()
+        };
+        let bytes = rlp::encode(&metadata);
+        let decode: Metadata = rlp::decode(bytes.as_ref()).unwrap();
+        assert_eq!(metadata, decode);
+    }
 }
@@ -1,6 +1,7 @@
+use bytes::BufMut;
 use rlp::{Decodable, DecoderError, Encodable, Prototype, Rlp, RlpStream};
 
-use crate::types::Receipt;
+use crate::types::{BytesMut, ConsensusReceipt, Receipt};
 
 impl Encodable for Receipt {
     fn rlp_append(&self, s: &mut RlpStream) {
@@ -44,9 +45,43 @@ impl Decodable for Receipt {
     }
 }
 
+impl Encodable for ConsensusReceipt {
+    fn rlp_append(&self, s: &mut RlpStream) {
+        s.begin_list(4)
+            .append(&self.status)
+            .append(&self.cumulative_gas_used)
+            .append(&self.logs_bloom)
+            .append_list(&self.logs);
+    }
+
+    fn rlp_bytes(&self) -> BytesMut {
+        let mut ret = BytesMut::new();
+        let mut s = RlpStream::new();
+        self.rlp_append(&mut s);
+        ret.put_u8(0x02);
+        ret.put(s.out());
+        ret
+    }
+}
+
+impl Decodable for ConsensusReceipt {
+    fn decode(r: &Rlp) -> Result<Self, DecoderError> {
+        match r.prototype()? {
+            Prototype::List(4) => Ok(ConsensusReceipt {
+                status:              r.val_at(0)?,
+                cumulative_gas_used: r.val_at(1)?,
+                logs_bloom:          r.val_at(2)?,
+                logs:                r.list_at(3)?,
+            }),
+            _ => Err(DecoderError::RlpExpectedToBeList),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::{logs_bloom, BloomInput, Log, H160, H256, U256};
 
     #[test]
     fn test_receipt_codec() {
@@ -55,4 +90,38 @@ mod tests {
         let decode: Receipt = rlp::decode(bytes.as_ref()).unwrap();
         assert_eq!(receipt, decode);
     }
+
+    #[test]
+    fn test_consensus_receipt_codec() {
+        let logs = vec![Log {
+            address: H160::repeat_byte(0x11),
+            topics:  vec![H256::repeat_byte(0x22)],
+            data:    vec![0x33],
+        }];
+        let receipt = ConsensusReceipt {
+            status:              U256::one(),
+            cumulative_gas_used: U256::from(42000u64),
+            logs_bloom:          logs_bloom(&logs),
+            logs:                logs.clone(),
+        };
+
+        let bloom = receipt.logs_bloom;
+        assert!(bloom.contains_input(BloomInput::Raw(H160::repeat_byte(0x11).as_bytes())));
+        assert!(bloom.contains_input(BloomInput::Raw(H256::repeat_byte(0x22).as_bytes())));
+        assert!(!bloom.contains_input(BloomInput::Raw(H256::repeat_byte(0x33).as_bytes())));
+
+        let bytes = receipt.rlp_bytes().freeze().to_vec();
+        assert_eq!(bytes[0], 0x02);
+        let decode = ConsensusReceipt::decode(&Rlp::new(&bytes[1..])).unwrap();
+        assert_eq!(decode, receipt);
+
+        // The reference EIP-658 receipt payload
+        let reference = ethereum::EIP658ReceiptData {
+            status_code: 1,
+            used_gas: U256::from(42000u64),
+            logs_bloom: receipt.logs_bloom,
+            logs,
+        };
+        assert_eq!(rlp::encode(&reference).to_vec(), bytes[1..].to_vec());
+    }
 }
@@ -3,7 +3,7 @@ use rlp::{Decodable, DecoderError, Encodable, Prototype, Rlp, RlpStream};
 
 use crate::types::{
     AccessList, AccessListItem, Bytes, BytesMut, SignatureComponents, SignedTransaction,
-    Transaction, TransactionAction, UnverifiedTransaction, H256, U256,
+    Transaction, TransactionAction, UnverifiedTransaction, EIP1559_TX_TYPE, H256, U256,
 };
 
 impl Encodable for SignatureComponents {
@@ -38,7 +38,7 @@ impl Encodable for UnverifiedTransaction {
             .append(&self.chain_id)
             .append(&self.unsigned.nonce)
             .append(&self.unsigned.max_priority_fee_per_gas)
-            .append(&self.unsigned.gas_price)
+            .append(&self.unsigned.max_fee_per_gas)
             .append(&self.unsigned.gas_limit)
             .append(&self.unsigned.action)
             .append(&self.unsigned.value)
@@ -62,7 +62,7 @@ impl Encodable for UnverifiedTransaction {
         let mut ret = BytesMut::new();
         let mut s = RlpStream::new();
         self.rlp_append(&mut s);
-        ret.put_u8(0x02);
+        ret.put_u8(EIP1559_TX_TYPE);
         ret.put(s.out());
         ret
     }
@@ -77,7 +77,7 @@ impl Decodable for UnverifiedTransaction {
         let chain_id: u64 = r.val_at(0)?;
         let nonce: U256 = r.val_at(1)?;
         let max_priority_fee_per_gas: U256 = r.val_at(2)?;
-        let gas_price: U256 = r.val_at(3)?;
+        let max_fee_per_gas: U256 = r.val_at(3)?;
         let gas_limit: U256 = r.val_at(4)?;
         let action: TransactionAction = r.val_at(5)?;
         let value: U256 = r.val_at(6)?;
@@ -111,7 +111,7 @@ impl Decodable for UnverifiedTransaction {
             unsigned: Transaction {
                 nonce,
                 max_priority_fee_per_gas,
-                gas_price,
+                max_fee_per_gas,
                 gas_limit,
                 action,
                 value,
@@ -165,7 +165,7 @@ mod tests {
             nonce:                    U256::one(),
             gas_limit:                U256::one(),
             max_priority_fee_per_gas: U256::one(),
-            gas_price:                U256::one(),
+            max_fee_per_gas:          U256::one(),
             action:                   TransactionAction::Create,
             value:                    U256::one(),
             data:                     rand_bytes(32).to_vec().into(),
@@ -207,6 +207,102 @@ mod tests {
         assert_eq!(origin, decode);
     }
 
+    #[test]
+    fn test_deserialize_legacy_gas_price_key() {
+        let tx = mock_transaction();
+        let mut json = serde_json::to_value(&tx).unwrap();
+        let obj = json.as_object_mut().unwrap();
+        let fee = obj.remove("max_fee_per_gas").unwrap();
+        obj.insert("gas_price".to_string(), fee);
+
+        let decoded: Transaction = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, tx);
+    }
+
+    #[test]
+    fn test_multi_item_access_list_codec() {
+        let mut tx = mock_transaction();
+        tx.action = TransactionAction::Call(H160::repeat_byte(0x33));
+        tx.access_list = vec![
+            AccessListItem {
+                address: H160::repeat_byte(0x01),
+                slots:   vec![H256::repeat_byte(0x02), H256::repeat_byte(0x03)],
+            },
+            AccessListItem {
+                address: H160::repeat_byte(0x04),
+                slots:   vec![
+                    H256::repeat_byte(0x05),
+                    H256::repeat_byte(0x06),
+                    H256::repeat_byte(0x07),
+                ],
+            },
+        ];
+        let utx = UnverifiedTransaction {
+            unsigned:  tx.clone(),
+            chain_id:  5,
+            hash:      H256::default(),
+            signature: Some(SignatureComponents {
+                standard_v: 1,
+                r:          H256::repeat_byte(0x08),
+                s:          H256::repeat_byte(0x09),
+            }),
+        }
+        .hash();
+
+        let encoded = utx.rlp_bytes().freeze().to_vec();
+        assert_eq!(encoded[0], EIP1559_TX_TYPE);
+        let decoded = UnverifiedTransaction::decode(&Rlp::new(&encoded[1..])).unwrap();
+        assert_eq!(decoded, utx);
+        assert_eq!(decoded.rlp_bytes().freeze().to_vec(), encoded);
+
+        // The reference EIP-1559 encoding
+        let reference = ethereum::EIP1559Transaction {
+            chain_id:                 5,
+            nonce:                    tx.nonce,
+            max_priority_fee_per_gas: tx.max_priority_fee_per_gas,
+            max_fee_per_gas:          tx.max_fee_per_gas,
+            gas_limit:                tx.gas_limit,
+            action:                   tx.action,
+            value:                    tx.value,
+            input:                    tx.data.to_vec(),
+            access_list:              tx.access_list.clone(),
+            odd_y_parity:             true,
+            r:                        H256::repeat_byte(0x08),
+            s:                        H256::repeat_byte(0x09),
+        };
+        assert_eq!(rlp::encode(&reference).to_vec(), encoded[1..].to_vec());
+        assert_eq!(ethereum::TransactionV2::EIP1559(reference).hash(), utx.hash);
+    }
+
+    #[test]
+    fn test_1559_fee_fields_codec() {
+        let reference = ethereum::EIP1559Transaction {
+            chain_id:                 5,
+            nonce:                    U256::from(7u64),
+            max_priority_fee_per_gas: U256::from(1_500_000_000u64),
+            max_fee_per_gas:          U256::from(30_000_000_000u64),
+            gas_limit:                U256::from(21000u64),
+            action:                   TransactionAction::Call(H160::repeat_byte(0x33)),
+            value:                    U256::one(),
+            input:                    vec![],
+            access_list:              vec![],
+            odd_y_parity:             false,
+            r:                        H256::repeat_byte(0x08),
+            s:                        H256::repeat_byte(0x09),
+        };
+        let encoded = rlp::encode(&reference).to_vec();
+
+        let utx = UnverifiedTransaction::decode(&Rlp::new(&encoded)).unwrap();
+        assert_eq!(utx.chain_id, 5);
+        assert_eq!(
+            utx.unsigned.max_priority_fee_per_gas,
+            U256::from(1_500_000_000u64)
+        );
+        assert_eq!(utx.unsigned.max_fee_per_gas, U256::from(30_000_000_000u64));
+        assert_eq!(utx.unsigned.gas_limit, U256::from(21000u64));
+        assert_eq!(utx.rlp_bytes().freeze()[1..].to_vec(), encoded);
+    }
+
     #[test]
     fn test_decode_unsigned_tx() {
         let raw = hex_decode("02f9016e2a80830f4240830f4240825208948d97689c9818892b700e27f316cc3e41e17fbeb9872386f26fc10000b8fe608060405234801561001057600080fd5b5060df8061001f6000396000f3006080604052600436106049576000357c0100000000000000000000000000000000000000000000000000000000900463ffffffff16806360fe47b114604e5780636d4ce63c146078575b600080fd5b348015605957600080fd5b5060766004803603810190808035906020019092919050505060a0565b005b348015608357600080fd5b50608a60aa565b6040518082815260200191505060405180910390f35b8060008190555050565b600080549050905600a165627a7a7230582099c66a25d59f0aa78f7ebc40748fa1d1fbc335d8d780f284841b30e0365acd960029c001a055ea090c41cb5c76a7065a04fc6355d7804809baccc8f86717ac4da1694621fba03310f10f3488b558f65a94fc164036aa69d88ab35f42dcf5d77b6f04c5cf8e72").unwrap();
@@ -214,4 +310,33 @@ mod tests {
         let res = UnverifiedTransaction::decode(&rlp);
         assert!(res.is_ok());
     }
+
+    #[test]
+    fn test_geth_1559_tx_hash() {
+        // A signed type 2 envelope and its hash as geth computes it, the
+        // keccak256 of the whole envelope
+        let raw = hex_decode("02f9016e2a80830f4240831e8480825208948d97689c9818892b700e27f316cc3e41e17fbeb9872386f26fc10000b8fe608060405234801561001057600080fd5b5060df8061001f6000396000f3006080604052600436106049576000357c0100000000000000000000000000000000000000000000000000000000900463ffffffff16806360fe47b114604e5780636d4ce63c146078575b600080fd5b348015605957600080fd5b5060766004803603810190808035906020019092919050505060a0565b005b348015608357600080fd5b50608a60aa565b6040518082815260200191505060405180910390f35b8060008190555050565b600080549050905600a165627a7a7230582099c66a25d59f0aa78f7ebc40748fa1d1fbc335d8d780f284841b30e0365acd960029c001a0ece7b318cac630a79f68bda5e5268be2d8fa18142f2f8128ab191fe7ea30fe5ca0243fbae567cf341fb4f1c38cac3da30f0c93586329dd0b687f9f836fe65be210").unwrap();
+        let geth_hash = H256::from_slice(
+            &hex_decode("3ec1505237777f18528953928c786a86a22ecfc20d67681c6e5ab6c3d0919a9e")
+                .unwrap(),
+        );
+
+        let utx = UnverifiedTransaction::decode(&Rlp::new(&raw[1..])).unwrap();
+        assert_eq!(utx.chain_id, 42);
+        assert_eq!(
+            utx.unsigned.max_priority_fee_per_gas,
+            U256::from(1_000_000u64)
+        );
+        assert_eq!(utx.unsigned.max_fee_per_gas, U256::from(2_000_000u64));
+        assert_eq!(utx.unsigned.gas_limit, U256::from(21000u64));
+        assert_eq!(utx.hash, geth_hash);
+        assert_eq!(utx.rlp_bytes().freeze().to_vec(), raw);
+
+        // The signature covers the fee fields in the 1559 order
+        let stx = SignedTransaction::try_from(utx).unwrap();
+        assert_eq!(
+            stx.sender,
+            H160::from_slice(&hex_decode("e69efccfed4a5b70a6e0a8923ccd1f1751afd4f2").unwrap())
+        );
+    }
 }
@@ -1,7 +1,10 @@
+use std::collections::BTreeMap;
+
 use crate::traits::Context;
 use crate::types::{
-    Account, Block, BlockNumber, Bytes, Hash, Header, Proposal, Receipt, SignedTransaction, TxResp,
-    H160,
+    AccessList, AccessSet, Account, AccountProof, Block, BlockNumber, BundleCall, Bytes, Hash,
+    Header, Metadata, OverlayReplay, PrestateAccount, Proof, Proposal, Receipt, SignedTransaction,
+    StorageRange, TxPoolStatus, TxPosition, TxResp, H160, H256,
 };
 use crate::ProtocolResult;
 use async_trait::async_trait;
@@ -48,6 +51,12 @@ pub trait APIAdapter: Send + Sync {
         tx_hash: Hash,
     ) -> ProtocolResult<Option<SignedTransaction>>;
 
+    async fn get_transaction_position(
+        &self,
+        ctx: Context,
+        tx_hash: Hash,
+    ) -> ProtocolResult<Option<TxPosition>>;
+
     async fn get_transactions_by_hashes(
         &self,
         ctx: Context,
@@ -62,18 +71,134 @@ pub trait APIAdapter: Send + Sync {
         number: Option<BlockNumber>,
     ) -> ProtocolResult<Account>;
 
+    /// The EIP-1186 proofs of `address` and of its `storage_keys` slots.
+    async fn get_proof(
+        &self,
+        ctx: Context,
+        address: H160,
+        storage_keys: Vec<H256>,
+        number: Option<BlockNumber>,
+    ) -> ProtocolResult<AccountProof>;
+
+    #[allow(clippy::too_many_arguments)]
     async fn evm_call(
         &self,
         ctx: Context,
+        from: Option<H160>,
         address: H160,
+        value: U256,
         data: Vec<u8>,
+        access_list: AccessList,
         state_root: Hash,
         proposal: Proposal,
     ) -> ProtocolResult<TxResp>;
 
+    /// Like `evm_call`, but return the state the call reads and writes.
+    #[allow(clippy::too_many_arguments)]
+    async fn simulate_access_set(
+        &self,
+        ctx: Context,
+        from: Option<H160>,
+        address: H160,
+        value: U256,
+        data: Vec<u8>,
+        access_list: AccessList,
+        state_root: Hash,
+        proposal: Proposal,
+    ) -> ProtocolResult<AccessSet>;
+
+    /// Run the `calls` on the state of `state_root` one after the other, each
+    /// one sees the state changes of the successful ones before it.
+    async fn simulate_bundle(
+        &self,
+        ctx: Context,
+        calls: Vec<BundleCall>,
+        state_root: Hash,
+        proposal: Proposal,
+    ) -> ProtocolResult<Vec<TxResp>>;
+
+    async fn trace_prestate(
+        &self,
+        ctx: Context,
+        preceding: Vec<SignedTransaction>,
+        tx: SignedTransaction,
+        state_root: Hash,
+        proposal: Proposal,
+    ) -> ProtocolResult<BTreeMap<H160, PrestateAccount>>;
+
+    /// Replay the `preceding` transactions on the state of `state_root`, then
+    /// return up to `max_result` non-zero storage slots of `address` from
+    /// `start` on.
+    #[allow(clippy::too_many_arguments)]
+    async fn storage_range(
+        &self,
+        ctx: Context,
+        preceding: Vec<SignedTransaction>,
+        address: H160,
+        start: H256,
+        max_result: usize,
+        state_root: Hash,
+        proposal: Proposal,
+    ) -> ProtocolResult<StorageRange>;
+
+    /// Replay the `preceding` transactions on the state of `state_root`, then
+    /// return the code of `address` and the values of its `slots`.
+    async fn replay_account(
+        &self,
+        ctx: Context,
+        preceding: Vec<SignedTransaction>,
+        address: H160,
+        slots: Vec<H256>,
+        state_root: Hash,
+        proposal: Proposal,
+    ) -> ProtocolResult<(Bytes, Vec<H256>)>;
+
+    /// Replay `txs` one after another on the state of `state_root` and return
+    /// the code and storage they leave the touched accounts with, along with
+    /// the logs of each transaction.
+    async fn replay_overlay(
+        &self,
+        ctx: Context,
+        txs: Vec<SignedTransaction>,
+        state_root: Hash,
+        proposal: Proposal,
+    ) -> ProtocolResult<OverlayReplay>;
+
+    /// The consensus proof of block `number`, which is carried by the header
+    /// of the next block, or is the latest proof for the latest block.
+    async fn get_block_proof(
+        &self,
+        ctx: Context,
+        number: BlockNumber,
+    ) -> ProtocolResult<Option<Proof>>;
+
+    /// The metadata, and so the validator set, in effect at `number`.
+    async fn get_metadata(
+        &self,
+        ctx: Context,
+        number: BlockNumber,
+    ) -> ProtocolResult<Option<Metadata>>;
+
     async fn get_code_by_hash(&self, ctx: Context, hash: &Hash) -> ProtocolResult<Option<Bytes>>;
 
     async fn peer_count(&self, ctx: Context) -> ProtocolResult<U256>;
 
     async fn get_number_by_hash(&self, ctx: Context, hash: Hash) -> ProtocolResult<Option<u64>>;
+
+    /// The lowest block number whose body and state are still stored.
+    async fn get_oldest_available_block(&self, ctx: Context) -> ProtocolResult<BlockNumber>;
+
+    async fn get_pool_tx_status(
+        &self,
+        ctx: Context,
+        tx_hash: Hash,
+    ) -> ProtocolResult<Option<TxPoolStatus>>;
+
+    async fn get_pending_txs(&self, ctx: Context) -> ProtocolResult<Vec<SignedTransaction>>;
+
+    async fn get_pool_txs_inserted_since(
+        &self,
+        ctx: Context,
+        cursor: Option<u64>,
+    ) -> ProtocolResult<(u64, Vec<Hash>)>;
 }
@@ -5,8 +5,8 @@ use bytes::Bytes;
 use creep::Context;
 
 use crate::types::{
-    Address, Block, BlockNumber, ExecResp, Hash, Header, Hex, Log, MerkleRoot, Proof, Proposal,
-    Receipt, SignedTransaction, Validator, U256,
+    Address, Block, BlockNumber, ExecResp, Hash, Header, Hex, Log, MerkleRoot, Metadata, Proof,
+    Proposal, Receipt, SignedTransaction, Validator, U256,
 };
 use crate::ProtocolResult;
 
@@ -87,6 +87,10 @@ pub trait CommonConsensusAdapter: Send + Sync {
 
     async fn save_proof(&self, ctx: Context, proof: Proof) -> ProtocolResult<()>;
 
+    /// Save a metadata version, so that the proofs of its blocks can be
+    /// verified after it is replaced.
+    async fn save_metadata(&self, ctx: Context, metadata: Metadata) -> ProtocolResult<()>;
+
     /// Save some signed transactions to the database.
     async fn save_signed_txs(
         &self,
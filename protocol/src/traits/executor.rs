@@ -1,8 +1,10 @@
 pub use evm::backend::{ApplyBackend, Backend};
 
+use std::collections::BTreeMap;
+
 use crate::types::{
-    Account, Bytes, ExecResp, ExecutorContext, Log, MerkleRoot, SignedTransaction, TxResp, H160,
-    U256,
+    AccessList, AccessSet, Account, BundleCall, Bytes, ExecResp, ExecutorContext, Log, MerkleRoot,
+    OverlayReplay, PrestateAccount, SignedTransaction, StorageRange, TxResp, H160, H256, U256,
 };
 
 pub trait ExecutorAdapter {
@@ -15,10 +17,45 @@ pub trait ExecutorAdapter {
     fn get(&self, key: &[u8]) -> Option<Bytes>;
 
     fn get_ctx(&self) -> ExecutorContext;
+
+    /// Up to `limit` non-zero storage slots of `address` from `start` on, in
+    /// key order.
+    fn storage_slots(&self, address: H160, start: H256, limit: usize) -> Vec<(H256, H256)>;
 }
 
 pub trait Executor: Send + Sync {
-    fn call<B: Backend>(&self, backend: &mut B, addr: H160, data: Vec<u8>) -> TxResp;
+    /// The addresses and storage slots of `access_list` are warm from the
+    /// start of the call, see EIP-2929 and EIP-2930. A call running out of
+    /// `gas_limit` fails with out of gas as a transaction would.
+    fn call<B: Backend>(
+        &self,
+        backend: &mut B,
+        from: Option<H160>,
+        addr: H160,
+        value: U256,
+        gas_limit: u64,
+        data: Vec<u8>,
+        access_list: AccessList,
+    ) -> TxResp;
+
+    /// Simulate a call like [`call`](Self::call) and return the accounts and
+    /// storage slots it reads and writes. The nonce bump of the caller is not
+    /// a write, the caller is only written when its balance changes.
+    #[allow(clippy::too_many_arguments)]
+    fn access_set<B: Backend>(
+        &self,
+        backend: &B,
+        from: Option<H160>,
+        addr: H160,
+        value: U256,
+        gas_limit: u64,
+        data: Vec<u8>,
+        access_list: AccessList,
+    ) -> AccessSet;
+
+    /// Run the `calls` one after the other, each on top of the state changes
+    /// of the successful ones before it. Nothing is committed to the backend.
+    fn call_bundle<B: Backend>(&self, backend: &B, calls: Vec<BundleCall>) -> Vec<TxResp>;
 
     fn exec<B: Backend + ApplyBackend + ExecutorAdapter>(
         &self,
@@ -27,4 +64,41 @@ pub trait Executor: Send + Sync {
     ) -> ExecResp;
 
     fn get_account<B: Backend + ExecutorAdapter>(&self, backend: &B, address: &H160) -> Account;
+
+    /// Replay the `preceding` transactions without committing them, then
+    /// return the state touched by `tx` as it was before `tx` executed.
+    fn trace_prestate<B: Backend>(
+        &self,
+        backend: &B,
+        preceding: Vec<SignedTransaction>,
+        tx: SignedTransaction,
+    ) -> BTreeMap<H160, PrestateAccount>;
+
+    /// Replay the `preceding` transactions without committing them, then
+    /// return up to `max_result` non-zero storage slots of `address` from
+    /// `start` on.
+    fn storage_range<B: Backend + ExecutorAdapter>(
+        &self,
+        backend: &B,
+        preceding: Vec<SignedTransaction>,
+        address: H160,
+        start: H256,
+        max_result: usize,
+    ) -> StorageRange;
+
+    /// Replay the `preceding` transactions without committing them, then
+    /// return the code of `address` and the values of its `slots`.
+    fn replay_account<B: Backend>(
+        &self,
+        backend: &B,
+        preceding: Vec<SignedTransaction>,
+        address: H160,
+        slots: &[H256],
+    ) -> (Vec<u8>, Vec<H256>);
+
+    /// Replay `txs` one after another without committing them and return the
+    /// code and storage they leave the touched accounts with, along with the
+    /// logs of each transaction.
+    fn replay_overlay<B: Backend>(&self, backend: &B, txs: Vec<SignedTransaction>)
+        -> OverlayReplay;
 }
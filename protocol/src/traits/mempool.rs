@@ -1,7 +1,7 @@
 use async_trait::async_trait;
 use creep::Context;
 
-use crate::types::{Hash, MerkleRoot, SignedTransaction, U256};
+use crate::types::{Hash, MerkleRoot, SignedTransaction, TxPoolStatus, U256};
 use crate::ProtocolResult;
 
 #[async_trait]
@@ -45,6 +45,14 @@ pub trait MemPool: Send + Sync {
         gas_limit: u64,
         max_tx_size: u64,
     );
+
+    fn get_tx_status(&self, ctx: Context, tx_hash: &Hash) -> Option<TxPoolStatus>;
+
+    fn get_pending_txs(&self, ctx: Context) -> Vec<SignedTransaction>;
+
+    /// Return the insertion sequence number of the last transaction and the
+    /// hashes of the transactions inserted after `cursor`.
+    fn get_txs_inserted_since(&self, ctx: Context, cursor: Option<u64>) -> (u64, Vec<Hash>);
 }
 
 #[async_trait]
@@ -63,6 +71,10 @@ pub trait MemPoolAdapter: Send + Sync {
 
     async fn check_transaction(&self, ctx: Context, tx: &SignedTransaction) -> ProtocolResult<()>;
 
+    /// Check the sender can pay the value and the gas of the transaction at
+    /// the current state.
+    async fn check_balance(&self, ctx: Context, tx: &SignedTransaction) -> ProtocolResult<()>;
+
     async fn check_storage_exist(&self, ctx: Context, tx_hash: &Hash) -> ProtocolResult<()>;
 
     async fn get_latest_height(&self, ctx: Context) -> ProtocolResult<u64>;
@@ -84,4 +96,8 @@ pub trait MemPoolAdapter: Send + Sync {
     );
 
     fn report_good(&self, ctx: Context);
+
+    /// Tell the peer the transaction came from that it is already known,
+    /// which is a race of the gossip rather than a misbehavior.
+    fn report_duplicate(&self, ctx: Context, tx_hash: &Hash);
 }
@@ -18,7 +18,8 @@ pub use cross_client::{CrossAdapter, CrossClient};
 pub use executor::{ApplyBackend, Backend, Executor, ExecutorAdapter};
 pub use mempool::{MemPool, MemPoolAdapter};
 pub use network::{
-    Gossip, MessageCodec, MessageHandler, Network, PeerTag, PeerTrust, Priority, Rpc, TrustFeedback,
+    Gossip, MessageCodec, MessageHandler, Network, PeerTag, PeerTrust, Priority, RejectCode, Rpc,
+    TrustFeedback,
 };
 pub use storage::{
     CommonStorage, IntoIteratorByRef, Storage, StorageAdapter, StorageBatchModify, StorageCategory,
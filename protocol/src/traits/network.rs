@@ -12,20 +12,48 @@ pub enum Priority {
     Normal,
 }
 
+/// The negative feedbacks carry a human readable reason for logging and
+/// optionally a code to branch on.
 #[derive(Debug, Display, Clone)]
 pub enum TrustFeedback {
     #[display(fmt = "fatal {}", _0)]
-    Fatal(String),
+    Fatal(String, Option<RejectCode>),
     #[display(fmt = "worse {}", _0)]
-    Worse(String),
+    Worse(String, Option<RejectCode>),
     #[display(fmt = "bad {}", _0)]
-    Bad(String),
+    Bad(String, Option<RejectCode>),
     #[display(fmt = "neutral")]
     Neutral,
     #[display(fmt = "good")]
     Good,
 }
 
+impl TrustFeedback {
+    pub fn code(&self) -> Option<RejectCode> {
+        match self {
+            TrustFeedback::Fatal(_, code)
+            | TrustFeedback::Worse(_, code)
+            | TrustFeedback::Bad(_, code) => *code,
+            TrustFeedback::Neutral | TrustFeedback::Good => None,
+        }
+    }
+}
+
+/// Why a message from a peer is rejected.
+#[derive(Debug, Display, Clone, Copy, PartialEq, Eq)]
+pub enum RejectCode {
+    #[display(fmt = "invalid signature")]
+    InvalidSignature,
+    #[display(fmt = "oversized")]
+    Oversized,
+    #[display(fmt = "duplicate")]
+    Duplicate,
+    #[display(fmt = "wrong chain")]
+    WrongChain,
+    #[display(fmt = "timeout")]
+    Timeout,
+}
+
 #[derive(Debug, Display, Clone)]
 pub enum PeerTag {
     #[display(fmt = "consensus")]
@@ -101,6 +129,18 @@ pub trait Gossip: Send + Sync {
     where
         M: MessageCodec;
 
+    /// Send `msg` to every connected consensus peer as an rpc call on `end`
+    /// and return how many peers it was sent to and how many of them
+    /// acknowledged it, by responding, within the deadline.
+    async fn broadcast_to_consensus_acked<M>(
+        &self,
+        cx: Context,
+        end: &str,
+        msg: M,
+    ) -> ProtocolResult<(usize, usize)>
+    where
+        M: MessageCodec + Clone;
+
     async fn multicast<'a, M, P>(
         &self,
         cx: Context,
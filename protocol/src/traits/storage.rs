@@ -2,7 +2,10 @@ use async_trait::async_trait;
 use derive_more::Display;
 
 use crate::traits::Context;
-use crate::types::{Block, Bytes, Hash, Header, Proof, Receipt, SignedTransaction, H256};
+use crate::types::{
+    Block, BlockNumber, Bytes, Hash, Header, Metadata, Proof, Receipt, SignedTransaction,
+    TxPosition, H256,
+};
 use crate::{codec::ProtocolCodec, ProtocolResult};
 
 #[derive(Debug, Copy, Clone, Display)]
@@ -14,6 +17,7 @@ pub enum StorageCategory {
     Wal,
     HashHeight,
     Code,
+    Metadata,
 }
 
 pub type StorageIterator<'a, S> = Box<
@@ -79,6 +83,14 @@ pub trait Storage: CommonStorage {
         hash: &Hash,
     ) -> ProtocolResult<Option<SignedTransaction>>;
 
+    /// Look up the block number and index of a committed transaction from the
+    /// index maintained on import, without reading any block.
+    async fn get_transaction_position(
+        &self,
+        ctx: Context,
+        hash: &Hash,
+    ) -> ProtocolResult<Option<TxPosition>>;
+
     async fn insert_receipts(
         &self,
         ctx: Context,
@@ -120,6 +132,17 @@ pub trait Storage: CommonStorage {
     async fn update_latest_proof(&self, ctx: Context, proof: Proof) -> ProtocolResult<()>;
 
     async fn get_latest_proof(&self, ctx: Context) -> ProtocolResult<Proof>;
+
+    /// Save a metadata version, so the one in effect at any height stays
+    /// available after the validators change.
+    async fn insert_metadata(&self, ctx: Context, metadata: Metadata) -> ProtocolResult<()>;
+
+    /// Return the metadata in effect at `number`, if it is saved.
+    async fn get_metadata(
+        &self,
+        ctx: Context,
+        number: BlockNumber,
+    ) -> ProtocolResult<Option<Metadata>>;
 }
 
 #[async_trait]
@@ -44,7 +44,7 @@ mod tests {
             unsigned:  Transaction {
                 nonce:                    U256::one(),
                 max_priority_fee_per_gas: U256::one(),
-                gas_price:                U256::one(),
+                max_fee_per_gas:          U256::one(),
                 gas_limit:                U256::one(),
                 action:                   TransactionAction::Create,
                 value:                    Default::default(),
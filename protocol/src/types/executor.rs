@@ -1,8 +1,10 @@
+use std::collections::{BTreeMap, BTreeSet};
+
 pub use ethereum::{AccessList, AccessListItem, Account};
 pub use evm::{backend::Log, Config, ExitError, ExitFatal, ExitReason, ExitRevert, ExitSucceed};
 
 use crate::codec::ProtocolCodec;
-use crate::types::{Hash, Hasher, MerkleRoot, Proposal, H160, U256};
+use crate::types::{Bytes, Hash, Hasher, MerkleRoot, Proposal, H160, H256, U256};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct ExecResp {
@@ -22,6 +24,83 @@ pub struct TxResp {
     pub code_address: Option<Hash>,
 }
 
+/// The state of an account before a transaction is executed, only the storage
+/// slots accessed by the transaction are included.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct PrestateAccount {
+    pub balance: U256,
+    pub nonce:   U256,
+    pub code:    Vec<u8>,
+    pub storage: BTreeMap<H256, H256>,
+}
+
+/// The code and the storage slots an account is left with by replayed
+/// transactions. `code` is `None` when they leave it unchanged, and with
+/// `reset_storage` the slots not in `storage` are cleared, as for a deleted
+/// account.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct AccountOverlay {
+    pub code:          Option<Vec<u8>>,
+    pub storage:       BTreeMap<H256, H256>,
+    pub reset_storage: bool,
+}
+
+/// The outcome of transactions replayed without committing them: the code
+/// and storage they leave the touched accounts with and the logs of each
+/// transaction in order, empty for a failed one.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct OverlayReplay {
+    pub overlay: BTreeMap<H160, AccountOverlay>,
+    pub logs:    Vec<Vec<Log>>,
+}
+
+/// One call of a bundle simulated by `Executor::call_bundle`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BundleCall {
+    pub from:        Option<H160>,
+    pub to:          H160,
+    pub value:       U256,
+    pub gas_limit:   u64,
+    pub data:        Vec<u8>,
+    pub access_list: AccessList,
+}
+
+/// A page of the non-zero storage slots of an account in key order.
+/// `next_key` is the first slot of the next page, `None` on the last one.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct StorageRange {
+    pub storage:  BTreeMap<H256, H256>,
+    pub next_key: Option<H256>,
+}
+
+/// The state loaded and changed by a call. An account or a storage slot whose
+/// value the call changes is only among the writes, the other ones it loads
+/// are the reads.
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct AccessSet {
+    pub reads_accounts:  BTreeSet<H160>,
+    pub writes_accounts: BTreeSet<H160>,
+    pub reads_slots:     BTreeMap<H160, BTreeSet<H256>>,
+    pub writes_slots:    BTreeMap<H160, BTreeSet<H256>>,
+}
+
+/// The Merkle proofs of an account and some of its storage slots, see
+/// EIP-1186. The state trie is keyed by the address and the storage tries by
+/// the slot, rather than by their hashes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AccountProof {
+    pub account:       Account,
+    pub account_proof: Vec<Bytes>,
+    pub storage_proof: Vec<StorageProof>,
+}
+
+#[derive(Default, Clone, Debug, PartialEq, Eq)]
+pub struct StorageProof {
+    pub key:   H256,
+    pub value: H256,
+    pub proof: Vec<Bytes>,
+}
+
 #[derive(Default, Clone, Debug, PartialEq, Eq)]
 pub struct ExecutorContext {
     pub block_number:           U256,
@@ -5,7 +5,9 @@ pub use block::*;
 pub use bytes::{Buf, BufMut, Bytes, BytesMut};
 pub use evm::{backend::*, ExitSucceed};
 pub use executor::{
-    AccessList, AccessListItem, Account, Config, ExecResp, ExecutorContext, ExitReason, TxResp,
+    AccessList, AccessListItem, AccessSet, Account, AccountOverlay, AccountProof, BundleCall,
+    Config, ExecResp, ExecutorContext, ExitError, ExitReason, OverlayReplay, PrestateAccount,
+    StorageProof, StorageRange, TxResp,
 };
 pub use primitive::*;
 pub use receipt::*;
@@ -38,9 +38,48 @@ impl Default for Receipt {
 
 impl Receipt {
     pub fn status(&self) -> U256 {
-        match self.ret {
-            ExitReason::Succeed(_) => U256::one(),
-            _ => U256::zero(),
+        exit_status(&self.ret)
+    }
+
+    /// The consensus part of the receipt, `cumulative_gas_used` is the gas
+    /// used by the transactions of the block up to and including this one.
+    pub fn to_consensus(&self, cumulative_gas_used: U256) -> ConsensusReceipt {
+        ConsensusReceipt {
+            status: self.status(),
+            cumulative_gas_used,
+            logs_bloom: self.logs_bloom,
+            logs: self.logs.clone(),
+        }
+    }
+}
+
+/// The fields of a receipt committed to by the `receipts_root` of a block.
+/// It is encoded in the typed envelope of EIP-2718, `0x02 || rlp([status,
+/// cumulative_gas_used, logs_bloom, logs])`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConsensusReceipt {
+    pub status:              U256,
+    pub cumulative_gas_used: U256,
+    pub logs_bloom:          Bloom,
+    pub logs:                Vec<Log>,
+}
+
+pub fn exit_status(exit_reason: &ExitReason) -> U256 {
+    match exit_reason {
+        ExitReason::Succeed(_) => U256::one(),
+        _ => U256::zero(),
+    }
+}
+
+/// The bloom of the logs of a transaction, accruing the address and every
+/// topic of each log.
+pub fn logs_bloom(logs: &[Log]) -> Bloom {
+    let mut bloom = Bloom::default();
+    for log in logs.iter() {
+        bloom.accrue(BloomInput::Raw(log.address.as_bytes()));
+        for topic in log.topics.iter() {
+            bloom.accrue(BloomInput::Raw(topic.as_bytes()));
         }
     }
+    bloom
 }
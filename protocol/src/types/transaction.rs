@@ -7,13 +7,20 @@ use serde::{Deserialize, Serialize};
 
 use common_crypto::secp256k1_recover;
 
-use crate::types::{Bytes, BytesMut, Hash, Hasher, Public, TypesError, H160, H256, H520, U256};
+use crate::types::{
+    BlockNumber, Bytes, BytesMut, Hash, Hasher, Public, TypesError, H160, H256, H520, U256,
+};
+
+/// The EIP-2718 type byte of an EIP-1559 transaction envelope.
+pub const EIP1559_TX_TYPE: u8 = 0x02;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct Transaction {
     pub nonce:                    U256,
     pub max_priority_fee_per_gas: U256,
-    pub gas_price:                U256,
+    // Genesis files written before the rename still carry `gas_price`.
+    #[serde(alias = "gas_price")]
+    pub max_fee_per_gas:          U256,
     pub gas_limit:                U256,
     pub action:                   TransactionAction,
     pub value:                    U256,
@@ -25,7 +32,7 @@ impl std::hash::Hash for Transaction {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
         self.nonce.hash(state);
         self.max_priority_fee_per_gas.hash(state);
-        self.gas_price.hash(state);
+        self.max_fee_per_gas.hash(state);
         self.gas_limit.hash(state);
         self.value.hash(state);
         self.data.hash(state);
@@ -109,6 +116,14 @@ impl SignatureComponents {
     }
 }
 
+/// Where a committed transaction lives, as kept by the transaction hash
+/// index.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TxPosition {
+    pub block_number: BlockNumber,
+    pub tx_index:     u32,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, Hash, PartialEq, Eq)]
 pub struct SignedTransaction {
     pub transaction: UnverifiedTransaction,
@@ -151,6 +166,17 @@ impl SignedTransaction {
     }
 }
 
+/// The status of a transaction which is still in the mempool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TxPoolStatus {
+    /// Ready to be packaged into a block.
+    Pending,
+    /// Waiting for a lower nonce transaction of the same sender.
+    Queued,
+    /// Replaced by another transaction with the same sender and nonce.
+    Dropped,
+}
+
 pub fn public_to_address(public: &Public) -> H160 {
     let hash = Hasher::digest(public);
     let mut ret = H160::zero();